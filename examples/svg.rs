@@ -0,0 +1,25 @@
+//! Example for embedding SVG drawings as vector graphics
+//!
+//! Run with `cargo run --example svg --features svg`.
+
+use genpdfi::{Position, Size};
+
+const CHART: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="200" height="100">
+  <rect x="0" y="0" width="200" height="100" fill="#f4f4f4"/>
+  <rect x="20" y="60" width="30" height="30" fill="#4e79a7"/>
+  <rect x="70" y="30" width="30" height="60" fill="#f28e2b"/>
+  <rect x="120" y="10" width="30" height="80" fill="#e15759"/>
+  <line x1="10" y1="90" x2="190" y2="90" stroke="black" stroke-width="1"/>
+</svg>"##;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let renderer = genpdfi::render::Renderer::new(genpdfi::PaperSize::A4, "SVG example")?;
+    let area = renderer.first_page().first_layer().area();
+    area.add_svg(CHART, Position::new(20, 20), Size::new(120, 60))?;
+    drop(area);
+
+    let mut file = std::fs::File::create("svg.pdf")?;
+    renderer.write(&mut file)?;
+    println!("Saved to svg.pdf");
+    Ok(())
+}
@@ -18,13 +18,14 @@ fn main() {
     ];
 
     // Load a test font (we'll use a system font)
-    let font_paths = vec![
+    let font_paths = [
         "/System/Library/Fonts/Supplemental/Arial Unicode.ttf",
         "/System/Library/Fonts/Helvetica.ttc",
         "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
     ];
 
-    let font_data = font_paths.iter()
+    let font_data = font_paths
+        .iter()
         .find_map(|path| std::fs::read(path).ok())
         .expect("No suitable font found");
 
@@ -0,0 +1,201 @@
+//! Barcode symbologies that can be drawn with [`Area::draw_barcode`][].
+//!
+//! *Only available if the `barcode` feature is enabled.*
+//!
+//! [`Area::draw_barcode`]: ../render/struct.Area.html#method.draw_barcode
+
+use crate::error::{Error, ErrorKind};
+
+/// The bar and space widths of the Code 128 symbols with the values 0 to 105, in modules.
+const CODE128_PATTERNS: [[u8; 6]; 106] = [
+    [2, 1, 2, 2, 2, 2],
+    [2, 2, 2, 1, 2, 2],
+    [2, 2, 2, 2, 2, 1],
+    [1, 2, 1, 2, 2, 3],
+    [1, 2, 1, 3, 2, 2],
+    [1, 3, 1, 2, 2, 2],
+    [1, 2, 2, 2, 1, 3],
+    [1, 2, 2, 3, 1, 2],
+    [1, 3, 2, 2, 1, 2],
+    [2, 2, 1, 2, 1, 3],
+    [2, 2, 1, 3, 1, 2],
+    [2, 3, 1, 2, 1, 2],
+    [1, 1, 2, 2, 3, 2],
+    [1, 2, 2, 1, 3, 2],
+    [1, 2, 2, 2, 3, 1],
+    [1, 1, 3, 2, 2, 2],
+    [1, 2, 3, 1, 2, 2],
+    [1, 2, 3, 2, 2, 1],
+    [2, 2, 3, 2, 1, 1],
+    [2, 2, 1, 1, 3, 2],
+    [2, 2, 1, 2, 3, 1],
+    [2, 1, 3, 2, 1, 2],
+    [2, 2, 3, 1, 1, 2],
+    [3, 1, 2, 1, 3, 1],
+    [3, 1, 1, 2, 2, 2],
+    [3, 2, 1, 1, 2, 2],
+    [3, 2, 1, 2, 2, 1],
+    [3, 1, 2, 2, 1, 2],
+    [3, 2, 2, 1, 1, 2],
+    [3, 2, 2, 2, 1, 1],
+    [2, 1, 2, 1, 2, 3],
+    [2, 1, 2, 3, 2, 1],
+    [2, 3, 2, 1, 2, 1],
+    [1, 1, 1, 3, 2, 3],
+    [1, 3, 1, 1, 2, 3],
+    [1, 3, 1, 3, 2, 1],
+    [1, 1, 2, 3, 1, 3],
+    [1, 3, 2, 1, 1, 3],
+    [1, 3, 2, 3, 1, 1],
+    [2, 1, 1, 3, 1, 3],
+    [2, 3, 1, 1, 1, 3],
+    [2, 3, 1, 3, 1, 1],
+    [1, 1, 2, 1, 3, 3],
+    [1, 1, 2, 3, 3, 1],
+    [1, 3, 2, 1, 3, 1],
+    [1, 1, 3, 1, 2, 3],
+    [1, 1, 3, 3, 2, 1],
+    [1, 3, 3, 1, 2, 1],
+    [3, 1, 3, 1, 2, 1],
+    [2, 1, 1, 3, 3, 1],
+    [2, 3, 1, 1, 3, 1],
+    [2, 1, 3, 1, 1, 3],
+    [2, 1, 3, 3, 1, 1],
+    [2, 1, 3, 1, 3, 1],
+    [3, 1, 1, 1, 2, 3],
+    [3, 1, 1, 3, 2, 1],
+    [3, 3, 1, 1, 2, 1],
+    [3, 1, 2, 1, 1, 3],
+    [3, 1, 2, 3, 1, 1],
+    [3, 3, 2, 1, 1, 1],
+    [3, 1, 4, 1, 1, 1],
+    [2, 2, 1, 4, 1, 1],
+    [4, 3, 1, 1, 1, 1],
+    [1, 1, 1, 2, 2, 4],
+    [1, 1, 1, 4, 2, 2],
+    [1, 2, 1, 1, 2, 4],
+    [1, 2, 1, 4, 2, 1],
+    [1, 4, 1, 1, 2, 2],
+    [1, 4, 1, 2, 2, 1],
+    [1, 1, 2, 2, 1, 4],
+    [1, 1, 2, 4, 1, 2],
+    [1, 2, 2, 1, 1, 4],
+    [1, 2, 2, 4, 1, 1],
+    [1, 4, 2, 1, 1, 2],
+    [1, 4, 2, 2, 1, 1],
+    [2, 4, 1, 2, 1, 1],
+    [2, 2, 1, 1, 1, 4],
+    [4, 1, 3, 1, 1, 1],
+    [2, 4, 1, 1, 1, 2],
+    [1, 3, 4, 1, 1, 1],
+    [1, 1, 1, 2, 4, 2],
+    [1, 2, 1, 1, 4, 2],
+    [1, 2, 1, 2, 4, 1],
+    [1, 1, 4, 2, 1, 2],
+    [1, 2, 4, 1, 1, 2],
+    [1, 2, 4, 2, 1, 1],
+    [4, 1, 1, 2, 1, 2],
+    [4, 2, 1, 1, 1, 2],
+    [4, 2, 1, 2, 1, 1],
+    [2, 1, 2, 1, 4, 1],
+    [2, 1, 4, 1, 2, 1],
+    [4, 1, 2, 1, 2, 1],
+    [1, 1, 1, 1, 4, 3],
+    [1, 1, 1, 3, 4, 1],
+    [1, 3, 1, 1, 4, 1],
+    [1, 1, 4, 1, 1, 3],
+    [1, 1, 4, 3, 1, 1],
+    [4, 1, 1, 1, 1, 3],
+    [4, 1, 1, 3, 1, 1],
+    [1, 1, 3, 1, 4, 1],
+    [1, 1, 4, 1, 3, 1],
+    [3, 1, 1, 1, 4, 1],
+    [4, 1, 1, 1, 3, 1],
+    [2, 1, 1, 4, 1, 2],
+    [2, 1, 1, 2, 1, 4],
+    [2, 1, 1, 2, 3, 2],
+];
+/// The bar and space widths of the Code 128 stop symbol, in modules.
+const CODE128_STOP: [u8; 7] = [2, 3, 3, 1, 1, 1, 2];
+/// The value of the Code 128 start symbol for code set B.
+const CODE128_START_B: usize = 104;
+
+/// A barcode symbology.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Barcode {
+    /// Code 128, encoded with code set B.
+    ///
+    /// This symbology supports the printable ASCII characters from the space (U+0020) to the
+    /// tilde (U+007E).  A checksum symbol is added automatically.
+    Code128,
+}
+
+impl Barcode {
+    /// Encodes the given data and returns the widths of the alternating bars and spaces in
+    /// modules, starting with a bar.
+    ///
+    /// Returns an error if the data is empty or if it contains characters that cannot be encoded
+    /// with this symbology.
+    pub fn encode(&self, data: &str) -> Result<Vec<u8>, Error> {
+        match self {
+            Barcode::Code128 => encode_code128(data),
+        }
+    }
+}
+
+fn encode_code128(data: &str) -> Result<Vec<u8>, Error> {
+    if data.is_empty() {
+        return Err(Error::new(
+            "Cannot encode empty data as a Code 128 barcode",
+            ErrorKind::InvalidData,
+        ));
+    }
+    let mut values = vec![CODE128_START_B];
+    for c in data.chars() {
+        if !(' '..='~').contains(&c) {
+            return Err(Error::new(
+                format!("Cannot encode character {:?} in a Code 128 barcode", c),
+                ErrorKind::InvalidData,
+            ));
+        }
+        values.push(c as usize - ' ' as usize);
+    }
+    let checksum = values
+        .iter()
+        .enumerate()
+        .map(|(idx, value)| idx.max(1) * value)
+        .sum::<usize>()
+        % 103;
+    values.push(checksum);
+
+    let mut widths: Vec<u8> = values
+        .into_iter()
+        .flat_map(|value| CODE128_PATTERNS[value].iter().copied())
+        .collect();
+    widths.extend_from_slice(&CODE128_STOP);
+    Ok(widths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Barcode, CODE128_PATTERNS};
+
+    #[test]
+    fn test_code128() {
+        let widths = Barcode::Code128.encode("HELLO").unwrap();
+        // start, five characters, checksum and the stop symbol with four bars
+        assert_eq!(7 * 6 + 7, widths.len());
+        assert_eq!(
+            7 * 11 + 13,
+            widths.iter().map(|w| usize::from(*w)).sum::<usize>()
+        );
+        // (104 + 40 * 1 + 37 * 2 + 44 * 3 + 44 * 4 + 47 * 5) % 103 = 40
+        assert_eq!(&CODE128_PATTERNS[40][..], &widths[36..42]);
+        assert_eq!(&CODE128_PATTERNS[104][..], &widths[..6]);
+
+        assert!(Barcode::Code128.encode("").is_err());
+        assert!(Barcode::Code128.encode("Caf\u{e9}").is_err());
+        assert!(Barcode::Code128.encode("a\nb").is_err());
+    }
+}
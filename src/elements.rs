@@ -6,6 +6,7 @@
 //! It includes the following elements:
 //! - Containers:
 //!   - [`LinearLayout`][]: arranges its elements sequentially
+//!   - [`ColumnLayout`][]: flows its element through multiple columns
 //!   - [`TableLayout`][]: arranges its elements in columns and rows
 //!   - [`OrderedList`][] and [`UnorderedList`][]: arrange their elements sequentially with bullet
 //!     points
@@ -25,6 +26,7 @@
 //!
 //! [`Element`]: ../trait.Element.html
 //! [`LinearLayout`]: struct.LinearLayout.html
+//! [`ColumnLayout`]: struct.ColumnLayout.html
 //! [`TableLayout`]: struct.TableLayout.html
 //! [`OrderedList`]: struct.OrderedList.html
 //! [`UnorderedList`]: struct.UnorderedList.html
@@ -166,6 +168,118 @@ impl<E: IntoBoxedElement> iter::Extend<E> for LinearLayout {
     }
 }
 
+/// Flows an element through multiple columns.
+///
+/// The element is rendered into the first column until it does not fit anymore, then into the
+/// next column and so on.  The columns have the same width and are separated by a gutter.  If a
+/// rule is set, a vertical line is drawn in the middle of each gutter between two columns with
+/// content.
+///
+/// # Examples
+///
+/// With setters:
+/// ```
+/// use genpdfi::elements;
+/// let mut layout = elements::ColumnLayout::new(2, elements::Paragraph::new("Test"));
+/// layout.set_gutter(10);
+/// layout.set_rule(genpdfi::style::LineStyle::new());
+/// ```
+///
+/// Chained:
+/// ```
+/// use genpdfi::elements;
+/// let layout = elements::ColumnLayout::new(2, elements::Paragraph::new("Test"))
+///     .with_gutter(10)
+///     .with_rule(genpdfi::style::LineStyle::new());
+/// ```
+pub struct ColumnLayout {
+    columns: usize,
+    gutter: Mm,
+    rule: Option<LineStyle>,
+    element: Box<dyn Element>,
+}
+
+impl ColumnLayout {
+    /// Creates a new column layout with the given number of columns for the given element.
+    ///
+    /// If the number of columns is zero, a single column is used.
+    pub fn new<E: IntoBoxedElement>(columns: usize, element: E) -> ColumnLayout {
+        ColumnLayout {
+            columns: columns.max(1),
+            gutter: Mm::from(5),
+            rule: None,
+            element: element.into_boxed_element(),
+        }
+    }
+
+    /// Sets the width of the gap between two columns.
+    pub fn set_gutter(&mut self, gutter: impl Into<Mm>) {
+        self.gutter = gutter.into();
+    }
+
+    /// Sets the width of the gap between two columns and returns the layout.
+    pub fn with_gutter(mut self, gutter: impl Into<Mm>) -> Self {
+        self.set_gutter(gutter);
+        self
+    }
+
+    /// Sets the line style of the rule that is drawn in the gutters.
+    pub fn set_rule(&mut self, rule: impl Into<LineStyle>) {
+        self.rule = Some(rule.into());
+    }
+
+    /// Sets the line style of the rule that is drawn in the gutters and returns the layout.
+    pub fn with_rule(mut self, rule: impl Into<LineStyle>) -> Self {
+        self.set_rule(rule);
+        self
+    }
+}
+
+impl Element for ColumnLayout {
+    fn render(
+        &mut self,
+        context: &Context,
+        area: render::Area<'_>,
+        style: Style,
+    ) -> Result<RenderResult, Error> {
+        let mut result = RenderResult::default();
+        let gutters = self.gutter * (self.columns - 1) as f32;
+        let column_width = (area.size().width - gutters) / self.columns as f32;
+        if column_width <= Mm(0.0) {
+            return Err(Error::new(
+                "The columns do not fit into the area",
+                ErrorKind::PageSizeExceeded,
+            ));
+        }
+
+        let mut filled_columns = 0;
+        result.has_more = true;
+        while result.has_more && filled_columns < self.columns {
+            let mut column_area = area.clone();
+            column_area.add_offset(Position::new(
+                (column_width + self.gutter) * filled_columns as f32,
+                0,
+            ));
+            column_area.set_width(column_width);
+            let column_result = self.element.render(context, column_area, style)?;
+            result.has_more = column_result.has_more;
+            result.size.height = result.size.height.max(column_result.size.height);
+            filled_columns += 1;
+        }
+        result.size.width = column_width * filled_columns as f32
+            + self.gutter * filled_columns.saturating_sub(1) as f32;
+
+        if let Some(rule) = &self.rule {
+            for column in 1..filled_columns {
+                let x = (column_width + self.gutter) * column as f32 - self.gutter / 2.0;
+                area.draw_vertical_rule(x, result.size.height, rule.clone());
+            }
+        }
+
+        Ok(result)
+    }
+}
+
 /// A single line of formatted text.
 ///
 /// This element renders a single styled string on a single line.  It does not wrap it if the
@@ -316,7 +430,7 @@ impl Paragraph {
 
     fn get_offset(&self, width: Mm, max_width: Mm) -> Mm {
         match self.alignment {
-            Alignment::Left => Mm::default(),
+            Alignment::Left | Alignment::Justify => Mm::default(),
             Alignment::Center => (max_width - width) / 2.0,
             Alignment::Right => max_width - width,
         }
@@ -356,8 +470,16 @@ impl Element for Paragraph {
             .map(|s| style::StyledStr::new(&s.s, s.style, s.link.as_deref()));
         let mut rendered_len = 0;
         let mut wrapper = wrap::Wrapper::new(words, context, area.size().width);
-        for (line, delta) in &mut wrapper {
-            let width = line.iter().map(|s| s.width(&context.font_cache)).sum();
+        let mut lines = wrapper.by_ref().peekable();
+        while let Some((mut line, delta)) = lines.next() {
+            let mut width = line.iter().map(|s| s.width(&context.font_cache)).sum();
+            // The last line of the paragraph is not justified
+            if self.alignment == Alignment::Justify
+                && lines.peek().is_some()
+                && justify(&mut line, &context.font_cache, area.size().width)
+            {
+                width = area.size().width;
+            }
             let metrics = line
                 .iter()
                 .map(|s| s.style.metrics(&context.font_cache))
@@ -407,6 +529,48 @@ impl Element for Paragraph {
     }
 }
 
+/// Increases the word spacing of the given line so that it is stretched to the given width and
+/// returns `true` if the line was changed.
+///
+/// Trailing whitespace is ignored.  If the line does not contain any spaces or if it is wider than
+/// the given width, it is not changed.
+fn justify(line: &mut [style::StyledCow<'_>], font_cache: &fonts::FontCache, width: Mm) -> bool {
+    let natural_width = line_width(line, font_cache);
+    let spaces: usize = line
+        .iter()
+        .enumerate()
+        .map(|(i, s)| {
+            if i + 1 == line.len() {
+                s.s.trim_end().matches(' ').count()
+            } else {
+                s.s.matches(' ').count()
+            }
+        })
+        .sum();
+    if spaces == 0 || natural_width >= width {
+        return false;
+    }
+    let extra = (width - natural_width) / spaces as f32;
+    for s in line.iter_mut() {
+        s.style = s.style.with_word_spacing(s.style.word_spacing() + extra);
+    }
+    true
+}
+
+/// Returns the width of the given line without trailing whitespace.
+fn line_width(line: &[style::StyledCow<'_>], font_cache: &fonts::FontCache) -> Mm {
+    line.iter()
+        .enumerate()
+        .map(|(i, s)| {
+            if i + 1 == line.len() {
+                s.style.str_width(font_cache, s.s.trim_end())
+            } else {
+                s.width(font_cache)
+            }
+        })
+        .sum()
+}
+
 impl From<Vec<StyledString>> for Paragraph {
     fn from(text: Vec<StyledString>) -> Paragraph {
         Paragraph {
@@ -716,18 +880,18 @@ impl<E: Element> Element for FramedElement<E> {
             result.size.height += line_thickness;
             frame_area.draw_line(
                 vec![bottom_right, top_right, top_left, bottom_left],
-                self.line_style,
+                self.line_style.clone(),
             );
         }
         if !result.has_more {
             result.size.height += line_thickness;
             frame_area.draw_line(
                 vec![top_left, bottom_left, bottom_right, top_right],
-                self.line_style,
+                self.line_style.clone(),
             );
         } else {
-            frame_area.draw_line(vec![top_left, bottom_left], self.line_style);
-            frame_area.draw_line(vec![top_right, bottom_right], self.line_style);
+            frame_area.draw_line(vec![top_left, bottom_left], self.line_style.clone());
+            frame_area.draw_line(vec![top_right, bottom_right], self.line_style.clone());
         }
 
         self.is_first = false;
@@ -1254,7 +1418,7 @@ impl CellDecorator for FrameCellDecorator {
                     Position::new(left, top + line_offset),
                     Position::new(right, top + line_offset),
                 ],
-                self.line_style,
+                self.line_style.clone(),
             );
             total_height += self.line_style.thickness();
         }
@@ -1265,7 +1429,7 @@ impl CellDecorator for FrameCellDecorator {
                     Position::new(right - line_offset, top),
                     Position::new(right - line_offset, bottom),
                 ],
-                self.line_style,
+                self.line_style.clone(),
             );
         }
 
@@ -1275,7 +1439,7 @@ impl CellDecorator for FrameCellDecorator {
                     Position::new(left, bottom - line_offset),
                     Position::new(right, bottom - line_offset),
                 ],
-                self.line_style,
+                self.line_style.clone(),
             );
             total_height += self.line_style.thickness();
         }
@@ -1286,7 +1450,7 @@ impl CellDecorator for FrameCellDecorator {
                     Position::new(left + line_offset, top),
                     Position::new(left + line_offset, bottom),
                 ],
-                self.line_style,
+                self.line_style.clone(),
             );
         }
 
@@ -1528,3 +1692,122 @@ impl Element for TableLayout {
         Ok(result)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use lopdf::content::Content;
+
+    use super::{justify, line_width, ColumnLayout, LinearLayout, Paragraph};
+    use crate::error::Error;
+    use crate::fonts::{FontCache, FontData, FontFamily};
+    use crate::render::{Area, Renderer};
+    use crate::style::{self, LineStyle, Style};
+    use crate::{Alignment, Context, Element, Mm, RenderResult, Size};
+
+    const FONT: &[u8] = include_bytes!("../tests/fonts/DejaVuSans-Regular.ttf");
+
+    /// An element with a fixed height that is moved to the next area if it does not fit.
+    struct Block(Mm);
+
+    impl Element for Block {
+        fn render(
+            &mut self,
+            _context: &Context,
+            area: Area<'_>,
+            _style: Style,
+        ) -> Result<RenderResult, Error> {
+            let mut result = RenderResult::default();
+            if self.0 <= area.size().height {
+                result.size = Size::new(area.size().width, self.0);
+            } else {
+                result.has_more = true;
+            }
+            Ok(result)
+        }
+    }
+
+    fn context() -> Context {
+        let data = FontData::new(FONT.to_vec(), None).expect("Could not load test font");
+        Context::new(FontCache::new(FontFamily {
+            regular: data.clone(),
+            bold: data.clone(),
+            italic: data.clone(),
+            bold_italic: data,
+        }))
+    }
+
+    #[test]
+    fn test_justify() {
+        let mut context = context();
+        let style = Style::new();
+        let mut line: Vec<_> = ["The ", "quick ", "brown ", "fox "]
+            .iter()
+            .map(|s| style::StyledCow::new(*s, style, None))
+            .collect();
+        assert!(line_width(&line, &context.font_cache) < Mm(80.0));
+        assert!(justify(&mut line, &context.font_cache, Mm(80.0)));
+        let width = line_width(&line, &context.font_cache);
+        assert!((width - Mm(80.0)).0.abs() < 0.01);
+
+        let mut single = vec![style::StyledCow::new("fox ", style, None)];
+        assert!(!justify(&mut single, &context.font_cache, Mm(80.0)));
+
+        let renderer = Renderer::new(Size::new(60, 100), "test").unwrap();
+        context.font_cache.load_pdf_fonts(&renderer).unwrap();
+        let mut paragraph = Paragraph::new("The quick brown fox jumps over the lazy dog.")
+            .aligned(Alignment::Justify);
+        let area = renderer.first_page().first_layer().area();
+        let result = paragraph.render(&context, area, style).unwrap();
+        assert!(!result.has_more);
+        assert_eq!(Mm(60.0), result.size.width);
+    }
+
+    #[test]
+    fn test_column_layout_rule() {
+        let renderer = Renderer::new(Size::new(200, 100), "test").unwrap();
+        let mut content = LinearLayout::vertical();
+        for _ in 0..7 {
+            content.push(Block(Mm(30.0)));
+        }
+        let mut layout = ColumnLayout::new(3, content)
+            .with_gutter(10)
+            .with_rule(LineStyle::new());
+        let area = renderer.first_page().first_layer().area();
+        let result = layout.render(&context(), area, Style::new()).unwrap();
+        assert!(!result.has_more);
+        assert_eq!(Size::new(200, 90), result.size);
+
+        let mut buf = Vec::new();
+        renderer.write(&mut buf).unwrap();
+        let doc = lopdf::Document::load_mem(&buf).unwrap();
+        let page_id = *doc.get_pages().values().next().unwrap();
+        let content = Content::decode(&doc.get_page_content(page_id).unwrap()).unwrap();
+        let rules: Vec<_> = content
+            .operations
+            .iter()
+            .filter(|op| op.operator == "m")
+            .map(|op| op.operands[0].as_f64().unwrap())
+            .collect();
+        assert_eq!(2, rules.len());
+        for (rule, expected) in rules.into_iter().zip(&[65.0, 135.0]) {
+            let expected = f64::from(printpdf::Pt::from(Mm(*expected)).0);
+            assert!((rule - expected).abs() < 0.01, "{} != {}", rule, expected);
+        }
+    }
+
+    #[test]
+    fn test_column_layout_single_column() {
+        let renderer = Renderer::new(Size::new(200, 100), "test").unwrap();
+        let mut layout = ColumnLayout::new(1, Block(Mm(30.0))).with_rule(LineStyle::new());
+        let area = renderer.first_page().first_layer().area();
+        let result = layout.render(&context(), area, Style::new()).unwrap();
+        assert_eq!(Size::new(200, 30), result.size);
+
+        let mut buf = Vec::new();
+        renderer.write(&mut buf).unwrap();
+        let doc = lopdf::Document::load_mem(&buf).unwrap();
+        let page_id = *doc.get_pages().values().next().unwrap();
+        let content = Content::decode(&doc.get_page_content(page_id).unwrap()).unwrap();
+        assert!(content.operations.iter().all(|op| op.operator != "m"));
+    }
+}
@@ -148,7 +148,7 @@ impl Image {
     /// Determines the offset from left-side based on provided Alignment.
     fn get_offset(&self, width: Mm, max_width: Mm) -> Position {
         let horizontal_offset = match self.alignment {
-            Alignment::Left => Mm::default(),
+            Alignment::Left | Alignment::Justify => Mm::default(),
             Alignment::Center => (max_width - width) / 2.0,
             Alignment::Right => max_width - width,
         };
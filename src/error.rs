@@ -83,8 +83,13 @@ impl error::Error for Error {
             ErrorKind::PdfIndexError(err) => Some(err),
             ErrorKind::RusttypeError(err) => Some(err),
             ErrorKind::FaceParsingError(err) => Some(err),
+            ErrorKind::LopdfError(err) => Some(err),
             #[cfg(feature = "images")]
             ErrorKind::ImageError(err) => Some(err),
+            #[cfg(feature = "svg")]
+            ErrorKind::SvgError(err) => Some(err),
+            #[cfg(feature = "qrcode")]
+            ErrorKind::QrCodeError(err) => Some(err),
         }
     }
 }
@@ -113,11 +118,23 @@ pub enum ErrorKind {
     RusttypeError(rusttype::Error),
     /// An error caused by face parsing in `printpdf`.
     FaceParsingError(printpdf::Error),
+    /// An error caused by `lopdf` while post-processing the generated document.
+    LopdfError(printpdf::lopdf::Error),
     /// An error caused by `image`.
     ///
     /// *Only available if the `images` feature is enabled.*
     #[cfg(feature = "images")]
     ImageError(image::ImageError),
+    /// An error caused by parsing an SVG drawing in `printpdf`.
+    ///
+    /// *Only available if the `svg` feature is enabled.*
+    #[cfg(feature = "svg")]
+    SvgError(printpdf::SvgParseError),
+    /// An error caused by encoding a QR code.
+    ///
+    /// *Only available if the `qrcode` feature is enabled.*
+    #[cfg(feature = "qrcode")]
+    QrCodeError(qrcode::types::QrError),
 }
 
 impl From<io::Error> for ErrorKind {
@@ -151,6 +168,12 @@ impl From<printpdf::PdfError> for ErrorKind {
     }
 }
 
+impl From<printpdf::lopdf::Error> for ErrorKind {
+    fn from(error: printpdf::lopdf::Error) -> ErrorKind {
+        ErrorKind::LopdfError(error)
+    }
+}
+
 impl From<rusttype::Error> for ErrorKind {
     fn from(error: rusttype::Error) -> ErrorKind {
         ErrorKind::RusttypeError(error)
@@ -163,3 +186,17 @@ impl From<image::ImageError> for ErrorKind {
         ErrorKind::ImageError(error)
     }
 }
+
+#[cfg(feature = "svg")]
+impl From<printpdf::SvgParseError> for ErrorKind {
+    fn from(error: printpdf::SvgParseError) -> ErrorKind {
+        ErrorKind::SvgError(error)
+    }
+}
+
+#[cfg(feature = "qrcode")]
+impl From<qrcode::types::QrError> for ErrorKind {
+    fn from(error: qrcode::types::QrError) -> ErrorKind {
+        ErrorKind::QrCodeError(error)
+    }
+}
@@ -24,7 +24,8 @@
 //! proprietary Helvetica, Times and Courier fonts.
 //!
 //! Built-in fonts can only be used with characters that are supported by the [Windows-1252][]
-//! encoding.
+//! encoding, or by the [`BuiltinEncoding`][] selected with
+//! [`Renderer::set_builtin_encoding`][].
 //!
 //! **Note:**  The [`Font`][] and [`FontFamily<Font>`][`FontFamily`] structs are only valid for the
 //! [`FontCache`][] they have been created with.  If you dont use the low-level [`render`][] module
@@ -60,16 +61,21 @@
 //! [`printpdf`]: https://docs.rs/printpdf
 //! [`printpdf::IndirectFontRef`]: https://docs.rs/printpdf/0.3.2/printpdf/types/plugins/graphics/two_dimensional/font/struct.IndirectFontRef.html
 //! [Windows-1252]: https://en.wikipedia.org/wiki/Windows-1252
+//! [`BuiltinEncoding`]: enum.BuiltinEncoding.html
+//! [`Renderer::set_builtin_encoding`]: ../render/struct.Renderer.html#method.set_builtin_encoding
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::fs;
+use std::io;
+use std::io::Read as _;
 use std::path;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use crate::error::{Context as _, Error, ErrorKind};
 use crate::render;
 use crate::style::Style;
+use crate::subsetting;
 use crate::Mm;
 
 /// Stores font data that can be referenced by a [`Font`][] or [`FontFamily`][].
@@ -88,10 +94,19 @@ pub struct FontCache {
     // a font, but the default font is always loaded in new, so this options is always some
     // (outside of new).
     default_font_family: Option<FontFamily<Font>>,
-    // Cache to deduplicate embedded fonts by their data pointer
-    embedded_font_cache: HashMap<*const Vec<u8>, printpdf::IndirectFontRef>,
+    // Cache to deduplicate embedded fonts by their data.  Fonts that share the same allocation
+    // are compared by pointer, other fonts by content.
+    embedded_font_cache: HashMap<Arc<Vec<u8>>, printpdf::IndirectFontRef>,
+    // The characters printed with the embedded fonts since the PDF fonts have been loaded, by the
+    // index of the font
+    used_chars: Mutex<HashMap<usize, HashSet<char>>>,
+    // The memoized string widths by font index and font size, if enabled.  As fonts cannot be
+    // modified once they have been added to the cache, the widths never become stale.
+    width_cache: Option<Mutex<WidthCache>>,
 }
 
+type WidthCache = HashMap<(usize, u32), HashMap<String, Mm>>;
+
 impl FontCache {
     /// Creates a new font cache with the given default font family.
     pub fn new(default_font_family: FontFamily<FontData>) -> FontCache {
@@ -100,22 +115,64 @@ impl FontCache {
             pdf_fonts: Vec::new(),
             default_font_family: None,
             embedded_font_cache: HashMap::new(),
+            used_chars: Default::default(),
+            width_cache: None,
         };
         font_cache.default_font_family = Some(font_cache.add_font_family(default_font_family));
         font_cache
     }
 
+    /// Enables or disables the memoization of string widths.
+    ///
+    /// If enabled, [`Font::str_width`][] and [`Style::str_width`][] store the calculated width
+    /// for every combination of font, font size and string and return the stored value for
+    /// subsequent calls.  This speeds up the layout of documents that measure the same strings
+    /// repeatedly, for example the labels of a large table, at the cost of additional memory.
+    /// Disabling the cache discards all stored widths.  The cache is disabled by default.
+    ///
+    /// [`Font::str_width`]: struct.Font.html#method.str_width
+    /// [`Style::str_width`]: ../style/struct.Style.html#method.str_width
+    pub fn set_width_cache(&mut self, enabled: bool) {
+        if !enabled {
+            self.width_cache = None;
+        } else if self.width_cache.is_none() {
+            self.width_cache = Some(Default::default());
+        }
+    }
+
+    /// Returns the number of string widths stored in the width cache.
+    ///
+    /// See [`set_width_cache`](#method.set_width_cache) for more information.
+    pub fn width_cache_len(&self) -> usize {
+        self.width_cache
+            .as_ref()
+            .map(|cache| cache.lock().unwrap().values().map(HashMap::len).sum())
+            .unwrap_or_default()
+    }
+
     /// Adds the given font to the cache and returns a reference to it.
     pub fn add_font(&mut self, font_data: FontData) -> Font {
-        let is_builtin = match &font_data.raw_data {
-            RawFontData::Builtin(_) => true,
-            RawFontData::Embedded(_) => false,
-        };
-        let font = Font::new(self.fonts.len(), is_builtin, &font_data.rt_font);
+        let font = Font::new(self.fonts.len(), &font_data);
         self.fonts.push(font_data);
         font
     }
 
+    /// Returns whether the given fonts use the same font data.
+    pub(crate) fn is_same_font(&self, font: Font, other: Font) -> bool {
+        font.idx == other.idx || self.fonts[font.idx].is_same_font(&self.fonts[other.idx])
+    }
+
+    /// Returns a reference to the given font if it has been added to this cache.
+    ///
+    /// Fonts are identified by their data, so this also finds clones of the added font data, but
+    /// not fonts that have been loaded separately from the same file.
+    pub fn find_font(&self, font_data: &FontData) -> Option<Font> {
+        self.fonts
+            .iter()
+            .position(|data| data.is_same_font(font_data))
+            .map(|idx| Font::new(idx, &self.fonts[idx]))
+    }
+
     /// Adds the given font family to the cache and returns a reference to it.
     pub fn add_font_family(&mut self, family: FontFamily<FontData>) -> FontFamily<Font> {
         FontFamily {
@@ -128,22 +185,28 @@ impl FontCache {
 
     /// Embeds all loaded fonts into the document generated by the given renderer and caches a
     /// reference to them.
+    ///
+    /// Embedded fonts with identical data are only embedded once, even if they have been loaded
+    /// separately.
     pub fn load_pdf_fonts(&mut self, renderer: &render::Renderer) -> Result<(), Error> {
         self.pdf_fonts.clear();
         self.embedded_font_cache.clear(); // Clear cache for this document
+        self.used_chars
+            .lock()
+            .expect("Font cache mutex is poisoned")
+            .clear();
 
         for font in &self.fonts {
             let pdf_font = match &font.raw_data {
                 RawFontData::Builtin(builtin) => renderer.add_builtin_font(*builtin)?,
                 RawFontData::Embedded(data) => {
-                    let data_ptr = Arc::as_ptr(data);
-
                     // Check if we've already embedded this exact font data
-                    if let Some(cached_font_ref) = self.embedded_font_cache.get(&data_ptr) {
+                    if let Some(cached_font_ref) = self.embedded_font_cache.get(data) {
                         cached_font_ref.clone()
                     } else {
                         let font_ref = renderer.add_embedded_font(data.as_ref())?;
-                        self.embedded_font_cache.insert(data_ptr, font_ref.clone());
+                        self.embedded_font_cache
+                            .insert(data.clone(), font_ref.clone());
                         font_ref
                     }
                 }
@@ -153,6 +216,70 @@ impl FontCache {
         Ok(())
     }
 
+    /// Replaces the embedded fonts in the document generated by the given renderer with subsets
+    /// that only contain the glyphs that have been printed since [`load_pdf_fonts`][] was called.
+    ///
+    /// This method should be called after all text has been printed.  Built-in fonts and fonts
+    /// that are already subset fonts (see [`FontData::new_with_subset`][]) are not changed.
+    /// Embedded fonts that are shared by multiple fonts of this cache are replaced with a single
+    /// subset containing the glyphs printed with any of these fonts.
+    ///
+    /// [`load_pdf_fonts`]: #method.load_pdf_fonts
+    /// [`FontData::new_with_subset`]: struct.FontData.html#method.new_with_subset
+    pub fn subset_pdf_fonts(&self, renderer: &mut render::Renderer) -> Result<(), Error> {
+        let used_chars = self
+            .used_chars
+            .lock()
+            .expect("Font cache mutex is poisoned");
+        let mut subset_data = HashSet::new();
+        for font in &self.fonts {
+            let data = match &font.raw_data {
+                RawFontData::Embedded(data) => data,
+                RawFontData::Builtin(_) => continue,
+            };
+            if !subset_data.insert(data) {
+                continue;
+            }
+            let font_ref = match self.embedded_font_cache.get(data) {
+                Some(font_ref) => font_ref,
+                None => continue,
+            };
+            let sharing_fonts: Vec<_> = self
+                .fonts
+                .iter()
+                .enumerate()
+                .filter(|(_, font)| match &font.raw_data {
+                    RawFontData::Embedded(other) => other == data,
+                    RawFontData::Builtin(_) => false,
+                })
+                .collect();
+            if sharing_fonts
+                .iter()
+                .any(|(_, font)| font.glyph_id_map.is_some())
+            {
+                continue;
+            }
+            let chars = sharing_fonts
+                .iter()
+                .filter_map(|(idx, _)| used_chars.get(idx))
+                .flatten()
+                .copied();
+            let subset = subsetting::subset_glyphs(data, chars)?;
+            renderer.add_font_subset(font_ref, subset.data, subset.glyph_map);
+        }
+        Ok(())
+    }
+
+    /// Records that the given string has been printed with the given font.
+    pub(crate) fn add_used_chars(&self, font: Font, s: &str) {
+        self.used_chars
+            .lock()
+            .expect("Font cache mutex is poisoned")
+            .entry(font.idx)
+            .or_default()
+            .extend(s.chars());
+    }
+
     /// Returns the default font family for this font cache.
     pub fn default_font_family(&self) -> FontFamily<Font> {
         self.default_font_family
@@ -179,6 +306,51 @@ impl FontCache {
     pub fn get_rt_font(&self, font: Font) -> &rusttype::Font<'static> {
         &self.fonts[font.idx].rt_font
     }
+
+    /// Checks whether the fonts selected by the given styles contain glyphs for all characters of
+    /// the given strings.
+    ///
+    /// This can be used to detect missing glyphs before rendering a document.  The strings are
+    /// grouped by the font selected by their style, so the returned vector contains one
+    /// [`GlyphCoverage`][] per font, in the order of the first occurrence of the font.  The font
+    /// families of the styles must have been created by this font cache.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use genpdfi::fonts::{FontCache, FontData, FontFamily};
+    /// # use genpdfi::style::Style;
+    /// # let data = FontData::load("font.ttf", None).unwrap();
+    /// # let family = FontFamily {
+    /// #     regular: data.clone(),
+    /// #     bold: data.clone(),
+    /// #     italic: data.clone(),
+    /// #     bold_italic: data,
+    /// # };
+    /// let font_cache = FontCache::new(family);
+    /// let texts = [(Style::new(), "Hello"), (Style::new().bold(), "Привет")];
+    /// for coverage in font_cache.check_document_coverage(&texts) {
+    ///     if !coverage.is_complete() {
+    ///         println!("Missing characters: {:?}", coverage.missing_chars());
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// [`GlyphCoverage`]: struct.GlyphCoverage.html
+    pub fn check_document_coverage(&self, text_by_style: &[(Style, &str)]) -> Vec<GlyphCoverage> {
+        let mut text_by_font: Vec<(usize, String)> = Vec::new();
+        for (style, text) in text_by_style {
+            let idx = style.font(self).idx;
+            if let Some((_, font_text)) = text_by_font.iter_mut().find(|(i, _)| *i == idx) {
+                font_text.push_str(text);
+            } else {
+                text_by_font.push((idx, text.to_string()));
+            }
+        }
+        text_by_font
+            .into_iter()
+            .map(|(idx, text)| self.fonts[idx].check_coverage(&text))
+            .collect()
+    }
 }
 
 /// The data for a font that is cached by a [`FontCache`][].
@@ -187,32 +359,89 @@ impl FontCache {
 #[derive(Clone, Debug)]
 pub struct FontData {
     /// The rusttype font used for metrics (glyph widths, kerning).
-    /// For subset fonts, this is parsed from the FULL original font.
-    rt_font: rusttype::Font<'static>,
+    /// For subset fonts, this is parsed from the FULL original font.  The parsed font is shared
+    /// between all clones of this font data.
+    rt_font: Arc<rusttype::Font<'static>>,
     /// The raw font data to embed in the PDF.
     /// For subset fonts, this contains the SUBSET data (smaller).
     raw_data: RawFontData,
     /// Optional glyph ID mapping for subset fonts.
     /// Maps characters to their glyph IDs in the subset font.
     glyph_id_map: Option<Arc<GlyphIdMap>>,
+    /// The underline position and thickness from the `post` table, relative to the em size.
+    underline_metrics: Option<(f32, f32)>,
+    /// The strikeout position and thickness from the `OS/2` table, relative to the em size.
+    strikeout_metrics: Option<(f32, f32)>,
+    /// The x-height and the cap height, relative to the em size.
+    x_and_cap_height: (f32, f32),
+    /// The advance widths and left side bearings from an AFM file by glyph name, relative to the
+    /// em size.
+    afm_metrics: Option<Arc<HashMap<String, rusttype::HMetrics>>>,
+    /// The memoized pair kerning values of the rusttype font, scaled to the em size.
+    ///
+    /// The cache stores at most [`MAX_KERNING_CACHE_LEN`][] pairs.  Once it is full, the kerning
+    /// of new pairs is calculated without storing it.
+    kerning_cache: Arc<Mutex<KerningCache>>,
+    /// The family and subfamily names from the `name` table.
+    names: Arc<FontNames>,
+    /// Whether the `post` table marks the font as fixed-pitch.
+    is_fixed_pitch: bool,
+}
+
+/// The family and subfamily names of a font.
+#[derive(Clone, Debug, Default)]
+struct FontNames {
+    family: Option<String>,
+    subfamily: Option<String>,
+}
+
+/// The pair kerning values of a font by the glyph IDs of the left and the right glyph.
+type KerningCache = HashMap<(rusttype::GlyphId, rusttype::GlyphId), f32>;
+
+/// The maximum number of glyph pairs stored in the kerning cache of a font.
+const MAX_KERNING_CACHE_LEN: usize = 64 * 1024;
+
+/// Returns the pair kerning of the given glyphs from the kerning cache, calculating and storing
+/// it if it is not cached yet and the cache is not full.
+fn cached_pair_kerning(
+    kerning_cache: &mut KerningCache,
+    font: &rusttype::Font<'_>,
+    scale: rusttype::Scale,
+    pair: (rusttype::GlyphId, rusttype::GlyphId),
+) -> f32 {
+    if let Some(kerning) = kerning_cache.get(&pair) {
+        return *kerning;
+    }
+    let kerning = font.pair_kerning(scale, pair.0, pair.1);
+    if kerning_cache.len() < MAX_KERNING_CACHE_LEN {
+        kerning_cache.insert(pair, kerning);
+    }
+    kerning
 }
 
 impl FontData {
     /// Loads a font from the given data.
     ///
-    /// The provided data must by readable by [`rusttype`][].  If `builtin` is set, a built-in PDF
-    /// font is used instead of embedding the font in the PDF file (see the [module
+    /// The provided data must by readable by [`rusttype`][] or be a WOFF font, which is
+    /// decompressed to a plain font before it is parsed and embedded.  If `builtin` is set, a
+    /// built-in PDF font is used instead of embedding the font in the PDF file (see the [module
     /// documentation](index.html) for more information).  In this case, the given font must be
     /// metrically identical to the built-in font.
     ///
     /// [`rusttype`]: https://docs.rs/rusttype
     pub fn new(data: Vec<u8>, builtin: Option<printpdf::BuiltinFont>) -> Result<FontData, Error> {
+        let data = if data.starts_with(b"wOFF") {
+            decode_woff(&data)?
+        } else {
+            data
+        };
         let raw_data = if let Some(builtin) = builtin {
             RawFontData::Builtin(builtin)
         } else {
             RawFontData::Embedded(Arc::new(data.clone()))
         };
-        let rt_font = rusttype::Font::from_bytes(data).context("Failed to read rusttype font")?;
+        let tables = read_font_tables(&data);
+        let rt_font = parse_rt_font(data).context("Failed to read rusttype font")?;
         if rt_font.units_per_em() == 0 {
             Err(Error::new(
                 "The font is not scalable",
@@ -223,6 +452,13 @@ impl FontData {
                 rt_font,
                 raw_data,
                 glyph_id_map: None,
+                underline_metrics: tables.underline_metrics,
+                strikeout_metrics: tables.strikeout_metrics,
+                x_and_cap_height: tables.x_and_cap_height,
+                afm_metrics: None,
+                kerning_cache: Default::default(),
+                names: Arc::new(tables.names),
+                is_fixed_pitch: tables.is_fixed_pitch,
             })
         }
     }
@@ -239,8 +475,9 @@ impl FontData {
         } else {
             RawFontData::Embedded(shared_data.clone())
         };
-        let rt_font = rusttype::Font::from_bytes(shared_data.to_vec())
-            .context("Failed to read rusttype font")?;
+        let tables = read_font_tables(&shared_data);
+        let rt_font =
+            parse_rt_font(shared_data.to_vec()).context("Failed to read rusttype font")?;
         if rt_font.units_per_em() == 0 {
             Err(Error::new(
                 "The font is not scalable",
@@ -251,10 +488,30 @@ impl FontData {
                 rt_font,
                 raw_data,
                 glyph_id_map: None,
+                underline_metrics: tables.underline_metrics,
+                strikeout_metrics: tables.strikeout_metrics,
+                x_and_cap_height: tables.x_and_cap_height,
+                afm_metrics: None,
+                kerning_cache: Default::default(),
+                names: Arc::new(tables.names),
+                is_fixed_pitch: tables.is_fixed_pitch,
             })
         }
     }
 
+    /// Creates a copy of this font data that shares all parsed state with the original.
+    ///
+    /// The parsed [`rusttype`][] font, the font bytes, the glyph ID mapping and the kerning cache
+    /// are reference-counted, so the font is never parsed again.  Use this method to reuse a font
+    /// that has been loaded once for multiple documents, for example in a server that renders
+    /// many documents with the same fonts.  This is equivalent to [`Clone::clone`][].
+    ///
+    /// [`rusttype`]: https://docs.rs/rusttype
+    /// [`Clone::clone`]: https://doc.rust-lang.org/std/clone/trait.Clone.html#tymethod.clone
+    pub fn clone_shared(&self) -> FontData {
+        self.clone()
+    }
+
     /// Creates a new FontData by cloning an existing one with different raw data.
     /// This avoids re-parsing the font with rusttype, which is expensive for large fonts.
     ///
@@ -271,6 +528,13 @@ impl FontData {
             rt_font: source.rt_font.clone(),
             raw_data: RawFontData::Embedded(embed_data),
             glyph_id_map: glyph_id_map.map(Arc::new),
+            underline_metrics: source.underline_metrics,
+            strikeout_metrics: source.strikeout_metrics,
+            x_and_cap_height: source.x_and_cap_height,
+            afm_metrics: source.afm_metrics.clone(),
+            kerning_cache: source.kerning_cache.clone(),
+            names: source.names.clone(),
+            is_fixed_pitch: source.is_fixed_pitch,
         }
     }
 
@@ -295,8 +559,8 @@ impl FontData {
         glyph_id_map: GlyphIdMap,
     ) -> Result<FontData, Error> {
         // Parse the FULL font for metrics (glyph widths, kerning)
-        let rt_font = rusttype::Font::from_bytes(metrics_data.to_vec())
-            .context("Failed to read font for metrics")?;
+        let rt_font =
+            parse_rt_font(metrics_data.to_vec()).context("Failed to read font for metrics")?;
 
         if rt_font.units_per_em() == 0 {
             return Err(Error::new(
@@ -305,10 +569,18 @@ impl FontData {
             ));
         }
 
+        let tables = read_font_tables(&metrics_data);
         Ok(FontData {
             rt_font,
             raw_data: RawFontData::Embedded(embed_data),
             glyph_id_map: Some(Arc::new(glyph_id_map)),
+            underline_metrics: tables.underline_metrics,
+            strikeout_metrics: tables.strikeout_metrics,
+            x_and_cap_height: tables.x_and_cap_height,
+            afm_metrics: None,
+            kerning_cache: Default::default(),
+            names: Arc::new(tables.names),
+            is_fixed_pitch: tables.is_fixed_pitch,
         })
     }
 
@@ -329,6 +601,107 @@ impl FontData {
         FontData::new(data, builtin)
     }
 
+    /// Reads a font from the given reader.
+    ///
+    /// The reader is read to the end and the data is passed to [`new`](#method.new), see there
+    /// for more information.
+    pub fn from_reader(
+        mut reader: impl io::Read,
+        builtin: Option<printpdf::BuiltinFont>,
+    ) -> Result<FontData, Error> {
+        let mut data = Vec::new();
+        reader
+            .read_to_end(&mut data)
+            .context("Failed to read font data")?;
+        FontData::new(data, builtin)
+    }
+
+    /// Loads the face with the given index from the given font data.
+    ///
+    /// The data may contain a single font or a TrueType collection (`.ttc`) with multiple faces.
+    /// For collections, the selected face is extracted into a standalone font that is used for
+    /// the metrics and embedded in the PDF file.  A single font only has the index 0.  See
+    /// [`new`](#method.new) for more information about the `builtin` argument.
+    pub fn new_with_index(
+        data: Vec<u8>,
+        index: u32,
+        builtin: Option<printpdf::BuiltinFont>,
+    ) -> Result<FontData, Error> {
+        if data.starts_with(b"ttcf") {
+            FontData::new(extract_collection_face(&data, index)?, builtin)
+        } else if index == 0 {
+            FontData::new(data, builtin)
+        } else {
+            Err(Error::new(
+                format!(
+                    "Invalid face index {} for a font that is not a collection",
+                    index
+                ),
+                ErrorKind::InvalidFont,
+            ))
+        }
+    }
+
+    /// Loads the face with the given index from the font at the given path.
+    ///
+    /// See [`new_with_index`](#method.new_with_index) for more information.
+    pub fn load_with_index(
+        path: impl AsRef<path::Path>,
+        index: u32,
+        builtin: Option<printpdf::BuiltinFont>,
+    ) -> Result<FontData, Error> {
+        let data = fs::read(path.as_ref())
+            .with_context(|| format!("Failed to open font file {}", path.as_ref().display()))?;
+        FontData::new_with_index(data, index, builtin)
+    }
+
+    /// Loads the character widths of this built-in font from the AFM file at the given path.
+    ///
+    /// The widths and bounding boxes of the character metrics (`C … ; WX … ; N … ; B … ;`) in the
+    /// AFM file are used instead of the standard widths that are included in this crate and the
+    /// left side bearings of the font data.  The characters are mapped
+    /// to the glyph names using the Windows-1252 encoding that is used for built-in fonts.  This
+    /// method returns an error if this is not a built-in font.
+    pub fn with_afm_metrics(self, path: impl AsRef<path::Path>) -> Result<FontData, Error> {
+        let file = fs::File::open(path.as_ref())
+            .with_context(|| format!("Failed to open AFM file {}", path.as_ref().display()))?;
+        self.with_afm_metrics_from_reader(file)
+    }
+
+    /// Reads the character widths of this built-in font from the given AFM data.
+    ///
+    /// See [`with_afm_metrics`](#method.with_afm_metrics) for more information.
+    pub fn with_afm_metrics_from_reader(
+        mut self,
+        mut reader: impl io::Read,
+    ) -> Result<FontData, Error> {
+        if let RawFontData::Embedded(_) = self.raw_data {
+            return Err(Error::new(
+                "AFM metrics can only be used for built-in fonts",
+                ErrorKind::InvalidFont,
+            ));
+        }
+        let mut afm = String::new();
+        reader
+            .read_to_string(&mut afm)
+            .context("Failed to read AFM data")?;
+        self.afm_metrics = Some(Arc::new(parse_afm_metrics(&afm)?));
+        Ok(self)
+    }
+
+    /// Returns whether this font data and the given font data refer to the same font.
+    fn is_same_font(&self, other: &FontData) -> bool {
+        match (&self.raw_data, &other.raw_data) {
+            (RawFontData::Builtin(builtin), RawFontData::Builtin(other_builtin)) => {
+                builtin == other_builtin
+            }
+            (RawFontData::Embedded(data), RawFontData::Embedded(other_data)) => {
+                Arc::ptr_eq(data, other_data)
+            }
+            _ => false,
+        }
+    }
+
     /// Gets the raw font data bytes (for embedded fonts only).
     ///
     /// # Returns
@@ -344,6 +717,62 @@ impl FontData {
         }
     }
 
+    /// Returns the family name of this font from its `name` table, if available.
+    ///
+    /// The typographic family name is preferred over the legacy family name, so that all styles
+    /// of a font family with more than four styles have the same family name.
+    pub fn family_name(&self) -> Option<String> {
+        self.names.family.clone()
+    }
+
+    /// Returns the subfamily name (e. g. `Bold Italic`) of this font from its `name` table, if
+    /// available.
+    pub fn subfamily_name(&self) -> Option<String> {
+        self.names.subfamily.clone()
+    }
+
+    /// Returns whether this font is monospaced.
+    ///
+    /// A font is monospaced if its `post` table marks it as fixed-pitch or if a sample of
+    /// narrow and wide characters has the same advance width.  For built-in fonts, only the
+    /// Courier fonts are monospaced, regardless of the font data.
+    pub fn is_monospace(&self) -> bool {
+        if let RawFontData::Builtin(builtin) = &self.raw_data {
+            return matches!(
+                builtin,
+                printpdf::BuiltinFont::Courier
+                    | printpdf::BuiltinFont::CourierBold
+                    | printpdf::BuiltinFont::CourierOblique
+                    | printpdf::BuiltinFont::CourierBoldOblique
+            );
+        }
+        if self.is_fixed_pitch {
+            return true;
+        }
+        let mut widths = MONOSPACE_SAMPLE
+            .chars()
+            .map(|c| self.rt_font.glyph(c))
+            .filter(|glyph| glyph.id().0 != 0)
+            .map(|glyph| {
+                glyph
+                    .scaled(rusttype::Scale::uniform(1.0))
+                    .h_metrics()
+                    .advance_width
+            });
+        match widths.next() {
+            Some(first) => widths.all(|width| width == first),
+            None => false,
+        }
+    }
+
+    /// Returns the number of glyphs in this font as stored in the `maxp` table.
+    ///
+    /// For subset fonts, this is the number of glyphs in the full font that is used for the
+    /// metrics.
+    pub fn glyph_count(&self) -> u16 {
+        self.rt_font.glyph_count() as u16
+    }
+
     /// Checks if this font has a glyph for the given character.
     ///
     /// # Arguments
@@ -367,6 +796,19 @@ impl FontData {
         self.rt_font.glyph(c).id().0 != 0
     }
 
+    /// Returns whether the given character can be printed with this font.
+    ///
+    /// Built-in fonts can only print characters from the given encoding, regardless of the glyphs
+    /// of the font data that is used for their metrics.
+    fn covers_char(&self, c: char, encoding: BuiltinEncoding) -> bool {
+        match self.raw_data {
+            RawFontData::Builtin(_) => {
+                render::try_encode_builtin(c.encode_utf8(&mut [0; 4]), encoding).is_some()
+            }
+            RawFontData::Embedded(_) => self.has_glyph(c),
+        }
+    }
+
     /// Analyzes glyph coverage for the given text.
     ///
     /// This method checks which characters in the text are supported by this font
@@ -404,6 +846,589 @@ impl FontData {
             missing_chars,
         }
     }
+
+    /// Analyzes glyph coverage for the grapheme clusters of the given text.
+    ///
+    /// In contrast to [`check_coverage`][], this method counts the unique extended grapheme
+    /// clusters of the text, for example a base character followed by a combining accent or a
+    /// flag emoji.  A cluster is covered only if this font has glyphs for all of its code points.
+    /// The missing characters of the returned coverage are the code points of the uncovered
+    /// clusters that don't have a glyph in this font.
+    ///
+    /// *Only available if the `unicode-segmentation` feature is enabled.*
+    ///
+    /// [`check_coverage`]: #method.check_coverage
+    #[cfg(feature = "unicode-segmentation")]
+    pub fn check_coverage_graphemes(&self, text: &str) -> GlyphCoverage {
+        use unicode_segmentation::UnicodeSegmentation as _;
+
+        let mut missing_chars = Vec::new();
+        let unique_clusters: HashSet<&str> = text.graphemes(true).collect();
+        let mut covered = 0;
+
+        for cluster in &unique_clusters {
+            let mut is_covered = true;
+            for c in cluster.chars().filter(|c| !self.has_glyph(*c)) {
+                is_covered = false;
+                if !missing_chars.contains(&c) {
+                    missing_chars.push(c);
+                }
+            }
+            if is_covered {
+                covered += 1;
+            }
+        }
+
+        GlyphCoverage {
+            total_unique: unique_clusters.len(),
+            covered,
+            missing_chars,
+        }
+    }
+}
+
+#[cfg(test)]
+thread_local! {
+    /// The number of fonts parsed by rusttype in the current thread.
+    static RT_FONT_PARSE_COUNT: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// Parses the given font data with rusttype.
+fn parse_rt_font(data: Vec<u8>) -> Result<Arc<rusttype::Font<'static>>, rusttype::Error> {
+    #[cfg(test)]
+    RT_FONT_PARSE_COUNT.with(|count| count.set(count.get() + 1));
+    rusttype::Font::from_bytes(data).map(Arc::new)
+}
+
+/// The metrics and names of a font that are read from its tables with ttf-parser.
+#[derive(Debug, Default)]
+struct FontTables {
+    /// The underline position and thickness from the `post` table, relative to the em size.
+    underline_metrics: Option<(f32, f32)>,
+    /// The strikeout position and thickness from the `OS/2` table, relative to the em size.
+    strikeout_metrics: Option<(f32, f32)>,
+    /// The x-height and the cap height, relative to the em size.
+    x_and_cap_height: (f32, f32),
+    /// The family and subfamily names from the `name` table.
+    names: FontNames,
+    /// Whether the `post` table marks the font as fixed-pitch.
+    is_fixed_pitch: bool,
+}
+
+/// Parses the given font data with ttf-parser and reads the metrics and names from its tables.
+///
+/// If the font cannot be parsed, the default values are returned, i. e. no decoration metrics,
+/// zero heights and no names.
+fn read_font_tables(data: &[u8]) -> FontTables {
+    let face = match ttf_parser::Face::parse(data, 0) {
+        Ok(face) => face,
+        Err(_) => return FontTables::default(),
+    };
+    FontTables {
+        underline_metrics: read_line_metrics(&face, face.underline_metrics()),
+        strikeout_metrics: read_line_metrics(&face, face.strikeout_metrics()),
+        x_and_cap_height: read_x_and_cap_height(&face),
+        names: read_font_names(&face),
+        is_fixed_pitch: face.is_monospaced(),
+    }
+}
+
+/// Converts the given underline or strikeout metrics of the given font to the em size.
+///
+/// Returns `None` if the font does not specify a usable thickness.
+fn read_line_metrics(
+    face: &ttf_parser::Face<'_>,
+    metrics: Option<ttf_parser::LineMetrics>,
+) -> Option<(f32, f32)> {
+    let metrics = metrics?;
+    let units_per_em = f32::from(face.units_per_em());
+    if metrics.thickness <= 0 || units_per_em == 0.0 {
+        return None;
+    }
+    Some((
+        f32::from(metrics.position) / units_per_em,
+        f32::from(metrics.thickness) / units_per_em,
+    ))
+}
+
+/// Reads the x-height and the cap height from the `OS/2` table of the given font.
+///
+/// If the table does not specify these values, the heights of the bounding boxes of the `x` and
+/// `H` glyphs are used instead.  The values are relative to the em size and zero if they cannot be
+/// determined.
+fn read_x_and_cap_height(face: &ttf_parser::Face<'_>) -> (f32, f32) {
+    let units_per_em = f32::from(face.units_per_em());
+    if units_per_em == 0.0 {
+        return (0.0, 0.0);
+    }
+    let height = |value: Option<i16>, c: char| {
+        value
+            .filter(|value| *value > 0)
+            .or_else(|| {
+                face.glyph_index(c)
+                    .and_then(|id| face.glyph_bounding_box(id))
+                    .map(|bbox| bbox.y_max)
+            })
+            .map(|value| f32::from(value) / units_per_em)
+            .unwrap_or(0.0)
+    };
+    (
+        height(face.x_height(), 'x'),
+        height(face.capital_height(), 'H'),
+    )
+}
+
+/// Reads the family and subfamily names from the `name` table of the given font.
+///
+/// The typographic names are preferred over the legacy names.  Names that cannot be decoded are
+/// ignored.
+fn read_font_names(face: &ttf_parser::Face<'_>) -> FontNames {
+    let find_name = |ids: &[u16]| {
+        ids.iter().find_map(|id| {
+            face.names()
+                .into_iter()
+                .filter(|name| name.name_id == *id)
+                .find_map(|name| name.to_string())
+        })
+    };
+    FontNames {
+        family: find_name(&[
+            ttf_parser::name_id::TYPOGRAPHIC_FAMILY,
+            ttf_parser::name_id::FAMILY,
+        ]),
+        subfamily: find_name(&[
+            ttf_parser::name_id::TYPOGRAPHIC_SUBFAMILY,
+            ttf_parser::name_id::SUBFAMILY,
+        ]),
+    }
+}
+
+/// Parses the advance widths of the character metrics in the given AFM data and returns them by
+/// glyph name, relative to the em size.
+fn parse_afm_metrics(afm: &str) -> Result<HashMap<String, rusttype::HMetrics>, Error> {
+    let mut metrics = HashMap::new();
+    for line in afm.lines().map(str::trim) {
+        if !line.starts_with("C ") && !line.starts_with("CH ") {
+            continue;
+        }
+        let mut width = None;
+        let mut name = None;
+        let mut left_side_bearing = 0.0;
+        for field in line.split(';').map(str::trim) {
+            if let Some(value) = field.strip_prefix("WX ") {
+                let value = value.trim().parse::<f32>().map_err(|_| {
+                    Error::new(
+                        format!("Invalid width in AFM character metrics: {}", line),
+                        ErrorKind::InvalidData,
+                    )
+                })?;
+                width = Some(value / 1000.0);
+            } else if let Some(value) = field.strip_prefix("N ") {
+                name = Some(value.trim());
+            } else if let Some(value) = field.strip_prefix("B ") {
+                // The bounding box is given as llx lly urx ury, and llx is the left side bearing.
+                let llx = value
+                    .split_whitespace()
+                    .next()
+                    .and_then(|v| v.parse::<f32>().ok());
+                left_side_bearing = llx.ok_or_else(|| {
+                    Error::new(
+                        format!("Invalid bounding box in AFM character metrics: {}", line),
+                        ErrorKind::InvalidData,
+                    )
+                })? / 1000.0;
+            }
+        }
+        if let (Some(advance_width), Some(name)) = (width, name) {
+            metrics.insert(
+                name.to_owned(),
+                rusttype::HMetrics {
+                    advance_width,
+                    left_side_bearing,
+                },
+            );
+        }
+    }
+    if metrics.is_empty() {
+        Err(Error::new(
+            "The AFM data does not contain character widths",
+            ErrorKind::InvalidData,
+        ))
+    } else {
+        Ok(metrics)
+    }
+}
+
+/// The glyph names of the printable ASCII characters, starting with the space character.
+const ASCII_GLYPH_NAMES: [&str; 95] = [
+    "space",
+    "exclam",
+    "quotedbl",
+    "numbersign",
+    "dollar",
+    "percent",
+    "ampersand",
+    "quotesingle",
+    "parenleft",
+    "parenright",
+    "asterisk",
+    "plus",
+    "comma",
+    "hyphen",
+    "period",
+    "slash",
+    "zero",
+    "one",
+    "two",
+    "three",
+    "four",
+    "five",
+    "six",
+    "seven",
+    "eight",
+    "nine",
+    "colon",
+    "semicolon",
+    "less",
+    "equal",
+    "greater",
+    "question",
+    "at",
+    "A",
+    "B",
+    "C",
+    "D",
+    "E",
+    "F",
+    "G",
+    "H",
+    "I",
+    "J",
+    "K",
+    "L",
+    "M",
+    "N",
+    "O",
+    "P",
+    "Q",
+    "R",
+    "S",
+    "T",
+    "U",
+    "V",
+    "W",
+    "X",
+    "Y",
+    "Z",
+    "bracketleft",
+    "backslash",
+    "bracketright",
+    "asciicircum",
+    "underscore",
+    "grave",
+    "a",
+    "b",
+    "c",
+    "d",
+    "e",
+    "f",
+    "g",
+    "h",
+    "i",
+    "j",
+    "k",
+    "l",
+    "m",
+    "n",
+    "o",
+    "p",
+    "q",
+    "r",
+    "s",
+    "t",
+    "u",
+    "v",
+    "w",
+    "x",
+    "y",
+    "z",
+    "braceleft",
+    "bar",
+    "braceright",
+    "asciitilde",
+];
+
+/// The glyph names of the Latin-1 characters, starting with the no-break space.
+const LATIN1_GLYPH_NAMES: [&str; 96] = [
+    "space",
+    "exclamdown",
+    "cent",
+    "sterling",
+    "currency",
+    "yen",
+    "brokenbar",
+    "section",
+    "dieresis",
+    "copyright",
+    "ordfeminine",
+    "guillemotleft",
+    "logicalnot",
+    "hyphen",
+    "registered",
+    "macron",
+    "degree",
+    "plusminus",
+    "twosuperior",
+    "threesuperior",
+    "acute",
+    "mu",
+    "paragraph",
+    "periodcentered",
+    "cedilla",
+    "onesuperior",
+    "ordmasculine",
+    "guillemotright",
+    "onequarter",
+    "onehalf",
+    "threequarters",
+    "questiondown",
+    "Agrave",
+    "Aacute",
+    "Acircumflex",
+    "Atilde",
+    "Adieresis",
+    "Aring",
+    "AE",
+    "Ccedilla",
+    "Egrave",
+    "Eacute",
+    "Ecircumflex",
+    "Edieresis",
+    "Igrave",
+    "Iacute",
+    "Icircumflex",
+    "Idieresis",
+    "Eth",
+    "Ntilde",
+    "Ograve",
+    "Oacute",
+    "Ocircumflex",
+    "Otilde",
+    "Odieresis",
+    "multiply",
+    "Oslash",
+    "Ugrave",
+    "Uacute",
+    "Ucircumflex",
+    "Udieresis",
+    "Yacute",
+    "Thorn",
+    "germandbls",
+    "agrave",
+    "aacute",
+    "acircumflex",
+    "atilde",
+    "adieresis",
+    "aring",
+    "ae",
+    "ccedilla",
+    "egrave",
+    "eacute",
+    "ecircumflex",
+    "edieresis",
+    "igrave",
+    "iacute",
+    "icircumflex",
+    "idieresis",
+    "eth",
+    "ntilde",
+    "ograve",
+    "oacute",
+    "ocircumflex",
+    "otilde",
+    "odieresis",
+    "divide",
+    "oslash",
+    "ugrave",
+    "uacute",
+    "ucircumflex",
+    "udieresis",
+    "yacute",
+    "thorn",
+    "ydieresis",
+];
+
+/// Returns the glyph name of the given character in the Windows-1252 encoding, if it is
+/// supported by the encoding.
+fn glyph_name(c: char) -> Option<&'static str> {
+    match c {
+        ' '..='~' => Some(ASCII_GLYPH_NAMES[c as usize - 0x20]),
+        '\u{a0}'..='\u{ff}' => Some(LATIN1_GLYPH_NAMES[c as usize - 0xa0]),
+        '€' => Some("Euro"),
+        '‚' => Some("quotesinglbase"),
+        'ƒ' => Some("florin"),
+        '„' => Some("quotedblbase"),
+        '…' => Some("ellipsis"),
+        '†' => Some("dagger"),
+        '‡' => Some("daggerdbl"),
+        'ˆ' => Some("circumflex"),
+        '‰' => Some("perthousand"),
+        'Š' => Some("Scaron"),
+        '‹' => Some("guilsinglleft"),
+        'Œ' => Some("OE"),
+        'Ž' => Some("Zcaron"),
+        '\u{2018}' => Some("quoteleft"),
+        '\u{2019}' => Some("quoteright"),
+        '\u{201c}' => Some("quotedblleft"),
+        '\u{201d}' => Some("quotedblright"),
+        '•' => Some("bullet"),
+        '–' => Some("endash"),
+        '—' => Some("emdash"),
+        '˜' => Some("tilde"),
+        '™' => Some("trademark"),
+        'š' => Some("scaron"),
+        '›' => Some("guilsinglright"),
+        'œ' => Some("oe"),
+        'ž' => Some("zcaron"),
+        'Ÿ' => Some("Ydieresis"),
+        _ => None,
+    }
+}
+
+/// The maximum size of a decompressed WOFF font.
+const MAX_WOFF_FONT_SIZE: usize = 64 * 1024 * 1024;
+
+/// Decompresses the given WOFF font into a plain `sfnt` font.
+///
+/// Fonts that decompress to more than [`MAX_WOFF_FONT_SIZE`] bytes are rejected.
+fn decode_woff(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let invalid = || Error::new("The WOFF font is malformed", ErrorKind::InvalidFont);
+    let bad_size = || {
+        Error::new(
+            "The decompressed size of the WOFF font is invalid",
+            ErrorKind::InvalidData,
+        )
+    };
+    let read_u16 = |offset: usize| {
+        data.get(offset..offset + 2)
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+            .ok_or_else(invalid)
+    };
+    let read_u32 = |offset: usize| {
+        data.get(offset..offset + 4)
+            .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .ok_or_else(invalid)
+    };
+
+    let flavor = read_u32(4)?;
+    let num_tables = read_u16(12)?;
+    // The search range of the sfnt header must fit into 16 bits
+    if num_tables >= 0x1000 {
+        return Err(invalid());
+    }
+    let entry_selector = 15 - num_tables.max(1).leading_zeros() as u16;
+    let search_range: u16 = (1 << entry_selector) * 16;
+    let header_len = 12 + 16 * usize::from(num_tables);
+
+    let total_size = read_u32(16)? as usize;
+    if total_size > MAX_WOFF_FONT_SIZE {
+        return Err(bad_size());
+    }
+    let mut font = Vec::with_capacity(total_size.min(header_len + data.len()));
+    font.extend_from_slice(&flavor.to_be_bytes());
+    font.extend_from_slice(&num_tables.to_be_bytes());
+    font.extend_from_slice(&search_range.to_be_bytes());
+    font.extend_from_slice(&entry_selector.to_be_bytes());
+    font.extend_from_slice(&(num_tables * 16 - search_range).to_be_bytes());
+
+    let mut tables = Vec::new();
+    for i in 0..usize::from(num_tables) {
+        let entry = 44 + 20 * i;
+        let tag = data.get(entry..entry + 4).ok_or_else(invalid)?;
+        let offset = read_u32(entry + 4)? as usize;
+        let comp_length = read_u32(entry + 8)? as usize;
+        let orig_length = read_u32(entry + 12)? as usize;
+        let checksum = read_u32(entry + 16)?;
+        let end = offset.checked_add(comp_length).ok_or_else(invalid)?;
+        let compressed = data.get(offset..end).ok_or_else(invalid)?;
+        if orig_length > MAX_WOFF_FONT_SIZE - tables.len() {
+            return Err(bad_size());
+        }
+
+        let new_offset = (header_len + tables.len()) as u32;
+        if comp_length < orig_length {
+            let mut table = Vec::with_capacity(orig_length.min(comp_length.saturating_mul(4)));
+            flate2::read::ZlibDecoder::new(compressed)
+                .take(orig_length as u64)
+                .read_to_end(&mut table)
+                .context("Failed to decompress WOFF table")?;
+            if table.len() != orig_length {
+                return Err(bad_size());
+            }
+            tables.extend(table);
+        } else if comp_length == orig_length {
+            tables.extend_from_slice(compressed);
+        } else {
+            return Err(invalid());
+        }
+        tables.resize(tables.len().div_ceil(4) * 4, 0);
+
+        font.extend_from_slice(tag);
+        font.extend_from_slice(&checksum.to_be_bytes());
+        font.extend_from_slice(&new_offset.to_be_bytes());
+        font.extend_from_slice(&(orig_length as u32).to_be_bytes());
+    }
+    font.extend(tables);
+    Ok(font)
+}
+
+/// Copies the face with the given index from the given TrueType collection into a standalone font.
+fn extract_collection_face(data: &[u8], index: u32) -> Result<Vec<u8>, Error> {
+    let invalid = || Error::new("The font collection is malformed", ErrorKind::InvalidFont);
+    let read_u16 = |offset: usize| {
+        data.get(offset..offset + 2)
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+            .ok_or_else(invalid)
+    };
+    let read_u32 = |offset: usize| {
+        data.get(offset..offset + 4)
+            .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .ok_or_else(invalid)
+    };
+
+    let num_fonts = read_u32(8)?;
+    if index >= num_fonts {
+        return Err(Error::new(
+            format!(
+                "Invalid face index {} for a font collection with {} faces",
+                index, num_fonts
+            ),
+            ErrorKind::InvalidFont,
+        ));
+    }
+    let font_offset = read_u32(12 + 4 * index as usize)? as usize;
+    let num_tables = usize::from(read_u16(font_offset + 4)?);
+    let header_len = 12 + 16 * num_tables;
+    let header = data
+        .get(font_offset..font_offset + header_len)
+        .ok_or_else(invalid)?;
+
+    // The table offsets in a collection are relative to the start of the collection, so we have
+    // to rewrite them for the standalone font.
+    let mut font = header.to_vec();
+    let mut tables = Vec::new();
+    for i in 0..num_tables {
+        let record = font_offset + 12 + 16 * i;
+        let offset = read_u32(record + 8)? as usize;
+        let length = read_u32(record + 12)? as usize;
+        let table = data.get(offset..offset + length).ok_or_else(invalid)?;
+        let new_offset = (header_len + tables.len()) as u32;
+        font[12 + 16 * i + 8..12 + 16 * i + 12].copy_from_slice(&new_offset.to_be_bytes());
+        tables.extend_from_slice(table);
+        tables.resize(tables.len().div_ceil(4) * 4, 0);
+    }
+    font.extend(tables);
+    Ok(font)
 }
 
 /// Statistics about glyph coverage for a given text.
@@ -512,12 +1537,26 @@ impl GlyphIdMap {
 /// let font_for_я = chain.find_font_for_char('я');      // Uses cyrillic fallback
 /// let font_for_emoji = chain.find_font_for_char('😀'); // Uses emoji fallback
 /// ```
+///
+/// The chain may also contain built-in fonts, for example a built-in Helvetica font for Latin
+/// text with an embedded fallback font for other scripts.  As built-in fonts only support a
+/// single-byte encoding, a built-in font only covers the characters of the
+/// [`BuiltinEncoding`][] of the chain, even if the font data used for its metrics has glyphs for
+/// other characters.  The encoding defaults to Windows-1252 and should match the encoding of the
+/// renderer, see [`set_builtin_encoding`](#method.set_builtin_encoding).
+///
+/// [`BuiltinEncoding`]: enum.BuiltinEncoding.html
 #[derive(Clone, Debug)]
 pub struct FontFallbackChain {
     /// The primary font to try first
     primary: FontData,
     /// List of fallback fonts to try if primary doesn't have a character
     fallbacks: Vec<FontData>,
+    /// The scripts preferred by the fallback fonts, by the index of the fallback font.  An empty
+    /// list means that the fallback font is used for any script.
+    fallback_scripts: Vec<Vec<Script>>,
+    /// The encoding that determines the characters covered by built-in fonts
+    builtin_encoding: BuiltinEncoding,
 }
 
 impl FontFallbackChain {
@@ -526,35 +1565,108 @@ impl FontFallbackChain {
         Self {
             primary,
             fallbacks: Vec::new(),
+            fallback_scripts: Vec::new(),
+            builtin_encoding: BuiltinEncoding::default(),
         }
     }
 
-    /// Adds a fallback font to the chain.
-    pub fn with_fallback(mut self, fallback: FontData) -> Self {
+    /// Adds a fallback font for any script to the chain.
+    pub fn with_fallback(self, fallback: FontData) -> Self {
+        self.with_fallback_for_scripts(fallback, &[])
+    }
+
+    /// Adds a fallback font that is preferred for the given scripts to the chain.
+    ///
+    /// If the primary font does not have a glyph for a character of one of these scripts, this
+    /// fallback font is used before other fallback fonts, regardless of the order of the
+    /// fallback fonts.  If the list of scripts is empty, the font is used for any script, see
+    /// [`with_fallback`](#method.with_fallback).
+    pub fn with_fallback_for_scripts(mut self, fallback: FontData, scripts: &[Script]) -> Self {
         self.fallbacks.push(fallback);
+        self.fallback_scripts.push(scripts.to_vec());
+        self
+    }
+
+    /// Sets the encoding that determines the characters covered by the built-in fonts of this
+    /// chain.
+    ///
+    /// This should be the encoding set with [`Renderer::set_builtin_encoding`][].
+    /// [`TextSection::print_str_with_fallback`][] always uses the encoding of the renderer.
+    ///
+    /// [`Renderer::set_builtin_encoding`]: ../render/struct.Renderer.html#method.set_builtin_encoding
+    /// [`TextSection::print_str_with_fallback`]: ../render/struct.TextSection.html#method.print_str_with_fallback
+    pub fn set_builtin_encoding(&mut self, encoding: BuiltinEncoding) {
+        self.builtin_encoding = encoding;
+    }
+
+    /// Sets the encoding that determines the characters covered by the built-in fonts of this
+    /// chain and returns the chain.
+    ///
+    /// See [`set_builtin_encoding`](#method.set_builtin_encoding) for more information.
+    pub fn with_builtin_encoding(mut self, encoding: BuiltinEncoding) -> Self {
+        self.set_builtin_encoding(encoding);
         self
     }
 
+    /// Returns the encoding that determines the characters covered by the built-in fonts of this
+    /// chain.
+    pub fn builtin_encoding(&self) -> BuiltinEncoding {
+        self.builtin_encoding
+    }
+
     /// Finds the best font in the chain for the given character.
     ///
     /// Returns a reference to the first font (starting with primary) that has
-    /// a glyph for this character. If no font in the chain supports the character,
-    /// returns the primary font (which will render the .notdef glyph).
+    /// a glyph for this character, preferring fallback fonts for the script of the character.
+    /// If no font in the chain supports the character, returns the primary font (which will
+    /// render the .notdef glyph).
     pub fn find_font_for_char(&self, c: char) -> &FontData {
-        // Try primary first
-        if self.primary.has_glyph(c) {
-            return &self.primary;
-        }
+        // No font has it, return primary (will show .notdef)
+        self.try_find_font_for_char(c).unwrap_or(&self.primary)
+    }
 
-        // Try each fallback
-        for fallback in &self.fallbacks {
-            if fallback.has_glyph(c) {
-                return fallback;
+    /// Finds the first font in the chain that has a glyph for the given character.
+    ///
+    /// If the primary font does not have a glyph for the character, the fallback fonts that have
+    /// been added for the [`Script`][] of the character are searched before the other fallback
+    /// fonts.  Built-in fonts only have glyphs for the characters of the
+    /// [`builtin_encoding`](#method.builtin_encoding) of the chain.
+    ///
+    /// [`Script`]: enum.Script.html
+    ///
+    /// Unlike [`find_font_for_char`](#method.find_font_for_char), this method returns `None` if
+    /// no font in the chain supports the character.
+    pub fn try_find_font_for_char(&self, c: char) -> Option<&FontData> {
+        let encoding = self.builtin_encoding;
+        if self.primary.covers_char(c, encoding) {
+            return Some(&self.primary);
+        }
+        if let Some(script) = Script::of(c) {
+            let font = self
+                .fallbacks
+                .iter()
+                .zip(&self.fallback_scripts)
+                .find(|(font, scripts)| scripts.contains(&script) && font.covers_char(c, encoding));
+            if let Some((font, _)) = font {
+                return Some(font);
             }
         }
+        self.fallbacks
+            .iter()
+            .find(|font| font.covers_char(c, encoding))
+    }
 
-        // No font has it, return primary (will show .notdef)
-        &self.primary
+    /// Returns the characters of the given text that are not supported by any font in the chain.
+    ///
+    /// The characters are returned in the order of their first occurrence, without duplicates.
+    pub fn missing_in_chain(&self, text: &str) -> Vec<char> {
+        let mut missing_chars = Vec::new();
+        for c in text.chars() {
+            if !missing_chars.contains(&c) && self.try_find_font_for_char(c).is_none() {
+                missing_chars.push(c);
+            }
+        }
+        missing_chars
     }
 
     /// Returns the primary font.
@@ -577,10 +1689,7 @@ impl FontFallbackChain {
 
         for c in unique_chars.iter() {
             // Check if ANY font in chain has this character
-            let has_glyph = self.primary.has_glyph(*c)
-                || self.fallbacks.iter().any(|f| f.has_glyph(*c));
-
-            if !has_glyph {
+            if self.try_find_font_for_char(*c).is_none() {
                 missing_chars.push(*c);
             }
         }
@@ -645,6 +1754,92 @@ impl FontFallbackChain {
     }
 }
 
+/// A Unicode script that can be used to select a fallback font, see
+/// [`FontFallbackChain::with_fallback_for_scripts`][].
+///
+/// Only the most common scripts are supported.
+///
+/// [`FontFallbackChain::with_fallback_for_scripts`]: struct.FontFallbackChain.html#method.with_fallback_for_scripts
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Script {
+    /// The Latin script.
+    Latin,
+    /// The Greek script.
+    Greek,
+    /// The Cyrillic script.
+    Cyrillic,
+    /// The Armenian script.
+    Armenian,
+    /// The Hebrew script.
+    Hebrew,
+    /// The Arabic script.
+    Arabic,
+    /// The Devanagari script.
+    Devanagari,
+    /// The Thai script.
+    Thai,
+    /// The Hangul script (Korean).
+    Hangul,
+    /// The Hiragana script (Japanese).
+    Hiragana,
+    /// The Katakana script (Japanese).
+    Katakana,
+    /// The Han script (Chinese characters, Japanese kanji and Korean hanja).
+    Han,
+}
+
+impl Script {
+    /// Returns the script of the given character.
+    ///
+    /// Returns `None` if the character is not part of a supported script or if it is shared by
+    /// multiple scripts, for example digits, punctuation and emoji.
+    pub fn of(c: char) -> Option<Script> {
+        let script = match c {
+            'A'..='Z' | 'a'..='z' | '\u{aa}' | '\u{ba}' => Script::Latin,
+            '\u{c0}'..='\u{24f}' if c != '\u{d7}' && c != '\u{f7}' => Script::Latin,
+            '\u{1e00}'..='\u{1eff}'
+            | '\u{2c60}'..='\u{2c7f}'
+            | '\u{a720}'..='\u{a7ff}'
+            | '\u{ff21}'..='\u{ff3a}'
+            | '\u{ff41}'..='\u{ff5a}' => Script::Latin,
+            '\u{370}'..='\u{3ff}' | '\u{1f00}'..='\u{1fff}' => Script::Greek,
+            '\u{400}'..='\u{52f}'
+            | '\u{1c80}'..='\u{1c8f}'
+            | '\u{2de0}'..='\u{2dff}'
+            | '\u{a640}'..='\u{a69f}' => Script::Cyrillic,
+            '\u{530}'..='\u{58f}' => Script::Armenian,
+            '\u{590}'..='\u{5ff}' | '\u{fb1d}'..='\u{fb4f}' => Script::Hebrew,
+            '\u{600}'..='\u{6ff}'
+            | '\u{750}'..='\u{77f}'
+            | '\u{8a0}'..='\u{8ff}'
+            | '\u{fb50}'..='\u{fdff}'
+            | '\u{fe70}'..='\u{feff}' => Script::Arabic,
+            '\u{900}'..='\u{97f}' | '\u{a8e0}'..='\u{a8ff}' => Script::Devanagari,
+            '\u{e00}'..='\u{e7f}' => Script::Thai,
+            '\u{1100}'..='\u{11ff}'
+            | '\u{3130}'..='\u{318f}'
+            | '\u{a960}'..='\u{a97f}'
+            | '\u{ac00}'..='\u{d7ff}' => Script::Hangul,
+            '\u{3040}'..='\u{309f}' => Script::Hiragana,
+            '\u{30a0}'..='\u{30ff}' | '\u{31f0}'..='\u{31ff}' | '\u{ff66}'..='\u{ff9f}' => {
+                Script::Katakana
+            }
+            '\u{2e80}'..='\u{2fdf}'
+            | '\u{3005}'
+            | '\u{3007}'
+            | '\u{3021}'..='\u{3029}'
+            | '\u{3038}'..='\u{303b}'
+            | '\u{3400}'..='\u{4dbf}'
+            | '\u{4e00}'..='\u{9fff}'
+            | '\u{f900}'..='\u{faff}'
+            | '\u{20000}'..='\u{3ffff}' => Script::Han,
+            _ => return None,
+        };
+        Some(script)
+    }
+}
+
 #[derive(Clone, Debug)]
 enum RawFontData {
     Builtin(printpdf::BuiltinFont),
@@ -717,6 +1912,35 @@ impl Builtin {
     }
 }
 
+/// The single-byte encoding used for strings printed with a built-in font.
+///
+/// Only the characters that are part of the selected encoding can be printed with a built-in
+/// font.  The encoding is set for the whole document, see
+/// [`Renderer::set_builtin_encoding`][].
+///
+/// [`Renderer::set_builtin_encoding`]: ../render/struct.Renderer.html#method.set_builtin_encoding
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum BuiltinEncoding {
+    /// The Windows-1252 encoding (`WinAnsiEncoding`).  This is the default encoding.
+    #[default]
+    WinAnsi,
+    /// The Mac OS standard encoding for Latin text (`MacRomanEncoding`).
+    MacRoman,
+    /// The Adobe standard encoding (`StandardEncoding`).
+    Standard,
+}
+
+impl BuiltinEncoding {
+    /// Returns the PDF name of this encoding.
+    pub(crate) fn pdf_name(&self) -> &'static str {
+        match self {
+            BuiltinEncoding::WinAnsi => "WinAnsiEncoding",
+            BuiltinEncoding::MacRoman => "MacRomanEncoding",
+            BuiltinEncoding::Standard => "StandardEncoding",
+        }
+    }
+}
+
 /// A collection of fonts with different styles.
 ///
 /// See the [module documentation](index.html) for details on the internals.
@@ -732,13 +1956,43 @@ pub struct FontFamily<T: Clone + fmt::Debug> {
     pub bold_italic: T,
 }
 
-impl<T: Clone + Copy + fmt::Debug + PartialEq> FontFamily<T> {
-    /// Returns the font for the given style.
-    pub fn get(&self, style: Style) -> T {
-        if style.is_bold() && style.is_italic() {
-            self.bold_italic
-        } else if style.is_bold() {
-            self.bold
+impl<T: Clone + fmt::Debug> FontFamily<T> {
+    /// Creates a new font family that uses the given font for all styles.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use genpdfi::fonts::{FontData, FontFamily};
+    /// let font = FontData::load("LiberationMono-Regular.ttf", None).unwrap();
+    /// let family = FontFamily::from_single(font);
+    /// ```
+    pub fn from_single(font: T) -> FontFamily<T> {
+        FontFamily {
+            regular: font.clone(),
+            bold: font.clone(),
+            italic: font.clone(),
+            bold_italic: font,
+        }
+    }
+
+    /// Maps the fonts of this family with the given function, keeping the style assignment.
+    pub fn map<U: Clone + fmt::Debug>(self, f: impl Fn(T) -> U) -> FontFamily<U> {
+        FontFamily {
+            regular: f(self.regular),
+            bold: f(self.bold),
+            italic: f(self.italic),
+            bold_italic: f(self.bold_italic),
+        }
+    }
+}
+
+impl<T: Clone + Copy + fmt::Debug + PartialEq> FontFamily<T> {
+    /// Returns the font for the given style.
+    pub fn get(&self, style: Style) -> T {
+        if style.is_bold() && style.is_italic() {
+            self.bold_italic
+        } else if style.is_bold() {
+            self.bold
         } else if style.is_italic() {
             self.italic
         } else {
@@ -747,6 +2001,890 @@ impl<T: Clone + Copy + fmt::Debug + PartialEq> FontFamily<T> {
     }
 }
 
+/// The distance of the underline below the baseline relative to the glyph height, used if the font
+/// does not provide underline metrics.
+const UNDERLINE_POSITION: f32 = 0.1;
+/// The underline thickness relative to the glyph height, used if the font does not provide
+/// underline metrics.
+const UNDERLINE_THICKNESS: f32 = 0.05;
+
+/// The height of the strikeout line above the baseline relative to the x-height, used if the font
+/// does not provide strikeout metrics.
+const STRIKEOUT_POSITION: f32 = 0.5;
+
+/// The characters that are compared to check whether a font is monospaced.
+const MONOSPACE_SAMPLE: &str = "iljmMW0 .";
+
+/// The advance width of all characters of the built-in Courier fonts relative to the em size.
+const COURIER_CHAR_WIDTH: f32 = 0.6;
+
+/// Returns the advance width of the given character relative to the em size for the built-in
+/// Helvetica font, based on its AFM file.
+fn helvetica_char_width(c: char) -> f32 {
+    match c {
+        ' ' => 0.278,       // space
+        '!' => 0.278,       // exclamation
+        '"' => 0.355,       // quotation
+        '#' => 0.556,       // hash
+        '$' => 0.556,       // dollar
+        '%' => 0.889,       // percent
+        '&' => 0.667,       // ampersand
+        '\'' => 0.191,      // apostrophe
+        '(' => 0.333,       // left paren
+        ')' => 0.333,       // right paren
+        '*' => 0.389,       // asterisk
+        '+' => 0.584,       // plus
+        ',' => 0.278,       // comma
+        '-' => 0.333,       // hyphen
+        '.' => 0.278,       // period
+        '/' => 0.278,       // slash
+        '0'..='9' => 0.556, // digits
+        ':' => 0.278,       // colon
+        ';' => 0.278,       // semicolon
+        '<' => 0.584,       // less than
+        '=' => 0.584,       // equals
+        '>' => 0.584,       // greater than
+        '?' => 0.556,       // question
+        '@' => 1.015,       // at sign
+        'A' => 0.667,       // A
+        'B' => 0.667,       // B
+        'C' => 0.722,       // C
+        'D' => 0.722,       // D
+        'E' => 0.667,       // E
+        'F' => 0.611,       // F
+        'G' => 0.778,       // G
+        'H' => 0.722,       // H
+        'I' => 0.278,       // I
+        'J' => 0.500,       // J
+        'K' => 0.667,       // K
+        'L' => 0.556,       // L
+        'M' => 0.833,       // M
+        'N' => 0.722,       // N
+        'O' => 0.778,       // O
+        'P' => 0.667,       // P
+        'Q' => 0.778,       // Q
+        'R' => 0.722,       // R
+        'S' => 0.667,       // S
+        'T' => 0.611,       // T
+        'U' => 0.722,       // U
+        'V' => 0.667,       // V
+        'W' => 0.944,       // W
+        'X' => 0.667,       // X
+        'Y' => 0.667,       // Y
+        'Z' => 0.611,       // Z
+        '[' => 0.278,       // left bracket
+        '\\' => 0.278,      // backslash
+        ']' => 0.278,       // right bracket
+        '^' => 0.469,       // caret
+        '_' => 0.556,       // underscore
+        '`' => 0.333,       // grave
+        'a' => 0.556,       // a
+        'b' => 0.556,       // b
+        'c' => 0.500,       // c
+        'd' => 0.556,       // d
+        'e' => 0.556,       // e
+        'f' => 0.278,       // f
+        'g' => 0.556,       // g
+        'h' => 0.556,       // h
+        'i' => 0.222,       // i
+        'j' => 0.222,       // j
+        'k' => 0.500,       // k
+        'l' => 0.222,       // l
+        'm' => 0.833,       // m
+        'n' => 0.556,       // n
+        'o' => 0.556,       // o
+        'p' => 0.556,       // p
+        'q' => 0.556,       // q
+        'r' => 0.333,       // r
+        's' => 0.500,       // s
+        't' => 0.278,       // t
+        'u' => 0.556,       // u
+        'v' => 0.500,       // v
+        'w' => 0.722,       // w
+        'x' => 0.500,       // x
+        'y' => 0.500,       // y
+        'z' => 0.500,       // z
+        '{' => 0.334,       // left brace
+        '|' => 0.260,       // pipe
+        '}' => 0.334,       // right brace
+        '~' => 0.584,       // tilde
+        _ => 0.556,         // default width for unknown characters
+    }
+}
+
+/// Returns the left side bearing of the given character relative to the em size for the built-in
+/// Helvetica font, based on the glyph bounding boxes in its AFM file.
+///
+/// Returns `None` for characters outside of the printable ASCII range.
+fn helvetica_char_left_side_bearing(c: char) -> Option<f32> {
+    let llx = match c {
+        ' ' => 0,
+        '!' => 90,
+        '"' => 70,
+        '#' => 28,
+        '$' => 32,
+        '%' => 39,
+        '&' => 44,
+        '\'' => 59,
+        '(' => 68,
+        ')' => 34,
+        '*' => 39,
+        '+' => 39,
+        ',' => 87,
+        '-' => 44,
+        '.' => 87,
+        '/' => -17,
+        '0' => 37,
+        '1' => 101,
+        '2' => 26,
+        '3' => 34,
+        '4' => 25,
+        '5' => 32,
+        '6' => 38,
+        '7' => 37,
+        '8' => 38,
+        '9' => 42,
+        ':' => 87,
+        ';' => 87,
+        '<' => 48,
+        '=' => 39,
+        '>' => 48,
+        '?' => 56,
+        '@' => 147,
+        'A' => 14,
+        'B' => 74,
+        'C' => 44,
+        'D' => 81,
+        'E' => 86,
+        'F' => 86,
+        'G' => 48,
+        'H' => 77,
+        'I' => 91,
+        'J' => 17,
+        'K' => 76,
+        'L' => 76,
+        'M' => 73,
+        'N' => 76,
+        'O' => 39,
+        'P' => 86,
+        'Q' => 39,
+        'R' => 88,
+        'S' => 49,
+        'T' => 14,
+        'U' => 79,
+        'V' => 20,
+        'W' => 16,
+        'X' => 19,
+        'Y' => 14,
+        'Z' => 23,
+        '[' => 63,
+        '\\' => -17,
+        ']' => 28,
+        '^' => -14,
+        '_' => 0,
+        '`' => 14,
+        'a' => 36,
+        'b' => 58,
+        'c' => 30,
+        'd' => 35,
+        'e' => 40,
+        'f' => 14,
+        'g' => 40,
+        'h' => 65,
+        'i' => 67,
+        'j' => -16,
+        'k' => 67,
+        'l' => 67,
+        'm' => 65,
+        'n' => 65,
+        'o' => 35,
+        'p' => 58,
+        'q' => 35,
+        'r' => 77,
+        's' => 32,
+        't' => 14,
+        'u' => 68,
+        'v' => 8,
+        'w' => 14,
+        'x' => 11,
+        'y' => 11,
+        'z' => 31,
+        '{' => 42,
+        '|' => 94,
+        '}' => 42,
+        '~' => 61,
+        _ => return None,
+    };
+    Some(llx as f32 / 1000.0)
+}
+
+/// Returns the advance width of the given character relative to the em size for the built-in
+/// Helvetica-Bold font, based on its AFM file.
+fn helvetica_bold_char_width(c: char) -> f32 {
+    match c {
+        ' ' => 0.278,       // space
+        '!' => 0.333,       // exclamation
+        '"' => 0.474,       // quotation
+        '#' => 0.556,       // hash
+        '$' => 0.556,       // dollar
+        '%' => 0.889,       // percent
+        '&' => 0.722,       // ampersand
+        '\'' => 0.238,      // apostrophe
+        '(' => 0.333,       // left paren
+        ')' => 0.333,       // right paren
+        '*' => 0.389,       // asterisk
+        '+' => 0.584,       // plus
+        ',' => 0.278,       // comma
+        '-' => 0.333,       // hyphen
+        '.' => 0.278,       // period
+        '/' => 0.278,       // slash
+        '0'..='9' => 0.556, // digits
+        ':' => 0.333,       // colon
+        ';' => 0.333,       // semicolon
+        '<' => 0.584,       // less than
+        '=' => 0.584,       // equals
+        '>' => 0.584,       // greater than
+        '?' => 0.611,       // question
+        '@' => 0.975,       // at sign
+        'A' => 0.722,       // A
+        'B' => 0.722,       // B
+        'C' => 0.722,       // C
+        'D' => 0.722,       // D
+        'E' => 0.667,       // E
+        'F' => 0.611,       // F
+        'G' => 0.778,       // G
+        'H' => 0.722,       // H
+        'I' => 0.278,       // I
+        'J' => 0.556,       // J
+        'K' => 0.722,       // K
+        'L' => 0.611,       // L
+        'M' => 0.833,       // M
+        'N' => 0.722,       // N
+        'O' => 0.778,       // O
+        'P' => 0.667,       // P
+        'Q' => 0.778,       // Q
+        'R' => 0.722,       // R
+        'S' => 0.667,       // S
+        'T' => 0.611,       // T
+        'U' => 0.722,       // U
+        'V' => 0.667,       // V
+        'W' => 0.944,       // W
+        'X' => 0.667,       // X
+        'Y' => 0.667,       // Y
+        'Z' => 0.611,       // Z
+        '[' => 0.333,       // left bracket
+        '\\' => 0.278,      // backslash
+        ']' => 0.333,       // right bracket
+        '^' => 0.584,       // caret
+        '_' => 0.556,       // underscore
+        '`' => 0.333,       // grave
+        'a' => 0.556,       // a
+        'b' => 0.611,       // b
+        'c' => 0.556,       // c
+        'd' => 0.611,       // d
+        'e' => 0.556,       // e
+        'f' => 0.333,       // f
+        'g' => 0.611,       // g
+        'h' => 0.611,       // h
+        'i' => 0.278,       // i
+        'j' => 0.278,       // j
+        'k' => 0.556,       // k
+        'l' => 0.278,       // l
+        'm' => 0.889,       // m
+        'n' => 0.611,       // n
+        'o' => 0.611,       // o
+        'p' => 0.611,       // p
+        'q' => 0.611,       // q
+        'r' => 0.389,       // r
+        's' => 0.556,       // s
+        't' => 0.333,       // t
+        'u' => 0.611,       // u
+        'v' => 0.556,       // v
+        'w' => 0.778,       // w
+        'x' => 0.556,       // x
+        'y' => 0.556,       // y
+        'z' => 0.500,       // z
+        '{' => 0.389,       // left brace
+        '|' => 0.280,       // pipe
+        '}' => 0.389,       // right brace
+        '~' => 0.584,       // tilde
+        _ => 0.556,         // default width for unknown characters
+    }
+}
+
+/// Returns the advance width of the given character relative to the em size for the built-in
+/// Times-Roman font, based on its AFM file.
+fn times_char_width(c: char) -> f32 {
+    match c {
+        ' ' => 0.250,       // space
+        '!' => 0.333,       // exclamation
+        '"' => 0.408,       // quotation
+        '#' => 0.500,       // hash
+        '$' => 0.500,       // dollar
+        '%' => 0.833,       // percent
+        '&' => 0.778,       // ampersand
+        '\'' => 0.180,      // apostrophe
+        '(' => 0.333,       // left paren
+        ')' => 0.333,       // right paren
+        '*' => 0.500,       // asterisk
+        '+' => 0.564,       // plus
+        ',' => 0.250,       // comma
+        '-' => 0.333,       // hyphen
+        '.' => 0.250,       // period
+        '/' => 0.278,       // slash
+        '0'..='9' => 0.500, // digits
+        ':' => 0.278,       // colon
+        ';' => 0.278,       // semicolon
+        '<' => 0.564,       // less than
+        '=' => 0.564,       // equals
+        '>' => 0.564,       // greater than
+        '?' => 0.444,       // question
+        '@' => 0.921,       // at sign
+        'A' => 0.722,       // A
+        'B' => 0.667,       // B
+        'C' => 0.667,       // C
+        'D' => 0.722,       // D
+        'E' => 0.611,       // E
+        'F' => 0.556,       // F
+        'G' => 0.722,       // G
+        'H' => 0.722,       // H
+        'I' => 0.333,       // I
+        'J' => 0.389,       // J
+        'K' => 0.722,       // K
+        'L' => 0.611,       // L
+        'M' => 0.889,       // M
+        'N' => 0.722,       // N
+        'O' => 0.722,       // O
+        'P' => 0.556,       // P
+        'Q' => 0.722,       // Q
+        'R' => 0.667,       // R
+        'S' => 0.556,       // S
+        'T' => 0.611,       // T
+        'U' => 0.722,       // U
+        'V' => 0.722,       // V
+        'W' => 0.944,       // W
+        'X' => 0.722,       // X
+        'Y' => 0.722,       // Y
+        'Z' => 0.611,       // Z
+        '[' => 0.333,       // left bracket
+        '\\' => 0.278,      // backslash
+        ']' => 0.333,       // right bracket
+        '^' => 0.469,       // caret
+        '_' => 0.500,       // underscore
+        '`' => 0.333,       // grave
+        'a' => 0.444,       // a
+        'b' => 0.500,       // b
+        'c' => 0.444,       // c
+        'd' => 0.500,       // d
+        'e' => 0.444,       // e
+        'f' => 0.333,       // f
+        'g' => 0.500,       // g
+        'h' => 0.500,       // h
+        'i' => 0.278,       // i
+        'j' => 0.278,       // j
+        'k' => 0.500,       // k
+        'l' => 0.278,       // l
+        'm' => 0.778,       // m
+        'n' => 0.500,       // n
+        'o' => 0.500,       // o
+        'p' => 0.500,       // p
+        'q' => 0.500,       // q
+        'r' => 0.333,       // r
+        's' => 0.389,       // s
+        't' => 0.278,       // t
+        'u' => 0.500,       // u
+        'v' => 0.500,       // v
+        'w' => 0.722,       // w
+        'x' => 0.500,       // x
+        'y' => 0.500,       // y
+        'z' => 0.444,       // z
+        '{' => 0.480,       // left brace
+        '|' => 0.200,       // pipe
+        '}' => 0.480,       // right brace
+        '~' => 0.541,       // tilde
+        _ => 0.500,         // default width for unknown characters
+    }
+}
+
+/// Returns the advance width of the given character relative to the em size for the built-in
+/// Times-Bold font, based on its AFM file.
+fn times_bold_char_width(c: char) -> f32 {
+    match c {
+        ' ' => 0.250,       // space
+        '!' => 0.333,       // exclamation
+        '"' => 0.555,       // quotation
+        '#' => 0.500,       // hash
+        '$' => 0.500,       // dollar
+        '%' => 1.000,       // percent
+        '&' => 0.833,       // ampersand
+        '\'' => 0.278,      // apostrophe
+        '(' => 0.333,       // left paren
+        ')' => 0.333,       // right paren
+        '*' => 0.500,       // asterisk
+        '+' => 0.570,       // plus
+        ',' => 0.250,       // comma
+        '-' => 0.333,       // hyphen
+        '.' => 0.250,       // period
+        '/' => 0.278,       // slash
+        '0'..='9' => 0.500, // digits
+        ':' => 0.333,       // colon
+        ';' => 0.333,       // semicolon
+        '<' => 0.570,       // less than
+        '=' => 0.570,       // equals
+        '>' => 0.570,       // greater than
+        '?' => 0.500,       // question
+        '@' => 0.930,       // at sign
+        'A' => 0.722,       // A
+        'B' => 0.667,       // B
+        'C' => 0.722,       // C
+        'D' => 0.722,       // D
+        'E' => 0.667,       // E
+        'F' => 0.611,       // F
+        'G' => 0.778,       // G
+        'H' => 0.778,       // H
+        'I' => 0.389,       // I
+        'J' => 0.500,       // J
+        'K' => 0.778,       // K
+        'L' => 0.667,       // L
+        'M' => 0.944,       // M
+        'N' => 0.722,       // N
+        'O' => 0.778,       // O
+        'P' => 0.611,       // P
+        'Q' => 0.778,       // Q
+        'R' => 0.722,       // R
+        'S' => 0.556,       // S
+        'T' => 0.667,       // T
+        'U' => 0.722,       // U
+        'V' => 0.722,       // V
+        'W' => 1.000,       // W
+        'X' => 0.722,       // X
+        'Y' => 0.722,       // Y
+        'Z' => 0.667,       // Z
+        '[' => 0.333,       // left bracket
+        '\\' => 0.278,      // backslash
+        ']' => 0.333,       // right bracket
+        '^' => 0.581,       // caret
+        '_' => 0.500,       // underscore
+        '`' => 0.333,       // grave
+        'a' => 0.500,       // a
+        'b' => 0.556,       // b
+        'c' => 0.444,       // c
+        'd' => 0.556,       // d
+        'e' => 0.444,       // e
+        'f' => 0.333,       // f
+        'g' => 0.500,       // g
+        'h' => 0.556,       // h
+        'i' => 0.278,       // i
+        'j' => 0.333,       // j
+        'k' => 0.556,       // k
+        'l' => 0.278,       // l
+        'm' => 0.833,       // m
+        'n' => 0.556,       // n
+        'o' => 0.500,       // o
+        'p' => 0.556,       // p
+        'q' => 0.556,       // q
+        'r' => 0.444,       // r
+        's' => 0.389,       // s
+        't' => 0.333,       // t
+        'u' => 0.556,       // u
+        'v' => 0.500,       // v
+        'w' => 0.722,       // w
+        'x' => 0.500,       // x
+        'y' => 0.500,       // y
+        'z' => 0.444,       // z
+        '{' => 0.394,       // left brace
+        '|' => 0.220,       // pipe
+        '}' => 0.394,       // right brace
+        '~' => 0.520,       // tilde
+        _ => 0.500,         // default width for unknown characters
+    }
+}
+
+/// Returns the advance width of the given character relative to the em size for the built-in
+/// Times-Italic font, based on its AFM file.
+fn times_italic_char_width(c: char) -> f32 {
+    match c {
+        ' ' => 0.250,       // space
+        '!' => 0.333,       // exclamation
+        '"' => 0.420,       // quotation
+        '#' => 0.500,       // hash
+        '$' => 0.500,       // dollar
+        '%' => 0.833,       // percent
+        '&' => 0.778,       // ampersand
+        '\'' => 0.214,      // apostrophe
+        '(' => 0.333,       // left paren
+        ')' => 0.333,       // right paren
+        '*' => 0.500,       // asterisk
+        '+' => 0.675,       // plus
+        ',' => 0.250,       // comma
+        '-' => 0.333,       // hyphen
+        '.' => 0.250,       // period
+        '/' => 0.278,       // slash
+        '0'..='9' => 0.500, // digits
+        ':' => 0.333,       // colon
+        ';' => 0.333,       // semicolon
+        '<' => 0.675,       // less than
+        '=' => 0.675,       // equals
+        '>' => 0.675,       // greater than
+        '?' => 0.500,       // question
+        '@' => 0.920,       // at sign
+        'A' => 0.611,       // A
+        'B' => 0.611,       // B
+        'C' => 0.667,       // C
+        'D' => 0.722,       // D
+        'E' => 0.611,       // E
+        'F' => 0.611,       // F
+        'G' => 0.722,       // G
+        'H' => 0.722,       // H
+        'I' => 0.333,       // I
+        'J' => 0.444,       // J
+        'K' => 0.667,       // K
+        'L' => 0.556,       // L
+        'M' => 0.833,       // M
+        'N' => 0.667,       // N
+        'O' => 0.722,       // O
+        'P' => 0.611,       // P
+        'Q' => 0.722,       // Q
+        'R' => 0.611,       // R
+        'S' => 0.500,       // S
+        'T' => 0.556,       // T
+        'U' => 0.722,       // U
+        'V' => 0.611,       // V
+        'W' => 0.833,       // W
+        'X' => 0.611,       // X
+        'Y' => 0.556,       // Y
+        'Z' => 0.556,       // Z
+        '[' => 0.389,       // left bracket
+        '\\' => 0.278,      // backslash
+        ']' => 0.389,       // right bracket
+        '^' => 0.422,       // caret
+        '_' => 0.500,       // underscore
+        '`' => 0.333,       // grave
+        'a' => 0.500,       // a
+        'b' => 0.500,       // b
+        'c' => 0.444,       // c
+        'd' => 0.500,       // d
+        'e' => 0.444,       // e
+        'f' => 0.278,       // f
+        'g' => 0.500,       // g
+        'h' => 0.500,       // h
+        'i' => 0.278,       // i
+        'j' => 0.278,       // j
+        'k' => 0.444,       // k
+        'l' => 0.278,       // l
+        'm' => 0.722,       // m
+        'n' => 0.500,       // n
+        'o' => 0.500,       // o
+        'p' => 0.500,       // p
+        'q' => 0.500,       // q
+        'r' => 0.389,       // r
+        's' => 0.389,       // s
+        't' => 0.278,       // t
+        'u' => 0.500,       // u
+        'v' => 0.444,       // v
+        'w' => 0.667,       // w
+        'x' => 0.444,       // x
+        'y' => 0.444,       // y
+        'z' => 0.389,       // z
+        '{' => 0.400,       // left brace
+        '|' => 0.275,       // pipe
+        '}' => 0.400,       // right brace
+        '~' => 0.541,       // tilde
+        _ => 0.500,         // default width for unknown characters
+    }
+}
+
+/// Returns the advance width of the given character relative to the em size for the built-in
+/// Times-BoldItalic font, based on its AFM file.
+fn times_bold_italic_char_width(c: char) -> f32 {
+    match c {
+        ' ' => 0.250,       // space
+        '!' => 0.389,       // exclamation
+        '"' => 0.555,       // quotation
+        '#' => 0.500,       // hash
+        '$' => 0.500,       // dollar
+        '%' => 0.833,       // percent
+        '&' => 0.778,       // ampersand
+        '\'' => 0.278,      // apostrophe
+        '(' => 0.333,       // left paren
+        ')' => 0.333,       // right paren
+        '*' => 0.500,       // asterisk
+        '+' => 0.570,       // plus
+        ',' => 0.250,       // comma
+        '-' => 0.333,       // hyphen
+        '.' => 0.250,       // period
+        '/' => 0.278,       // slash
+        '0'..='9' => 0.500, // digits
+        ':' => 0.333,       // colon
+        ';' => 0.333,       // semicolon
+        '<' => 0.570,       // less than
+        '=' => 0.570,       // equals
+        '>' => 0.570,       // greater than
+        '?' => 0.500,       // question
+        '@' => 0.832,       // at sign
+        'A' => 0.667,       // A
+        'B' => 0.667,       // B
+        'C' => 0.667,       // C
+        'D' => 0.722,       // D
+        'E' => 0.667,       // E
+        'F' => 0.667,       // F
+        'G' => 0.722,       // G
+        'H' => 0.778,       // H
+        'I' => 0.389,       // I
+        'J' => 0.500,       // J
+        'K' => 0.667,       // K
+        'L' => 0.611,       // L
+        'M' => 0.889,       // M
+        'N' => 0.722,       // N
+        'O' => 0.722,       // O
+        'P' => 0.611,       // P
+        'Q' => 0.722,       // Q
+        'R' => 0.667,       // R
+        'S' => 0.556,       // S
+        'T' => 0.611,       // T
+        'U' => 0.722,       // U
+        'V' => 0.667,       // V
+        'W' => 0.889,       // W
+        'X' => 0.667,       // X
+        'Y' => 0.611,       // Y
+        'Z' => 0.611,       // Z
+        '[' => 0.333,       // left bracket
+        '\\' => 0.278,      // backslash
+        ']' => 0.333,       // right bracket
+        '^' => 0.570,       // caret
+        '_' => 0.500,       // underscore
+        '`' => 0.333,       // grave
+        'a' => 0.500,       // a
+        'b' => 0.500,       // b
+        'c' => 0.444,       // c
+        'd' => 0.500,       // d
+        'e' => 0.444,       // e
+        'f' => 0.333,       // f
+        'g' => 0.500,       // g
+        'h' => 0.556,       // h
+        'i' => 0.278,       // i
+        'j' => 0.278,       // j
+        'k' => 0.500,       // k
+        'l' => 0.278,       // l
+        'm' => 0.778,       // m
+        'n' => 0.556,       // n
+        'o' => 0.500,       // o
+        'p' => 0.500,       // p
+        'q' => 0.500,       // q
+        'r' => 0.389,       // r
+        's' => 0.389,       // s
+        't' => 0.278,       // t
+        'u' => 0.556,       // u
+        'v' => 0.444,       // v
+        'w' => 0.667,       // w
+        'x' => 0.500,       // x
+        'y' => 0.444,       // y
+        'z' => 0.389,       // z
+        '{' => 0.348,       // left brace
+        '|' => 0.220,       // pipe
+        '}' => 0.348,       // right brace
+        '~' => 0.570,       // tilde
+        _ => 0.500,         // default width for unknown characters
+    }
+}
+
+/// Returns the advance width of the given character relative to the em size for the built-in
+/// Symbol font, based on its AFM file.
+fn symbol_char_width(c: char) -> f32 {
+    match c {
+        ' ' => 0.250,       // space
+        '!' => 0.333,       // exclam
+        '"' => 0.713,       // universal
+        '#' => 0.500,       // numbersign
+        '$' => 0.549,       // existential
+        '%' => 0.833,       // percent
+        '&' => 0.778,       // ampersand
+        '\'' => 0.439,      // suchthat
+        '(' => 0.333,       // parenleft
+        ')' => 0.333,       // parenright
+        '*' => 0.500,       // asteriskmath
+        '+' => 0.549,       // plus
+        ',' => 0.250,       // comma
+        '-' => 0.549,       // minus
+        '.' => 0.250,       // period
+        '/' => 0.278,       // slash
+        '0'..='9' => 0.500, // digits
+        ':' => 0.278,       // colon
+        ';' => 0.278,       // semicolon
+        '<' => 0.549,       // less
+        '=' => 0.549,       // equal
+        '>' => 0.549,       // greater
+        '?' => 0.444,       // question
+        '@' => 0.549,       // congruent
+        'A' => 0.722,       // Alpha
+        'B' => 0.667,       // Beta
+        'C' => 0.722,       // Chi
+        'D' => 0.612,       // Delta
+        'E' => 0.611,       // Epsilon
+        'F' => 0.763,       // Phi
+        'G' => 0.603,       // Gamma
+        'H' => 0.722,       // Eta
+        'I' => 0.333,       // Iota
+        'J' => 0.631,       // theta1
+        'K' => 0.722,       // Kappa
+        'L' => 0.686,       // Lambda
+        'M' => 0.889,       // Mu
+        'N' => 0.722,       // Nu
+        'O' => 0.722,       // Omicron
+        'P' => 0.768,       // Pi
+        'Q' => 0.741,       // Theta
+        'R' => 0.556,       // Rho
+        'S' => 0.592,       // Sigma
+        'T' => 0.611,       // Tau
+        'U' => 0.690,       // Upsilon
+        'V' => 0.439,       // sigma1
+        'W' => 0.768,       // Omega
+        'X' => 0.645,       // Xi
+        'Y' => 0.795,       // Psi
+        'Z' => 0.611,       // Zeta
+        '[' => 0.333,       // bracketleft
+        '\\' => 0.863,      // therefore
+        ']' => 0.333,       // bracketright
+        '^' => 0.658,       // perpendicular
+        '_' => 0.500,       // underscore
+        '`' => 0.500,       // radicalex
+        'a' => 0.631,       // alpha
+        'b' => 0.549,       // beta
+        'c' => 0.549,       // chi
+        'd' => 0.494,       // delta
+        'e' => 0.439,       // epsilon
+        'f' => 0.521,       // phi
+        'g' => 0.411,       // gamma
+        'h' => 0.603,       // eta
+        'i' => 0.329,       // iota
+        'j' => 0.603,       // phi1
+        'k' => 0.549,       // kappa
+        'l' => 0.549,       // lambda
+        'm' => 0.576,       // mu
+        'n' => 0.521,       // nu
+        'o' => 0.549,       // omicron
+        'p' => 0.549,       // pi
+        'q' => 0.521,       // theta
+        'r' => 0.549,       // rho
+        's' => 0.603,       // sigma
+        't' => 0.439,       // tau
+        'u' => 0.576,       // upsilon
+        'v' => 0.713,       // omega1
+        'w' => 0.686,       // omega
+        'x' => 0.493,       // xi
+        'y' => 0.686,       // psi
+        'z' => 0.494,       // zeta
+        '{' => 0.480,       // braceleft
+        '|' => 0.200,       // bar
+        '}' => 0.480,       // braceright
+        '~' => 0.549,       // similar
+        _ => 0.500,         // default width for unknown characters
+    }
+}
+
+/// Returns the advance width of the given character relative to the em size for the built-in
+/// ZapfDingbats font, based on its AFM file.
+fn zapf_dingbats_char_width(c: char) -> f32 {
+    match c {
+        ' ' => 0.278,  // space
+        '!' => 0.974,  // a1
+        '"' => 0.961,  // a2
+        '#' => 0.974,  // a202
+        '$' => 0.980,  // a3
+        '%' => 0.719,  // a4
+        '&' => 0.789,  // a5
+        '\'' => 0.790, // a119
+        '(' => 0.791,  // a118
+        ')' => 0.690,  // a117
+        '*' => 0.960,  // a11
+        '+' => 0.939,  // a12
+        ',' => 0.549,  // a13
+        '-' => 0.855,  // a14
+        '.' => 0.911,  // a15
+        '/' => 0.933,  // a16
+        '0' => 0.911,  // a105
+        '1' => 0.945,  // a17
+        '2' => 0.974,  // a18
+        '3' => 0.755,  // a19
+        '4' => 0.846,  // a20
+        '5' => 0.762,  // a21
+        '6' => 0.761,  // a22
+        '7' => 0.571,  // a23
+        '8' => 0.677,  // a24
+        '9' => 0.763,  // a25
+        ':' => 0.760,  // a26
+        ';' => 0.759,  // a27
+        '<' => 0.754,  // a28
+        '=' => 0.494,  // a6
+        '>' => 0.552,  // a7
+        '?' => 0.537,  // a8
+        '@' => 0.577,  // a9
+        'A' => 0.692,  // a10
+        'B' => 0.786,  // a29
+        'C' => 0.788,  // a30
+        'D' => 0.788,  // a31
+        'E' => 0.790,  // a32
+        'F' => 0.793,  // a33
+        'G' => 0.794,  // a34
+        'H' => 0.816,  // a35
+        'I' => 0.823,  // a36
+        'J' => 0.789,  // a37
+        'K' => 0.841,  // a38
+        'L' => 0.823,  // a39
+        'M' => 0.833,  // a40
+        'N' => 0.816,  // a41
+        'O' => 0.831,  // a42
+        'P' => 0.923,  // a43
+        'Q' => 0.744,  // a44
+        'R' => 0.723,  // a45
+        'S' => 0.749,  // a46
+        'T' => 0.790,  // a47
+        'U' => 0.792,  // a48
+        'V' => 0.695,  // a49
+        'W' => 0.776,  // a50
+        'X' => 0.768,  // a51
+        'Y' => 0.792,  // a52
+        'Z' => 0.759,  // a53
+        '[' => 0.707,  // a54
+        '\\' => 0.708, // a55
+        ']' => 0.682,  // a56
+        '^' => 0.701,  // a57
+        '_' => 0.826,  // a58
+        '`' => 0.815,  // a59
+        'a' => 0.789,  // a60
+        'b' => 0.789,  // a61
+        'c' => 0.707,  // a62
+        'd' => 0.687,  // a63
+        'e' => 0.696,  // a64
+        'f' => 0.689,  // a65
+        'g' => 0.786,  // a66
+        'h' => 0.787,  // a67
+        'i' => 0.713,  // a68
+        'j' => 0.791,  // a69
+        'k' => 0.785,  // a70
+        'l' => 0.791,  // a71
+        'm' => 0.873,  // a72
+        'n' => 0.761,  // a73
+        'o' => 0.762,  // a74
+        'p' => 0.762,  // a203
+        'q' => 0.759,  // a75
+        'r' => 0.759,  // a204
+        's' => 0.892,  // a76
+        't' => 0.892,  // a77
+        'u' => 0.788,  // a78
+        'v' => 0.784,  // a79
+        'w' => 0.438,  // a81
+        'x' => 0.138,  // a82
+        'y' => 0.277,  // a83
+        'z' => 0.415,  // a84
+        '{' => 0.392,  // a97
+        '|' => 0.392,  // a98
+        '}' => 0.668,  // a99
+        '~' => 0.668,  // a100
+        _ => 0.788,    // default width for unknown characters
+    }
+}
+
 /// A reference to a font cached by a [`FontCache`][].
 ///
 /// See the [module documentation](index.html) for details on the internals.
@@ -755,16 +2893,27 @@ impl<T: Clone + Copy + fmt::Debug + PartialEq> FontFamily<T> {
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Font {
     idx: usize,
-    is_builtin: bool,
+    builtin: Option<printpdf::BuiltinFont>,
     scale: rusttype::Scale,
     line_height: Mm,
     glyph_height: Mm,
     ascent: Mm,
     descent: Mm,
+    underline_position: Mm,
+    underline_thickness: Mm,
+    strikeout_position: Mm,
+    strikeout_thickness: Mm,
+    x_height: Mm,
+    cap_height: Mm,
 }
 
 impl Font {
-    fn new(idx: usize, is_builtin: bool, rt_font: &rusttype::Font<'static>) -> Font {
+    fn new(idx: usize, font_data: &FontData) -> Font {
+        let builtin = match &font_data.raw_data {
+            RawFontData::Builtin(builtin) => Some(*builtin),
+            RawFontData::Embedded(_) => None,
+        };
+        let rt_font = &font_data.rt_font;
         let units_per_em = rt_font.units_per_em();
         assert!(units_per_em != 0);
 
@@ -776,40 +2925,123 @@ impl Font {
         let ascent = v_metrics.ascent / units_per_em;
         let descent = v_metrics.descent / units_per_em;
         let line_height = glyph_height + v_metrics.line_gap / units_per_em;
+        let (x_height, cap_height) = font_data.x_and_cap_height;
+
+        // The PDF viewer renders built-in fonts with its own metrics, so we don't rely on the
+        // metrics of the font data for the underline.
+        let (underline_position, underline_thickness) = match font_data.underline_metrics {
+            Some(metrics) if builtin.is_none() => metrics,
+            _ => (
+                glyph_height * -UNDERLINE_POSITION,
+                glyph_height * UNDERLINE_THICKNESS,
+            ),
+        };
+        let (strikeout_position, strikeout_thickness) = match font_data.strikeout_metrics {
+            Some(metrics) if builtin.is_none() => metrics,
+            _ => {
+                // If the x-height is unknown, it is approximated with half the ascent.
+                let x_height = if x_height > 0.0 {
+                    x_height
+                } else {
+                    ascent * 0.5
+                };
+                let thickness = glyph_height * UNDERLINE_THICKNESS;
+                (x_height * STRIKEOUT_POSITION + thickness / 2.0, thickness)
+            }
+        };
 
         Font {
             idx,
-            is_builtin,
+            builtin,
             scale,
-            line_height: printpdf::Pt(f32::from(line_height)).into(),
-            glyph_height: printpdf::Pt(f32::from(glyph_height)).into(),
-            ascent: printpdf::Pt(f32::from(ascent)).into(),
-            descent: printpdf::Pt(f32::from(descent)).into(),
+            line_height: printpdf::Pt(line_height).into(),
+            glyph_height: printpdf::Pt(glyph_height).into(),
+            ascent: printpdf::Pt(ascent).into(),
+            descent: printpdf::Pt(descent).into(),
+            underline_position: printpdf::Pt(underline_position).into(),
+            underline_thickness: printpdf::Pt(underline_thickness).into(),
+            strikeout_position: printpdf::Pt(strikeout_position).into(),
+            strikeout_thickness: printpdf::Pt(strikeout_thickness).into(),
+            x_height: printpdf::Pt(x_height).into(),
+            cap_height: printpdf::Pt(cap_height).into(),
         }
     }
     /// Returns whether this font is a built-in PDF font.
     pub fn is_builtin(&self) -> bool {
-        self.is_builtin
+        self.builtin.is_some()
     }
 
     /// Returns the line height for text with this font and the given font size.
-    pub fn get_line_height(&self, font_size: u8) -> Mm {
-        self.line_height * f32::from(font_size)
+    pub fn get_line_height(&self, font_size: f32) -> Mm {
+        self.line_height * font_size
     }
 
     /// Returns the glyph height for text with this font and the given font size.
-    pub fn glyph_height(&self, font_size: u8) -> Mm {
-        self.glyph_height * f32::from(font_size)
+    pub fn glyph_height(&self, font_size: f32) -> Mm {
+        self.glyph_height * font_size
     }
 
     /// Returns the ascent for text with this font and the given font size.
-    pub fn ascent(&self, font_size: u8) -> Mm {
-        self.ascent * f32::from(font_size)
+    pub fn ascent(&self, font_size: f32) -> Mm {
+        self.ascent * font_size
     }
 
     /// Returns the descent for text with this font and the given font size.
-    pub fn descent(&self, font_size: u8) -> Mm {
-        self.descent * f32::from(font_size)
+    pub fn descent(&self, font_size: f32) -> Mm {
+        self.descent * font_size
+    }
+
+    /// Returns the x-height, i. e. the height of lowercase letters like `x` above the baseline,
+    /// for text with this font and the given font size.
+    ///
+    /// The value is read from the `OS/2` table of the font data or, if it is not set there,
+    /// calculated from the bounding box of the `x` glyph.
+    pub fn x_height(&self, font_size: f32) -> Mm {
+        self.x_height * font_size
+    }
+
+    /// Returns the cap height, i. e. the height of flat uppercase letters like `H` above the
+    /// baseline, for text with this font and the given font size.
+    ///
+    /// The value is read from the `OS/2` table of the font data or, if it is not set there,
+    /// calculated from the bounding box of the `H` glyph.
+    pub fn cap_height(&self, font_size: f32) -> Mm {
+        self.cap_height * font_size
+    }
+
+    /// Returns the offset of the top of the underline from the baseline for text with this font
+    /// and the given font size.  Negative values are below the baseline.
+    ///
+    /// The value is read from the `post` table of the font data.  For built-in fonts and fonts
+    /// that do not provide underline metrics, a tenth of the glyph height below the baseline is
+    /// used.
+    pub fn underline_position(&self, font_size: f32) -> Mm {
+        self.underline_position * font_size
+    }
+
+    /// Returns the underline thickness for text with this font and the given font size.
+    ///
+    /// The value is read from the `post` table of the font data.  For built-in fonts and fonts
+    /// that do not provide underline metrics, a twentieth of the glyph height is used.
+    pub fn underline_thickness(&self, font_size: f32) -> Mm {
+        self.underline_thickness * font_size
+    }
+
+    /// Returns the offset of the top of the strikeout line from the baseline for text with this
+    /// font and the given font size.
+    ///
+    /// The value is read from the `OS/2` table of the font data.  For built-in fonts and fonts
+    /// that do not provide strikeout metrics, the line is centered at half the x-height.
+    pub fn strikeout_position(&self, font_size: f32) -> Mm {
+        self.strikeout_position * font_size
+    }
+
+    /// Returns the strikeout line thickness for text with this font and the given font size.
+    ///
+    /// The value is read from the `OS/2` table of the font data.  For built-in fonts and fonts
+    /// that do not provide strikeout metrics, the fallback underline thickness is used.
+    pub fn strikeout_thickness(&self, font_size: f32) -> Mm {
+        self.strikeout_thickness * font_size
     }
 
     /// Returns the width of a character with this font and the given font size.
@@ -817,11 +3049,9 @@ impl Font {
     /// The given [`FontCache`][] must be the font cache that loaded this font.
     ///
     /// [`FontCache`]: struct.FontCache.html
-    pub fn char_width(&self, font_cache: &FontCache, c: char, font_size: u8) -> Mm {
+    pub fn char_width(&self, font_cache: &FontCache, c: char, font_size: f32) -> Mm {
         let advance_width = self.char_h_metrics(font_cache, c).advance_width;
-        Mm::from(printpdf::Pt(f32::from(
-            advance_width * f32::from(font_size),
-        )))
+        Mm::from(printpdf::Pt(advance_width * font_size))
     }
 
     /// Returns the width of the empty space between the origin of the glyph bounding
@@ -830,17 +3060,47 @@ impl Font {
     /// The given [`FontCache`][] must be the font cache that loaded this font.
     ///
     /// [`FontCache`]: struct.FontCache.html
-    pub fn char_left_side_bearing(&self, font_cache: &FontCache, c: char, font_size: u8) -> Mm {
+    pub fn char_left_side_bearing(&self, font_cache: &FontCache, c: char, font_size: f32) -> Mm {
         let left_side_bearing = self.char_h_metrics(font_cache, c).left_side_bearing;
-        Mm::from(printpdf::Pt(f32::from(
-            left_side_bearing * f32::from(font_size),
-        )))
+        Mm::from(printpdf::Pt(left_side_bearing * font_size))
+    }
+
+    /// Returns the bounding box of the outline of a character with this font and the given font
+    /// size, or `None` if the glyph has no outline, for example for a space.
+    ///
+    /// The bounding box is returned as `(x_min, y_min, x_max, y_max)`, relative to the origin of
+    /// the glyph on the baseline, with the y axis pointing upwards.  In contrast to the advance
+    /// width, the bounding box only contains the area that is actually covered by the glyph.
+    ///
+    /// The given [`FontCache`][] must be the font cache that loaded this font.
+    ///
+    /// [`FontCache`]: struct.FontCache.html
+    pub fn glyph_bounding_box(
+        &self,
+        font_cache: &FontCache,
+        c: char,
+        font_size: f32,
+    ) -> Option<(Mm, Mm, Mm, Mm)> {
+        let rect = font_cache
+            .get_rt_font(*self)
+            .glyph(c)
+            .scaled(self.scale)
+            .exact_bounding_box()
+            .filter(|rect| rect.width() > 0.0 && rect.height() > 0.0)?;
+        let scale = |value: f32| Mm::from(printpdf::Pt(value * font_size));
+        // rusttype uses a y axis that points downwards
+        Some((
+            scale(rect.min.x),
+            scale(-rect.max.y),
+            scale(rect.max.x),
+            scale(-rect.min.y),
+        ))
     }
 
     fn char_h_metrics(&self, font_cache: &FontCache, c: char) -> rusttype::HMetrics {
         // If this is a built-in font, use standardized metrics instead of system font metrics
-        if self.is_builtin {
-            self.builtin_char_h_metrics(c)
+        if self.is_builtin() {
+            self.builtin_char_h_metrics(font_cache, c)
         } else {
             font_cache
                 .get_rt_font(*self)
@@ -851,116 +3111,95 @@ impl Font {
     }
 
     /// Returns standardized character metrics for built-in PDF fonts.
-    /// These values are based on the Adobe Font Metrics (AFM) for standard PDF fonts.
-    fn builtin_char_h_metrics(&self, c: char) -> rusttype::HMetrics {
-        let advance_width = match c {
-            // Standard character widths for Helvetica (in 1000ths of em)
-            ' ' => 0.278,       // space
-            '!' => 0.278,       // exclamation
-            '"' => 0.355,       // quotation
-            '#' => 0.556,       // hash
-            '$' => 0.556,       // dollar
-            '%' => 0.889,       // percent
-            '&' => 0.667,       // ampersand
-            '\'' => 0.191,      // apostrophe
-            '(' => 0.333,       // left paren
-            ')' => 0.333,       // right paren
-            '*' => 0.389,       // asterisk
-            '+' => 0.584,       // plus
-            ',' => 0.278,       // comma
-            '-' => 0.333,       // hyphen
-            '.' => 0.278,       // period
-            '/' => 0.278,       // slash
-            '0'..='9' => 0.556, // digits
-            ':' => 0.278,       // colon
-            ';' => 0.278,       // semicolon
-            '<' => 0.584,       // less than
-            '=' => 0.584,       // equals
-            '>' => 0.584,       // greater than
-            '?' => 0.556,       // question
-            '@' => 1.015,       // at sign
-            'A' => 0.667,       // A
-            'B' => 0.667,       // B
-            'C' => 0.722,       // C
-            'D' => 0.722,       // D
-            'E' => 0.667,       // E
-            'F' => 0.611,       // F
-            'G' => 0.778,       // G
-            'H' => 0.722,       // H
-            'I' => 0.278,       // I
-            'J' => 0.500,       // J
-            'K' => 0.667,       // K
-            'L' => 0.556,       // L
-            'M' => 0.833,       // M
-            'N' => 0.722,       // N
-            'O' => 0.778,       // O
-            'P' => 0.667,       // P
-            'Q' => 0.778,       // Q
-            'R' => 0.722,       // R
-            'S' => 0.667,       // S
-            'T' => 0.611,       // T
-            'U' => 0.722,       // U
-            'V' => 0.667,       // V
-            'W' => 0.944,       // W
-            'X' => 0.667,       // X
-            'Y' => 0.667,       // Y
-            'Z' => 0.611,       // Z
-            '[' => 0.278,       // left bracket
-            '\\' => 0.278,      // backslash
-            ']' => 0.278,       // right bracket
-            '^' => 0.469,       // caret
-            '_' => 0.556,       // underscore
-            '`' => 0.333,       // grave
-            'a' => 0.556,       // a
-            'b' => 0.556,       // b
-            'c' => 0.500,       // c
-            'd' => 0.556,       // d
-            'e' => 0.556,       // e
-            'f' => 0.278,       // f
-            'g' => 0.556,       // g
-            'h' => 0.556,       // h
-            'i' => 0.222,       // i
-            'j' => 0.222,       // j
-            'k' => 0.500,       // k
-            'l' => 0.222,       // l
-            'm' => 0.833,       // m
-            'n' => 0.556,       // n
-            'o' => 0.556,       // o
-            'p' => 0.556,       // p
-            'q' => 0.556,       // q
-            'r' => 0.333,       // r
-            's' => 0.500,       // s
-            't' => 0.278,       // t
-            'u' => 0.556,       // u
-            'v' => 0.500,       // v
-            'w' => 0.722,       // w
-            'x' => 0.500,       // x
-            'y' => 0.500,       // y
-            'z' => 0.500,       // z
-            '{' => 0.334,       // left brace
-            '|' => 0.260,       // pipe
-            '}' => 0.334,       // right brace
-            '~' => 0.584,       // tilde
-            _ => 0.556,         // default width for unknown characters
+    /// These values are based on the Adobe Font Metrics (AFM) for standard PDF fonts, unless the
+    /// metrics have been loaded from an AFM file.  The oblique Helvetica variants share the widths
+    /// of their upright counterparts, and the Symbol and ZapfDingbats widths are looked up by the
+    /// character code of the glyph.  For the printable ASCII characters of the Helvetica font,
+    /// the left side bearing is taken from the glyph bounding boxes of its AFM file.  For all
+    /// other characters and fonts, the left side bearing is taken from the font data, which
+    /// should be metrically compatible with the built-in font.
+    fn builtin_char_h_metrics(&self, font_cache: &FontCache, c: char) -> rusttype::HMetrics {
+        let afm_metrics = font_cache.fonts[self.idx]
+            .afm_metrics
+            .as_ref()
+            .and_then(|metrics| metrics.get(glyph_name(c)?))
+            .copied();
+        if let Some(metrics) = afm_metrics {
+            return metrics;
+        }
+        let advance_width = match self.builtin {
+            Some(printpdf::BuiltinFont::Courier)
+            | Some(printpdf::BuiltinFont::CourierBold)
+            | Some(printpdf::BuiltinFont::CourierOblique)
+            | Some(printpdf::BuiltinFont::CourierBoldOblique) => COURIER_CHAR_WIDTH,
+            Some(printpdf::BuiltinFont::TimesRoman) => times_char_width(c),
+            Some(printpdf::BuiltinFont::TimesBold) => times_bold_char_width(c),
+            Some(printpdf::BuiltinFont::TimesItalic) => times_italic_char_width(c),
+            Some(printpdf::BuiltinFont::TimesBoldItalic) => times_bold_italic_char_width(c),
+            Some(printpdf::BuiltinFont::HelveticaBold)
+            | Some(printpdf::BuiltinFont::HelveticaBoldOblique) => helvetica_bold_char_width(c),
+            Some(printpdf::BuiltinFont::Symbol) => symbol_char_width(c),
+            Some(printpdf::BuiltinFont::ZapfDingbats) => zapf_dingbats_char_width(c),
+            _ => helvetica_char_width(c),
+        };
+        let afm_left_side_bearing = match self.builtin {
+            Some(printpdf::BuiltinFont::Helvetica) => helvetica_char_left_side_bearing(c),
+            _ => None,
         };
+        let left_side_bearing = afm_left_side_bearing.unwrap_or_else(|| {
+            font_cache
+                .get_rt_font(*self)
+                .glyph(c)
+                .scaled(self.scale)
+                .h_metrics()
+                .left_side_bearing
+        });
 
         rusttype::HMetrics {
-            advance_width: advance_width,
-            left_side_bearing: 0.0, // Standard left side bearing for most characters
+            advance_width,
+            left_side_bearing,
         }
     }
 
     /// Returns the width of a string with this font and the given font size.
     ///
-    /// The given [`FontCache`][] must be the font cache that loaded this font.
+    /// The given [`FontCache`][] must be the font cache that loaded this font.  If its width cache
+    /// is enabled, the width is memoized, see [`FontCache::set_width_cache`][].
     ///
     /// [`FontCache`]: struct.FontCache.html
-    pub fn str_width(&self, font_cache: &FontCache, s: &str, font_size: u8) -> Mm {
-        let str_width: Mm = if self.is_builtin {
+    /// [`FontCache::set_width_cache`]: struct.FontCache.html#method.set_width_cache
+    pub fn str_width(&self, font_cache: &FontCache, s: &str, font_size: f32) -> Mm {
+        let cache = match &font_cache.width_cache {
+            Some(cache) => cache,
+            None => return self.str_width_uncached(font_cache, s, font_size),
+        };
+        let key = (self.idx, font_size.to_bits());
+        if let Some(width) = cache
+            .lock()
+            .unwrap()
+            .get(&key)
+            .and_then(|widths| widths.get(s))
+        {
+            return *width;
+        }
+        let width = self.str_width_uncached(font_cache, s, font_size);
+        cache
+            .lock()
+            .unwrap()
+            .entry(key)
+            .or_default()
+            .insert(s.to_owned(), width);
+        width
+    }
+
+    /// Returns the width of a string with this font and the given font size without using the
+    /// width cache of the font cache.
+    fn str_width_uncached(&self, font_cache: &FontCache, s: &str, font_size: f32) -> Mm {
+        let str_width: Mm = if self.is_builtin() {
             // Use standardized metrics for built-in fonts
             s.chars()
-                .map(|c| self.builtin_char_h_metrics(c).advance_width)
-                .map(|w| Mm::from(printpdf::Pt(f32::from(w * f32::from(font_size)))))
+                .map(|c| self.builtin_char_h_metrics(font_cache, c).advance_width)
+                .map(|w| Mm::from(printpdf::Pt(w * font_size)))
                 .sum()
         } else {
             // Use system font metrics for embedded fonts
@@ -968,23 +3207,25 @@ impl Font {
                 .get_rt_font(*self)
                 .glyphs_for(s.chars())
                 .map(|g| g.scaled(self.scale).h_metrics().advance_width)
-                .map(|w| Mm::from(printpdf::Pt(f32::from(w * f32::from(font_size)))))
+                .map(|w| Mm::from(printpdf::Pt(w * font_size)))
                 .sum()
         };
 
-        let kerning_width: Mm = self
-            .kerning(font_cache, s.chars())
-            .into_iter()
-            .map(|val| val * f32::from(font_size))
-            .map(|val| Mm::from(printpdf::Pt(f32::from(val))))
-            .sum();
+        let kerning_width: Mm = self.with_kerning(font_cache, s.chars(), |kerning| {
+            kerning
+                .map(|val| val * font_size)
+                .map(|val| Mm::from(printpdf::Pt(val)))
+                .sum()
+        });
         str_width + kerning_width
     }
 
     /// Returns the kerning data for the given sequence of characters.
     ///
     /// The *i*-th value of the returned data is the amount of kerning to insert before the *i*-th
-    /// character of the sequence.
+    /// character of the sequence.  The kerning cache of the font is locked once for the whole
+    /// sequence.  See [`kerning_iter`](#method.kerning_iter) for a variant that does not allocate a
+    /// vector.
     ///
     /// The given [`FontCache`][] must be the font cache that loaded this font.
     ///
@@ -992,37 +3233,102 @@ impl Font {
     pub fn kerning<I>(&self, font_cache: &FontCache, iter: I) -> Vec<f32>
     where
         I: IntoIterator<Item = char>,
+    {
+        self.with_kerning(font_cache, iter, |kerning| kerning.collect())
+    }
+
+    /// Calls the given function with an iterator over the kerning data for the given sequence of
+    /// characters while holding the lock of the kerning cache.
+    ///
+    /// The iterator yields the same values as [`kerning_iter`](#method.kerning_iter), but the
+    /// kerning cache is only locked once.
+    fn with_kerning<I, F, R>(&self, font_cache: &FontCache, iter: I, f: F) -> R
+    where
+        I: IntoIterator<Item = char>,
+        F: FnOnce(&mut dyn Iterator<Item = f32>) -> R,
+    {
+        // Built-in fonts are not kerned, see kerning_iter
+        let font = font_cache.get_rt_font(*self);
+        let scale = self.scale;
+        let mut kerning_cache = if self.is_builtin() {
+            None
+        } else {
+            Some(
+                font_cache.fonts[self.idx]
+                    .kerning_cache
+                    .lock()
+                    .expect("Font cache mutex is poisoned"),
+            )
+        };
+        let mut last = None;
+        let mut kerning = font.glyphs_for(iter.into_iter()).map(|g| {
+            let pos = match (last, kerning_cache.as_deref_mut()) {
+                (Some(last), Some(kerning_cache)) => {
+                    cached_pair_kerning(kerning_cache, font, scale, (last, g.id()))
+                }
+                _ => 0.0,
+            };
+            last = Some(g.id());
+            pos
+        });
+        f(&mut kerning)
+    }
+
+    /// Returns an iterator over the kerning data for the given sequence of characters.
+    ///
+    /// This method yields the same values as [`kerning`](#method.kerning) without collecting them
+    /// into a vector.  The kerning cache of the font is only locked while the next value is
+    /// calculated, so prefer [`kerning`](#method.kerning) if the values of a whole string are
+    /// needed at once.
+    ///
+    /// The given [`FontCache`][] must be the font cache that loaded this font.
+    ///
+    /// [`FontCache`]: struct.FontCache.html
+    pub fn kerning_iter<'a, I>(
+        &self,
+        font_cache: &'a FontCache,
+        iter: I,
+    ) -> impl Iterator<Item = f32> + 'a
+    where
+        I: IntoIterator<Item = char>,
+        I::IntoIter: 'a,
     {
         // Built-in PDF fonts already have their own (device) kerning information that the PDF
         // viewer applies automatically. Passing additional kerning adjustments – especially ones
         // derived from a *similar* but not identical system TTF – results in characters being
         // pushed apart instead of pulled together. Therefore we disable kerning completely for
-        // built-in fonts and only return actual kerning values for embedded/system fonts.
-        if self.is_builtin {
-            // Return a zero adjustment for every glyph so the caller's `positions.zip(codepoints)`
-            // iterator remains the correct length.
-            iter.into_iter().map(|_| 0.0).collect()
+        // built-in fonts and only return actual kerning values for embedded/system fonts.  We
+        // still return a zero adjustment for every glyph so the caller's
+        // `positions.zip(codepoints)` iterator remains the correct length.
+        let font = font_cache.get_rt_font(*self);
+        let scale = self.scale;
+        let kerning_cache = if self.is_builtin() {
+            None
         } else {
-            let font = font_cache.get_rt_font(*self);
-            font.glyphs_for(iter.into_iter())
-                .scan(None, |last, g| {
-                    let pos = if let Some(last) = last {
-                        Some(font.pair_kerning(self.scale, *last, g.id()))
-                    } else {
-                        Some(0.0)
-                    };
-                    *last = Some(g.id());
-                    pos
-                })
-                .collect()
-        }
+            Some(&font_cache.fonts[self.idx].kerning_cache)
+        };
+        font.glyphs_for(iter.into_iter())
+            .scan(None, move |last, g| {
+                let pos = match (*last, kerning_cache) {
+                    (Some(last), Some(kerning_cache)) => cached_pair_kerning(
+                        &mut kerning_cache.lock().expect("Font cache mutex is poisoned"),
+                        font,
+                        scale,
+                        (last, g.id()),
+                    ),
+                    _ => 0.0,
+                };
+                *last = Some(g.id());
+                Some(pos)
+            })
     }
 
     /// Returns the glyphs IDs for the given sequence of characters.
     ///
     /// For subset fonts, this returns the remapped glyph IDs that correspond
     /// to the glyphs in the subset font. For non-subset fonts, it returns
-    /// the original glyph IDs from rusttype.
+    /// the original glyph IDs from rusttype.  See [`glyph_ids_iter`](#method.glyph_ids_iter) for a
+    /// variant that does not allocate a vector.
     ///
     /// The given [`FontCache`][] must be the font cache that loaded this font.
     ///
@@ -1031,48 +3337,78 @@ impl Font {
     where
         I: IntoIterator<Item = char>,
     {
-        let font_data = &font_cache.fonts[self.idx];
-        let font = font_cache.get_rt_font(*self);
+        self.glyph_ids_iter(font_cache, iter).collect()
+    }
 
-        if let Some(ref glyph_map) = font_data.glyph_id_map {
+    /// Returns an iterator over the glyph IDs for the given sequence of characters.
+    ///
+    /// This method yields the same values as [`glyph_ids`](#method.glyph_ids) without collecting
+    /// them into a vector.
+    ///
+    /// The given [`FontCache`][] must be the font cache that loaded this font.
+    ///
+    /// [`FontCache`]: struct.FontCache.html
+    pub fn glyph_ids_iter<'a, I>(
+        &self,
+        font_cache: &'a FontCache,
+        iter: I,
+    ) -> impl Iterator<Item = u16> + 'a
+    where
+        I: IntoIterator<Item = char>,
+        I::IntoIter: 'a,
+    {
+        let glyph_map = font_cache.fonts[self.idx].glyph_id_map.as_deref();
+        let font = font_cache.get_rt_font(*self);
+        iter.into_iter().map(move |c| {
             // Use mapped glyph IDs for subset fonts
-            iter.into_iter()
-                .map(|c| {
-                    glyph_map
-                        .get(c)
-                        .unwrap_or_else(|| font.glyph(c).id().0 as u16)
-                })
-                .collect()
-        } else {
-            // Original behavior for non-subset fonts
-            font.glyphs_for(iter.into_iter())
-                .map(|g| g.id().0 as u16)
-                .collect()
-        }
+            glyph_map
+                .and_then(|glyph_map| glyph_map.get(c))
+                .unwrap_or_else(|| font.glyph(c).id().0 as u16)
+        })
     }
 
     /// Calculate the metrics of a given font size for this font.
-    pub fn metrics(&self, font_size: u8) -> Metrics {
-        Metrics::new(
-            self.line_height * f32::from(font_size),
-            self.glyph_height * f32::from(font_size),
-            self.ascent * f32::from(font_size),
-            self.descent * f32::from(font_size),
-        )
+    pub fn metrics(&self, font_size: f32) -> Metrics {
+        Metrics {
+            line_height: self.line_height * font_size,
+            glyph_height: self.glyph_height * font_size,
+            ascent: self.ascent * font_size,
+            descent: self.descent * font_size,
+            x_height: self.x_height * font_size,
+            cap_height: self.cap_height * font_size,
+        }
     }
 }
 
+/// Returns whether the given string can be encoded using the Windows-1252 encoding.
+///
+/// Built-in PDF fonts only support the Windows-1252 encoding, so printing a string that is not
+/// encodable with a built-in font fails.  This function can be used to decide whether a built-in
+/// or an embedded font should be used before rendering the string.
+pub fn is_win1252_encodable(s: &str) -> bool {
+    render::try_encode_builtin(s, BuiltinEncoding::WinAnsi).is_some()
+}
+
+/// The file name pattern used by [`from_files`](fn.from_files.html).
+const DEFAULT_FILE_PATTERN: &str = "{name}-{style}.ttf";
+
 fn from_file(
-    dir: impl AsRef<path::Path>,
+    dir: &path::Path,
     name: &str,
+    pattern: &str,
     style: FontStyle,
     builtin: Option<Builtin>,
 ) -> Result<FontData, Error> {
     let builtin = builtin.map(|b| b.style(style));
-    FontData::load(
-        &dir.as_ref().join(format!("{}-{}.ttf", name, style)),
-        builtin,
-    )
+    FontData::load(file_path(dir, name, pattern, style), builtin)
+}
+
+fn file_path(dir: &path::Path, name: &str, pattern: &str, style: FontStyle) -> path::PathBuf {
+    let file_name = pattern
+        .replace("{name}", name)
+        .replace("{style}", style.name())
+        .replace("{style_lower}", &style.name().to_lowercase());
+    dir.join(file_name)
 }
 
 /// Loads the font family at the given path with the given name.
@@ -1090,16 +3426,99 @@ pub fn from_files(
     dir: impl AsRef<path::Path>,
     name: &str,
     builtin: Option<Builtin>,
+) -> Result<FontFamily<FontData>, Error> {
+    from_files_with_pattern(dir, name, DEFAULT_FILE_PATTERN, builtin)
+}
+
+/// Loads the font family at the given path with the given name and file name pattern.
+///
+/// The file name of each font is created from the pattern by replacing these placeholders:
+/// - `{name}`:  the given name
+/// - `{style}`:  the style, i. e. `Regular`, `Bold`, `Italic` or `BoldItalic`
+/// - `{style_lower}`:  the style in lowercase, i. e. `regular`, `bold`, `italic` or `bolditalic`
+///
+/// For example, [`from_files`][] uses the pattern `{name}-{style}.ttf`.  If `builtin` is set,
+/// built-in PDF fonts are used instead of embedding the fonts in the PDF file (see the [module
+/// documentation](index.html) for more information).  In this case, the given fonts must be
+/// metrically identical to the built-in fonts.
+///
+/// # Example
+///
+/// ```no_run
+/// // Loads ./fonts/Inter_regular.otf, ./fonts/Inter_bold.otf etc.
+/// let font_family =
+///     genpdfi::fonts::from_files_with_pattern("./fonts", "Inter", "{name}_{style_lower}.otf", None)
+///         .expect("Failed to load font family");
+/// ```
+///
+/// [`from_files`]: fn.from_files.html
+pub fn from_files_with_pattern(
+    dir: impl AsRef<path::Path>,
+    name: &str,
+    pattern: &str,
+    builtin: Option<Builtin>,
 ) -> Result<FontFamily<FontData>, Error> {
     let dir = dir.as_ref();
     Ok(FontFamily {
-        regular: from_file(dir, name, FontStyle::Regular, builtin)?,
-        bold: from_file(dir, name, FontStyle::Bold, builtin)?,
-        italic: from_file(dir, name, FontStyle::Italic, builtin)?,
-        bold_italic: from_file(dir, name, FontStyle::BoldItalic, builtin)?,
+        regular: from_file(dir, name, pattern, FontStyle::Regular, builtin)?,
+        bold: from_file(dir, name, pattern, FontStyle::Bold, builtin)?,
+        italic: from_file(dir, name, pattern, FontStyle::Italic, builtin)?,
+        bold_italic: from_file(dir, name, pattern, FontStyle::BoldItalic, builtin)?,
     })
 }
 
+/// Loads the font family at the given path with the given name, substituting missing variants.
+///
+/// This method works like [`from_files`][], but only the `{name}-Regular.ttf` file is required.
+/// If the file for another style does not exist, the bold italic variant is replaced with the bold
+/// variant and all other variants are replaced with the regular variant.  Besides the font family,
+/// this method returns a family of flags that indicate which variants have been substituted.
+///
+/// # Example
+///
+/// ```no_run
+/// let (font_family, substituted) =
+///     genpdfi::fonts::from_files_lenient("./fonts", "LiberationSans", None)
+///         .expect("Failed to load font family");
+/// if substituted.italic {
+///     eprintln!("Italic text will be printed with the regular font");
+/// }
+/// ```
+///
+/// [`from_files`]: fn.from_files.html
+pub fn from_files_lenient(
+    dir: impl AsRef<path::Path>,
+    name: &str,
+    builtin: Option<Builtin>,
+) -> Result<(FontFamily<FontData>, FontFamily<bool>), Error> {
+    let dir = dir.as_ref();
+    let load = |style, fallback: &FontData| {
+        if file_path(dir, name, DEFAULT_FILE_PATTERN, style).exists() {
+            from_file(dir, name, DEFAULT_FILE_PATTERN, style, builtin).map(|font| (font, false))
+        } else {
+            Ok((fallback.clone(), true))
+        }
+    };
+    let regular = from_file(dir, name, DEFAULT_FILE_PATTERN, FontStyle::Regular, builtin)?;
+    let (bold, bold_substituted) = load(FontStyle::Bold, &regular)?;
+    let (italic, italic_substituted) = load(FontStyle::Italic, &regular)?;
+    let (bold_italic, bold_italic_substituted) = load(FontStyle::BoldItalic, &bold)?;
+    Ok((
+        FontFamily {
+            regular,
+            bold,
+            italic,
+            bold_italic,
+        },
+        FontFamily {
+            regular: false,
+            bold: bold_substituted,
+            italic: italic_substituted,
+            bold_italic: bold_italic_substituted,
+        },
+    ))
+}
+
 /// The metrics of a font at a given scale.
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct Metrics {
@@ -1111,16 +3530,24 @@ pub struct Metrics {
     pub ascent: Mm,
     /// The descent of the font at a given scale.
     pub descent: Mm,
+    /// The x-height of the font at a given scale.
+    pub x_height: Mm,
+    /// The cap height of the font at a given scale.
+    pub cap_height: Mm,
 }
 
 impl Metrics {
     /// Create a new metrics instance with the given heights.
+    ///
+    /// The x-height and the cap height are set to zero.
     pub fn new(line_height: Mm, glyph_height: Mm, ascent: Mm, descent: Mm) -> Metrics {
         Metrics {
             line_height,
             glyph_height,
             ascent,
             descent,
+            x_height: Mm(0.0),
+            cap_height: Mm(0.0),
         }
     }
 
@@ -1131,6 +3558,703 @@ impl Metrics {
             glyph_height: self.glyph_height.max(other.glyph_height),
             ascent: self.ascent.max(other.ascent),
             descent: self.descent.max(other.descent),
+            x_height: self.x_height.max(other.x_height),
+            cap_height: self.cap_height.max(other.cap_height),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        decode_woff, is_win1252_encodable, parse_afm_metrics, FontCache, FontData,
+        FontFallbackChain, FontFamily, Script,
+    };
+    use crate::style::Style;
+    use crate::Mm;
+
+    const FONT: &[u8] = include_bytes!("../tests/fonts/DejaVuSans-Regular.ttf");
+    const MONO_FONT: &[u8] = include_bytes!("../tests/fonts/DejaVuSansMono-Regular.ttf");
+    const LATIN_FONT: &[u8] = include_bytes!("../tests/fonts/DejaVuSans-Latin.ttf");
+    const COLLECTION: &[u8] = include_bytes!("../tests/fonts/DejaVuSans.ttc");
+    const WOFF_FONT: &[u8] = include_bytes!("../tests/fonts/DejaVuSans-Latin.woff");
+
+    fn font_family(data: &[u8]) -> FontFamily<FontData> {
+        let data = FontData::new(data.to_vec(), None).expect("Could not load test font");
+        FontFamily {
+            regular: data.clone(),
+            bold: data.clone(),
+            italic: data.clone(),
+            bold_italic: data,
+        }
+    }
+
+    #[test]
+    fn test_check_document_coverage() {
+        let mut font_cache = FontCache::new(font_family(LATIN_FONT));
+        let full = font_cache.add_font_family(font_family(FONT));
+        let latin = Style::new();
+        let texts = [
+            (latin, "Hello "),
+            (Style::new().with_font_family(full), "Привет"),
+            (latin, "Привет"),
+            (latin.bold(), "Hello"),
+        ];
+
+        let coverage = font_cache.check_document_coverage(&texts);
+        assert_eq!(3, coverage.len());
+
+        let mut missing = coverage[0].missing_chars().to_vec();
+        missing.sort_unstable();
+        assert_eq!(vec!['П', 'в', 'е', 'и', 'р', 'т'], missing);
+        assert_eq!(5, coverage[0].covered_count());
+        assert!(coverage[1].is_complete());
+        assert!(coverage[2].is_complete());
+    }
+
+    #[test]
+    fn test_from_single() {
+        let font = FontData::new(FONT.to_vec(), None).unwrap();
+        let family = FontFamily::from_single(font.clone());
+        for data in &[
+            family.regular,
+            family.bold,
+            family.italic,
+            family.bold_italic,
+        ] {
+            assert!(data.is_same_font(&font));
         }
     }
+
+    #[test]
+    fn test_map() {
+        let family = FontFamily {
+            regular: "regular",
+            bold: "bold",
+            italic: "italic",
+            bold_italic: "bold_italic",
+        };
+        let mapped = family.map(str::len);
+        assert_eq!(
+            FontFamily {
+                regular: 7,
+                bold: 4,
+                italic: 6,
+                bold_italic: 11,
+            },
+            mapped
+        );
+        let family = family.map(String::from);
+        assert_eq!("regular", family.regular);
+        assert_eq!("bold", family.bold);
+        assert_eq!("italic", family.italic);
+        assert_eq!("bold_italic", family.bold_italic);
+    }
+
+    #[test]
+    fn test_font_names() {
+        let font = FontData::new(FONT.to_vec(), None).unwrap();
+        assert!(font.family_name().unwrap().contains("DejaVu"));
+        assert_eq!(Some("Book".to_owned()), font.subfamily_name());
+
+        let bold = FontData::new_with_index(COLLECTION.to_vec(), 1, None).unwrap();
+        assert_eq!(font.family_name(), bold.family_name());
+        assert_eq!(Some("Bold".to_owned()), bold.subfamily_name());
+    }
+
+    #[test]
+    fn test_monospace_and_glyph_count() {
+        let mono = FontData::new(MONO_FONT.to_vec(), None).unwrap();
+        assert!(mono.is_monospace());
+        let font_cache = FontCache::new(font_family(MONO_FONT));
+        let font = font_cache.default_font_family().regular;
+        assert_eq!(
+            font.str_width(&font_cache, "iii", 12.0),
+            font.str_width(&font_cache, "WWW", 12.0)
+        );
+
+        let courier = FontData::new(FONT.to_vec(), Some(printpdf::BuiltinFont::Courier)).unwrap();
+        assert!(courier.is_monospace());
+        let helvetica =
+            FontData::new(MONO_FONT.to_vec(), Some(printpdf::BuiltinFont::Helvetica)).unwrap();
+        assert!(!helvetica.is_monospace());
+
+        let font = FontData::new(FONT.to_vec(), None).unwrap();
+        assert!(!font.is_monospace());
+        let latin = FontData::new(LATIN_FONT.to_vec(), None).unwrap();
+        assert!(!latin.is_monospace());
+        assert!(latin.glyph_count() < font.glyph_count());
+    }
+
+    #[test]
+    fn test_from_files_with_pattern() {
+        let family =
+            super::from_files_with_pattern("tests/fonts", "DejaVu", "{name}Sans-{style}.ttf", None)
+                .unwrap();
+        let expected = super::from_files("tests/fonts", "DejaVuSans", None).unwrap();
+        assert_eq!(
+            expected.regular.get_data().unwrap(),
+            family.regular.get_data().unwrap()
+        );
+        assert_eq!(
+            expected.bold.get_data().unwrap(),
+            family.bold.get_data().unwrap()
+        );
+        assert_eq!(
+            expected.italic.get_data().unwrap(),
+            family.italic.get_data().unwrap()
+        );
+        assert_eq!(
+            expected.bold_italic.get_data().unwrap(),
+            family.bold_italic.get_data().unwrap()
+        );
+        assert!(super::from_files_with_pattern(
+            "tests/fonts",
+            "DejaVuSans",
+            "{name}-{style_lower}.ttf",
+            None
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_from_files_lenient() {
+        let (family, substituted) =
+            super::from_files_lenient("tests/fonts", "DejaVuSans", None).unwrap();
+        assert_eq!(FontFamily::from_single(false), substituted);
+        assert!(!family.italic.is_same_font(&family.regular));
+
+        let (family, substituted) =
+            super::from_files_lenient("tests/fonts", "DejaVuSansMono", None).unwrap();
+        assert_eq!(
+            FontFamily {
+                regular: false,
+                bold: true,
+                italic: true,
+                bold_italic: true,
+            },
+            substituted
+        );
+        assert!(family.bold.is_same_font(&family.regular));
+        assert!(family.italic.is_same_font(&family.regular));
+        assert!(family.bold_italic.is_same_font(&family.regular));
+
+        assert!(super::from_files_lenient("tests/fonts", "Missing", None).is_err());
+    }
+
+    #[test]
+    fn test_fractional_font_size() {
+        let font_cache = FontCache::new(font_family(FONT));
+        let width = Style::new()
+            .with_font_size(12)
+            .str_width(&font_cache, "Hello");
+        let fractional = Style::new()
+            .with_font_size(10.5)
+            .str_width(&font_cache, "Hello");
+        assert!((fractional.0 - width.0 * 10.5 / 12.0).abs() < 1e-4);
+        assert!(
+            fractional
+                < Style::new()
+                    .with_font_size(11)
+                    .str_width(&font_cache, "Hello")
+        );
+        assert_eq!(10.5, Style::new().with_font_size(10.5).font_size());
+    }
+
+    #[test]
+    fn test_font_collection() {
+        let regular = FontData::new_with_index(COLLECTION.to_vec(), 0, None).unwrap();
+        let bold = FontData::new_with_index(COLLECTION.to_vec(), 1, None).unwrap();
+        assert!(FontData::new_with_index(COLLECTION.to_vec(), 2, None).is_err());
+        assert!(FontData::new_with_index(FONT.to_vec(), 1, None).is_err());
+
+        let font_cache = FontCache::new(FontFamily {
+            regular: regular.clone(),
+            bold: bold.clone(),
+            italic: regular,
+            bold_italic: bold,
+        });
+        let style = Style::new();
+        let reference = FontCache::new(font_family(FONT));
+        assert_eq!(
+            reference
+                .default_font_family()
+                .regular
+                .str_width(&reference, "Hello", 12.0),
+            style.str_width(&font_cache, "Hello")
+        );
+        assert_ne!(
+            style.str_width(&font_cache, "Hello"),
+            style.bold().str_width(&font_cache, "Hello")
+        );
+
+        let data = FontData::new_with_index(COLLECTION.to_vec(), 0, None).unwrap();
+        assert_eq!(FONT.len(), data.get_data().unwrap().len());
+    }
+
+    #[test]
+    fn test_woff() {
+        let data = FontData::new(WOFF_FONT.to_vec(), None).unwrap();
+        assert!(data.has_glyph('A'));
+        assert!(!data.has_glyph('П'));
+        assert_eq!(LATIN_FONT, data.get_data().unwrap());
+
+        let mut truncated = WOFF_FONT.to_vec();
+        truncated.truncate(1000);
+        assert!(FontData::new(truncated, None).is_err());
+
+        // A header that claims a huge table backed by only a few bytes of data
+        let mut hostile = WOFF_FONT[..44].to_vec();
+        hostile[12..14].copy_from_slice(&1u16.to_be_bytes());
+        hostile[16..20].copy_from_slice(&u32::MAX.to_be_bytes());
+        let mut entry = b"glyf".to_vec();
+        entry.extend_from_slice(&64u32.to_be_bytes());
+        entry.extend_from_slice(&8u32.to_be_bytes());
+        entry.extend_from_slice(&u32::MAX.to_be_bytes());
+        entry.extend_from_slice(&0u32.to_be_bytes());
+        hostile.extend_from_slice(&entry);
+        hostile.extend_from_slice(&[0x78, 0x9c, 0x03, 0x00, 0x00, 0x00, 0x00, 0x01]);
+        let is_invalid_data = |data: &[u8]| {
+            decode_woff(data)
+                .err()
+                .is_some_and(|err| matches!(err.kind(), crate::error::ErrorKind::InvalidData))
+        };
+        assert!(is_invalid_data(&hostile));
+        hostile[16..20].copy_from_slice(&1024u32.to_be_bytes());
+        assert!(is_invalid_data(&hostile));
+        hostile[56..60].copy_from_slice(&16u32.to_be_bytes());
+        assert!(is_invalid_data(&hostile));
+    }
+
+    #[test]
+    fn test_from_reader() {
+        let data = FontData::from_reader(std::io::Cursor::new(FONT.to_vec()), None).unwrap();
+        assert!(data.has_glyph('П'));
+        assert_eq!(FONT, data.get_data().unwrap());
+        assert!(FontData::from_reader(std::io::Cursor::new(vec![0; 16]), None).is_err());
+    }
+
+    #[test]
+    fn test_builtin_str_width() {
+        let builtin = |builtin| FontData::new(FONT.to_vec(), Some(builtin)).unwrap();
+        let mut font_cache = FontCache::new(font_family(FONT));
+        // The sums of the AFM advance widths of the glyphs of "Hello"
+        let cases = [
+            (printpdf::BuiltinFont::Helvetica, 2278.0),
+            (printpdf::BuiltinFont::TimesRoman, 2222.0),
+            (printpdf::BuiltinFont::HelveticaBold, 2445.0),
+            (printpdf::BuiltinFont::HelveticaOblique, 2278.0),
+            (printpdf::BuiltinFont::TimesBold, 2278.0),
+            (printpdf::BuiltinFont::TimesItalic, 2222.0),
+            (printpdf::BuiltinFont::TimesBoldItalic, 2278.0),
+            (printpdf::BuiltinFont::Symbol, 2808.0),
+            (printpdf::BuiltinFont::ZapfDingbats, 3856.0),
+            (printpdf::BuiltinFont::Courier, 3000.0),
+            (printpdf::BuiltinFont::CourierBoldOblique, 3000.0),
+        ];
+        for (font, expected) in cases.iter() {
+            let font = font_cache.add_font(builtin(*font));
+            let width = font.str_width(&font_cache, "Hello", 12.0);
+            let expected = Mm::from(printpdf::Pt(expected / 1000.0 * 12.0));
+            assert!((width.0 - expected.0).abs() < 1e-4, "{:?}", font);
+        }
+    }
+
+    const AFM: &str = "StartFontMetrics 4.1
+FontName Helvetica
+StartCharMetrics 6
+C 32 ; WX 278 ; N space ; B 0 0 0 0 ;
+C 72 ; WX 722 ; N H ; B 79 0 643 718 ;
+C 101 ; WX 556 ; N e ; B 40 -15 516 538 ;
+C 108 ; WX 222 ; N l ; B 67 0 155 718 ;
+C 111 ; WX 556 ; N o ; B 35 -14 521 538 ;
+C -1 ; WX 667 ; N Eacute ; B 79 0 597 929 ;
+EndCharMetrics
+EndFontMetrics
+";
+
+    #[test]
+    fn test_is_win1252_encodable() {
+        assert!(is_win1252_encodable(""));
+        assert!(is_win1252_encodable("Hello, world!"));
+        assert!(is_win1252_encodable(
+            "Caf\u{e9} \u{20ac}5 \u{2014} na\u{ef}ve"
+        ));
+        assert!(!is_win1252_encodable("\u{65e5}\u{672c}\u{8a9e}"));
+        assert!(!is_win1252_encodable("Price: 5\u{20ac} \u{2192} 6\u{20ac}"));
+    }
+
+    #[test]
+    fn test_parse_afm_metrics() {
+        let metrics = parse_afm_metrics(AFM).unwrap();
+        assert_eq!(6, metrics.len());
+        assert_eq!(0.722, metrics["H"].advance_width);
+        assert_eq!(0.079, metrics["H"].left_side_bearing);
+        assert_eq!(0.667, metrics["Eacute"].advance_width);
+        assert_eq!(0.0, metrics["space"].left_side_bearing);
+        assert!(parse_afm_metrics("StartFontMetrics 4.1\nEndFontMetrics\n").is_err());
+        assert!(parse_afm_metrics("C 32 ; WX wide ; N space ;").is_err());
+        assert!(parse_afm_metrics("C 32 ; WX 278 ; N space ; B x ;").is_err());
+    }
+
+    #[test]
+    fn test_afm_metrics() {
+        let helvetica = Some(printpdf::BuiltinFont::Helvetica);
+        let mut font_cache = FontCache::new(font_family(FONT));
+        let baked = font_cache.add_font(FontData::new(FONT.to_vec(), helvetica).unwrap());
+        let afm = FontData::new(FONT.to_vec(), helvetica)
+            .unwrap()
+            .with_afm_metrics_from_reader(AFM.as_bytes())
+            .unwrap();
+        let afm = font_cache.add_font(afm);
+        assert_eq!(
+            baked.str_width(&font_cache, "Hello", 12.0),
+            afm.str_width(&font_cache, "Hello", 12.0)
+        );
+        // The baked table does not contain É, so it falls back to the default width
+        assert!(afm.char_width(&font_cache, 'É', 12.0) > baked.char_width(&font_cache, 'É', 12.0));
+
+        let embedded = FontData::new(FONT.to_vec(), None).unwrap();
+        assert!(embedded
+            .with_afm_metrics_from_reader(AFM.as_bytes())
+            .is_err());
+    }
+
+    #[test]
+    fn test_x_and_cap_height() {
+        let font_cache = FontCache::new(font_family(FONT));
+        let font = font_cache.default_font_family().regular;
+        // DejaVu Sans has no x-height and cap height in its OS/2 table, so the glyph bounding
+        // boxes of x (1120 units) and H (1493 units) with 2048 units per em are used.
+        let expected = |units: f32| Mm::from(printpdf::Pt(units / 2048.0 * 12.0));
+        assert!((font.x_height(12.0) - expected(1120.0)).0.abs() < 0.001);
+        assert!((font.cap_height(12.0) - expected(1493.0)).0.abs() < 0.001);
+        assert!(font.x_height(12.0) < font.cap_height(12.0));
+        assert!(font.cap_height(12.0) < font.ascent(12.0));
+
+        let metrics = font.metrics(12.0);
+        assert_eq!(font.x_height(12.0), metrics.x_height);
+        assert_eq!(font.cap_height(12.0), metrics.cap_height);
+    }
+
+    #[test]
+    fn test_decoration_metrics() {
+        let font_cache = FontCache::new(font_family(FONT));
+        let font = font_cache.default_font_family().regular;
+        // DejaVu Sans has an underline position of -40 units and thickness of 90 units in its
+        // post table and a strikeout position of 530 units and thickness of 102 units in its OS/2
+        // table, with 2048 units per em.
+        let expected = |units: f32| Mm::from(printpdf::Pt(units / 2048.0 * 12.0));
+        assert!((font.underline_position(12.0) - expected(-40.0)).0.abs() < 0.001);
+        assert!((font.underline_thickness(12.0) - expected(90.0)).0.abs() < 0.001);
+        assert!((font.strikeout_position(12.0) - expected(530.0)).0.abs() < 0.001);
+        assert!((font.strikeout_thickness(12.0) - expected(102.0)).0.abs() < 0.001);
+
+        let builtin = FontData::new(FONT.to_vec(), Some(printpdf::BuiltinFont::Helvetica)).unwrap();
+        let font_cache = FontCache::new(FontFamily::from_single(builtin));
+        let font = font_cache.default_font_family().regular;
+        assert!(font.underline_position(12.0) < Mm(0.0));
+        assert!(font.underline_thickness(12.0) > Mm(0.0));
+        let center = font.strikeout_position(12.0) - font.strikeout_thickness(12.0) / 2.0;
+        assert!((center - font.x_height(12.0) / 2.0).0.abs() < 0.001);
+    }
+
+    #[test]
+    fn test_glyph_bounding_box() {
+        let font_cache = FontCache::new(font_family(FONT));
+        let font = font_cache.default_font_family().regular;
+        let (x_min, y_min, x_max, y_max) = font.glyph_bounding_box(&font_cache, 'H', 12.0).unwrap();
+        assert!(x_max > x_min);
+        assert!(y_max > y_min);
+        assert!(x_max <= font.char_width(&font_cache, 'H', 12.0));
+        assert!((y_min - Mm(0.0)).0.abs() < 0.001);
+        assert!((y_max - font.cap_height(12.0)).0.abs() < 0.001);
+        assert!(
+            (x_min - font.char_left_side_bearing(&font_cache, 'H', 12.0))
+                .0
+                .abs()
+                < 0.001
+        );
+
+        let (_, y_min, _, _) = font.glyph_bounding_box(&font_cache, 'g', 12.0).unwrap();
+        assert!(y_min < Mm(0.0));
+        assert_eq!(None, font.glyph_bounding_box(&font_cache, ' ', 12.0));
+    }
+
+    #[test]
+    fn test_width_cache() {
+        let sizes = [8.0, 10.5, 12.0];
+        let labels: Vec<_> = (0..50).map(|i| format!("Label {}", i % 10)).collect();
+        let widths = |font_cache: &FontCache| {
+            let font = font_cache.default_font_family().regular;
+            let bold = font_cache.default_font_family().bold;
+            let mut widths = Vec::new();
+            for size in &sizes {
+                for label in &labels {
+                    widths.push(font.str_width(font_cache, label, *size));
+                    widths.push(bold.str_width(font_cache, label, *size));
+                }
+            }
+            widths
+        };
+
+        let mut font_cache = FontCache::new(font_family(FONT));
+        let uncached = widths(&font_cache);
+        assert_eq!(0, font_cache.width_cache_len());
+
+        font_cache.set_width_cache(true);
+        assert_eq!(uncached, widths(&font_cache));
+        // 10 distinct labels with 2 fonts and 3 sizes
+        assert_eq!(60, font_cache.width_cache_len());
+        assert_eq!(uncached, widths(&font_cache));
+        assert_eq!(60, font_cache.width_cache_len());
+
+        font_cache.set_width_cache(false);
+        assert_eq!(0, font_cache.width_cache_len());
+        assert_eq!(uncached, widths(&font_cache));
+    }
+
+    #[test]
+    fn test_iterator_variants() {
+        let text = "AVAWAVTo, Hello World!";
+        for builtin in &[None, Some(printpdf::BuiltinFont::Helvetica)] {
+            let font_data = FontData::new(FONT.to_vec(), *builtin).unwrap();
+            let font_cache = FontCache::new(FontFamily::from_single(font_data));
+            let font = font_cache.default_font_family().regular;
+            let kerning: Vec<_> = font.kerning_iter(&font_cache, text.chars()).collect();
+            assert_eq!(font.kerning(&font_cache, text.chars()), kerning);
+            assert_eq!(text.chars().count(), kerning.len());
+            let glyph_ids: Vec<_> = font.glyph_ids_iter(&font_cache, text.chars()).collect();
+            assert_eq!(font.glyph_ids(&font_cache, text.chars()), glyph_ids);
+
+            // The kerning cache is not locked between the values of the iterator
+            let mut iter = font.kerning_iter(&font_cache, text.chars());
+            iter.next();
+            iter.next();
+            assert_eq!(kerning, font.kerning(&font_cache, text.chars()));
+            assert_eq!(kerning[2..], iter.collect::<Vec<_>>()[..]);
+        }
+    }
+
+    #[test]
+    fn test_kerning_cache() {
+        let font_cache = FontCache::new(font_family(FONT));
+        let font = font_cache.default_font_family().regular;
+        let text = "AVAWAVTo";
+        let rt_font = font_cache.get_rt_font(font);
+        let glyphs: Vec<_> = rt_font.glyphs_for(text.chars()).map(|g| g.id()).collect();
+        let mut expected = vec![0.0];
+        expected.extend(
+            glyphs
+                .windows(2)
+                .map(|pair| rt_font.pair_kerning(font.scale, pair[0], pair[1])),
+        );
+
+        let kerning = font.kerning(&font_cache, text.chars());
+        assert_eq!(expected, kerning);
+        assert!(kerning.iter().any(|val| *val != 0.0));
+        assert_eq!(6, font_cache.fonts[0].kerning_cache.lock().unwrap().len());
+        assert_eq!(kerning, font.kerning(&font_cache, text.chars()));
+        let kerning_width: f32 = kerning.iter().sum();
+        let width_without_kerning: f32 = glyphs
+            .iter()
+            .map(|id| {
+                rt_font
+                    .glyph(*id)
+                    .scaled(font.scale)
+                    .h_metrics()
+                    .advance_width
+            })
+            .sum();
+        let expected = Mm::from(printpdf::Pt((width_without_kerning + kerning_width) * 12.0));
+        assert!((font.str_width(&font_cache, text, 12.0).0 - expected.0).abs() < 1e-4);
+        assert_eq!(6, font_cache.fonts[0].kerning_cache.lock().unwrap().len());
+    }
+
+    #[test]
+    fn test_kerning_cache_limit() {
+        let font_cache = FontCache::new(font_family(FONT));
+        let font = font_cache.default_font_family().regular;
+        let text = "AVAWAVTo";
+        let expected = font.kerning(&font_cache, text.chars());
+
+        let font_cache = FontCache::new(font_family(FONT));
+        let font = font_cache.default_font_family().regular;
+        let id = rusttype::GlyphId(u32::MAX);
+        font_cache.fonts[0].kerning_cache.lock().unwrap().extend(
+            (0..super::MAX_KERNING_CACHE_LEN).map(|i| ((id, rusttype::GlyphId(i as u32)), 0.0)),
+        );
+        assert_eq!(expected, font.kerning(&font_cache, text.chars()));
+        assert_eq!(
+            super::MAX_KERNING_CACHE_LEN,
+            font_cache.fonts[0].kerning_cache.lock().unwrap().len()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "unicode-segmentation")]
+    fn test_check_coverage_graphemes() {
+        let latin = FontData::new(LATIN_FONT.to_vec(), None).unwrap();
+        assert!(latin.has_glyph('e'));
+        assert!(latin.has_glyph('\u{e9}'));
+        assert!(!latin.has_glyph('\u{301}'));
+
+        let precomposed = latin.check_coverage_graphemes("caf\u{e9}");
+        assert!(precomposed.is_complete());
+        assert_eq!(4, precomposed.total_count());
+        assert_eq!(4, precomposed.covered_count());
+
+        let combining = "cafe\u{301}";
+        let by_char = latin.check_coverage(combining);
+        assert_eq!(5, by_char.total_count());
+        assert_eq!(4, by_char.covered_count());
+        let by_cluster = latin.check_coverage_graphemes(combining);
+        assert_eq!(4, by_cluster.total_count());
+        assert_eq!(3, by_cluster.covered_count());
+        assert_eq!(&['\u{301}'], by_cluster.missing_chars());
+        assert_eq!(75.0, by_cluster.coverage_percent());
+    }
+
+    #[test]
+    fn test_embedded_font_dedup() {
+        let mut font_cache = FontCache::new(font_family(FONT));
+        let copy = font_cache.add_font_family(font_family(FONT));
+        let latin = font_cache.add_font_family(font_family(LATIN_FONT));
+        let renderer = crate::render::Renderer::new(crate::Size::new(100, 100), "test").unwrap();
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+
+        let default = font_cache.default_font_family();
+        let pdf_font = |font| font_cache.get_pdf_font(font).unwrap();
+        assert_eq!(pdf_font(default.regular), pdf_font(default.bold));
+        assert_eq!(pdf_font(default.regular), pdf_font(copy.regular));
+        assert_ne!(pdf_font(default.regular), pdf_font(latin.regular));
+        assert_eq!(2, font_cache.embedded_font_cache.len());
+
+        let mut buf = Vec::new();
+        renderer.write(&mut buf).unwrap();
+        let doc = lopdf::Document::load_mem(&buf).unwrap();
+        let font_files = doc
+            .objects
+            .values()
+            .filter_map(|object| object.as_dict().ok())
+            .filter(|dict| dict.has(b"FontFile2"))
+            .count();
+        assert_eq!(2, font_files);
+    }
+
+    #[test]
+    fn test_clone_shared() {
+        let parse_count = || super::RT_FONT_PARSE_COUNT.with(|count| count.get());
+        let start = parse_count();
+        let data = FontData::new(FONT.to_vec(), None).unwrap();
+        assert_eq!(start + 1, parse_count());
+
+        let widths: Vec<_> = (0..10)
+            .map(|_| {
+                let shared = data.clone_shared();
+                assert!(std::sync::Arc::ptr_eq(&data.rt_font, &shared.rt_font));
+                let family = FontFamily {
+                    regular: shared.clone(),
+                    bold: shared.clone(),
+                    italic: shared.clone(),
+                    bold_italic: shared,
+                };
+                let font_cache = FontCache::new(family);
+                let font = font_cache.default_font_family().regular;
+                font.str_width(&font_cache, "Hello World", 12.0)
+            })
+            .collect();
+        assert_eq!(start + 1, parse_count());
+        assert!(widths.windows(2).all(|pair| pair[0] == pair[1]));
+
+        let _ = FontData::new_shared(std::sync::Arc::new(FONT.to_vec()), None).unwrap();
+        assert_eq!(start + 2, parse_count());
+    }
+
+    #[test]
+    fn test_fallback_chain_scripts() {
+        assert_eq!(Some(Script::Latin), Script::of('é'));
+        assert_eq!(Some(Script::Cyrillic), Script::of('П'));
+        assert_eq!(Some(Script::Han), Script::of('漢'));
+        assert_eq!(Some(Script::Hiragana), Script::of('か'));
+        assert_eq!(None, Script::of('1'));
+        assert_eq!(None, Script::of('😀'));
+
+        let latin = FontData::new(LATIN_FONT.to_vec(), None).unwrap();
+        let full = FontData::new(FONT.to_vec(), None).unwrap();
+        let chain = FontFallbackChain::new(latin)
+            .with_fallback(full.clone())
+            .with_fallback_for_scripts(full.clone(), &[Script::Greek, Script::Cyrillic])
+            .with_fallback_for_scripts(full, &[Script::Han]);
+
+        // Both fallback fonts have a glyph, but the second one is preferred for Cyrillic
+        assert!(std::ptr::eq(
+            &chain.fallbacks()[1],
+            chain.find_font_for_char('П')
+        ));
+        // The primary font is always preferred
+        assert!(std::ptr::eq(chain.primary(), chain.find_font_for_char('A')));
+        // The fallback font for Han does not have the glyph
+        assert!(chain.try_find_font_for_char('漢').is_none());
+
+        let segments = chain.segment_text("Hi Пр");
+        assert_eq!(2, segments.len());
+        assert!(std::ptr::eq(&chain.fallbacks()[1], segments[1].1));
+    }
+
+    #[test]
+    fn test_fallback_chain_builtin() {
+        let builtin = FontData::new(FONT.to_vec(), Some(printpdf::BuiltinFont::Helvetica)).unwrap();
+        let embedded = FontData::new(FONT.to_vec(), None).unwrap();
+        assert!(builtin.has_glyph('П'));
+
+        let chain = FontFallbackChain::new(builtin.clone());
+        assert_eq!(vec!['П', 'я'], chain.missing_in_chain("Aé€Пя"));
+
+        let chain = chain.with_fallback(embedded);
+        let is_builtin = |c| match chain.find_font_for_char(c).raw_data {
+            super::RawFontData::Builtin(_) => true,
+            super::RawFontData::Embedded(_) => false,
+        };
+        assert!("Aé€".chars().all(is_builtin));
+        assert!(!"Пя".chars().any(is_builtin));
+        assert!(chain.check_coverage("Aé€Пя").is_complete());
+
+        // The accented characters and the Euro sign are not part of the standard encoding
+        let chain = FontFallbackChain::new(builtin)
+            .with_builtin_encoding(super::BuiltinEncoding::Standard)
+            .with_fallback(FontData::new(FONT.to_vec(), None).unwrap());
+        assert_eq!(super::BuiltinEncoding::Standard, chain.builtin_encoding());
+        let is_builtin = |c| match chain.find_font_for_char(c).raw_data {
+            super::RawFontData::Builtin(_) => true,
+            super::RawFontData::Embedded(_) => false,
+        };
+        assert!(is_builtin('A'));
+        assert!(!"é€Пя".chars().any(is_builtin));
+    }
+
+    #[test]
+    fn test_try_find_font_for_char() {
+        let latin = FontData::new(LATIN_FONT.to_vec(), None).unwrap();
+        let full = FontData::new(FONT.to_vec(), None).unwrap();
+        let chain = FontFallbackChain::new(latin).with_fallback(full);
+
+        assert!(std::ptr::eq(
+            chain.primary(),
+            chain.try_find_font_for_char('A').unwrap()
+        ));
+        assert!(std::ptr::eq(
+            &chain.fallbacks()[0],
+            chain.try_find_font_for_char('П').unwrap()
+        ));
+        // Neither font contains CJK characters
+        assert!(chain.try_find_font_for_char('漢').is_none());
+        assert!(std::ptr::eq(
+            chain.primary(),
+            chain.find_font_for_char('漢')
+        ));
+
+        assert_eq!(vec!['漢', '字'], chain.missing_in_chain("Пр漢 A字漢"));
+        assert!(chain.missing_in_chain("Hello Привет").is_empty());
+    }
 }
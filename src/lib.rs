@@ -8,6 +8,8 @@
 
 mod wrap;
 
+#[cfg(feature = "barcode")]
+pub mod barcode;
 pub mod elements;
 pub mod error;
 pub mod fonts;
@@ -17,6 +19,7 @@ pub mod subsetting;
 
 use std::fs;
 use std::io;
+use std::ops;
 use std::path;
 
 use derive_more::{Add, AddAssign, Div, DivAssign, Into, Mul, MulAssign, Sub, SubAssign, Sum};
@@ -51,6 +54,7 @@ use error::Context as _;
     SubAssign,
     Sum,
 )]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Mm(f32);
 
 impl Mm {
@@ -58,8 +62,48 @@ impl Mm {
     pub fn max(self, other: Mm) -> Mm {
         Mm(self.0.max(other.0))
     }
+
+    /// Returns the minimum of this value and the given value.
+    pub fn min(self, other: Mm) -> Mm {
+        Mm(self.0.min(other.0))
+    }
+
+    /// Creates a length from the given number of inches.
+    pub fn from_inches(inches: f32) -> Mm {
+        Mm(inches * MM_PER_INCH)
+    }
+
+    /// Returns this length in inches.
+    pub fn to_inches(self) -> f32 {
+        self.0 / MM_PER_INCH
+    }
+
+    /// Creates a length from the given number of centimeters.
+    pub fn from_cm(cm: f32) -> Mm {
+        Mm(cm * 10.0)
+    }
+
+    /// Returns this length in centimeters.
+    pub fn to_cm(self) -> f32 {
+        self.0 / 10.0
+    }
+
+    /// Creates a length from the given number of PostScript points (1/72 inch).
+    pub fn from_pt(pt: f32) -> Mm {
+        Mm(pt * MM_PER_INCH / PT_PER_INCH)
+    }
+
+    /// Returns this length in PostScript points (1/72 inch).
+    pub fn to_pt(self) -> f32 {
+        self.0 * PT_PER_INCH / MM_PER_INCH
+    }
 }
 
+/// The number of millimeters per inch.
+const MM_PER_INCH: f32 = 25.4;
+/// The number of PostScript points per inch.
+const PT_PER_INCH: f32 = 72.0;
+
 impl From<i8> for Mm {
     fn from(mm: i8) -> Mm {
         Mm(mm.into())
@@ -98,7 +142,7 @@ impl From<u32> for Mm {
 
 impl From<f32> for Mm {
     fn from(mm: f32) -> Mm {
-        Mm(mm.into())
+        Mm(mm)
     }
 }
 
@@ -133,20 +177,21 @@ impl From<Mm> for printpdf::Pt {
 ///
 /// [`Paragraph`]: elements/struct.Paragraph.html
 /// [`Image`]: elements/struct.Image.html
-#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub enum Alignment {
     /// Left-flushed.
+    #[default]
     Left,
     /// Right-flushed.
     Right,
     /// Centered.
     Center,
-}
-
-impl Default for Alignment {
-    fn default() -> Alignment {
-        Alignment::Left
-    }
+    /// Justified.
+    ///
+    /// All lines of a paragraph except for the last line are stretched to the full width by
+    /// increasing the word spacing.  The last line is left-flushed.  For images and single lines,
+    /// this is the same as `Left`.
+    Justify,
 }
 
 /// A position on a PDF layer, measured in millimeters.
@@ -176,6 +221,34 @@ impl<X: Into<Mm>, Y: Into<Mm>> From<(X, Y)> for Position {
     }
 }
 
+impl ops::Add<Size> for Position {
+    type Output = Position;
+
+    fn add(self, size: Size) -> Position {
+        Position::new(self.x + size.width, self.y + size.height)
+    }
+}
+
+impl ops::AddAssign<Size> for Position {
+    fn add_assign(&mut self, size: Size) {
+        *self = *self + size;
+    }
+}
+
+impl ops::Sub<Size> for Position {
+    type Output = Position;
+
+    fn sub(self, size: Size) -> Position {
+        Position::new(self.x - size.width, self.y - size.height)
+    }
+}
+
+impl ops::SubAssign<Size> for Position {
+    fn sub_assign(&mut self, size: Size) {
+        *self = *self - size;
+    }
+}
+
 /// A rotation in degrees clock-wise in range [-180.0, 180.0] inclusive.
 #[derive(Clone, Copy, Default, Debug, PartialEq, PartialOrd, Add, AddAssign, Sub, SubAssign)]
 pub struct Rotation {
@@ -253,7 +326,25 @@ impl<X: Into<f32>, Y: Into<f32>> From<(X, Y)> for Scale {
 }
 
 /// A size of an area on a PDF layer, measured in millimeters.
-#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd, Add, AddAssign, Sub, SubAssign)]
+///
+/// Sizes can be added and subtracted, and multiplied and divided by a scalar factor.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    PartialOrd,
+    Add,
+    AddAssign,
+    Div,
+    DivAssign,
+    Mul,
+    MulAssign,
+    Sub,
+    SubAssign,
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Size {
     /// The width of the area.
     pub width: Mm,
@@ -280,6 +371,45 @@ impl Size {
         self.height += other.height;
         self
     }
+
+    /// Returns the aspect ratio of this size, i. e. its width divided by its height.
+    pub fn aspect_ratio(&self) -> f32 {
+        self.width.0 / self.height.0
+    }
+
+    /// Returns the largest size with the aspect ratio of this size that fits into the given
+    /// bounds.
+    ///
+    /// This size is scaled up or down so that it fills the bounds in at least one dimension.  If
+    /// this size has a zero width or height, it is returned unchanged.
+    #[must_use]
+    pub fn scaled_to_fit(&self, bounds: Size) -> Size {
+        if self.width.0 <= 0.0 || self.height.0 <= 0.0 {
+            return *self;
+        }
+        let factor = (bounds.width.0 / self.width.0).min(bounds.height.0 / self.height.0);
+        *self * factor
+    }
+
+    /// Returns this size in landscape orientation, i. e. with the width not less than the height.
+    #[must_use]
+    pub fn landscape(self) -> Size {
+        if self.width < self.height {
+            Size::new(self.height, self.width)
+        } else {
+            self
+        }
+    }
+
+    /// Returns this size in portrait orientation, i. e. with the height not less than the width.
+    #[must_use]
+    pub fn portrait(self) -> Size {
+        if self.height < self.width {
+            Size::new(self.height, self.width)
+        } else {
+            self
+        }
+    }
 }
 
 impl<W: Into<Mm>, H: Into<Mm>> From<(W, H)> for Size {
@@ -296,26 +426,56 @@ impl<W: Into<Mm>, H: Into<Mm>> From<(W, H)> for Size {
 /// [`Size`]: struct.Size.html
 #[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
 pub enum PaperSize {
+    /// The A3 paper size (297x420mm).
+    A3,
     /// The A4 paper size (210x297mm).
     A4,
-    /// The legal paper size (216x356mm).
+    /// The A5 paper size (148x210mm).
+    A5,
+    /// The legal paper size (215.9x355.6mm).
     Legal,
-    /// The letter paper size (216x279mm).
+    /// The letter paper size (215.9x279.4mm).
     Letter,
+    /// The tabloid paper size (279.4x431.8mm).
+    Tabloid,
 }
 
-impl From<PaperSize> for Size {
-    fn from(size: PaperSize) -> Size {
-        match size {
+impl PaperSize {
+    /// Returns the dimensions of this paper size in the given orientation.
+    pub fn dimensions(&self, orientation: Orientation) -> Size {
+        let size = match self {
+            PaperSize::A3 => Size::new(297, 420),
             PaperSize::A4 => Size::new(210, 297),
-            PaperSize::Legal => Size::new(216, 356),
-            PaperSize::Letter => Size::new(216, 279),
+            PaperSize::A5 => Size::new(148, 210),
+            PaperSize::Legal => Size::new(215.9, 355.6),
+            PaperSize::Letter => Size::new(215.9, 279.4),
+            PaperSize::Tabloid => Size::new(279.4, 431.8),
+        };
+        match orientation {
+            Orientation::Portrait => size,
+            Orientation::Landscape => size.landscape(),
         }
     }
 }
 
+impl From<PaperSize> for Size {
+    fn from(size: PaperSize) -> Size {
+        size.dimensions(Orientation::Portrait)
+    }
+}
+
+/// The orientation of a page.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Orientation {
+    /// The portrait orientation, with the height greater than the width.
+    Portrait,
+    /// The landscape orientation, with the width greater than the height.
+    Landscape,
+}
+
 /// The margins of an area, measured in millimeters.
 #[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Margins {
     /// The top margin of the area.
     top: Mm,
@@ -329,6 +489,10 @@ pub struct Margins {
 
 impl Margins {
     /// Creates a new `Margins` instance from the given top, right, bottom and left margins.
+    ///
+    /// The arguments are given in clockwise order starting at the top, like in the CSS `margin`
+    /// property: `Margins::trbl(1, 2, 3, 4)` sets a top margin of 1 mm, a right margin of 2 mm, a
+    /// bottom margin of 3 mm and a left margin of 4 mm.
     pub fn trbl(
         top: impl Into<Mm>,
         right: impl Into<Mm>,
@@ -345,11 +509,23 @@ impl Margins {
 
     /// Creates a new `Margins` instance from the given vertical (top and bottom) and horizontal
     /// (left and right) margins.
+    ///
+    /// The vertical margin is the first argument, like in the CSS `margin` property:
+    /// `Margins::vh(1, 2)` sets the top and bottom margins to 1 mm and the left and right margins
+    /// to 2 mm.
     pub fn vh(vertical: impl Into<Mm>, horizontal: impl Into<Mm>) -> Margins {
         let (vertical, horizontal) = (vertical.into(), horizontal.into());
         Margins::trbl(vertical, horizontal, vertical, horizontal)
     }
 
+    /// Creates a new `Margins` instance from the given vertical (top and bottom) and horizontal
+    /// (left and right) margins.
+    ///
+    /// This is an alias for [`vh`](#method.vh), so the vertical margin is the first argument.
+    pub fn symmetric(vertical: impl Into<Mm>, horizontal: impl Into<Mm>) -> Margins {
+        Margins::vh(vertical, horizontal)
+    }
+
     /// Creates a new `Margins` instance with all four margins set to the given value.
     pub fn all(all: impl Into<Mm>) -> Margins {
         let all = all.into();
@@ -424,6 +600,8 @@ pub struct Document {
     conformance: Option<printpdf::PdfConformance>,
     creation_date: Option<printpdf::OffsetDateTime>,
     modification_date: Option<printpdf::OffsetDateTime>,
+    subsetting: bool,
+    builtin_encoding: fonts::BuiltinEncoding,
 }
 
 impl Document {
@@ -440,6 +618,8 @@ impl Document {
             conformance: None,
             creation_date: None,
             modification_date: None,
+            subsetting: false,
+            builtin_encoding: Default::default(),
         }
     }
 
@@ -467,6 +647,15 @@ impl Document {
         &self.context.font_cache
     }
 
+    /// Enables or disables the memoization of string widths in the font cache of this document.
+    ///
+    /// See [`FontCache::set_width_cache`][] for more information.
+    ///
+    /// [`FontCache::set_width_cache`]: fonts/struct.FontCache.html#method.set_width_cache
+    pub fn set_width_cache(&mut self, enabled: bool) {
+        self.context.font_cache.set_width_cache(enabled);
+    }
+
     /// Activates hyphenation and sets the hyphentor to use.
     ///
     /// *Only available if the `hyphenation` feature is enabled.*
@@ -482,10 +671,22 @@ impl Document {
         self.title = title.into();
     }
 
+    /// Sets the default style for this document.
+    ///
+    /// The default style is the base of the style cascade:  All elements of this document are
+    /// rendered with a style that is merged into the default style, so its font family, font size
+    /// and color are used for all text that does not set these values.  See also
+    /// [`Renderer::set_default_style`][].
+    ///
+    /// [`Renderer::set_default_style`]: render/struct.Renderer.html#method.set_default_style
+    pub fn set_default_style(&mut self, style: impl Into<style::Style>) {
+        self.style = style.into();
+    }
+
     /// Sets the default font size in points for this document.
     ///
     /// If this method is not called, the default value of 12 points is used.
-    pub fn set_font_size(&mut self, font_size: u8) {
+    pub fn set_font_size(&mut self, font_size: impl Into<f64>) {
         self.style.set_font_size(font_size);
     }
 
@@ -522,6 +723,14 @@ impl Document {
         self.conformance = Some(conformance);
     }
 
+    /// Enables font subsetting for this document.
+    ///
+    /// If subsetting is enabled, the embedded fonts only contain the glyphs that are used in the
+    /// document, which can reduce the file size significantly.  Built-in fonts are not affected.
+    pub fn enable_subsetting(&mut self) {
+        self.subsetting = true;
+    }
+
     /// Sets the minimal PDF conformance settings for this document.
     ///
     /// If this method is called, the generation of ICC profiles and XMP metadata is deactivated,
@@ -536,6 +745,16 @@ impl Document {
         ));
     }
 
+    /// Sets the encoding that is used for strings printed with a built-in font.
+    ///
+    /// If this method is not called, the Windows-1252 encoding is used.  See also
+    /// [`Renderer::set_builtin_encoding`][].
+    ///
+    /// [`Renderer::set_builtin_encoding`]: render/struct.Renderer.html#method.set_builtin_encoding
+    pub fn set_builtin_encoding(&mut self, encoding: fonts::BuiltinEncoding) {
+        self.builtin_encoding = encoding;
+    }
+
     /// Sets the creation date of the PDF file.
     pub fn set_creation_date(&mut self, date: printpdf::OffsetDateTime) {
         self.creation_date = Some(date);
@@ -574,6 +793,8 @@ impl Document {
         if let Some(modification_date) = self.modification_date {
             renderer = renderer.with_modification_date(modification_date);
         }
+        renderer.set_default_style(self.style);
+        renderer.set_builtin_encoding(self.builtin_encoding);
         self.context.font_cache.load_pdf_fonts(&renderer)?;
         loop {
             let mut area = renderer.last_page().last_layer().area();
@@ -593,6 +814,9 @@ impl Document {
                 break;
             }
         }
+        if self.subsetting {
+            self.context.font_cache.subset_pdf_fonts(&mut renderer)?;
+        }
         renderer.write(w)
     }
 
@@ -803,6 +1027,42 @@ pub trait Element {
     }
 }
 
+/// Determines the positions at which words may be hyphenated.
+///
+/// Hyphenators are used by [`Area::print_wrapped_with_hyphenator`][] to break words that do not
+/// fit into a line.  [`NoHyphenation`][] never hyphenates words.  If the `hyphenation` feature is
+/// enabled, this trait is also implemented for [`hyphenation::Standard`][].
+///
+/// [`Area::print_wrapped_with_hyphenator`]: render/struct.Area.html#method.print_wrapped_with_hyphenator
+/// [`NoHyphenation`]: struct.NoHyphenation.html
+/// [`hyphenation::Standard`]: https://docs.rs/hyphenation/latest/hyphenation/type.Standard.html
+pub trait Hyphenator {
+    /// Returns the byte offsets into the given word at which the word may be broken.
+    ///
+    /// When a word is broken at one of these offsets, a hyphen is appended to the first part.
+    /// Offsets that are not between two characters of the word are ignored.
+    fn hyphenation_points(&self, word: &str) -> Vec<usize>;
+}
+
+/// A [`Hyphenator`][] that never hyphenates words.
+///
+/// [`Hyphenator`]: trait.Hyphenator.html
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NoHyphenation;
+
+impl Hyphenator for NoHyphenation {
+    fn hyphenation_points(&self, _word: &str) -> Vec<usize> {
+        Vec::new()
+    }
+}
+
+#[cfg(feature = "hyphenation")]
+impl Hyphenator for hyphenation::Standard {
+    fn hyphenation_points(&self, word: &str) -> Vec<usize> {
+        hyphenation::Hyphenator::hyphenate(self, word).breaks
+    }
+}
+
 /// The context for a rendering process.
 ///
 /// This struct stores data that is shared between all elements during the rendering process.
@@ -863,6 +1123,146 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_paper_size() {
+        use super::{Orientation, PaperSize, Size};
+
+        assert_eq!(Size::new(210, 297), PaperSize::A4.into());
+        assert_eq!(
+            Size::new(210, 297),
+            PaperSize::A4.dimensions(Orientation::Portrait)
+        );
+        assert_eq!(
+            Size::new(297, 210),
+            PaperSize::A4.dimensions(Orientation::Landscape)
+        );
+        assert_eq!(Size::new(215.9, 279.4), PaperSize::Letter.into());
+        assert_eq!(Size::new(215.9, 355.6), PaperSize::Legal.into());
+        assert_eq!(
+            Size::new(431.8, 279.4),
+            PaperSize::Tabloid.dimensions(Orientation::Landscape)
+        );
+        assert_eq!(Size::new(20, 10), Size::new(10, 20).landscape());
+        assert_eq!(Size::new(20, 10), Size::new(20, 10).landscape());
+        assert_eq!(Size::new(10, 20), Size::new(20, 10).portrait());
+        assert_eq!(Size::new(10, 20), Size::new(10, 20).portrait());
+    }
+
+    #[test]
+    fn test_size_position_operators() {
+        use super::{Position, Size};
+
+        let size = Size::new(10, 20);
+        let position = Position::new(1, 2);
+        assert_eq!(Position::new(11, 22), position + size);
+        assert_eq!(Position::new(-9, -18), position - size);
+        let mut moved = position;
+        moved += size;
+        assert_eq!(Position::new(11, 22), moved);
+        moved -= size;
+        assert_eq!(position, moved);
+        assert_eq!(Position::new(3, 5), position + Position::new(2, 3));
+        assert_eq!(Position::new(-1, -1), position - Position::new(2, 3));
+
+        assert_eq!(Size::new(25, 50), size * 2.5);
+        assert_eq!(Size::new(5, 10), size / 2.0);
+        let mut scaled = size;
+        scaled *= 3.0;
+        assert_eq!(Size::new(30, 60), scaled);
+        scaled /= 3.0;
+        assert_eq!(size, scaled);
+    }
+
+    #[test]
+    fn test_scaled_to_fit() {
+        use super::Size;
+
+        let image = Size::new(400, 300);
+        assert_eq!(4.0 / 3.0, image.aspect_ratio());
+        let fitted = image.scaled_to_fit(Size::new(100, 100));
+        assert_eq!(Size::new(100, 75), fitted);
+        assert_eq!(image.aspect_ratio(), fitted.aspect_ratio());
+        let fitted = image.scaled_to_fit(Size::new(1000, 150));
+        assert_eq!(Size::new(200, 150), fitted);
+        assert_eq!(image.aspect_ratio(), fitted.aspect_ratio());
+        assert_eq!(
+            Size::new(0, 10),
+            Size::new(0, 10).scaled_to_fit(Size::new(5, 5))
+        );
+    }
+
+    #[test]
+    fn test_margins() {
+        use super::{Margins, Mm};
+
+        let margins = Margins::trbl(1, 2, 3.5, 4);
+        assert_eq!(Mm(1.0), margins.top);
+        assert_eq!(Mm(2.0), margins.right);
+        assert_eq!(Mm(3.5), margins.bottom);
+        assert_eq!(Mm(4.0), margins.left);
+        assert_eq!(margins, Margins::from((1, 2, 3.5, 4)));
+
+        let margins = Margins::symmetric(5, 10);
+        assert_eq!(Mm(5.0), margins.top);
+        assert_eq!(Mm(10.0), margins.right);
+        assert_eq!(Mm(5.0), margins.bottom);
+        assert_eq!(Mm(10.0), margins.left);
+        assert_eq!(margins, Margins::vh(5, 10));
+        assert_eq!(margins, Margins::from((5, 10)));
+        assert_eq!(margins, Margins::trbl(5, 10, 5, 10));
+
+        let margins = Margins::all(10);
+        assert_eq!(margins, Margins::trbl(10, 10, 10, 10));
+        assert_eq!(margins, Margins::from(10));
+    }
+
+    #[test]
+    fn test_mm_conversions() {
+        use super::Mm;
+        use float_cmp::approx_eq;
+
+        assert_eq!(Mm(25.4), Mm::from_inches(1.0));
+        assert_eq!(1.0, Mm(25.4).to_inches());
+        assert_eq!(Mm(15.0), Mm::from_cm(1.5));
+        assert_eq!(1.5, Mm(15.0).to_cm());
+        let epsilon = 0.0001;
+        assert!(approx_eq!(
+            Mm,
+            Mm::from_inches(1.0),
+            Mm::from_pt(72.0),
+            epsilon = epsilon
+        ));
+        assert!(approx_eq!(
+            f32,
+            72.0,
+            Mm::from_inches(1.0).to_pt(),
+            epsilon = epsilon
+        ));
+        assert!(approx_eq!(
+            Mm,
+            Mm::from(printpdf::Pt(12.0)),
+            Mm::from_pt(12.0),
+            epsilon = epsilon
+        ));
+
+        for mm in &[0.0, 1.0, 12.5, 210.0] {
+            let mm = Mm(*mm);
+            assert!(approx_eq!(
+                Mm,
+                mm,
+                Mm::from_inches(mm.to_inches()),
+                ulps = 2
+            ));
+            assert!(approx_eq!(Mm, mm, Mm::from_cm(mm.to_cm()), ulps = 2));
+            assert!(approx_eq!(
+                Mm,
+                mm,
+                Mm::from_pt(mm.to_pt()),
+                epsilon = 0.0001
+            ));
+        }
+    }
+
     #[test]
     fn test_rotation() {
         use super::Rotation;
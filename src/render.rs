@@ -15,15 +15,20 @@
 //! [`Area`]: struct.Area.html
 //! [`TextSection`]: struct.TextSection.html
 
+use std::borrow;
 use std::cell;
+use std::cmp;
+use std::collections::HashMap;
 use std::io;
+use std::mem;
 use std::ops;
 use std::rc;
 
 use crate::error::{Context as _, Error, ErrorKind};
 use crate::fonts;
 use crate::style::{Color, LineStyle, Style};
-use crate::{Margins, Mm, Position, Size};
+use crate::wrap;
+use crate::{Alignment, Hyphenator, Margins, Mm, NoHyphenation, Position, Size};
 
 #[cfg(feature = "images")]
 use crate::{Rotation, Scale};
@@ -72,6 +77,28 @@ pub struct Renderer {
     doc: printpdf::PdfDocumentReference,
     // invariant: pages.len() >= 1
     pages: Vec<Page>,
+    // pages that have been removed from the document, which are kept until the document is
+    // written as their images might be shared with other pages
+    removed_pages: Vec<Page>,
+    default_style: rc::Rc<cell::Cell<Style>>,
+    builtin_encoding: rc::Rc<cell::Cell<fonts::BuiltinEncoding>>,
+    // the images embedded in this document with their IDs, keyed by a hash of their pixel data
+    image_cache: rc::Rc<cell::RefCell<ImageCache>>,
+    // custom entries that are added to the document information dictionary when writing
+    custom_properties: printpdf::lopdf::Dictionary,
+    // font subsets that replace the embedded fonts when writing, with the mapping from the
+    // original glyph IDs to the glyph IDs of the subset
+    font_subsets: Vec<(printpdf::IndirectFontRef, Vec<u8>, HashMap<u16, u16>)>,
+    // entries of the document outline that are added to the document catalog when writing
+    bookmarks: Vec<Bookmark>,
+    // the user password, the owner password and the permissions used to encrypt the document
+    #[cfg(feature = "encryption")]
+    encryption: Option<(String, String, Permissions)>,
+    // viewer preferences that are added to the document catalog when writing
+    page_layout: Option<PageLayout>,
+    page_mode: Option<PageMode>,
+    // the index of the page and the zoom factor that the document is opened with
+    open_action: Option<(usize, f32)>,
 }
 
 impl Renderer {
@@ -85,13 +112,116 @@ impl Renderer {
             "Layer 1",
         );
         let page_ref = doc.get_page(page_idx);
-        let layer_ref = page_ref.get_layer(layer_idx);
-        let page = Page::new(page_ref, layer_ref, size);
+        let layer = LayerData::new(page_ref.get_layer(layer_idx), Some("Layer 1".to_owned()));
+        Ok(Renderer::from_pages(doc, vec![(page_ref, layer, size)]))
+    }
+
+    /// Creates a new PDF document renderer for an existing printpdf document.
+    ///
+    /// This can be used to combine the layout features of this crate with features of printpdf
+    /// that are not exposed by this crate.  The given pages must be all pages of the document in
+    /// the order in which they have been added, each with the layer that should be used for
+    /// rendering and the page size.  The pages must not contain images that have been added with
+    /// printpdf directly.  The given layers have no name, see [`Page::layer_by_name`][].  Returns
+    /// an error if the list of pages is empty, if a page or layer belongs to another document, if
+    /// the pages are not in the order in which they have been added or if a layer does not belong
+    /// to its page.  As printpdf does not expose
+    /// the number of pages of a document, [`write`](#method.write) returns an error if the
+    /// document has pages that have not been passed to this function, or if images have been
+    /// added to a page with printpdf and with this renderer.
+    ///
+    /// [`Page::layer_by_name`]: struct.Page.html#method.layer_by_name
+    pub fn from_document(
+        doc: printpdf::PdfDocumentReference,
+        pages: Vec<(
+            printpdf::PdfPageReference,
+            printpdf::PdfLayerReference,
+            Size,
+        )>,
+    ) -> Result<Renderer, Error> {
+        if pages.is_empty() {
+            return Err(Error::new(
+                "A renderer requires at least one page",
+                ErrorKind::InvalidData,
+            ));
+        }
+        // The bookmark that is added by pdf_page_index is left in the document if the page
+        // belongs to another document, but then the document is discarded anyway.
+        let first_page = &pages[0].0;
+        let is_same_document = pdf_page_index(&doc, first_page).is_some()
+            && pages.iter().all(|(page, layer, _)| {
+                rc::Weak::ptr_eq(&page.document, &first_page.document)
+                    && rc::Weak::ptr_eq(&layer.document, &first_page.document)
+            });
+        if !is_same_document {
+            return Err(Error::new(
+                "The pages and layers must belong to the given document",
+                ErrorKind::InvalidData,
+            ));
+        }
+        if pages.iter().any(|(page, layer, _)| page.page != layer.page) {
+            return Err(Error::new(
+                "The layer of a page belongs to another page",
+                ErrorKind::InvalidData,
+            ));
+        }
+        let is_in_order = pages
+            .iter()
+            .enumerate()
+            .all(|(idx, (page, _, _))| pdf_page_index(&doc, page) == Some(idx));
+        if !is_in_order {
+            return Err(Error::new(
+                "The pages must be all pages of the document in the order in which they have been \
+                added",
+                ErrorKind::InvalidData,
+            ));
+        }
+        let pages = pages
+            .into_iter()
+            .map(|(page_ref, layer_ref, size)| (page_ref, LayerData::new(layer_ref, None), size))
+            .collect();
+        Ok(Renderer::from_pages(doc, pages))
+    }
 
-        Ok(Renderer {
+    fn from_pages(
+        doc: printpdf::PdfDocumentReference,
+        pages: Vec<(printpdf::PdfPageReference, LayerData, Size)>,
+    ) -> Renderer {
+        let default_style = rc::Rc::new(cell::Cell::new(Style::new()));
+        let builtin_encoding = rc::Rc::new(cell::Cell::new(fonts::BuiltinEncoding::default()));
+        let image_cache = rc::Rc::new(cell::RefCell::new(ImageCache::default()));
+        let pages = pages
+            .into_iter()
+            .enumerate()
+            .map(|(doc_index, (page_ref, layer, size))| {
+                Page::new(
+                    page_ref,
+                    layer,
+                    size,
+                    doc_index,
+                    default_style.clone(),
+                    builtin_encoding.clone(),
+                    image_cache.clone(),
+                )
+            })
+            .collect();
+
+        Renderer {
             doc,
-            pages: vec![page],
-        })
+            pages,
+            removed_pages: Vec::new(),
+            default_style,
+            builtin_encoding,
+            image_cache,
+            custom_properties: printpdf::lopdf::Dictionary::new(),
+            font_subsets: Vec::new(),
+            bookmarks: Vec::new(),
+            #[cfg(feature = "encryption")]
+            encryption: None,
+            page_layout: None,
+            page_mode: None,
+            open_action: None,
+        }
     }
 
     /// Sets the PDF conformance for the generated PDF document.
@@ -112,15 +242,308 @@ impl Renderer {
         self
     }
 
+    /// Sets the author of the generated PDF document.
+    pub fn with_author(mut self, author: impl Into<String>) -> Self {
+        self.doc = self.doc.with_author(author);
+        self
+    }
+
+    /// Sets the subject of the generated PDF document.
+    pub fn with_subject(mut self, subject: impl Into<String>) -> Self {
+        self.doc = self.doc.with_subject(subject);
+        self
+    }
+
+    /// Sets the keywords of the generated PDF document.
+    pub fn with_keywords<S: Into<String>>(mut self, keywords: Vec<S>) -> Self {
+        self.doc = self.doc.with_keywords(keywords);
+        self
+    }
+
+    /// Sets the producer of the generated PDF document, i. e. the name of the application that
+    /// converted the document to PDF.
+    pub fn with_producer(mut self, producer: impl Into<String>) -> Self {
+        self.doc = self.doc.with_producer(producer);
+        self
+    }
+
+    /// Encrypts the generated PDF document with the given passwords and permissions.
+    ///
+    /// The document is encrypted using the standard security handler (revision 6) with the AES
+    /// algorithm and a 256-bit key, as defined in PDF 2.0.  The user password is required to open
+    /// the document and may be empty, so that the document can be opened by anyone but is still
+    /// restricted to the given permissions.  The owner password grants full access to the
+    /// document.  If it is empty, the user password is used as the owner password.
+    ///
+    /// The passwords are encoded as UTF-8 and truncated to 127 bytes.  They are not normalized
+    /// with SASLprep, so passwords that contain characters that are changed by SASLprep, for
+    /// example non-ASCII spaces or characters that are not in normalization form KC, might not be
+    /// accepted by PDF viewers.
+    ///
+    /// Note that PDF/A documents must not be encrypted.
+    ///
+    /// *Only available if the `encryption` feature is enabled.*
+    #[cfg(feature = "encryption")]
+    pub fn with_encryption(
+        mut self,
+        user_password: &str,
+        owner_password: &str,
+        permissions: Permissions,
+    ) -> Self {
+        self.encryption = Some((
+            user_password.to_owned(),
+            owner_password.to_owned(),
+            permissions,
+        ));
+        self
+    }
+
+    /// Sets the page layout that PDF viewers should use when opening the document.
+    pub fn with_page_layout(mut self, layout: PageLayout) -> Self {
+        self.page_layout = Some(layout);
+        self
+    }
+
+    /// Sets the page mode that PDF viewers should use when opening the document, for example to
+    /// open it in full-screen mode.
+    pub fn with_page_mode(mut self, mode: PageMode) -> Self {
+        self.page_mode = Some(mode);
+        self
+    }
+
+    /// Sets the page with the given index and the zoom factor that PDF viewers should display
+    /// when opening the document.
+    ///
+    /// The document is opened at the top of the page.  A zoom factor of 1.0 displays the page at
+    /// its actual size, and a zoom factor of 0.0 keeps the current zoom factor of the viewer.  If
+    /// the page does not exist when the document is written, an error is returned.  If the page is
+    /// removed, the open action is removed too.
+    pub fn with_open_action(mut self, page_idx: usize, zoom: f32) -> Self {
+        self.open_action = Some((page_idx, zoom));
+        self
+    }
+
+    /// Sets a custom property with the given key and value in the document information
+    /// dictionary.
+    ///
+    /// Custom properties can be used to attach additional metadata to the document, for example a
+    /// document class or a retention period, that can be read by document management systems.  If
+    /// the property is already set, its value is replaced.  The key must not be empty and must not
+    /// be one of the standard keys of the document information dictionary (`Title`, `Author`,
+    /// `Subject`, `Keywords`, `Creator`, `Producer`, `CreationDate`, `ModDate` and `Trapped`).
+    pub fn set_custom_property(&mut self, key: &str, value: &str) -> Result<(), Error> {
+        if key.is_empty() {
+            return Err(Error::new(
+                "The key of a custom property must not be empty",
+                ErrorKind::InvalidData,
+            ));
+        }
+        if RESERVED_INFO_KEYS.contains(&key) {
+            return Err(Error::new(
+                format!("{} is a reserved document information key", key),
+                ErrorKind::InvalidData,
+            ));
+        }
+        self.custom_properties.set(key, text_string(value));
+        Ok(())
+    }
+
+    /// Adds a bookmark with the given title that links to the top of the page with the given
+    /// index to the document outline and returns the index of the bookmark.
+    ///
+    /// The document outline is displayed as a navigation sidebar by PDF viewers.  Bookmarks are
+    /// displayed in the order in which they are added.  Use [`add_child_bookmark`][] to add
+    /// nested bookmarks.
+    ///
+    /// [`add_child_bookmark`]: #method.add_child_bookmark
+    pub fn add_bookmark(&mut self, title: &str, page_idx: usize) -> Result<usize, Error> {
+        self.push_bookmark(title, page_idx, None)
+    }
+
+    /// Adds a bookmark with the given title that links to the top of the page with the given
+    /// index as the last child of the bookmark with the given index and returns the index of the
+    /// new bookmark.
+    ///
+    /// Nested bookmarks are displayed below their parent in the document outline.  All bookmarks
+    /// are initially expanded.
+    pub fn add_child_bookmark(
+        &mut self,
+        parent: usize,
+        title: &str,
+        page_idx: usize,
+    ) -> Result<usize, Error> {
+        if parent >= self.bookmarks.len() {
+            return Err(Error::new(
+                format!("There is no bookmark with index {}", parent),
+                ErrorKind::InvalidData,
+            ));
+        }
+        self.push_bookmark(title, page_idx, Some(parent))
+    }
+
+    fn push_bookmark(
+        &mut self,
+        title: &str,
+        page_idx: usize,
+        parent: Option<usize>,
+    ) -> Result<usize, Error> {
+        if page_idx >= self.pages.len() {
+            return Err(Error::new(
+                format!("There is no page with index {}", page_idx),
+                ErrorKind::InvalidData,
+            ));
+        }
+        self.bookmarks.push(Bookmark {
+            title: title.to_owned(),
+            page_idx: Some(page_idx),
+            parent,
+        });
+        Ok(self.bookmarks.len() - 1)
+    }
+
     /// Adds a new page with the given size to the document.
     pub fn add_page(&mut self, size: impl Into<Size>) {
-        let size = size.into();
+        let page = self.new_page(size.into());
+        self.pages.push(page);
+    }
+
+    /// Inserts a new page with the given size at the given index.
+    ///
+    /// The following pages are moved back by one index.  Bookmarks and internal links are updated
+    /// so that they still link to the same pages.  Returns an error if the index is greater than
+    /// the number of pages.
+    pub fn insert_page(&mut self, idx: usize, size: impl Into<Size>) -> Result<(), Error> {
+        if idx > self.pages.len() {
+            return Err(Error::new(
+                format!("Cannot insert a page at index {}", idx),
+                ErrorKind::InvalidData,
+            ));
+        }
+        let page = self.new_page(size.into());
+        self.pages.insert(idx, page);
+        self.remap_page_indices(|i| Some(if i >= idx { i + 1 } else { i }));
+        Ok(())
+    }
+
+    /// Removes the page with the given index from the document.
+    ///
+    /// The following pages are moved forward by one index.  Bookmarks that link to the removed
+    /// page are kept without a destination and internal links to the removed page are dropped.
+    /// Returns an error if there is no page with the given index or if it is the only page of the
+    /// document.
+    pub fn remove_page(&mut self, idx: usize) -> Result<(), Error> {
+        if idx >= self.pages.len() {
+            return Err(Error::new(
+                format!("There is no page with index {}", idx),
+                ErrorKind::InvalidData,
+            ));
+        }
+        if self.pages.len() == 1 {
+            return Err(Error::new(
+                "Cannot remove the only page of the document",
+                ErrorKind::InvalidData,
+            ));
+        }
+        let page = self.pages.remove(idx);
+        self.removed_pages.push(page);
+        self.remap_page_indices(|i| match i {
+            i if i == idx => None,
+            i if i > idx => Some(i - 1),
+            i => Some(i),
+        });
+        Ok(())
+    }
+
+    /// Moves the page with the given index to the given target index.
+    ///
+    /// The pages between the two indices are shifted accordingly.  Bookmarks and internal links
+    /// are updated so that they still link to the same pages.  Returns an error if one of the
+    /// indices is out of bounds.
+    pub fn move_page(&mut self, from: usize, to: usize) -> Result<(), Error> {
+        if let Some(idx) = [from, to].iter().find(|idx| **idx >= self.pages.len()) {
+            return Err(Error::new(
+                format!("There is no page with index {}", idx),
+                ErrorKind::InvalidData,
+            ));
+        }
+        let page = self.pages.remove(from);
+        self.pages.insert(to, page);
+        self.remap_page_indices(|i| {
+            Some(match i {
+                i if i == from => to,
+                i if from < to && i > from && i <= to => i - 1,
+                i if to < from && i >= to && i < from => i + 1,
+                i => i,
+            })
+        });
+        Ok(())
+    }
+
+    fn new_page(&mut self, size: Size) -> Page {
         let (page_idx, layer_idx) =
             self.doc
                 .add_page(size.width.into(), size.height.into(), "Layer 1");
         let page_ref = self.doc.get_page(page_idx);
-        let layer_ref = page_ref.get_layer(layer_idx);
-        self.pages.push(Page::new(page_ref, layer_ref, size))
+        let layer = LayerData::new(page_ref.get_layer(layer_idx), Some("Layer 1".to_owned()));
+        Page::new(
+            page_ref,
+            layer,
+            size,
+            self.pages.len() + self.removed_pages.len(),
+            self.default_style.clone(),
+            self.builtin_encoding.clone(),
+            self.image_cache.clone(),
+        )
+    }
+
+    /// Updates the page indices of the bookmarks and internal links using the given mapping from
+    /// the old to the new page index, removing the destinations that are mapped to `None`.
+    fn remap_page_indices(&mut self, map: impl Fn(usize) -> Option<usize>) {
+        for bookmark in &mut self.bookmarks {
+            bookmark.page_idx = bookmark.page_idx.and_then(&map);
+        }
+        self.open_action = self
+            .open_action
+            .and_then(|(page_idx, zoom)| map(page_idx).map(|idx| (idx, zoom)));
+        for page in &self.pages {
+            let mut links = page.internal_links.borrow_mut();
+            *links = links
+                .drain(..)
+                .filter_map(|(rect, target_page)| map(target_page).map(|idx| (rect, idx)))
+                .collect();
+        }
+    }
+
+    /// Sets the default style for this document.
+    ///
+    /// The default style is the base of the style cascade:  Every style that is printed on a page
+    /// of this document is merged into the default style, so the font family, font size and color
+    /// of the default style are used for all strings whose style does not set these values.  If
+    /// this method is not called, an empty style is used, meaning that the default font family of
+    /// the font cache, a font size of 12 points and black text are used.
+    pub fn set_default_style(&mut self, style: impl Into<Style>) {
+        self.default_style.set(style.into());
+    }
+
+    /// Returns the default style for this document.
+    pub fn default_style(&self) -> Style {
+        self.default_style.get()
+    }
+
+    /// Sets the encoding that is used for strings printed with a built-in font.
+    ///
+    /// The encoding applies to all built-in fonts of this document and should be set before any
+    /// text is printed.  If this method is not called, the Windows-1252 encoding
+    /// ([`BuiltinEncoding::WinAnsi`][]) is used.
+    ///
+    /// [`BuiltinEncoding::WinAnsi`]: ../fonts/enum.BuiltinEncoding.html#variant.WinAnsi
+    pub fn set_builtin_encoding(&mut self, encoding: fonts::BuiltinEncoding) {
+        self.builtin_encoding.set(encoding);
+    }
+
+    /// Returns the encoding that is used for strings printed with a built-in font.
+    pub fn builtin_encoding(&self) -> fonts::BuiltinEncoding {
+        self.builtin_encoding.get()
     }
 
     /// Returns the number of pages in this document.
@@ -159,6 +582,114 @@ impl Renderer {
         &mut self.pages[idx]
     }
 
+    /// Calls the given closure for every page of this document with the page index and the
+    /// area of its first layer, reduced by the given margins.
+    ///
+    /// This only iterates the existing pages and never adds new ones.
+    pub fn for_each_page_area(
+        &mut self,
+        margins: impl Into<Margins>,
+        mut f: impl FnMut(usize, &Area<'_>),
+    ) {
+        let margins = margins.into();
+        for (idx, page) in self.pages.iter().enumerate() {
+            let mut area = page.first_layer().area();
+            area.add_margins(margins);
+            f(idx, &area);
+        }
+    }
+
+    /// Prints a page number on every page of this document at the given position.
+    ///
+    /// The `{page}` token in `format` is replaced with the one-based index of the page and the
+    /// `{total}` token is replaced with the total number of pages.  As the total is only known
+    /// once all pages have been added, this method should be called after the content of the
+    /// document has been rendered.  Returns an error if the page number does not fit on a page.
+    /// In this case, no page number is printed on any page.
+    pub fn stamp_page_numbers(
+        &mut self,
+        position: Position,
+        style: Style,
+        format: &str,
+        font_cache: &fonts::FontCache,
+    ) -> Result<(), Error> {
+        for (idx, page) in self.pages.iter().enumerate() {
+            let area = page.first_layer().area();
+            let metrics = area.resolve_style(style).metrics(font_cache);
+            if !area.fits_text_section(position, metrics) {
+                return Err(Error::new(
+                    format!("The page number does not fit on page {}", idx + 1),
+                    ErrorKind::PageSizeExceeded,
+                ));
+            }
+        }
+
+        let total = self.pages.len().to_string();
+        for (idx, page) in self.pages.iter().enumerate() {
+            let s = format
+                .replace("{page}", &(idx + 1).to_string())
+                .replace("{total}", &total);
+            page.first_layer()
+                .area()
+                .print_str(font_cache, position, style, s)?;
+        }
+        Ok(())
+    }
+
+    /// Adds a watermark with the given text to every page of this document.
+    ///
+    /// The text is printed with the given style and opacity, rotated counter-clockwise by the
+    /// given angle in degrees and centered on the page.  It is printed on a new background layer
+    /// named `Watermark`, see [`Page::add_background_layer`][], so it is drawn behind the other
+    /// content of the page.  Returns an error if the watermark does not fit on a page.  In this
+    /// case, no watermark is added to any page.
+    ///
+    /// [`Page::add_background_layer`]: struct.Page.html#method.add_background_layer
+    pub fn add_watermark(
+        &mut self,
+        text: &str,
+        style: Style,
+        font_cache: &fonts::FontCache,
+        opacity: f32,
+        angle: f32,
+    ) -> Result<(), Error> {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let mut positions = Vec::with_capacity(self.pages.len());
+        for (idx, page) in self.pages.iter().enumerate() {
+            let area = page.first_layer().area();
+            let style = area.resolve_style(style);
+            let metrics = style.metrics(font_cache);
+            // The offset of the center of the text from the start of its baseline, rotated like
+            // in TextSection::position
+            let width = style.str_width(font_cache, text);
+            let d = Position::new(width / 2.0, metrics.line_height / 2.0 - metrics.ascent);
+            let offset = Position::new(d.x * cos + d.y * sin, d.y * cos - d.x * sin);
+            let center = Position::new(page.size.width / 2.0, page.size.height / 2.0);
+            let position = center - offset - Position::new(0, metrics.ascent);
+            if !area.fits_text_section(position, metrics) {
+                return Err(Error::new(
+                    format!("The watermark does not fit on page {}", idx + 1),
+                    ErrorKind::PageSizeExceeded,
+                ));
+            }
+            positions.push(position);
+        }
+
+        for (page, position) in self.pages.iter_mut().zip(positions) {
+            page.add_background_layer("Watermark");
+            let layer = page.last_layer();
+            layer.set_opacity(opacity);
+            let area = layer.area();
+            let style = area.resolve_style(style);
+            let mut section = area
+                .text_section(font_cache, position, style.metrics(font_cache))
+                .expect("The watermark fits on the page");
+            section.rotate(angle);
+            section.print_str(text, style)?;
+        }
+        Ok(())
+    }
+
     /// Loads the font from the given data, adds it to the generated document and returns a
     /// reference to it.
     pub fn add_builtin_font(
@@ -178,12 +709,347 @@ impl Renderer {
             .context("Failed to load PDF font")
     }
 
+    /// Replaces the program of the given embedded font with the given subset when writing the
+    /// document.
+    ///
+    /// The text of the document still uses the glyph IDs of the original font.  They are mapped to
+    /// the glyph IDs of the subset using the given map.
+    pub(crate) fn add_font_subset(
+        &mut self,
+        font: &printpdf::IndirectFontRef,
+        data: Vec<u8>,
+        glyph_map: HashMap<u16, u16>,
+    ) {
+        self.font_subsets.push((font.clone(), data, glyph_map));
+    }
+
     /// Writes this PDF document to a writer.
     pub fn write(self, w: impl io::Write) -> Result<(), Error> {
-        self.doc
-            .save(&mut io::BufWriter::new(w))
+        // Removed pages are still part of the printpdf document and might embed images that are
+        // shared with other pages, so they are processed too.
+        let all_pages: Vec<_> = self.pages.iter().chain(&self.removed_pages).collect();
+        let graphics_states: Vec<_> = all_pages
+            .iter()
+            .map(|page| page.graphics_states.take())
+            .collect();
+        let internal_links: Vec<_> = self
+            .pages
+            .iter()
+            .map(|page| page.internal_links.take())
+            .collect();
+        let soft_masks: Vec<_> = all_pages
+            .iter()
+            .map(|page| page.soft_masks.take())
+            .collect();
+        let embedded_images: Vec<_> = all_pages
+            .iter()
+            .map(|page| page.embedded_images.take())
+            .collect();
+        let image_references: Vec<_> = all_pages
+            .iter()
+            .map(|page| page.image_references.take())
+            .collect();
+        #[cfg(any(feature = "images", feature = "svg"))]
+        let xobject_counts: Vec<_> = all_pages
+            .iter()
+            .map(|page| page.xobject_count.get())
+            .collect();
+        let background_pages: Vec<_> = self
+            .pages
+            .iter()
+            .map(|page| page.has_background_layers)
+            .collect();
+        let reordered = !self.removed_pages.is_empty()
+            || self
+                .pages
+                .iter()
+                .enumerate()
+                .any(|(idx, page)| page.doc_index != idx);
+        if let Some(target_page) = internal_links
+            .iter()
+            .flatten()
+            .map(|(_, target_page)| *target_page)
+            .find(|target_page| *target_page >= self.pages.len())
+        {
+            return Err(Error::new(
+                format!("Internal link target page {} does not exist", target_page),
+                ErrorKind::InvalidData,
+            ));
+        }
+        if let Some((page_idx, _)) = self.open_action {
+            if page_idx >= self.pages.len() {
+                return Err(Error::new(
+                    format!("Open action page {} does not exist", page_idx),
+                    ErrorKind::InvalidData,
+                ));
+            }
+        }
+        #[cfg(feature = "encryption")]
+        let is_encrypted = self.encryption.is_some();
+        #[cfg(not(feature = "encryption"))]
+        let is_encrypted = false;
+        if graphics_states.iter().all(|states| states.is_empty())
+            && internal_links.iter().all(|links| links.is_empty())
+            && soft_masks.iter().all(|masks| masks.is_empty())
+            && image_references
+                .iter()
+                .all(|references| references.is_empty())
+            && self.custom_properties.is_empty()
+            && self.font_subsets.is_empty()
+            && self.bookmarks.is_empty()
+            && !is_encrypted
+            && self.page_layout.is_none()
+            && self.page_mode.is_none()
+            && self.open_action.is_none()
+            && !background_pages.contains(&true)
+            && self.builtin_encoding.get() == fonts::BuiltinEncoding::WinAnsi
+            && !reordered
+        {
+            return self
+                .doc
+                .save(&mut io::BufWriter::new(w))
+                .context("Failed to save document");
+        }
+
+        // printpdf does not support adding arbitrary graphics states to a page, custom properties
+        // to the document information dictionary, font subsets without additional dependencies,
+        // nested bookmarks, links to other pages, built-in fonts with an encoding other than
+        // Windows-1252, images with transparency, images that are shared by multiple pages,
+        // background layers, reordering and removing pages, encryption or viewer preferences, so we
+        // have to modify the generated document.
+        let page_heights: Vec<_> = self.pages.iter().map(|page| page.size.height).collect();
+        let bytes = self
+            .doc
+            .save_to_bytes()
+            .context("Failed to save document")?;
+        let mut doc = printpdf::lopdf::Document::load_mem(&bytes)
+            .context("Failed to load generated document")?;
+        let doc_page_ids: Vec<_> = doc.get_pages().values().copied().collect();
+        if doc_page_ids.len() != all_pages.len() {
+            return Err(Error::new(
+                "The document has pages that are not managed by the renderer",
+                ErrorKind::InvalidData,
+            ));
+        }
+        let all_page_ids = all_pages
+            .iter()
+            .map(|page| {
+                doc_page_ids.get(page.doc_index).copied().ok_or_else(|| {
+                    Error::new(
+                        format!("Page {} is missing in the document", page.doc_index),
+                        ErrorKind::InvalidData,
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let page_ids = &all_page_ids[..self.pages.len()];
+        // The XObject indices of the images are only valid if all XObjects of a page have been
+        // added by the renderer.
+        #[cfg(any(feature = "images", feature = "svg"))]
+        for (page_id, count) in all_page_ids.iter().copied().zip(xobject_counts) {
+            let doc_count = page_xobject_count(&doc, page_id)
+                .context("Failed to read the XObjects of a page")?;
+            if count > 0 && doc_count != count {
+                return Err(Error::new(
+                    "A page contains XObjects that have not been added by the renderer",
+                    ErrorKind::InvalidData,
+                ));
+            }
+        }
+        for (page_id, states) in all_page_ids.iter().copied().zip(graphics_states) {
+            if !states.is_empty() {
+                add_page_resources(&mut doc, page_id, "ExtGState", states)
+                    .context("Failed to add graphics states to the document")?;
+            }
+        }
+        for (page_id, links) in page_ids.iter().copied().zip(internal_links) {
+            if !links.is_empty() {
+                add_internal_links(&mut doc, page_id, links, page_ids, &page_heights)
+                    .context("Failed to add internal links to the document")?;
+            }
+        }
+        for (page_id, masks) in all_page_ids.iter().copied().zip(soft_masks) {
+            if !masks.is_empty() {
+                add_soft_masks(&mut doc, page_id, masks)
+                    .context("Failed to add soft masks to the document")?;
+            }
+        }
+        for (page_id, has_background_layers) in page_ids.iter().copied().zip(background_pages) {
+            if has_background_layers {
+                move_background_layers(&mut doc, page_id)
+                    .context("Failed to move the background layers of a page")?;
+            }
+        }
+        if image_references
+            .iter()
+            .any(|references| !references.is_empty())
+        {
+            share_images(&mut doc, &all_page_ids, embedded_images, image_references)
+                .context("Failed to share images between pages")?;
+        }
+        let builtin_encoding = self.builtin_encoding.get();
+        if builtin_encoding != fonts::BuiltinEncoding::WinAnsi {
+            set_builtin_font_encoding(&mut doc, builtin_encoding);
+        }
+        if !self.custom_properties.is_empty() {
+            add_info_entries(&mut doc, self.custom_properties)
+                .context("Failed to add custom properties to the document")?;
+        }
+        for (font, data, glyph_map) in self.font_subsets {
+            replace_font_program(&mut doc, &font, data, &glyph_map)
+                .context("Failed to add a font subset to the document")?;
+        }
+        if !self.bookmarks.is_empty() {
+            add_outline(&mut doc, page_ids, &page_heights, &self.bookmarks)
+                .context("Failed to add the document outline")?;
+        }
+        if self.page_layout.is_some() || self.page_mode.is_some() || self.open_action.is_some() {
+            let open_action = self
+                .open_action
+                .map(|(page_idx, zoom)| (page_ids[page_idx], page_heights[page_idx], zoom));
+            set_viewer_preferences(&mut doc, self.page_layout, self.page_mode, open_action)
+                .context("Failed to set the viewer preferences")?;
+        }
+        if reordered {
+            set_page_order(&mut doc, page_ids).context("Failed to reorder the pages")?;
+            if !self.removed_pages.is_empty() {
+                doc.prune_objects();
+            }
+        }
+        #[cfg(feature = "encryption")]
+        if let Some((user_password, owner_password, permissions)) = self.encryption {
+            encrypt_document(&mut doc, &user_password, &owner_password, permissions)?;
+        }
+        doc.save_to(&mut io::BufWriter::new(w))
             .context("Failed to save document")
     }
+
+    /// Writes this PDF document to a byte vector and returns it.
+    pub fn write_to_vec(self) -> Result<Vec<u8>, Error> {
+        let mut buf = Vec::new();
+        self.write(&mut buf)?;
+        Ok(buf)
+    }
+}
+
+/// The permissions for an encrypted PDF document, see [`Renderer::with_encryption`][].
+///
+/// The permissions only apply to users that opened the document with the user password.  By
+/// default, all operations are permitted.  Note that the permissions are not enforced by the
+/// encryption itself but by the PDF viewer.
+///
+///
+/// *Only available if the `encryption` feature is enabled.*
+///
+/// [`Renderer::with_encryption`]: struct.Renderer.html#method.with_encryption
+#[cfg(feature = "encryption")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Permissions {
+    /// Whether the document may be printed.
+    pub print: bool,
+    /// Whether text and graphics may be copied from the document.
+    pub copy: bool,
+    /// Whether the document may be modified, including annotations and form fields.
+    pub modify: bool,
+}
+
+#[cfg(feature = "encryption")]
+impl Permissions {
+    /// Returns the permission flags for the `P` entry of the encryption dictionary.
+    fn flags(&self) -> u32 {
+        // The bits 7, 8 and 13 to 32 are reserved and must be set.  The bit 10 permits extracting
+        // text for accessibility and is always set.
+        let mut flags = 0xFFFF_F0C0 | 0x200;
+        if self.print {
+            flags |= 0x4 | 0x800;
+        }
+        if self.modify {
+            flags |= 0x8 | 0x20 | 0x100 | 0x400;
+        }
+        if self.copy {
+            flags |= 0x10;
+        }
+        flags
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl Default for Permissions {
+    fn default() -> Permissions {
+        Permissions {
+            print: true,
+            copy: true,
+            modify: true,
+        }
+    }
+}
+
+/// The page layout that PDF viewers should use when opening a document, see
+/// [`Renderer::with_page_layout`][].
+///
+/// [`Renderer::with_page_layout`]: struct.Renderer.html#method.with_page_layout
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PageLayout {
+    /// Display one page at a time.
+    SinglePage,
+    /// Display the pages in one column.
+    OneColumn,
+    /// Display the pages in two columns, with odd-numbered pages on the left.
+    TwoColumnLeft,
+    /// Display the pages in two columns, with odd-numbered pages on the right.
+    TwoColumnRight,
+    /// Display two pages at a time, with odd-numbered pages on the left.
+    TwoPageLeft,
+    /// Display two pages at a time, with odd-numbered pages on the right.
+    TwoPageRight,
+}
+
+impl PageLayout {
+    /// Returns the PDF name of this page layout.
+    fn pdf_name(&self) -> &'static str {
+        match self {
+            PageLayout::SinglePage => "SinglePage",
+            PageLayout::OneColumn => "OneColumn",
+            PageLayout::TwoColumnLeft => "TwoColumnLeft",
+            PageLayout::TwoColumnRight => "TwoColumnRight",
+            PageLayout::TwoPageLeft => "TwoPageLeft",
+            PageLayout::TwoPageRight => "TwoPageRight",
+        }
+    }
+}
+
+/// The page mode that PDF viewers should use when opening a document, see
+/// [`Renderer::with_page_mode`][].
+///
+/// [`Renderer::with_page_mode`]: struct.Renderer.html#method.with_page_mode
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PageMode {
+    /// Show neither the document outline nor the page thumbnails.
+    UseNone,
+    /// Show the document outline.
+    UseOutlines,
+    /// Show the page thumbnails.
+    UseThumbs,
+    /// Show the document in full-screen mode, without menu bar, window controls or other windows.
+    FullScreen,
+    /// Show the optional content group (layer) panel.
+    UseOC,
+    /// Show the attachments panel.
+    UseAttachments,
+}
+
+impl PageMode {
+    /// Returns the PDF name of this page mode.
+    fn pdf_name(&self) -> &'static str {
+        match self {
+            PageMode::UseNone => "UseNone",
+            PageMode::UseOutlines => "UseOutlines",
+            PageMode::UseThumbs => "UseThumbs",
+            PageMode::FullScreen => "FullScreen",
+            PageMode::UseOC => "UseOC",
+            PageMode::UseAttachments => "UseAttachments",
+        }
+    }
 }
 
 /// A page of a PDF document.
@@ -194,26 +1060,165 @@ impl Renderer {
 pub struct Page {
     page: printpdf::PdfPageReference,
     size: Size,
+    // the index of the page in the printpdf document, which is not changed if pages are inserted,
+    // removed or moved
+    doc_index: usize,
     layers: Layers,
+    default_style: rc::Rc<cell::Cell<Style>>,
+    builtin_encoding: rc::Rc<cell::Cell<fonts::BuiltinEncoding>>,
+    // the top left and bottom right corners of the drawn content, relative to the top left corner
+    content_bounds: cell::Cell<Option<(Position, Position)>>,
+    extensions: cell::RefCell<printpdf::lopdf::Dictionary>,
+    // extended graphics states that are added to the page resources when writing the document
+    graphics_states: cell::RefCell<printpdf::lopdf::Dictionary>,
+    // links to other pages of the document that are added to the page annotations when writing
+    // the document, as the target pages might not exist yet
+    internal_links: cell::RefCell<Vec<(printpdf::Rect, usize)>>,
+    // the number of XObjects added to this page, as printpdf names them X0, X1, etc.
+    #[cfg(any(feature = "images", feature = "svg"))]
+    xobject_count: cell::Cell<usize>,
+    // soft masks with the XObject index of their image that are added to the images when writing
+    // the document, as printpdf does not embed them correctly
+    soft_masks: cell::RefCell<Vec<(usize, Vec<u8>)>>,
+    #[cfg_attr(not(feature = "images"), allow(dead_code))]
+    image_cache: rc::Rc<cell::RefCell<ImageCache>>,
+    // the XObject indices and IDs of the images whose data is embedded on this page
+    embedded_images: cell::RefCell<Vec<(usize, usize)>>,
+    // the XObject indices and IDs of the images whose data is embedded on another page and that
+    // are replaced with a reference to that data when writing the document
+    image_references: cell::RefCell<Vec<(usize, usize)>>,
+    // whether background layers have been added to this page, whose content is moved to the
+    // beginning of the page content when writing the document
+    has_background_layers: bool,
 }
 
 impl Page {
     fn new(
         page: printpdf::PdfPageReference,
-        layer: printpdf::PdfLayerReference,
+        layer: LayerData,
         size: Size,
+        doc_index: usize,
+        default_style: rc::Rc<cell::Cell<Style>>,
+        builtin_encoding: rc::Rc<cell::Cell<fonts::BuiltinEncoding>>,
+        image_cache: rc::Rc<cell::RefCell<ImageCache>>,
     ) -> Page {
         Page {
             page,
             size,
+            doc_index,
             layers: Layers::new(layer),
+            default_style,
+            builtin_encoding,
+            content_bounds: Default::default(),
+            extensions: Default::default(),
+            graphics_states: Default::default(),
+            internal_links: Default::default(),
+            #[cfg(any(feature = "images", feature = "svg"))]
+            xobject_count: Default::default(),
+            soft_masks: Default::default(),
+            image_cache,
+            embedded_images: Default::default(),
+            image_references: Default::default(),
+            has_background_layers: false,
+        }
+    }
+
+    /// Returns the bounding box of the content drawn on this page, or `None` if nothing has been
+    /// drawn on this page yet.
+    ///
+    /// The bounding box is returned as its top left corner, relative to the top left corner of
+    /// the page, and its size.  It includes all lines, images and text printed on any layer of
+    /// this page.  For text, the full line height is used.
+    pub fn content_bounds(&self) -> Option<(Position, Size)> {
+        self.content_bounds
+            .get()
+            .map(|(min, max)| (min, Size::new(max.x - min.x, max.y - min.y)))
+    }
+
+    /// Sets the crop box of this page to the bounding box of its content, extended by the given
+    /// padding on all sides.
+    ///
+    /// The crop box is the region of the page that is displayed or printed by PDF viewers, so this
+    /// method can be used to remove excess whitespace from pages with dynamically sized content.
+    /// The crop box never exceeds the page size.  If nothing has been drawn on this page, this
+    /// method does nothing.
+    pub fn crop_to_content(&mut self, padding: Mm) {
+        if let Some((min, max)) = self.content_bounds.get() {
+            let left = (min.x - padding).max(Mm(0.0));
+            let top = (min.y - padding).max(Mm(0.0));
+            let right = self.size.width.min(max.x + padding);
+            let bottom = self.size.height.min(max.y + padding);
+            let crop_box = vec![
+                printpdf::Pt::from(left).0.into(),
+                printpdf::Pt::from(self.size.height - bottom).0.into(),
+                printpdf::Pt::from(right).0.into(),
+                printpdf::Pt::from(self.size.height - top).0.into(),
+            ];
+            self.extend_with("CropBox", crop_box.into());
+        }
+    }
+
+    fn add_content_bounds(&self, min: Position, max: Position) {
+        let bounds = match self.content_bounds.get() {
+            Some((old_min, old_max)) => (
+                Position::new(old_min.x.min(min.x), old_min.y.min(min.y)),
+                Position::new(old_max.x.max(max.x), old_max.y.max(max.y)),
+            ),
+            None => (min, max),
+        };
+        self.content_bounds.set(Some(bounds));
+    }
+
+    /// Registers an extended graphics state with the given name and parameters for this page.
+    fn add_graphics_state(&self, name: &str, params: Vec<(&str, printpdf::lopdf::Object)>) {
+        let mut graphics_states = self.graphics_states.borrow_mut();
+        if !graphics_states.has(name.as_bytes()) {
+            let mut state = printpdf::lopdf::Dictionary::new();
+            state.set("Type", printpdf::lopdf::Object::Name(b"ExtGState".to_vec()));
+            for (key, value) in params {
+                state.set(key, value);
+            }
+            graphics_states.set(name, state);
         }
     }
 
+    /// Registers an XObject that is added to this page and returns its index.
+    #[cfg(any(feature = "images", feature = "svg"))]
+    fn add_xobject(&self) -> usize {
+        let index = self.xobject_count.get();
+        self.xobject_count.set(index + 1);
+        index
+    }
+
+    /// Sets an entry of the PDF page dictionary.
+    fn extend_with(&self, key: &str, value: printpdf::lopdf::Object) {
+        let mut extensions = self.extensions.borrow_mut();
+        extensions.set(key, value);
+        self.page.extend_with(extensions.clone());
+    }
+
     /// Adds a new layer with the given name to the page.
     pub fn add_layer(&mut self, name: impl Into<String>) {
-        let layer = self.page.add_layer(name);
-        self.layers.push(layer);
+        let name = name.into();
+        let layer = self.page.add_layer(name.clone());
+        self.layers.push(LayerData::new(layer, Some(name)));
+    }
+
+    /// Adds a new layer with the given name to the page that is drawn behind all other layers.
+    ///
+    /// The content of background layers is moved to the beginning of the page content when the
+    /// document is written, so it is covered by the content of the other layers, even if that
+    /// content was drawn before.  Multiple background layers are drawn in the order in which they
+    /// were added.
+    pub fn add_background_layer(&mut self, name: impl Into<String>) {
+        use printpdf::lopdf::{content::Operation, Object};
+
+        self.add_layer(name);
+        self.layers.last().layer.add_operation(Operation::new(
+            "MP",
+            vec![Object::Name(BACKGROUND_LAYER_MARKER.to_vec())],
+        ));
+        self.has_background_layers = true;
     }
 
     /// Returns the number of layers on this page.
@@ -226,6 +1231,19 @@ impl Page {
         self.layers.get(idx).map(|l| Layer::new(self, l))
     }
 
+    /// Returns the first layer of this page with the given name.
+    ///
+    /// The first layer of pages created by the [`Renderer`][] and layers created by
+    /// [`Layer::next`][] are named `Layer {n}`, where `{n}` is the number of the layer.
+    /// Layers that have been passed to [`Renderer::from_document`][] have no name.
+    ///
+    /// [`Renderer`]: struct.Renderer.html
+    /// [`Layer::next`]: struct.Layer.html#method.next
+    /// [`Renderer::from_document`]: struct.Renderer.html#method.from_document
+    pub fn layer_by_name(&self, name: &str) -> Option<Layer<'_>> {
+        self.layers.by_name(name).map(|l| Layer::new(self, l))
+    }
+
     /// Returns the first layer of this page.
     pub fn first_layer(&self) -> Layer<'_> {
         Layer::new(self, self.layers.first())
@@ -238,10 +1256,9 @@ impl Page {
 
     fn next_layer(&self, layer: &printpdf::PdfLayerReference) -> Layer<'_> {
         let layer = self.layers.next(layer).unwrap_or_else(|| {
-            let layer = self
-                .page
-                .add_layer(format!("Layer {}", self.layers.len() + 1));
-            self.layers.push(layer)
+            let name = format!("Layer {}", self.layers.len() + 1);
+            let layer = self.page.add_layer(name.clone());
+            self.layers.push(LayerData::new(layer, Some(name)))
         });
         Layer::new(self, layer)
     }
@@ -251,8 +1268,8 @@ impl Page {
 struct Layers(cell::RefCell<Vec<rc::Rc<LayerData>>>);
 
 impl Layers {
-    pub fn new(layer: printpdf::PdfLayerReference) -> Self {
-        Self(vec![LayerData::from(layer).into()].into())
+    pub fn new(layer: LayerData) -> Self {
+        Self(vec![layer.into()].into())
     }
 
     pub fn len(&self) -> usize {
@@ -271,8 +1288,16 @@ impl Layers {
         self.0.borrow().get(idx).cloned()
     }
 
-    pub fn push(&self, layer: printpdf::PdfLayerReference) -> rc::Rc<LayerData> {
-        let layer_data = rc::Rc::from(LayerData::from(layer));
+    pub fn by_name(&self, name: &str) -> Option<rc::Rc<LayerData>> {
+        self.0
+            .borrow()
+            .iter()
+            .find(|l| l.name.as_deref() == Some(name))
+            .cloned()
+    }
+
+    pub fn push(&self, layer: LayerData) -> rc::Rc<LayerData> {
+        let layer_data = rc::Rc::from(layer);
         self.0.borrow_mut().push(layer_data.clone());
         layer_data
     }
@@ -330,10 +1355,46 @@ impl<'p> Layer<'p> {
         rotation: Rotation,
         dpi: Option<f32>,
     ) {
-        let dynamic_image = printpdf::Image::from_dynamic_image(image);
-        let position = self.transform_position(position);
-        let rotation = Some(printpdf::ImageRotation {
-            angle_ccw_degrees: rotation.degrees,
+        let index = self.page.add_xobject();
+        let key = image_key(image);
+        let cached_id = cached_image_id(&self.page.image_cache.borrow(), key, image);
+        let dynamic_image = if let Some(id) = cached_id {
+            // The image data is already embedded, so we only add a placeholder with the same
+            // dimensions that is replaced with a reference to the data when writing the document.
+            self.page.image_references.borrow_mut().push((index, id));
+            printpdf::Image::from(printpdf::ImageXObject {
+                width: printpdf::Px(image.width() as usize),
+                height: printpdf::Px(image.height() as usize),
+                color_space: printpdf::ColorSpace::Greyscale,
+                bits_per_component: printpdf::ColorBits::Bit8,
+                interpolate: false,
+                image_data: Vec::new(),
+                image_filter: None,
+                smask: None,
+                clipping_bbox: None,
+            })
+        } else {
+            let mut image_cache = self.page.image_cache.borrow_mut();
+            let id = image_cache.values().map(Vec::len).sum();
+            image_cache
+                .entry(key)
+                .or_default()
+                .push((image.clone(), id));
+            self.page.embedded_images.borrow_mut().push((index, id));
+
+            let mut dynamic_image = printpdf::Image::from_dynamic_image(image);
+            // printpdf embeds the soft mask as a direct object with the wrong height, so we add it
+            // ourselves when writing the document.
+            if let Some(mask) = dynamic_image.image.smask.take() {
+                let alpha = mask.matte.into_iter().map(|a| a as u8).collect();
+                self.page.soft_masks.borrow_mut().push((index, alpha));
+            }
+            dynamic_image
+        };
+        self.add_image_bounds(&dynamic_image, &position, scale, rotation, dpi);
+        let position = self.transform_position(position);
+        let rotation = Some(printpdf::ImageRotation {
+            angle_ccw_degrees: rotation.degrees,
             rotation_center_x: printpdf::Px(dynamic_image.image.width.0 / 2),
             rotation_center_y: printpdf::Px(dynamic_image.image.height.0 / 2),
         });
@@ -350,13 +1411,122 @@ impl<'p> Layer<'p> {
         );
     }
 
+    /// Adds the given JPEG data without re-encoding it, with its upper left corner at the given
+    /// position, scaled to the given size.
+    #[cfg(feature = "images")]
+    fn add_jpeg(&self, data: &[u8], header: JpegHeader, position: LayerPosition, size: Size) {
+        self.page.add_xobject();
+        let top_left = position.0;
+        let bottom_right = top_left + Position::new(size.width, size.height);
+        self.add_content_bounds(&[top_left, bottom_right], Mm(0.0));
+
+        let lower_left =
+            self.transform_position(LayerPosition(Position::new(top_left.x, bottom_right.y)));
+        // At 72 dpi, one pixel of the image is one point.
+        let scale = |target: Mm, px: usize| printpdf::Pt::from(target).0 / px.max(1) as f32;
+        let image = printpdf::Image::from(printpdf::ImageXObject {
+            width: printpdf::Px(header.width),
+            height: printpdf::Px(header.height),
+            color_space: header.color_space,
+            bits_per_component: printpdf::ColorBits::Bit8,
+            interpolate: true,
+            image_data: data.to_vec(),
+            image_filter: Some(printpdf::ImageFilter::DCT),
+            smask: None,
+            clipping_bbox: None,
+        });
+        image.add_to_layer(
+            self.data.layer.clone(),
+            printpdf::ImageTransform {
+                translate_x: Some(lower_left.x.into()),
+                translate_y: Some(lower_left.y.into()),
+                rotate: None,
+                scale_x: Some(scale(size.width, header.width)),
+                scale_y: Some(scale(size.height, header.height)),
+                dpi: Some(72.0),
+            },
+        );
+    }
+
+    /// Adds the given SVG drawing with its upper left corner at the given position, scaled to the
+    /// given size.
+    #[cfg(feature = "svg")]
+    fn add_svg(&self, svg: printpdf::Svg, position: LayerPosition, size: Size) {
+        self.page.add_xobject();
+        let top_left = position.0;
+        let bottom_right = top_left + Position::new(size.width, size.height);
+        self.add_content_bounds(&[top_left, bottom_right], Mm(0.0));
+
+        let lower_left =
+            self.transform_position(LayerPosition(Position::new(top_left.x, bottom_right.y)));
+        // At 72 dpi, one pixel of the drawing is one point.
+        let scale = |target: Mm, px: usize| printpdf::Pt::from(target).0 / px.max(1) as f32;
+        let scale_x = scale(size.width, svg.width.0);
+        let scale_y = scale(size.height, svg.height.0);
+        svg.add_to_layer(
+            &self.data.layer,
+            printpdf::SvgTransform {
+                translate_x: Some(lower_left.x.into()),
+                translate_y: Some(lower_left.y.into()),
+                rotate: None,
+                scale_x: Some(scale_x),
+                scale_y: Some(scale_y),
+                dpi: Some(72.0),
+            },
+        );
+    }
+
+    /// Adds the bounding box of the given image, rotated around its center, with its lower left
+    /// corner at the given position to the content bounds of the page.
+    #[cfg(feature = "images")]
+    fn add_image_bounds(
+        &self,
+        image: &printpdf::Image,
+        position: &LayerPosition,
+        scale: Scale,
+        rotation: Rotation,
+        dpi: Option<f32>,
+    ) {
+        let dpi = dpi.unwrap_or(300.0);
+        let width = Mm::from(printpdf::Px(image.image.width.0).into_pt(dpi)) * scale.x;
+        let height = Mm::from(printpdf::Px(image.image.height.0).into_pt(dpi)) * scale.y;
+        let center = position.0 + Position::new(width / 2.0, height / -2.0);
+        let (sin, cos) = rotation.degrees.to_radians().sin_cos();
+        let corners: Vec<_> = [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)]
+            .iter()
+            .map(|(dx, dy)| {
+                let x = width * 0.5 * *dx;
+                let y = height * 0.5 * *dy;
+                center + Position::new(x * cos - y * sin, x * sin + y * cos)
+            })
+            .collect();
+        self.add_content_bounds(&corners, Mm(0.0));
+    }
+
+    /// Adds the bounding box of the given points, extended by the given margin, to the content
+    /// bounds of the page.
+    fn add_content_bounds(&self, points: &[Position], margin: Mm) {
+        if let Some(first) = points.first() {
+            let (min, max) = points.iter().fold((*first, *first), |(min, max), p| {
+                (
+                    Position::new(min.x.min(p.x), min.y.min(p.y)),
+                    Position::new(max.x.max(p.x), max.y.max(p.y)),
+                )
+            });
+            let margin = Position::new(margin, margin);
+            self.page.add_content_bounds(min - margin, max + margin);
+        }
+    }
+
     fn add_line_shape<I>(&self, points: I)
     where
         I: IntoIterator<Item = LayerPosition>,
     {
+        let points: Vec<_> = points.into_iter().map(|pos| pos.0).collect();
+        self.add_content_bounds(&points, self.data.outline_thickness.get() / 2.0);
         let line_points: Vec<_> = points
             .into_iter()
-            .map(|pos| (self.transform_position(pos).into(), false))
+            .map(|pos| (self.transform_position(LayerPosition(pos)).into(), false))
             .collect();
         let line = printpdf::Line {
             points: line_points,
@@ -365,12 +1535,175 @@ impl<'p> Layer<'p> {
         self.data.layer.add_line(line);
     }
 
-    fn set_fill_color(&self, color: Option<Color>) {
+    fn add_rect(&self, position: LayerPosition, size: Size, mode: printpdf::path::PaintMode) {
+        let top_left = position.0;
+        let bottom_right = top_left + Position::new(size.width, size.height);
+        let margin = match mode {
+            printpdf::path::PaintMode::Clip => None,
+            printpdf::path::PaintMode::Fill => Some(Mm(0.0)),
+            _ => Some(self.data.outline_thickness.get() / 2.0),
+        };
+        if let Some(margin) = margin {
+            self.add_content_bounds(&[top_left, bottom_right], margin);
+        }
+
+        let lower_left =
+            self.transform_position(LayerPosition(Position::new(top_left.x, bottom_right.y)));
+        let upper_right =
+            self.transform_position(LayerPosition(Position::new(bottom_right.x, top_left.y)));
+        let rect = printpdf::Rect::new(
+            lower_left.x.into(),
+            lower_left.y.into(),
+            upper_right.x.into(),
+            upper_right.y.into(),
+        )
+        .with_mode(mode);
+        self.data.layer.add_rect(rect);
+    }
+
+    /// Adds the given rectangles to a single path and fills it.
+    #[cfg(feature = "qrcode")]
+    fn fill_rects(&self, rects: impl IntoIterator<Item = (LayerPosition, Size)>) {
+        use printpdf::lopdf::content::Operation;
+
+        let mut is_empty = true;
+        for (position, size) in rects {
+            let top_left = position.0;
+            let bottom_right = top_left + Position::new(size.width, size.height);
+            self.add_content_bounds(&[top_left, bottom_right], Mm(0.0));
+            let lower_left =
+                self.transform_position(LayerPosition(Position::new(top_left.x, bottom_right.y)));
+            let operands = vec![
+                printpdf::Pt::from(lower_left.x).0.into(),
+                printpdf::Pt::from(lower_left.y).0.into(),
+                printpdf::Pt::from(size.width).0.into(),
+                printpdf::Pt::from(size.height).0.into(),
+            ];
+            self.data
+                .layer
+                .add_operation(Operation::new("re", operands));
+            is_empty = false;
+        }
+        if !is_empty {
+            self.data.layer.add_operation(Operation::new("f", vec![]));
+        }
+    }
+
+    /// Adds a closed path with the given points and paints it using the given mode.
+    ///
+    /// The flags have the same meaning as for [`printpdf::Line`]:  if a point and its successor
+    /// are both flagged, they start a cubic bezier curve and the next two points are used as the
+    /// second control point and the end point.
+    fn add_path(&self, points: Vec<(LayerPosition, bool)>, mode: printpdf::path::PaintMode) {
+        use printpdf::lopdf::content::Operation;
+        use printpdf::path::PaintMode;
+
+        let bounds: Vec<_> = points.iter().map(|(pos, _)| pos.0).collect();
+        match mode {
+            PaintMode::Clip => {}
+            PaintMode::Fill => self.add_content_bounds(&bounds, Mm(0.0)),
+            _ => self.add_content_bounds(&bounds, self.data.outline_thickness.get() / 2.0),
+        }
+
+        let line = printpdf::Line {
+            points: points
+                .into_iter()
+                .map(|(pos, flag)| (self.transform_position(pos).into(), flag))
+                .collect(),
+            is_closed: false,
+        };
+        let mut operations = line.into_stream_op();
+        // Replace the stroke operator with the operators for the requested paint mode
+        operations.pop();
+        operations.push(Operation::new("h", vec![]));
+        match mode {
+            PaintMode::Clip => {
+                operations.push(Operation::new("W", vec![]));
+                operations.push(Operation::new("n", vec![]));
+            }
+            PaintMode::Fill => operations.push(Operation::new("f", vec![])),
+            PaintMode::Stroke => operations.push(Operation::new("S", vec![])),
+            PaintMode::FillStroke => operations.push(Operation::new("B", vec![])),
+        }
+        for operation in operations {
+            self.data.layer.add_operation(operation);
+        }
+    }
+
+    /// Adds an open path with the given points, see [`add_path`][] for the meaning of the flags.
+    ///
+    /// [`add_path`]: #method.add_path
+    fn add_curve_shape(&self, points: Vec<(LayerPosition, bool)>) {
+        let bounds: Vec<_> = points.iter().map(|(pos, _)| pos.0).collect();
+        self.add_content_bounds(&bounds, self.data.outline_thickness.get() / 2.0);
+        let line = printpdf::Line {
+            points: points
+                .into_iter()
+                .map(|(pos, flag)| (self.transform_position(pos).into(), flag))
+                .collect(),
+            is_closed: false,
+        };
+        self.data.layer.add_line(line);
+    }
+
+    /// Saves the graphics state, runs the given closure and restores the graphics state.
+    fn with_saved_state<F: FnOnce()>(&self, f: F) {
+        self.data.preserve_state(|| {
+            self.data.layer.save_graphics_state();
+            f();
+            self.data.layer.restore_graphics_state();
+        });
+    }
+
+    fn set_fill_color(&self, color: Option<Color>, alpha: f32) {
         if self.data.update_fill_color(color) {
             self.data
                 .layer
                 .set_fill_color(color.unwrap_or(Color::Rgb(0, 0, 0)).into());
         }
+        self.set_fill_alpha(alpha);
+    }
+
+    fn set_fill_alpha(&self, alpha: f32) {
+        let alpha = clamp_alpha(alpha);
+        if self.data.update_fill_alpha(alpha) {
+            self.set_alpha("GsFillAlpha", "ca", alpha * self.data.opacity.get());
+        }
+    }
+
+    /// Sets the opacity of this layer.
+    ///
+    /// The opacity is a value between 0.0 (transparent) and 1.0 (opaque) that is applied to all
+    /// subsequent drawing operations on this layer, both for filling and for stroking.  It is
+    /// combined with the fill opacity of the style that is used for drawing.  Content that has
+    /// already been drawn is not affected.  Values outside of this range are clamped, NaN is
+    /// treated as opaque, and the opacity is rounded to multiples of 0.001 when it is written to
+    /// the document.
+    pub fn set_opacity(&self, alpha: f32) {
+        let alpha = clamp_alpha(alpha);
+        if self.data.update_opacity(alpha) {
+            self.set_alpha("GsStrokeAlpha", "CA", alpha);
+            self.set_alpha("GsFillAlpha", "ca", self.data.fill_alpha.get() * alpha);
+        }
+    }
+
+    /// Activates a graphics state with the given alpha value for the given key (`ca` for filling,
+    /// `CA` for stroking).
+    ///
+    /// The alpha value is rounded to multiples of 0.001, so that graphics states with the same
+    /// name always have the same alpha value.
+    fn set_alpha(&self, prefix: &str, key: &str, alpha: f32) {
+        let steps = (alpha * 1000.0).round();
+        let alpha = steps / 1000.0;
+        let name = format!("{}{}", prefix, steps as u32);
+        self.page
+            .add_graphics_state(&name, vec![(key, printpdf::lopdf::Object::Real(alpha))]);
+        self.data
+            .layer
+            .add_operation(printpdf::lopdf::content::Operation::new(
+                "gs",
+                vec![printpdf::lopdf::Object::Name(name.into_bytes())],
+            ));
     }
 
     fn set_outline_thickness(&self, thickness: Mm) {
@@ -381,6 +1714,31 @@ impl<'p> Layer<'p> {
         }
     }
 
+    fn set_dash_pattern(&self, dash_pattern: Option<&[f32]>) {
+        if self.data.update_dash_pattern(dash_pattern) {
+            let dashes = dash_pattern
+                .unwrap_or_default()
+                .iter()
+                .map(|&length| printpdf::lopdf::Object::Real(printpdf::Pt::from(Mm(length)).0))
+                .collect();
+            self.data
+                .layer
+                .add_operation(printpdf::lopdf::content::Operation::new(
+                    "d",
+                    vec![
+                        printpdf::lopdf::Object::Array(dashes),
+                        printpdf::lopdf::Object::Integer(0),
+                    ],
+                ));
+        }
+    }
+
+    fn set_line_style(&self, line_style: &LineStyle) {
+        self.set_outline_thickness(line_style.thickness());
+        self.set_outline_color(line_style.color());
+        self.set_dash_pattern(line_style.dash_pattern());
+    }
+
     fn set_outline_color(&self, color: Color) {
         if self.data.update_outline_color(color) {
             self.data.layer.set_outline_color(color.into());
@@ -394,6 +1752,14 @@ impl<'p> Layer<'p> {
             .set_text_cursor(cursor.x.into(), cursor.y.into());
     }
 
+    /// Moves the text cursor relative to the start of the current line, with positive offsets
+    /// moving it to the right and down.
+    fn move_text_cursor(&self, offset: Position) {
+        self.data
+            .layer
+            .set_text_cursor(offset.x.into(), (offset.y * -1.0).into());
+    }
+
     fn begin_text_section(&self) {
         self.data.layer.begin_text_section();
     }
@@ -406,22 +1772,98 @@ impl<'p> Layer<'p> {
         self.data.layer.add_line_break();
     }
 
+    fn set_baseline_shift(&self, shift: f32) {
+        self.data.layer.set_line_offset(shift);
+    }
+
+    /// Sets the text matrix to a horizontal shear with the given factor, followed by a
+    /// counter-clockwise rotation by the given angle in degrees, that is translated to the given
+    /// position.
+    fn set_text_matrix(&self, position: LayerPosition, rotation: f32, shear: f32) {
+        let position = self.transform_position(position);
+        let (sin, cos) = rotation.to_radians().sin_cos();
+        self.data.layer.set_text_matrix(printpdf::TextMatrix::Raw([
+            cos,
+            sin,
+            shear * cos - sin,
+            shear * sin + cos,
+            printpdf::Pt::from(position.x).0,
+            printpdf::Pt::from(position.y).0,
+        ]));
+    }
+
     fn set_line_height(&self, line_height: Mm) {
         self.data.layer.set_line_height(line_height.0);
     }
 
-    fn set_font(&self, font: &printpdf::IndirectFontRef, font_size: u8) {
-        self.data.layer.set_font(font, font_size.into());
+    fn set_font(&self, font: &printpdf::IndirectFontRef, font_size: f32) {
+        self.data.layer.set_font(font, font_size);
     }
 
-    fn write_positioned_codepoints<P, C>(&self, positions: P, codepoints: C)
-    where
-        P: IntoIterator<Item = i64>,
+    fn set_character_spacing(&self, spacing: Mm) {
+        self.data
+            .layer
+            .set_character_spacing(printpdf::Pt::from(spacing).0);
+    }
+
+    /// Writes the given single-byte codepoints of a built-in font without position adjustments.
+    fn write_builtin_codepoints(&self, codepoints: Vec<u16>) {
+        use printpdf::lopdf::{Object, StringFormat};
+
+        let bytes = codepoints.into_iter().map(|c| c as u8).collect();
+        self.data
+            .layer
+            .add_operation(printpdf::lopdf::content::Operation::new(
+                "Tj",
+                vec![Object::String(bytes, StringFormat::Hexadecimal)],
+            ));
+    }
+
+    /// Writes the given codepoints, each preceded by the given position adjustment in thousandths
+    /// of the font size, and followed by the given trailing adjustment.
+    ///
+    /// If `single_byte` is set, the codepoints are written as single bytes as used by the
+    /// built-in fonts.  Otherwise, they are written as two-byte glyph IDs.
+    fn write_positioned_codepoints<P, C>(
+        &self,
+        positions: P,
+        codepoints: C,
+        trailing: f32,
+        single_byte: bool,
+    ) where
+        P: IntoIterator<Item = f32>,
         C: IntoIterator<Item = u16>,
     {
+        use printpdf::lopdf::{Object, StringFormat};
+
+        let position = |pos: f32| {
+            if pos.fract() == 0.0 {
+                Object::Integer(pos as i64)
+            } else {
+                Object::Real(pos)
+            }
+        };
+        let mut list = Vec::new();
+        for (pos, codepoint) in positions.into_iter().zip(codepoints) {
+            if pos != 0.0 {
+                list.push(position(pos));
+            }
+            let bytes = if single_byte {
+                vec![codepoint as u8]
+            } else {
+                codepoint.to_be_bytes().to_vec()
+            };
+            list.push(Object::String(bytes, StringFormat::Hexadecimal));
+        }
+        if trailing != 0.0 {
+            list.push(position(trailing));
+        }
         self.data
             .layer
-            .write_positioned_codepoints(positions.into_iter().zip(codepoints.into_iter()));
+            .add_operation(printpdf::lopdf::content::Operation::new(
+                "TJ",
+                vec![Object::Array(list)],
+            ));
     }
 
     /// Transforms the given position that is relative to the upper left corner of the layer to a
@@ -434,22 +1876,60 @@ impl<'p> Layer<'p> {
     pub fn add_annotation(&mut self, annotation: printpdf::LinkAnnotation) {
         self.data.layer.add_link_annotation(annotation);
     }
+
+    /// Adds a link annotation with the given rectangle to the layer that jumps to the top of the
+    /// page with the given index.
+    ///
+    /// The target page does not have to exist yet, but it must exist when the document is
+    /// written.
+    pub fn add_internal_link(&mut self, rect: printpdf::Rect, target_page: usize) {
+        self.page
+            .internal_links
+            .borrow_mut()
+            .push((rect, target_page));
+    }
 }
 
 #[derive(Debug)]
 struct LayerData {
     layer: printpdf::PdfLayerReference,
+    // the name of the layer, or None if it is not known
+    name: Option<String>,
     fill_color: cell::Cell<Color>,
+    fill_alpha: cell::Cell<f32>,
+    opacity: cell::Cell<f32>,
     outline_color: cell::Cell<Color>,
     outline_thickness: cell::Cell<Mm>,
+    dash_pattern: cell::RefCell<Option<Vec<f32>>>,
 }
 
 impl LayerData {
+    fn new(layer: printpdf::PdfLayerReference, name: Option<String>) -> Self {
+        Self {
+            layer,
+            name,
+            fill_color: Color::Rgb(0, 0, 0).into(),
+            fill_alpha: 1.0.into(),
+            opacity: 1.0.into(),
+            outline_color: Color::Rgb(0, 0, 0).into(),
+            outline_thickness: Mm::from(printpdf::Pt(1.0)).into(),
+            dash_pattern: None.into(),
+        }
+    }
+
     pub fn update_fill_color(&self, color: Option<Color>) -> bool {
         let color = color.unwrap_or(Color::Rgb(0, 0, 0));
         self.fill_color.replace(color) != color
     }
 
+    pub fn update_fill_alpha(&self, alpha: f32) -> bool {
+        self.fill_alpha.replace(alpha) != alpha
+    }
+
+    pub fn update_opacity(&self, opacity: f32) -> bool {
+        self.opacity.replace(opacity) != opacity
+    }
+
     pub fn update_outline_color(&self, color: Color) -> bool {
         self.outline_color.replace(color) != color
     }
@@ -457,17 +1937,34 @@ impl LayerData {
     pub fn update_outline_thickness(&self, thickness: Mm) -> bool {
         self.outline_thickness.replace(thickness) != thickness
     }
-}
 
-impl From<printpdf::PdfLayerReference> for LayerData {
-    fn from(layer: printpdf::PdfLayerReference) -> Self {
-        Self {
-            layer,
-            fill_color: Color::Rgb(0, 0, 0).into(),
-            outline_color: Color::Rgb(0, 0, 0).into(),
-            outline_thickness: Mm::from(printpdf::Pt(1.0)).into(),
+    pub fn update_dash_pattern(&self, dash_pattern: Option<&[f32]>) -> bool {
+        if self.dash_pattern.borrow().as_deref() == dash_pattern {
+            false
+        } else {
+            self.dash_pattern
+                .replace(dash_pattern.map(ToOwned::to_owned));
+            true
         }
     }
+
+    /// Runs the given closure and resets the cached graphics state to its previous value
+    /// afterwards.
+    pub fn preserve_state<F: FnOnce()>(&self, f: F) {
+        let fill_color = self.fill_color.get();
+        let fill_alpha = self.fill_alpha.get();
+        let opacity = self.opacity.get();
+        let outline_color = self.outline_color.get();
+        let outline_thickness = self.outline_thickness.get();
+        let dash_pattern = self.dash_pattern.borrow().clone();
+        f();
+        self.fill_color.set(fill_color);
+        self.fill_alpha.set(fill_alpha);
+        self.opacity.set(opacity);
+        self.outline_color.set(outline_color);
+        self.outline_thickness.set(outline_thickness);
+        self.dash_pattern.replace(dash_pattern);
+    }
 }
 
 /// A view on an area of a PDF layer that can be drawn on.
@@ -506,12 +2003,21 @@ impl<'p> Area<'p> {
     }
 
     /// Reduces the size of the drawable area by the given margins.
+    ///
+    /// If the margins are larger than the area, its width or height is set to zero instead of
+    /// becoming negative.
     pub fn add_margins(&mut self, margins: impl Into<Margins>) {
         let margins = margins.into();
         self.origin.x += margins.left;
         self.origin.y += margins.top;
-        self.size.width -= margins.left + margins.right;
-        self.size.height -= margins.top + margins.bottom;
+        self.size.width = (self.size.width - margins.left - margins.right).max(Mm(0.0));
+        self.size.height = (self.size.height - margins.top - margins.bottom).max(Mm(0.0));
+    }
+
+    /// Returns the origin of this area, i. e. the position of its upper left corner relative to
+    /// the upper left corner of the page.
+    pub fn origin(&self) -> Position {
+        self.origin
     }
 
     /// Returns the size of this area.
@@ -519,13 +2025,69 @@ impl<'p> Area<'p> {
         self.size
     }
 
+    /// Returns the origin and the size of this area.
+    ///
+    /// See [`origin`](#method.origin) and [`size`](#method.size).
+    pub fn bounds(&self) -> (Position, Size) {
+        (self.origin, self.size)
+    }
+
+    /// Returns whether the given position is inside this area, including its edges.
+    ///
+    /// Like the [`origin`](#method.origin) of the area, the position is relative to the upper left
+    /// corner of the page.
+    pub fn contains(&self, p: Position) -> bool {
+        p.x >= self.origin.x
+            && p.y >= self.origin.y
+            && p.x <= self.origin.x + self.size.width
+            && p.y <= self.origin.y + self.size.height
+    }
+
+    /// Returns the area that is covered by both this area and the given area, or `None` if the
+    /// areas do not overlap.
+    ///
+    /// The returned area is drawn on the layer of this area.  Areas on different layers should not
+    /// be combined.
+    pub fn intersection(&self, other: &Area<'_>) -> Option<Area<'p>> {
+        let left = self.origin.x.max(other.origin.x);
+        let top = self.origin.y.max(other.origin.y);
+        let right = (self.origin.x + self.size.width).min(other.origin.x + other.size.width);
+        let bottom = (self.origin.y + self.size.height).min(other.origin.y + other.size.height);
+        if left < right && top < bottom {
+            let mut area = self.clone();
+            area.origin = Position::new(left, top);
+            area.size = Size::new(right - left, bottom - top);
+            Some(area)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the smallest area that contains both this area and the given area.
+    ///
+    /// The returned area is drawn on the layer of this area.  Areas on different layers should not
+    /// be combined.
+    pub fn bounding_union(&self, other: &Area<'_>) -> Area<'p> {
+        let left = self.origin.x.min(other.origin.x);
+        let top = self.origin.y.min(other.origin.y);
+        let right = (self.origin.x + self.size.width).max(other.origin.x + other.size.width);
+        let bottom = (self.origin.y + self.size.height).max(other.origin.y + other.size.height);
+        let mut area = self.clone();
+        area.origin = Position::new(left, top);
+        area.size = Size::new(right - left, bottom - top);
+        area
+    }
+
     /// Adds the given offset to the area, reducing the drawable area.
+    ///
+    /// If the offset is larger than the area, its width or height is set to zero instead of
+    /// becoming negative.
     pub fn add_offset(&mut self, offset: impl Into<Position>) {
         let offset = offset.into();
         self.origin.x += offset.x;
         self.origin.y += offset.y;
-        self.size.width -= offset.x;
-        self.size.height -= offset.y;
+        self.size.width = (self.size.width - offset.x).max(Mm(0.0));
+        self.size.height = (self.size.height - offset.y).max(Mm(0.0));
     }
 
     /// Sets the size of this area.
@@ -564,6 +2126,70 @@ impl<'p> Area<'p> {
         areas
     }
 
+    /// Splits this area horizontally using the given absolute widths.
+    ///
+    /// The returned vector has the same number of elements as the provided slice.  A width of
+    /// zero (or less) marks a flexible area:  The width that is not used by the areas with a
+    /// positive width is distributed evenly among the flexible areas.
+    ///
+    /// If the widths exceed the width of this area, they are clamped from left to right:  Each
+    /// area is at most as wide as the space that is left after the previous areas, so the areas
+    /// after the overflow and all flexible areas have a width of zero.
+    pub fn split_horizontally_abs(&self, widths: &[Mm]) -> Vec<Area<'p>> {
+        let fixed_width: Mm = widths
+            .iter()
+            .filter(|width| **width > Mm(0.0))
+            .copied()
+            .sum();
+        let flexible_count = widths.iter().filter(|width| **width <= Mm(0.0)).count();
+        let flexible_width = if flexible_count > 0 {
+            (self.size.width - fixed_width).max(Mm(0.0)) / flexible_count as f32
+        } else {
+            Mm(0.0)
+        };
+        let mut offset = Mm(0.0);
+        let mut areas = Vec::new();
+        for width in widths {
+            let width = if *width > Mm(0.0) {
+                *width
+            } else {
+                flexible_width
+            };
+            let width = width.min((self.size.width - offset).max(Mm(0.0)));
+            let mut area = self.clone();
+            area.origin.x += offset;
+            area.size.width = width;
+            areas.push(area);
+            offset += width;
+        }
+        areas
+    }
+
+    /// Splits this area vertically using the given weights.
+    ///
+    /// The returned vector has the same number of elements as the provided slice.  The height of
+    /// the *i*-th area is `height * weights[i] / total_weight`, where `height` is the height of
+    /// this area, and `total_weight` is the sum of all given weights.  If only one weight is given,
+    /// the returned area is a copy of this area.
+    pub fn split_vertically(&self, weights: &[usize]) -> Vec<Area<'p>> {
+        if weights.len() == 1 {
+            return vec![self.clone()];
+        }
+        let total_weight: usize = weights.iter().sum();
+        let factor = self.size.height / total_weight as f32;
+        let heights = weights.iter().map(|weight| factor * *weight as f32);
+        let mut offset = Mm(0.0);
+        let mut areas = Vec::new();
+        for height in heights {
+            let mut area = self.clone();
+            area.origin.y += offset;
+            area.size.height = height;
+            areas.push(area);
+            offset += height;
+        }
+        areas
+    }
+
     /// Inserts an image into the document.
     ///
     /// *Only available if the `images` feature is enabled.*
@@ -573,6 +2199,10 @@ impl<'p> Area<'p> {
     /// render functionality will do this for you and is the recommended way to
     /// insert an image into an Area.
     ///
+    /// If the same image is inserted multiple times, for example a logo on every page, its data is
+    /// only embedded once in the document.  Images are considered identical if they have the same
+    /// dimensions, color type and pixel data.
+    ///
     /// [`Image`]: ../elements/struct.Image.html
     #[cfg(feature = "images")]
     pub fn add_image(
@@ -587,6 +2217,81 @@ impl<'p> Area<'p> {
             .add_image(image, self.position(position), scale, rotation, dpi);
     }
 
+    /// Inserts an image from the given RGBA pixel buffer with the given position, scale and DPI.
+    ///
+    /// *Only available if the `images` feature is enabled.*
+    ///
+    /// The buffer must contain four bytes (red, green, blue and alpha) for each of the `width` ×
+    /// `height` pixels, row by row starting at the top left corner.  The alpha channel is embedded
+    /// as a soft mask.  Otherwise, the image is added like with [`add_image`](#method.add_image).
+    #[cfg(feature = "images")]
+    pub fn add_rgba(
+        &self,
+        pixels: &[u8],
+        width: u32,
+        height: u32,
+        position: Position,
+        scale: Scale,
+        dpi: Option<f32>,
+    ) -> Result<(), Error> {
+        let expected_len = (width as usize)
+            .checked_mul(height as usize)
+            .and_then(|len| len.checked_mul(4));
+        if expected_len != Some(pixels.len()) {
+            return Err(Error::new(
+                format!(
+                    "Invalid RGBA pixel buffer: expected {} × {} × 4 bytes, got {}",
+                    width,
+                    height,
+                    pixels.len()
+                ),
+                ErrorKind::InvalidData,
+            ));
+        }
+        let image = image::RgbaImage::from_raw(width, height, pixels.to_vec())
+            .map(image::DynamicImage::ImageRgba8)
+            .ok_or_else(|| Error::new("Invalid RGBA pixel buffer", ErrorKind::InvalidData))?;
+        self.add_image(&image, position, scale, Rotation::default(), dpi);
+        Ok(())
+    }
+
+    /// Inserts the given JPEG image with the given position and size without re-encoding it.
+    ///
+    /// *Only available if the `images` feature is enabled.*
+    ///
+    /// In contrast to [`add_image`](#method.add_image), the JPEG data is not decoded but embedded
+    /// byte for byte using the `DCTDecode` filter, so the file size and the image quality are
+    /// preserved.  The dimensions and the color space are read from the JPEG header.  Only
+    /// baseline and progressive JPEG images with eight bits per sample and one (greyscale) or
+    /// three (RGB) color components are supported.  The image is scaled to the given size and the
+    /// position of its upper left corner is relative to the upper left corner of the area.
+    #[cfg(feature = "images")]
+    pub fn add_jpeg_raw(
+        &self,
+        jpeg_bytes: &[u8],
+        position: Position,
+        size: Size,
+    ) -> Result<(), Error> {
+        let header = parse_jpeg_header(jpeg_bytes)?;
+        self.layer
+            .add_jpeg(jpeg_bytes, header, self.position(position), size);
+        Ok(())
+    }
+
+    /// Draws the given SVG drawing with the given position and size.
+    ///
+    /// *Only available if the `svg` feature is enabled.*
+    ///
+    /// The drawing is converted to PDF vector graphics and scaled to the given size.  The position
+    /// of its upper left corner is relative to the upper left corner of the area.
+    #[cfg(feature = "svg")]
+    pub fn add_svg(&self, svg_source: &str, position: Position, size: Size) -> Result<(), Error> {
+        let svg = printpdf::Svg::parse(svg_source)
+            .map_err(|err| Error::new(format!("Failed to parse SVG drawing: {}", err), err))?;
+        self.layer.add_svg(svg, self.position(position), size);
+        Ok(())
+    }
+
     /// Draws a line with the given points and the given line style.
     ///
     /// The points are relative to the upper left corner of the area.
@@ -594,55 +2299,448 @@ impl<'p> Area<'p> {
     where
         I: IntoIterator<Item = Position>,
     {
-        self.layer.set_outline_thickness(line_style.thickness());
-        self.layer.set_outline_color(line_style.color());
+        self.layer.set_line_style(&line_style);
         self.layer
             .add_line_shape(points.into_iter().map(|pos| self.position(pos)));
     }
 
-    /// Tries to draw the given string at the given position and returns `true` if the area was
-    /// large enough to draw the string.
+    /// Draws a rectangle with the given position and size that is filled with the given color.
     ///
-    /// The font cache must contain the PDF font for the font set in the style.  The position is
-    /// relative to the upper left corner of the area.
-    pub fn print_str<S: AsRef<str>>(
-        &self,
-        font_cache: &fonts::FontCache,
-        position: Position,
-        style: Style,
-        s: S,
-    ) -> Result<bool, Error> {
-        if let Some(mut section) =
-            self.text_section(font_cache, position, style.metrics(font_cache))
-        {
-            section.print_str(s, style)?;
-            Ok(true)
-        } else {
-            Ok(false)
-        }
+    /// The position of the upper left corner of the rectangle is relative to the upper left corner
+    /// of the area.  The rectangle is not outlined.
+    pub fn fill_rect(&self, position: Position, size: Size, color: Color) {
+        self.layer.set_fill_color(Some(color), 1.0);
+        self.layer.add_rect(
+            self.position(position),
+            size,
+            printpdf::path::PaintMode::Fill,
+        );
     }
 
-    /// Creates a new text section at the given position if the text section fits in this area.
+    /// Draws a QR code encoding the given data and returns its size.
     ///
-    /// The given style is only used to calculate the line height of the section.  The position is
-    /// relative to the upper left corner of the area.  The font cache must contain the PDF font
-    /// for all fonts printed with the text section.
-    pub fn text_section<'f>(
+    /// *Only available if the `qrcode` feature is enabled.*
+    ///
+    /// The QR code uses the medium error correction level that can restore about 15 % of the
+    /// data.  Use [`draw_qrcode_with_ec_level`][] to select a different level.
+    ///
+    /// [`draw_qrcode_with_ec_level`]: #method.draw_qrcode_with_ec_level
+    #[cfg(feature = "qrcode")]
+    pub fn draw_qrcode(
         &self,
-        font_cache: &'f fonts::FontCache,
+        data: &str,
         position: Position,
-        metrics: fonts::Metrics,
-    ) -> Option<TextSection<'f, 'p>> {
-        let mut area = self.clone();
-        area.add_offset(position);
-        TextSection::new(font_cache, area, metrics)
+        module_size: Mm,
+        dark: Color,
+    ) -> Result<Size, Error> {
+        self.draw_qrcode_with_ec_level(data, position, module_size, dark, qrcode::EcLevel::M)
     }
 
-    /// Returns a position relative to the top left corner of this area.
+    /// Draws a QR code encoding the given data with the given error correction level and returns
+    /// its size.
+    ///
+    /// *Only available if the `qrcode` feature is enabled.*
+    ///
+    /// Higher error correction levels make the code more robust against damage but require more
+    /// modules for the same data:  `L` restores about 7 %, `M` 15 %, `Q` 25 % and `H` 30 % of the
+    /// data.  The smallest version that fits the data is selected automatically.
+    ///
+    /// The position of the upper left corner of the code is relative to the upper left corner of
+    /// the area.  The returned size includes a quiet zone of four modules on each side, and the
+    /// dark modules are drawn with the given size and color.  Adjacent dark modules in a row are
+    /// merged into one rectangle, and all rectangles are filled as a single path, so that PDF
+    /// viewers do not show seams between them.  Light modules and the quiet zone are not painted.
+    #[cfg(feature = "qrcode")]
+    pub fn draw_qrcode_with_ec_level(
+        &self,
+        data: &str,
+        position: Position,
+        module_size: Mm,
+        dark: Color,
+        ec_level: qrcode::EcLevel,
+    ) -> Result<Size, Error> {
+        const QUIET_ZONE: usize = 4;
+
+        let code = qrcode::QrCode::with_error_correction_level(data, ec_level)
+            .context("Failed to encode QR code")?;
+        let width = code.width();
+        let colors = code.to_colors();
+        let mut rects = Vec::new();
+        for (row, modules) in colors.chunks(width).enumerate() {
+            let mut col = 0;
+            while col < width {
+                if modules[col] == qrcode::Color::Light {
+                    col += 1;
+                    continue;
+                }
+                let start = col;
+                while col < width && modules[col] == qrcode::Color::Dark {
+                    col += 1;
+                }
+                let x = (start + QUIET_ZONE) as f32;
+                let y = (row + QUIET_ZONE) as f32;
+                rects.push((
+                    self.position(position + Position::new(module_size * x, module_size * y)),
+                    Size::new(module_size * (col - start) as f32, module_size),
+                ));
+            }
+        }
+        self.layer.set_fill_color(Some(dark), 1.0);
+        self.layer.fill_rects(rects);
+        let size = module_size * (width + 2 * QUIET_ZONE) as f32;
+        Ok(Size::new(size, size))
+    }
+
+    /// Draws a barcode encoding the given data with the given symbology.
+    ///
+    /// *Only available if the `barcode` feature is enabled.*
+    ///
+    /// The bars are drawn as black filled rectangles and scaled so that the barcode fills the
+    /// given size.  The position of its upper left corner is relative to the upper left corner of
+    /// the area.  No quiet zone is added, so the caller should leave some space on both sides of
+    /// the barcode.  Returns an error if the data cannot be encoded with the symbology, see
+    /// [`Barcode::encode`][].
+    ///
+    /// [`Barcode::encode`]: ../barcode/enum.Barcode.html#method.encode
+    #[cfg(feature = "barcode")]
+    pub fn draw_barcode(
+        &self,
+        data: &str,
+        symbology: crate::barcode::Barcode,
+        position: Position,
+        size: Size,
+    ) -> Result<(), Error> {
+        let widths = symbology.encode(data)?;
+        let modules: u32 = widths.iter().map(|w| u32::from(*w)).sum();
+        let module_width = size.width / modules as f32;
+        self.layer.set_fill_color(Some(Color::Rgb(0, 0, 0)), 1.0);
+        let mut x = Mm(0.0);
+        for (idx, width) in widths.into_iter().enumerate() {
+            let width = module_width * f32::from(width);
+            if idx % 2 == 0 {
+                self.layer.add_rect(
+                    self.position(position + Position::new(x, 0)),
+                    Size::new(width, size.height),
+                    printpdf::path::PaintMode::Fill,
+                );
+            }
+            x += width;
+        }
+        Ok(())
+    }
+
+    /// Draws the outline of a rectangle with the given position and size and the given line style.
+    ///
+    /// The position of the upper left corner of the rectangle is relative to the upper left corner
+    /// of the area.  The outline is drawn as a single closed path, so the corners are joined
+    /// properly and a dash pattern continues around them.  The rectangle is not filled.
+    pub fn draw_rect(&self, position: Position, size: Size, style: LineStyle) {
+        self.layer.set_line_style(&style);
+        let corners = [
+            position,
+            position + Position::new(size.width, 0),
+            position + Position::new(size.width, size.height),
+            position + Position::new(0, size.height),
+        ];
+        let points = corners
+            .iter()
+            .map(|corner| (self.position(*corner), false))
+            .collect();
+        self.layer
+            .add_path(points, printpdf::path::PaintMode::Stroke);
+    }
+
+    /// Draws an ellipse with the given center and radii.
+    ///
+    /// The center is relative to the upper left corner of the area.  If a line style is set, the
+    /// ellipse is outlined using that style.  If a fill color is set, the ellipse is filled with
+    /// that color.  If neither is set, nothing is drawn.
+    pub fn draw_ellipse(
+        &self,
+        center: Position,
+        radius_x: impl Into<Mm>,
+        radius_y: impl Into<Mm>,
+        style: impl Into<Option<LineStyle>>,
+        fill: Option<Color>,
+    ) {
+        use printpdf::path::PaintMode;
+
+        let style = style.into();
+        let mode = match (&style, fill) {
+            (Some(_), Some(_)) => PaintMode::FillStroke,
+            (Some(_), None) => PaintMode::Stroke,
+            (None, Some(_)) => PaintMode::Fill,
+            (None, None) => return,
+        };
+        if let Some(style) = &style {
+            self.layer.set_line_style(style);
+        }
+        if let Some(color) = fill {
+            self.layer.set_fill_color(Some(color), 1.0);
+        }
+
+        let rx = radius_x.into();
+        let ry = radius_y.into();
+        // Each quarter of the ellipse is approximated by a cubic bezier curve with the control
+        // points at this fraction of the radius
+        const C: f32 = 0.551_915;
+        let quarters = [
+            (0.0, -1.0),
+            (1.0, 0.0),
+            (0.0, 1.0),
+            (-1.0, 0.0),
+            (0.0, -1.0),
+        ];
+        let point = |dx: f32, dy: f32| self.position(center + Position::new(rx * dx, ry * dy));
+        let mut points = vec![(point(0.0, -1.0), true)];
+        for window in quarters.windows(2) {
+            let ((x0, y0), (x1, y1)) = (window[0], window[1]);
+            points.push((point(x0 + x1 * C, y0 + y1 * C), true));
+            points.push((point(x1 + x0 * C, y1 + y0 * C), true));
+            points.push((point(x1, y1), true));
+        }
+        if let Some(last) = points.last_mut() {
+            last.1 = false;
+        }
+        self.layer.add_path(points, mode);
+    }
+
+    /// Draws a circle with the given center and radius, see [`draw_ellipse`][] for details.
+    ///
+    /// [`draw_ellipse`]: #method.draw_ellipse
+    pub fn draw_circle(
+        &self,
+        center: Position,
+        radius: impl Into<Mm>,
+        style: impl Into<Option<LineStyle>>,
+        fill: Option<Color>,
+    ) {
+        let radius = radius.into();
+        self.draw_ellipse(center, radius, radius, style, fill);
+    }
+
+    /// Draws a cubic bezier curve from `start` to `end` with the given control points and line
+    /// style.
+    ///
+    /// The curve leaves `start` in the direction of `control1` and arrives at `end` from the
+    /// direction of `control2`.  It does not pass through the control points.  All positions are
+    /// relative to the upper left corner of the area.
+    pub fn draw_bezier(
+        &self,
+        start: Position,
+        control1: Position,
+        control2: Position,
+        end: Position,
+        style: LineStyle,
+    ) {
+        self.layer.set_line_style(&style);
+        self.layer
+            .add_curve_shape(self.bezier_points(start, control1, control2, end));
+    }
+
+    /// Runs the given closure with a clipping path set to the bounds of this area.
+    ///
+    /// Everything that the closure draws outside of the area is not visible.  The graphics state
+    /// is saved before setting the clipping path and restored after running the closure, so the
+    /// clipping path does not affect drawing operations after this method returns.
+    pub fn with_clip<F: FnOnce(&Area<'p>)>(&self, f: F) {
+        self.layer.with_saved_state(|| {
+            self.layer.add_rect(
+                self.position(Position::default()),
+                self.size,
+                printpdf::path::PaintMode::Clip,
+            );
+            f(self);
+        });
+    }
+
+    /// Returns the points for a cubic bezier curve in the format expected by [`printpdf::Line`].
+    ///
+    /// printpdf starts a curve if a point and its successor are both flagged, so the start point
+    /// and the first control point are flagged while the other points are not.
+    fn bezier_points(
+        &self,
+        start: Position,
+        control1: Position,
+        control2: Position,
+        end: Position,
+    ) -> Vec<(LayerPosition, bool)> {
+        vec![
+            (self.position(start), true),
+            (self.position(control1), true),
+            (self.position(control2), false),
+            (self.position(end), false),
+        ]
+    }
+
+    /// Draws a vertical line with the given line style at the given horizontal offset, starting at
+    /// the top of the area and spanning the given height.
+    pub fn draw_vertical_rule(
+        &self,
+        x: impl Into<Mm>,
+        height: impl Into<Mm>,
+        line_style: LineStyle,
+    ) {
+        let x = x.into();
+        self.draw_line(
+            vec![Position::new(x, 0), Position::new(x, height)],
+            line_style,
+        );
+    }
+
+    /// Tries to draw the given string at the given position and returns `true` if the area was
+    /// large enough to draw the string.
+    ///
+    /// The font cache must contain the PDF font for the font set in the style.  The position is
+    /// relative to the upper left corner of the area.
+    pub fn print_str<S: AsRef<str>>(
+        &self,
+        font_cache: &fonts::FontCache,
+        position: Position,
+        style: Style,
+        s: S,
+    ) -> Result<bool, Error> {
+        let style = self.resolve_style(style);
+        if let Some(mut section) =
+            self.text_section(font_cache, position, style.metrics(font_cache))
+        {
+            section.print_str(s, style)?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Tries to draw the given string with the given alignment and returns `true` if the area was
+    /// large enough to draw the string.
+    ///
+    /// The string is aligned within the space between the given position and the right edge of
+    /// the area, so `Alignment::Right` ends the string at the right edge and `Alignment::Center`
+    /// centers it between the position and the right edge.  If the string is wider than this
+    /// space, it starts at the given position regardless of the alignment.  See [`print_str`][]
+    /// for details.
+    ///
+    /// [`print_str`]: #method.print_str
+    pub fn print_str_aligned<S: AsRef<str>>(
+        &self,
+        font_cache: &fonts::FontCache,
+        position: Position,
+        style: Style,
+        s: S,
+        alignment: Alignment,
+    ) -> Result<bool, Error> {
+        let s = s.as_ref();
+        let style = self.resolve_style(style);
+        let width = style.str_width(font_cache, s);
+        let available = self.size.width - position.x;
+        let x_offset = match alignment {
+            Alignment::Left | Alignment::Justify => Mm(0.0),
+            Alignment::Center => (available - width) / 2.0,
+            Alignment::Right => available - width,
+        };
+        let x_offset = x_offset.max(Mm(0.0));
+        self.print_str(font_cache, position + Position::new(x_offset, 0), style, s)
+    }
+
+    /// Prints the given text at the given position, wrapped to the width of the area, and returns
+    /// the consumed height and the remainder of the text that did not fit into the area.
+    ///
+    /// The text is broken into lines at whitespace and newline characters, and words that are
+    /// wider than a line are broken at a character boundary.  Printing stops when there is no
+    /// vertical space left for the next line.  In this case, the returned remainder starts with
+    /// the first line that was not printed; otherwise, it is empty.  The font cache must contain
+    /// the PDF font for the font set in the style.  The position is relative to the upper left
+    /// corner of the area.
+    pub fn print_wrapped<'s>(
+        &self,
+        font_cache: &fonts::FontCache,
+        position: Position,
+        style: Style,
+        text: &'s str,
+    ) -> Result<(Mm, &'s str), Error> {
+        self.print_wrapped_with_hyphenator(font_cache, position, style, text, &NoHyphenation)
+    }
+
+    /// Prints the given text at the given position, wrapped to the width of the area and
+    /// hyphenated with the given hyphenator, and returns the consumed height and the remainder of
+    /// the text that did not fit into the area.
+    ///
+    /// If a word does not fit into a line, it is broken at the last hyphenation point that fits
+    /// into the line, and a hyphen is appended to the line.  If the remainder starts with the
+    /// rest of a hyphenated word, the hyphen is not part of it.  See [`print_wrapped`][] for
+    /// details.
+    ///
+    /// [`print_wrapped`]: #method.print_wrapped
+    pub fn print_wrapped_with_hyphenator<'s>(
+        &self,
+        font_cache: &fonts::FontCache,
+        position: Position,
+        style: Style,
+        text: &'s str,
+        hyphenator: &dyn Hyphenator,
+    ) -> Result<(Mm, &'s str), Error> {
+        let style = self.resolve_style(style);
+        let metrics = style.metrics(font_cache);
+        let width = self.size.width - position.x;
+        let max_height = self.size.height - position.y;
+        let mut section = if let Some(section) = self.text_section(font_cache, position, metrics) {
+            section
+        } else {
+            return Ok((Mm(0.0), text));
+        };
+        let mut height = Mm(0.0);
+        for (i, line) in wrap::break_lines(font_cache, style, text, width, hyphenator)
+            .into_iter()
+            .enumerate()
+        {
+            // add_newline only checks that the current line fits, so we check the next line.
+            if i > 0 && (height + metrics.line_height > max_height || !section.add_newline()) {
+                return Ok((height, &text[line.range.start..]));
+            }
+            if line.is_hyphenated {
+                section.print_str(format!("{}-", &text[line.range]), style)?;
+            } else {
+                section.print_str(&text[line.range], style)?;
+            }
+            height += metrics.line_height;
+        }
+        Ok((height, ""))
+    }
+
+    /// Creates a new text section at the given position if the text section fits in this area.
+    ///
+    /// The given style is only used to calculate the line height of the section.  The position is
+    /// relative to the upper left corner of the area.  The font cache must contain the PDF font
+    /// for all fonts printed with the text section.
+    pub fn text_section<'f>(
+        &self,
+        font_cache: &'f fonts::FontCache,
+        position: Position,
+        metrics: fonts::Metrics,
+    ) -> Option<TextSection<'f, 'p>> {
+        let mut area = self.clone();
+        area.add_offset(position);
+        TextSection::new(font_cache, self.origin, area, metrics)
+    }
+
+    /// Returns whether a text section with the given metrics fits into this area at the given
+    /// position, see [`text_section`](#method.text_section).
+    fn fits_text_section(&self, position: Position, metrics: fonts::Metrics) -> bool {
+        let mut area = self.clone();
+        area.add_offset(position);
+        TextSection::fits(&area, metrics)
+    }
+
+    /// Returns a position relative to the top left corner of this area.
     fn position(&self, position: Position) -> LayerPosition {
         LayerPosition::from_area(self, position)
     }
 
+    /// Merges the given style into the default style of the document.
+    fn resolve_style(&self, style: Style) -> Style {
+        self.layer.page.default_style.get().and(style)
+    }
+
     /// Adds a clickable link to the document.
     ///
     /// The font cache must contain the PDF font for the font set in the style.  The position is
@@ -655,6 +2753,7 @@ impl<'p> Area<'p> {
         text: S,
         uri: S,
     ) -> Result<bool, Error> {
+        let style = self.resolve_style(style);
         if let Some(mut section) =
             self.text_section(font_cache, position, style.metrics(font_cache))
         {
@@ -664,26 +2763,67 @@ impl<'p> Area<'p> {
             Ok(false)
         }
     }
+
+    /// Adds a clickable link to the document that jumps to the top of the page with the given
+    /// index.
+    ///
+    /// The font cache must contain the PDF font for the font set in the style.  The position is
+    /// relative to the upper left corner of the area.  The target page does not have to exist yet,
+    /// but writing the document fails if it does not exist when the document is written.
+    pub fn add_internal_link<S: AsRef<str>>(
+        &self,
+        font_cache: &fonts::FontCache,
+        position: Position,
+        style: Style,
+        text: S,
+        target_page: usize,
+    ) -> Result<bool, Error> {
+        let style = self.resolve_style(style);
+        if let Some(mut section) =
+            self.text_section(font_cache, position, style.metrics(font_cache))
+        {
+            section.add_internal_link(text, target_page, style)?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
 }
 
 /// A text section that is drawn on an area of a PDF layer.
 pub struct TextSection<'f, 'p> {
     font_cache: &'f fonts::FontCache,
+    // the origin of the area that this text section has been created for
+    origin: Position,
     area: Area<'p>,
     is_first: bool,
+    // whether nothing has been printed on the current line yet, if it is not the first line
+    is_line_start: bool,
     metrics: fonts::Metrics,
-    font: Option<(printpdf::IndirectFontRef, u8)>,
+    font: Option<(printpdf::IndirectFontRef, f32)>,
+    // the offset of the first glyph origin, compensating its left side bearing
+    text_origin: Mm,
     current_x_offset: Mm,
-    cumulative_kerning: Mm,
+    // the distance of the start of the current line in the text object from the text origin
+    line_offset: Mm,
+    // the rotation of the text in degrees and the unrotated and rotated position of its center
+    rotation: Option<(f32, Position, Position)>,
+    tab_stops: Vec<Mm>,
+    // the additional space after every character (the `Tc` operator)
+    character_spacing: Mm,
+    is_filled: bool,
+    is_stroked: bool,
+    decorations: Vec<Decoration>,
 }
 
 impl<'f, 'p> TextSection<'f, 'p> {
     fn new(
         font_cache: &'f fonts::FontCache,
+        origin: Position,
         area: Area<'p>,
         metrics: fonts::Metrics,
     ) -> Option<TextSection<'f, 'p>> {
-        if metrics.glyph_height > area.size.height {
+        if !TextSection::fits(&area, metrics) {
             return None;
         }
 
@@ -692,23 +2832,74 @@ impl<'f, 'p> TextSection<'f, 'p> {
 
         Some(TextSection {
             font_cache,
+            origin,
             area,
             is_first: true,
+            is_line_start: false,
             metrics,
             font: None,
+            text_origin: Mm(0.0),
             current_x_offset: Mm(0.0),
-            cumulative_kerning: Mm(0.0),
+            line_offset: Mm(0.0),
+            rotation: None,
+            tab_stops: Vec::new(),
+            character_spacing: Mm(0.0),
+            is_filled: true,
+            is_stroked: false,
+            decorations: Vec::new(),
         })
     }
 
+    /// Returns whether a text section with the given metrics fits into the given area.
+    fn fits(area: &Area<'_>, metrics: fonts::Metrics) -> bool {
+        metrics.glyph_height <= area.size.height
+    }
+
     fn set_text_cursor(&self, x_offset: Mm) {
-        let cursor = self
-            .area
-            .position(Position::new(x_offset, self.metrics.ascent));
-        self.area.layer.set_text_cursor(cursor);
+        let cursor = self.position(Position::new(x_offset, self.metrics.ascent));
+        if let Some((degrees, _, _)) = self.rotation {
+            self.area.layer.set_text_matrix(cursor, degrees, 0.0);
+        } else {
+            self.area.layer.set_text_cursor(cursor);
+        }
+    }
+
+    /// Returns the layer position for the given position relative to the area, rotated according
+    /// to the current rotation of this section.
+    fn position(&self, position: Position) -> LayerPosition {
+        let position = self.area.position(position);
+        if let Some((degrees, center, rotated_center)) = self.rotation {
+            // Layer positions grow downwards, so a counter-clockwise rotation in user space is a
+            // clockwise rotation for layer positions.
+            let (sin, cos) = degrees.to_radians().sin_cos();
+            let d = position.0 - center;
+            LayerPosition(
+                rotated_center + Position::new(d.x * cos + d.y * sin, d.y * cos - d.x * sin),
+            )
+        } else {
+            position
+        }
+    }
+
+    /// Rotates the following text counter-clockwise by the given angle in degrees.
+    ///
+    /// The text is rotated around the origin of the current cursor position on the baseline, so
+    /// a rotation of 90 degrees prints the text from bottom to top, starting at the current
+    /// position.  Text widths and line breaks are measured along the rotated baseline, so they
+    /// are not affected by the rotation.  The rotation is absolute, i. e. a rotation of 0 degrees
+    /// continues with unrotated text at the current position.
+    pub fn rotate(&mut self, degrees: f32) {
+        let x_offset = self.text_origin + self.current_x_offset;
+        let center = Position::new(x_offset, self.metrics.ascent);
+        let rotated_center = self.position(center).0;
+        self.rotation = Some((degrees, self.area.position(center).0, rotated_center));
+        if !self.is_first {
+            self.set_text_cursor(x_offset);
+            self.line_offset = x_offset - self.text_origin;
+        }
     }
 
-    fn set_font(&mut self, font: &printpdf::IndirectFontRef, font_size: u8) {
+    fn set_font(&mut self, font: &printpdf::IndirectFontRef, font_size: f32) {
         let font_is_set = self
             .font
             .as_ref()
@@ -721,114 +2912,496 @@ impl<'f, 'p> TextSection<'f, 'p> {
         }
     }
 
-    /// Tries to add a new line and returns `true` if the area was large enough to fit the new
-    /// line.
-    #[must_use]
-    pub fn add_newline(&mut self) -> bool {
-        if self.metrics.line_height > self.area.size.height {
-            false
-        } else {
-            self.area.layer.add_line_break();
-            self.area.add_offset((0, self.metrics.line_height));
-            true
+    /// Sets the text rendering mode and the outline style for the given style.
+    fn set_stroke(&mut self, style: &Style) {
+        let (is_filled, is_stroked) = match style.stroke() {
+            Some((color, width)) => {
+                self.area.layer.set_outline_color(color);
+                self.area.layer.set_outline_thickness(width);
+                (!style.is_stroke_only(), true)
+            }
+            None if style.is_synthetic_bold(self.font_cache) => {
+                let width = printpdf::Pt(style.font_size() * SYNTHETIC_BOLD_STROKE_WIDTH);
+                self.area
+                    .layer
+                    .set_outline_color(style.color().unwrap_or(Color::Rgb(0, 0, 0)));
+                self.area.layer.set_outline_thickness(width.into());
+                (true, true)
+            }
+            None => (true, false),
+        };
+        self.set_rendering_mode(is_filled, is_stroked);
+    }
+
+    fn set_rendering_mode(&mut self, is_filled: bool, is_stroked: bool) {
+        if (self.is_filled, self.is_stroked) != (is_filled, is_stroked) {
+            self.is_filled = is_filled;
+            self.is_stroked = is_stroked;
+            let mode = match (is_filled, is_stroked) {
+                (true, true) => printpdf::TextRenderingMode::FillStroke,
+                (false, true) => printpdf::TextRenderingMode::Stroke,
+                (true, false) => printpdf::TextRenderingMode::Fill,
+                (false, false) => printpdf::TextRenderingMode::Invisible,
+            };
+            self.area.layer.data.layer.set_text_rendering_mode(mode);
         }
     }
 
-    /// Prints the given string with the given style.
+    /// Adds the box of the current line from the given offset with the given width to the content
+    /// bounds of the page.
+    fn add_content_bounds(&self, x_offset: Mm, width: Mm) {
+        let corners: Vec<_> = [
+            (x_offset, Mm(0.0)),
+            (x_offset + width, Mm(0.0)),
+            (x_offset, self.metrics.line_height),
+            (x_offset + width, self.metrics.line_height),
+        ]
+        .iter()
+        .map(|(x, y)| self.position(Position::new(*x, *y)).0)
+        .collect();
+        self.area.layer.add_content_bounds(&corners, Mm(0.0));
+    }
+
+    /// Selects the font and the baseline shift for the given style and writes the glyphs of the
+    /// given string.
     ///
-    /// The font cache for this text section must contain the PDF font for the given style.
-    pub fn print_str(&mut self, s: impl AsRef<str>, style: Style) -> Result<(), Error> {
+    /// The width must be the width of the string.
+    fn write_run(&mut self, s: &str, style: &Style, width: Mm) -> Result<(), Error> {
         let font = style.font(self.font_cache);
-        let s = s.as_ref();
-
-        if self.is_first {
-            if let Some(first_c) = s.chars().next() {
-                let x_offset = style.char_left_side_bearing(self.font_cache, first_c) * -1.0;
-                self.set_text_cursor(x_offset);
-            }
-            self.is_first = false;
-        }
-
         let pdf_font = self
             .font_cache
             .get_pdf_font(font)
             .expect("Could not find PDF font in font cache");
-        self.area.layer.set_fill_color(style.color());
         self.set_font(pdf_font, style.font_size());
+        self.set_stroke(style);
+        if style.baseline_shift() != 0.0 {
+            self.area.layer.set_baseline_shift(style.baseline_shift());
+        }
+        let x_offset = self.text_origin + self.current_x_offset;
+        let is_synthetic_italic = style.is_synthetic_italic(self.font_cache);
+        let rotation = self.rotation.map(|(degrees, _, _)| degrees).unwrap_or(0.0);
+        if is_synthetic_italic {
+            let position = self.position(Position::new(x_offset, self.metrics.ascent));
+            let shear = SYNTHETIC_ITALIC_ANGLE.to_radians().tan();
+            self.area.layer.set_text_matrix(position, rotation, shear);
+        }
+
+        let word_spacing = style.word_spacing();
+        // For built-in fonts, emit text as whole words/strings to avoid character-by-character
+        // spacing, unless we have to insert the word spacing.  Built-in fonts are never kerned
+        // (see `Font::kerning`), so the PDF viewer places the glyphs using the standard metrics of
+        // the font, which are also used to calculate the run width and thus `current_x_offset`.
+        if font.is_builtin() && word_spacing == Mm(0.0) {
+            let codepoints = encode_builtin(s, self.area.layer.page.builtin_encoding.get())?;
+            self.area.layer.write_builtin_codepoints(codepoints);
+        } else {
+            self.write_positioned_run(s, style, font)?;
+        }
 
-        // Store starting position for underline/strikethrough
-        let start_x = self.current_x_offset + self.cumulative_kerning;
-        let text_width = style.text_width(self.font_cache, s);
+        // The text rise is part of the text state, so we have to reset it.
+        if style.baseline_shift() != 0.0 {
+            self.area.layer.set_baseline_shift(0.0);
+        }
+        // The text matrix also sets the line start, so we continue at the end of the run.
+        if is_synthetic_italic {
+            let position = self.position(Position::new(x_offset + width, self.metrics.ascent));
+            self.area.layer.set_text_matrix(position, rotation, 0.0);
+            self.line_offset = x_offset + width - self.text_origin;
+        }
+        Ok(())
+    }
 
-        // For built-in fonts, emit text as whole words/strings to avoid character-by-character spacing
+    /// Writes the glyphs of the given string with the kerning and word spacing of the given style
+    /// and font.
+    fn write_positioned_run(&self, s: &str, style: &Style, font: fonts::Font) -> Result<(), Error> {
+        // The positions are given in thousandths of the font size, with positive values moving
+        // the next glyph to the left.  The word spacing is added after every space.
+        let word_spacing = style.word_spacing();
+        let spacing = if word_spacing != Mm(0.0) && style.font_size() > 0.0 {
+            Some(printpdf::Pt::from(word_spacing).0 / style.font_size() * 1000.0)
+        } else {
+            None
+        };
+        let previous_chars = std::iter::once(None).chain(s.chars().map(Some));
+        // Look up the kerning pairs of the whole run under a single lock of the kerning cache
+        let kerning = font.kerning(self.font_cache, s.chars());
+        let positions = kerning
+            .into_iter()
+            .zip(previous_chars)
+            .map(|(pos, previous)| {
+                let pos = (-pos * 1000.0) as i64 as f32;
+                match spacing {
+                    Some(spacing) if previous == Some(' ') => pos - spacing,
+                    _ => pos,
+                }
+            });
+        let trailing = match spacing {
+            Some(spacing) if s.ends_with(' ') => -spacing,
+            _ => 0.0,
+        };
         if font.is_builtin() {
-            // Use simple text emission for built-in fonts
-            // This avoids the character-by-character positioning that causes spacing issues
-            self.area.layer.data.layer.write_text(s, pdf_font);
+            let codepoints = encode_builtin(s, self.area.layer.page.builtin_encoding.get())?;
+            self.area
+                .layer
+                .write_positioned_codepoints(positions, codepoints, trailing, true);
         } else {
-            // For embedded fonts, we still need precise positioning for proper kerning
-            let kerning_positions = font.kerning(self.font_cache, s.chars());
-            let positions = kerning_positions
-                .clone()
-                .into_iter()
-                .map(|pos| (-pos * 1000.0) as i64);
-            let codepoints = font.glyph_ids(&self.font_cache, s.chars());
-
+            self.font_cache.add_used_chars(font, s);
+            let codepoints = font.glyph_ids_iter(self.font_cache, s.chars());
             self.area
                 .layer
-                .write_positioned_codepoints(positions, codepoints);
+                .write_positioned_codepoints(positions, codepoints, trailing, false);
         }
+        Ok(())
+    }
 
-        // Draw underline if enabled
-        if style.is_underline() {
-            let line_thickness = Mm(style.font_size() as f32 * 0.05); // 5% of font size
-            // Position just below baseline
-            let underline_y = self.metrics.ascent + Mm(style.font_size() as f32 * 0.06);
-            let line_style = LineStyle::new()
-                .with_thickness(line_thickness)
-                .with_color(style.color().unwrap_or(Color::Rgb(0, 0, 0)));
-
-            self.area.draw_line(
-                vec![
-                    Position::new(start_x, underline_y),
-                    Position::new(start_x + text_width, underline_y),
-                ],
-                line_style,
+    /// Positions the text cursor if this is the first run of this text section, compensating the
+    /// left side bearing of the first character.
+    fn start_run(&mut self, s: &str, style: &Style) {
+        if self.is_first {
+            if let Some(first_c) = s.chars().next() {
+                self.text_origin = style.char_left_side_bearing(self.font_cache, first_c) * -1.0;
+                self.set_text_cursor(self.text_origin + self.current_x_offset);
+                self.line_offset = self.current_x_offset;
+            }
+            self.is_first = false;
+        } else if self.is_line_start {
+            if let Some(first_c) = s.chars().next() {
+                // The line starts at the text origin of the previous line, so we only have to
+                // move it by the difference of the left side bearings.
+                let text_origin = style.char_left_side_bearing(self.font_cache, first_c) * -1.0;
+                if text_origin != self.text_origin {
+                    self.area
+                        .layer
+                        .move_text_cursor(Position::new(text_origin - self.text_origin, 0));
+                    self.text_origin = text_origin;
+                }
+                self.is_line_start = false;
+            }
+        }
+    }
+
+    /// Draws a rectangle with the given color behind text with the given style from the given
+    /// offset with the given width.
+    ///
+    /// As paths cannot be painted inside of a text object, the text object is closed before
+    /// drawing the rectangle and a new text object is started at the given offset afterwards.
+    fn add_highlight(&mut self, style: &Style, color: Color, x_offset: Mm, width: Mm) {
+        let font = style.font(self.font_cache);
+        let font_size = style.font_size();
+        let baseline = self.metrics.ascent - Mm::from(printpdf::Pt(style.baseline_shift()));
+        let top_left = Position::new(x_offset, baseline - font.ascent(font_size));
+        let size = Size::new(width, font.ascent(font_size) - font.descent(font_size));
+
+        let layer = &self.area.layer;
+        layer.end_text_section();
+        layer.set_fill_color(Some(color), style.fill_opacity());
+        if self.rotation.is_some() {
+            let corners = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]
+                .iter()
+                .map(|(dx, dy)| {
+                    let corner = top_left + Position::new(size.width * *dx, size.height * *dy);
+                    (self.position(corner), false)
+                })
+                .collect();
+            layer.add_path(corners, printpdf::path::PaintMode::Fill);
+        } else {
+            layer.add_rect(
+                self.area.position(top_left),
+                size,
+                printpdf::path::PaintMode::Fill,
             );
         }
+        layer.begin_text_section();
+        self.set_text_cursor(x_offset);
+        self.line_offset = x_offset - self.text_origin;
+    }
 
-        // Draw strikethrough if enabled
+    /// Adds the underline and strikethrough lines for text with the given style from the given
+    /// offset with the given width.
+    fn add_decorations(&mut self, style: &Style, x_offset: Mm, width: Mm) {
+        let color = style.color().unwrap_or(Color::Rgb(0, 0, 0));
+        if style.is_underline() {
+            let font = style.font(self.font_cache);
+            let font_size = style.font_size();
+            let thickness = font.underline_thickness(font_size);
+            // The underline position is the top of the line, but lines are stroked centered
+            let y = self.metrics.ascent - font.underline_position(font_size) + thickness / 2.0;
+            let line_style = LineStyle::new().with_thickness(thickness).with_color(color);
+            self.add_decoration(x_offset, width, y, line_style);
+        }
         if style.is_strikethrough() {
-            let line_thickness = Mm(style.font_size() as f32 * 0.05); // 5% of font size
-            // Position at middle of x-height (roughly middle of lowercase letters)
-            let strikethrough_y = self.metrics.ascent * 0.75;
-            let line_style = LineStyle::new()
-                .with_thickness(line_thickness)
-                .with_color(style.color().unwrap_or(Color::Rgb(0, 0, 0)));
-
-            self.area.draw_line(
-                vec![
-                    Position::new(start_x, strikethrough_y),
-                    Position::new(start_x + text_width, strikethrough_y),
-                ],
+            let font = style.font(self.font_cache);
+            let font_size = style.font_size();
+            let thickness = font.strikeout_thickness(font_size);
+            let y = self.metrics.ascent - font.strikeout_position(font_size) + thickness / 2.0;
+            let line_style = LineStyle::new().with_thickness(thickness).with_color(color);
+            self.add_decoration(x_offset, width, y, line_style);
+        }
+    }
+
+    /// Adds a decoration line at the given height, extending an existing line if it ends where
+    /// the new line starts so that consecutive runs are decorated with a continuous line.
+    fn add_decoration(&mut self, x_offset: Mm, width: Mm, y: Mm, line_style: LineStyle) {
+        let start = self.position(Position::new(x_offset, y));
+        let end = self.position(Position::new(x_offset + width, y));
+        let previous = self.decorations.iter_mut().rev().find(|decoration| {
+            decoration.line_style == line_style
+                && (decoration.end.0.y - start.0.y).0.abs() < 0.001
+                && (decoration.end.0.x - start.0.x).0.abs() < 0.001
+        });
+        if let Some(previous) = previous {
+            previous.end = end;
+        } else {
+            self.decorations.push(Decoration {
+                start,
+                end,
                 line_style,
-            );
+            });
         }
+    }
 
-        // Update position tracking
-        self.current_x_offset += text_width;
+    /// Returns the current position of the text cursor, i. e. the end of the last printed run on
+    /// the top of the current line.
+    ///
+    /// The position is relative to the upper left corner of the area that this text section has
+    /// been created for, so it can be used to place other content directly after the printed
+    /// text.  The rotation of the text section is ignored.
+    pub fn current_position(&self) -> Position {
+        Position::new(
+            self.area.origin.x - self.origin.x + self.current_x_offset,
+            self.area.origin.y - self.origin.y,
+        )
+    }
 
-        // For built-in fonts, we don't need kerning tracking since PDF viewers handle it
-        if !font.is_builtin() {
-            let kerning_positions = font.kerning(self.font_cache, s.chars());
-            let kerning_sum = Mm(kerning_positions.iter().sum::<f32>());
-            self.cumulative_kerning += kerning_sum;
+    /// Sets the additional space that is added after every character printed in this section.
+    ///
+    /// The spacing is applied by the PDF viewer using the `Tc` operator and is included in the
+    /// width of the printed runs, so the cursor position is updated accordingly.  It is reset when
+    /// the section is dropped.  Note that it is not considered when text is wrapped, for example
+    /// by [`Area::print_wrapped`][].
+    ///
+    /// [`Area::print_wrapped`]: struct.Area.html#method.print_wrapped
+    pub fn set_character_spacing(&mut self, spacing: impl Into<Mm>) {
+        let spacing = spacing.into();
+        if spacing != self.character_spacing {
+            self.character_spacing = spacing;
+            self.area.layer.set_character_spacing(spacing);
         }
+    }
 
-        Ok(())
+    /// Returns the additional width of the given string caused by the character spacing.
+    fn character_spacing_width(&self, s: &str) -> Mm {
+        self.character_spacing * s.chars().count() as f32
     }
 
-    /// Adds a clickable link with the given text, URI, and style.
+    /// Returns the height that is available for the current and the following lines, i. e. the
+    /// distance from the top of the current line to the bottom of the area.
+    pub fn remaining_height(&self) -> Mm {
+        self.area.size.height
+    }
+
+    /// Returns whether the area is large enough to fit another line, i. e. whether the next call
+    /// to [`add_newline`](#method.add_newline) will succeed.
+    pub fn can_fit_line(&self) -> bool {
+        self.metrics.line_height <= self.area.size.height
+    }
+
+    /// Tries to add a new line and returns `true` if the area was large enough to fit the new
+    /// line.
+    #[must_use]
+    pub fn add_newline(&mut self) -> bool {
+        if !self.can_fit_line() {
+            false
+        } else {
+            // If nothing has been printed yet, the text cursor is positioned when the first run is
+            // printed.
+            if !self.is_first {
+                if self.line_offset == Mm(0.0) {
+                    self.area.layer.add_line_break();
+                } else {
+                    // The line start was moved when the text object was interrupted, so we have
+                    // to move back to the text origin.
+                    self.area.layer.move_text_cursor(Position::new(
+                        self.line_offset * -1.0,
+                        self.metrics.line_height,
+                    ));
+                    self.line_offset = Mm(0.0);
+                }
+                self.is_line_start = true;
+            }
+            self.current_x_offset = Mm(0.0);
+            self.area.add_offset((0, self.metrics.line_height));
+            true
+        }
+    }
+
+    /// Sets the tab stops for this text section.
+    ///
+    /// The tab stops are measured from the start of the text section.  If no tab stops are set or
+    /// if the cursor is already past the last tab stop, tabs advance the cursor to the next
+    /// multiple of [`DEFAULT_TAB_INTERVAL`][].
+    ///
+    /// [`DEFAULT_TAB_INTERVAL`]: constant.DEFAULT_TAB_INTERVAL.html
+    pub fn set_tab_stops(&mut self, stops: Vec<Mm>) {
+        let mut stops = stops;
+        stops.sort_by(|a, b| a.partial_cmp(b).unwrap_or(cmp::Ordering::Equal));
+        self.tab_stops = stops;
+    }
+
+    /// Moves the cursor to the next tab stop after the current position.
+    fn advance_to_tab_stop(&mut self) {
+        let x_offset = self.current_x_offset;
+        let tab_stop = self
+            .tab_stops
+            .iter()
+            .copied()
+            .find(|stop| *stop > x_offset)
+            .unwrap_or_else(|| {
+                DEFAULT_TAB_INTERVAL * ((x_offset.0 / DEFAULT_TAB_INTERVAL.0).floor() + 1.0)
+            });
+        if self.is_first {
+            // The text cursor is positioned when the first run is printed.
+            self.current_x_offset = tab_stop;
+        } else {
+            self.move_cursor(tab_stop);
+        }
+    }
+
+    /// Prints the given string with the given style.
+    ///
+    /// Tab characters move the cursor to the next tab stop, see [`set_tab_stops`][].  The font
+    /// cache for this text section must contain the PDF font for the given style.
+    ///
+    /// [`set_tab_stops`]: #method.set_tab_stops
+    pub fn print_str(&mut self, s: impl AsRef<str>, style: Style) -> Result<(), Error> {
+        let style = self.area.resolve_style(style);
+        let parts: Vec<_> = s.as_ref().split('\t').collect();
+        for (i, part) in parts.iter().enumerate() {
+            if i > 0 {
+                self.advance_to_tab_stop();
+            }
+            // Empty runs would position the cursor before the tab stop is known
+            if part.is_empty() && parts.len() > 1 {
+                continue;
+            }
+            for (s, style) in style.split_runs(part) {
+                self.print_run(s, style)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Prints the given string with the given style, using the fonts of the given fallback chain
+    /// for characters that are not supported by its primary font.
+    ///
+    /// The string is split into segments that can be printed with a single font of the chain, see
+    /// [`FontFallbackChain::segment_text`][].  Each segment is printed with the font family of
+    /// the style replaced by its font.  All fonts of the chain must have been added to the font
+    /// cache of this text section (for example using [`FontCache::add_font`][] with a clone of the
+    /// font data), and the PDF fonts must have been loaded.  The characters covered by built-in
+    /// fonts are determined by the encoding of the renderer, regardless of the encoding of the
+    /// chain.
+    ///
+    /// [`FontFallbackChain::segment_text`]: ../fonts/struct.FontFallbackChain.html#method.segment_text
+    /// [`FontCache::add_font`]: ../fonts/struct.FontCache.html#method.add_font
+    pub fn print_str_with_fallback(
+        &mut self,
+        s: impl AsRef<str>,
+        chain: &fonts::FontFallbackChain,
+        style: Style,
+    ) -> Result<(), Error> {
+        let style = self.area.resolve_style(style);
+        let encoding = self.area.layer.page.builtin_encoding.get();
+        let chain = if chain.builtin_encoding() == encoding {
+            borrow::Cow::Borrowed(chain)
+        } else {
+            borrow::Cow::Owned(chain.clone().with_builtin_encoding(encoding))
+        };
+        for (segment, font_data) in chain.segment_text(s.as_ref()) {
+            let font = self.font_cache.find_font(font_data).ok_or_else(|| {
+                Error::new(
+                    "A font of the fallback chain has not been added to the font cache",
+                    ErrorKind::InvalidFont,
+                )
+            })?;
+            let segment_style = style.with_font_family(fonts::FontFamily::from_single(font));
+            for (s, style) in segment_style.split_runs(&segment) {
+                self.print_run(s, style)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn print_run(&mut self, s: &str, style: Style) -> Result<(), Error> {
+        self.start_run(s, &style);
+        let start_x = self.text_origin + self.current_x_offset;
+        let text_width = style.run_width(self.font_cache, s) + self.character_spacing_width(s);
+        self.add_content_bounds(start_x, text_width);
+        if let Some(highlight) = style.highlight() {
+            self.add_highlight(&style, highlight, start_x, text_width);
+        }
+
+        self.area
+            .layer
+            .set_fill_color(style.color(), style.fill_opacity());
+        self.write_run(s, &style, text_width)?;
+
+        self.add_decorations(&style, start_x, text_width);
+
+        // The text width already includes the kerning
+        self.current_x_offset += text_width;
+
+        Ok(())
+    }
+
+    /// Prints the given base string with the given ruby annotation centered above it.
+    ///
+    /// The base string is printed on the baseline with the given style.  The ruby string is
+    /// printed with a reduced font size directly above the ascent of the base string, so the
+    /// line height of this section should leave enough room for it.  If one of the strings is
+    /// wider than the other one, the narrower string is centered, and the cursor is advanced by
+    /// the width of the wider string.
+    ///
+    /// The font cache for this text section must contain the PDF font for the given style.
+    pub fn print_ruby(
+        &mut self,
+        base: impl AsRef<str>,
+        ruby: impl AsRef<str>,
+        style: Style,
+    ) -> Result<(), Error> {
+        let base = base.as_ref();
+        let ruby = ruby.as_ref();
+        let style = self.area.resolve_style(style);
+        let ruby_style = style.ruby_style(self.font_cache);
+
+        let start = self.current_x_offset;
+        let base_width =
+            style.str_width(self.font_cache, base) + self.character_spacing_width(base);
+        let ruby_width =
+            ruby_style.str_width(self.font_cache, ruby) + self.character_spacing_width(ruby);
+        let width = base_width.max(ruby_width);
+
+        self.start_run(base, &style);
+        self.move_cursor(start + (width - base_width) / 2.0);
+        self.print_str(base, style)?;
+        self.move_cursor(start + (width - ruby_width) / 2.0);
+        self.print_run(ruby, ruby_style)?;
+        self.move_cursor(start + width);
+        Ok(())
+    }
+
+    /// Moves the text cursor on the current line to the given offset.
+    fn move_cursor(&mut self, x_offset: Mm) {
+        if x_offset != self.current_x_offset {
+            self.area
+                .layer
+                .move_text_cursor(Position::new(x_offset - self.line_offset, 0));
+            self.line_offset = x_offset;
+            self.current_x_offset = x_offset;
+        }
+    }
+
+    /// Adds a clickable link with the given text, URI, and style.
     ///
     /// The font cache for this text section must contain the PDF font for the given style.
     pub fn add_link(
@@ -837,18 +3410,43 @@ impl<'f, 'p> TextSection<'f, 'p> {
         uri: impl AsRef<str>,
         style: Style,
     ) -> Result<(), Error> {
-        let font = style.font(self.font_cache);
-        let text = text.as_ref();
-        let uri = uri.as_ref();
+        self.add_link_with_target(text.as_ref(), LinkTarget::Uri(uri.as_ref()), style)
+    }
+
+    /// Adds a clickable link with the given text and style that jumps to the top of the page with
+    /// the given index.
+    ///
+    /// The font cache for this text section must contain the PDF font for the given style.
+    pub fn add_internal_link(
+        &mut self,
+        text: impl AsRef<str>,
+        target_page: usize,
+        style: Style,
+    ) -> Result<(), Error> {
+        self.add_link_with_target(text.as_ref(), LinkTarget::Page(target_page), style)
+    }
 
-        let kerning_positions: Vec<f32> = font.kerning(self.font_cache, text.chars());
+    fn add_link_with_target(
+        &mut self,
+        text: &str,
+        target: LinkTarget<'_>,
+        style: Style,
+    ) -> Result<(), Error> {
+        let style = self.area.resolve_style(style);
+        let font = style.font(self.font_cache);
 
+        self.start_run(text, &style);
         // Get current cursor position, including all accumulated offsets
-        let start_x = self.current_x_offset + self.cumulative_kerning;
+        let start_x = self.text_origin + self.current_x_offset;
         let current_pos = self.area.position(Position::new(start_x, 0.0));
 
         let pdf_pos = self.area.layer.transform_position(current_pos);
-        let text_width = style.text_width(self.font_cache, text);
+        let text_width =
+            style.run_width(self.font_cache, text) + self.character_spacing_width(text);
+        self.add_content_bounds(start_x, text_width);
+        if let Some(highlight) = style.highlight() {
+            self.add_highlight(&style, highlight, start_x, text_width);
+        }
         let rect = printpdf::Rect::new(
             printpdf::Mm(pdf_pos.x.0),                                     // left
             printpdf::Mm(pdf_pos.y.0 - font.ascent(style.font_size()).0),  // bottom
@@ -856,129 +3454,4063 @@ impl<'f, 'p> TextSection<'f, 'p> {
             printpdf::Mm(pdf_pos.y.0 + font.descent(style.font_size()).0), // top
         );
 
-        let annotation = printpdf::LinkAnnotation::new(
-            rect,
-            Some(printpdf::BorderArray::Solid([0.0, 0.0, 0.0])), // No border
-            Some(printpdf::ColorArray::Transparent),             // Transparent color
-            printpdf::Actions::uri(uri.to_string()),
-            None,
-        );
-        self.area.layer.add_annotation(annotation);
-
-        // Handle first character positioning
-        if self.is_first {
-            if let Some(first_c) = text.chars().next() {
-                let x_offset = style.char_left_side_bearing(self.font_cache, first_c) * -1.0;
-                self.set_text_cursor(x_offset);
+        match target {
+            LinkTarget::Uri(uri) => {
+                let annotation = printpdf::LinkAnnotation::new(
+                    rect,
+                    Some(printpdf::BorderArray::Solid([0.0, 0.0, 0.0])), // No border
+                    Some(printpdf::ColorArray::Transparent),             // Transparent color
+                    printpdf::Actions::uri(uri.to_string()),
+                    None,
+                );
+                self.area.layer.add_annotation(annotation);
             }
-            self.is_first = false;
+            LinkTarget::Page(target_page) => self.area.layer.add_internal_link(rect, target_page),
         }
 
-        let positions = kerning_positions
-            .clone()
-            .into_iter()
-            .map(|pos| (-pos * 1000.0) as i64);
+        self.area
+            .layer
+            .set_fill_color(style.color(), style.fill_opacity());
+        self.write_run(text, &style, text_width)?;
 
-        let codepoints = if font.is_builtin() {
-            encode_win1252(text)?
-        } else {
-            font.glyph_ids(&self.font_cache, text.chars())
-        };
+        self.add_decorations(&style, start_x, text_width);
 
-        let pdf_font = self
-            .font_cache
-            .get_pdf_font(font)
-            .expect("Could not find PDF font in font cache");
+        // The text width already includes the kerning
+        self.current_x_offset += text_width;
 
-        self.area.layer.set_fill_color(style.color());
-        self.set_font(pdf_font, style.font_size());
+        Ok(())
+    }
+}
 
-        // For built-in fonts, emit text as whole words/strings to avoid character-by-character spacing
-        if font.is_builtin() {
-            // Use simple text emission for built-in fonts
-            // This avoids the character-by-character positioning that causes spacing issues
-            self.area.layer.data.layer.write_text(text, pdf_font);
-        } else {
-            // For embedded fonts, we still need precise positioning for proper kerning
-            self.area
-                .layer
-                .write_positioned_codepoints(positions, codepoints);
+/// The target of a link added to a text section.
+enum LinkTarget<'a> {
+    Uri(&'a str),
+    Page(usize),
+}
+
+impl<'f, 'p> Drop for TextSection<'f, 'p> {
+    fn drop(&mut self) {
+        // The text rendering mode and the character spacing are part of the text state, so we
+        // have to reset them.
+        self.set_rendering_mode(true, false);
+        self.set_character_spacing(0);
+        self.area.layer.end_text_section();
+        // Path operators are not allowed in text objects, so we draw the decorations afterwards.
+        for decoration in self.decorations.drain(..) {
+            let layer = &self.area.layer;
+            layer.set_line_style(&decoration.line_style);
+            layer.add_line_shape(vec![decoration.start, decoration.end]);
         }
+    }
+}
 
-        // Draw underline if enabled
-        if style.is_underline() {
-            let line_thickness = Mm(style.font_size() as f32 * 0.05); // 5% of font size
-            // Position just below baseline
-            let underline_y = self.metrics.ascent + Mm(style.font_size() as f32 * 0.06);
-            let line_style = LineStyle::new()
-                .with_thickness(line_thickness)
-                .with_color(style.color().unwrap_or(Color::Rgb(0, 0, 0)));
-
-            self.area.draw_line(
-                vec![
-                    Position::new(start_x, underline_y),
-                    Position::new(start_x + text_width, underline_y),
-                ],
-                line_style,
-            );
+/// The distance between the default tab stops of a text section.
+pub const DEFAULT_TAB_INTERVAL: Mm = Mm(12.7);
+
+/// The slant angle for synthetic italic text in degrees.
+const SYNTHETIC_ITALIC_ANGLE: f32 = 12.0;
+
+/// The stroke width for synthetic bold text relative to the font size.
+const SYNTHETIC_BOLD_STROKE_WIDTH: f32 = 0.03;
+
+/// An entry of the document outline.
+struct Bookmark {
+    title: String,
+    // the index of the target page, or None if the target page has been removed
+    page_idx: Option<usize>,
+    // the index of the parent bookmark, or None for top-level bookmarks
+    parent: Option<usize>,
+}
+
+/// An underline or strikethrough line of a text section.
+struct Decoration {
+    start: LayerPosition,
+    end: LayerPosition,
+    line_style: LineStyle,
+}
+
+/// The keys of the document information dictionary that are defined by the PDF specification.
+const RESERVED_INFO_KEYS: &[&str] = &[
+    "Title",
+    "Author",
+    "Subject",
+    "Keywords",
+    "Creator",
+    "Producer",
+    "CreationDate",
+    "ModDate",
+    "Trapped",
+];
+
+/// Creates a PDF text string, using the UTF-16BE encoding if the string is not plain ASCII.
+fn text_string(s: &str) -> printpdf::lopdf::Object {
+    use printpdf::lopdf::{Object, StringFormat};
+
+    let bytes = if s.is_ascii() {
+        s.as_bytes().to_vec()
+    } else {
+        let mut bytes = vec![0xfe, 0xff];
+        bytes.extend(s.encode_utf16().flat_map(u16::to_be_bytes));
+        bytes
+    };
+    Object::String(bytes, StringFormat::Literal)
+}
+
+/// Replaces the document outline with the given bookmarks.
+///
+/// The bookmarks link to the top of the page with the given ID and height.  Parents must be added
+/// before their children.
+fn add_outline(
+    doc: &mut printpdf::lopdf::Document,
+    page_ids: &[printpdf::lopdf::ObjectId],
+    page_heights: &[Mm],
+    bookmarks: &[Bookmark],
+) -> Result<(), printpdf::lopdf::Error> {
+    use printpdf::lopdf::{Dictionary, Object};
+
+    let outlines_id = doc.new_object_id();
+    let ids: Vec<_> = bookmarks.iter().map(|_| doc.new_object_id()).collect();
+    let mut top_level = Vec::new();
+    let mut children = vec![Vec::new(); bookmarks.len()];
+    for (i, bookmark) in bookmarks.iter().enumerate() {
+        match bookmark.parent {
+            Some(parent) => children[parent].push(i),
+            None => top_level.push(i),
+        }
+    }
+    // The number of descendants of each bookmark, which are all visible as all bookmarks are open
+    let mut counts = vec![0; bookmarks.len()];
+    for (i, bookmark) in bookmarks.iter().enumerate().rev() {
+        if let Some(parent) = bookmark.parent {
+            counts[parent] += 1 + counts[i];
+        }
+    }
+
+    let link_children = |dict: &mut Dictionary, children: &[usize]| {
+        if let (Some(first), Some(last)) = (children.first(), children.last()) {
+            dict.set("First", Object::Reference(ids[*first]));
+            dict.set("Last", Object::Reference(ids[*last]));
         }
+    };
+    let mut outlines = Dictionary::new();
+    outlines.set("Type", Object::Name(b"Outlines".to_vec()));
+    link_children(&mut outlines, &top_level);
+    outlines.set("Count", bookmarks.len() as i64);
 
-        // Draw strikethrough if enabled
-        if style.is_strikethrough() {
-            let line_thickness = Mm(style.font_size() as f32 * 0.05); // 5% of font size
-            // Position at middle of x-height (roughly middle of lowercase letters)
-            let strikethrough_y = self.metrics.ascent * 0.75;
-            let line_style = LineStyle::new()
-                .with_thickness(line_thickness)
-                .with_color(style.color().unwrap_or(Color::Rgb(0, 0, 0)));
-
-            self.area.draw_line(
-                vec![
-                    Position::new(start_x, strikethrough_y),
-                    Position::new(start_x + text_width, strikethrough_y),
-                ],
-                line_style,
+    for (i, bookmark) in bookmarks.iter().enumerate() {
+        let (parent_id, siblings) = match bookmark.parent {
+            Some(parent) => (ids[parent], &children[parent]),
+            None => (outlines_id, &top_level),
+        };
+        let mut dict = Dictionary::new();
+        dict.set("Title", text_string(&bookmark.title));
+        dict.set("Parent", Object::Reference(parent_id));
+        if let Some(page_idx) = bookmark.page_idx {
+            dict.set(
+                "Dest",
+                page_destination(page_ids[page_idx], page_heights[page_idx]),
             );
         }
+        let position = siblings.iter().position(|j| *j == i).unwrap_or_default();
+        if position > 0 {
+            dict.set("Prev", Object::Reference(ids[siblings[position - 1]]));
+        }
+        if let Some(next) = siblings.get(position + 1) {
+            dict.set("Next", Object::Reference(ids[*next]));
+        }
+        link_children(&mut dict, &children[i]);
+        if counts[i] > 0 {
+            dict.set("Count", counts[i] as i64);
+        }
+        doc.objects.insert(ids[i], Object::Dictionary(dict));
+    }
+    doc.objects
+        .insert(outlines_id, Object::Dictionary(outlines));
 
-        // Update position tracking
-        self.current_x_offset += text_width;
+    let catalog_id = doc.trailer.get(b"Root")?.as_reference()?;
+    let catalog = doc.get_dictionary_mut(catalog_id)?;
+    let old_outlines_id = catalog.get(b"Outlines").and_then(Object::as_reference).ok();
+    catalog.set("Outlines", Object::Reference(outlines_id));
+    catalog.set("PageMode", Object::Name(b"UseOutlines".to_vec()));
+    if let Some(id) = old_outlines_id {
+        doc.objects.remove(&id);
+    }
+    Ok(())
+}
 
-        // For built-in fonts, we don't need kerning tracking since PDF viewers handle it
-        if !font.is_builtin() {
-            let kerning_sum = Mm(kerning_positions.iter().sum::<f32>());
-            self.cumulative_kerning += kerning_sum;
-        }
+/// Encrypts all strings and streams of the given document using the standard security handler
+/// (revision 6) with a 256-bit AES key, and adds the encryption dictionary to the trailer.
+#[cfg(feature = "encryption")]
+fn encrypt_document(
+    doc: &mut printpdf::lopdf::Document,
+    user_password: &str,
+    owner_password: &str,
+    permissions: Permissions,
+) -> Result<(), Error> {
+    use printpdf::lopdf::{Dictionary, Object, StringFormat};
 
+    fn encrypt_object(key: &[u8; 32], object: &mut Object) -> Result<(), Error> {
+        match object {
+            Object::String(s, _) => *s = aes256_encrypt(key, s)?,
+            Object::Array(array) => {
+                for object in array {
+                    encrypt_object(key, object)?;
+                }
+            }
+            Object::Dictionary(dict) => {
+                for (_, object) in dict.iter_mut() {
+                    encrypt_object(key, object)?;
+                }
+            }
+            Object::Stream(stream) => {
+                for (_, object) in stream.dict.iter_mut() {
+                    encrypt_object(key, object)?;
+                }
+                let content = aes256_encrypt(key, &stream.content)?;
+                stream.set_content(content);
+            }
+            _ => {}
+        }
         Ok(())
     }
+
+    let flags = permissions.flags();
+    let key: [u8; 32] = random_bytes()?;
+    let entries = SecurityEntries::new(
+        user_password,
+        owner_password,
+        flags,
+        &key,
+        &random_bytes()?,
+        &random_bytes()?,
+        &random_bytes()?,
+    );
+
+    for object in doc.objects.values_mut() {
+        encrypt_object(&key, object)?;
+    }
+
+    let mut filter = Dictionary::new();
+    filter.set("AuthEvent", Object::Name(b"DocOpen".to_vec()));
+    filter.set("CFM", Object::Name(b"AESV3".to_vec()));
+    filter.set("Length", 32);
+    let mut crypt_filters = Dictionary::new();
+    crypt_filters.set("StdCF", filter);
+
+    let mut dict = Dictionary::new();
+    dict.set("Filter", Object::Name(b"Standard".to_vec()));
+    dict.set("V", 5);
+    dict.set("R", 6);
+    dict.set("Length", 256);
+    dict.set("CF", crypt_filters);
+    dict.set("StmF", Object::Name(b"StdCF".to_vec()));
+    dict.set("StrF", Object::Name(b"StdCF".to_vec()));
+    dict.set(
+        "O",
+        Object::String(entries.owner, StringFormat::Hexadecimal),
+    );
+    dict.set("U", Object::String(entries.user, StringFormat::Hexadecimal));
+    dict.set(
+        "OE",
+        Object::String(entries.owner_key, StringFormat::Hexadecimal),
+    );
+    dict.set(
+        "UE",
+        Object::String(entries.user_key, StringFormat::Hexadecimal),
+    );
+    dict.set("P", i64::from(flags as i32));
+    dict.set(
+        "Perms",
+        Object::String(entries.perms, StringFormat::Hexadecimal),
+    );
+    let encrypt_id = doc.add_object(dict);
+    doc.trailer.set("Encrypt", Object::Reference(encrypt_id));
+
+    // AES-256 encryption is part of PDF 2.0, but is also supported by PDF 1.7 readers that
+    // implement the Adobe extension level 8.
+    if doc.version.as_str() < "1.7" {
+        doc.version = "1.7".to_owned();
+    }
+    let catalog_id = doc
+        .trailer
+        .get(b"Root")
+        .and_then(Object::as_reference)
+        .context("Failed to find the document catalog")?;
+    let mut extension = Dictionary::new();
+    extension.set("BaseVersion", Object::Name(b"1.7".to_vec()));
+    extension.set("ExtensionLevel", 8);
+    let mut extensions = Dictionary::new();
+    extensions.set("ADBE", extension);
+    doc.get_dictionary_mut(catalog_id)
+        .context("Failed to find the document catalog")?
+        .set("Extensions", extensions);
+    Ok(())
 }
 
-impl<'f, 'p> Drop for TextSection<'f, 'p> {
-    fn drop(&mut self) {
-        self.area.layer.end_text_section();
+/// The password and permissions entries of the encryption dictionary for the standard security
+/// handler (revision 6).
+#[cfg(feature = "encryption")]
+struct SecurityEntries {
+    owner: Vec<u8>,
+    user: Vec<u8>,
+    owner_key: Vec<u8>,
+    user_key: Vec<u8>,
+    perms: Vec<u8>,
+}
+
+#[cfg(feature = "encryption")]
+impl SecurityEntries {
+    /// Computes the entries for the given passwords, permission flags and file key with the given
+    /// random salts for the user and owner passwords and the given random bytes for the
+    /// permissions entry, see algorithms 8 to 10 of ISO 32000-2.
+    ///
+    /// If the owner password is empty, the user password is used instead.  The passwords are
+    /// truncated to 127 bytes.
+    fn new(
+        user_password: &str,
+        owner_password: &str,
+        flags: u32,
+        key: &[u8; 32],
+        user_salts: &[u8; 16],
+        owner_salts: &[u8; 16],
+        perms_random: &[u8; 4],
+    ) -> SecurityEntries {
+        fn truncate(password: &str) -> &[u8] {
+            let password = password.as_bytes();
+            &password[..password.len().min(127)]
+        }
+
+        let owner_password = if owner_password.is_empty() {
+            user_password
+        } else {
+            owner_password
+        };
+        let user_password = truncate(user_password);
+        let owner_password = truncate(owner_password);
+
+        // Algorithm 8: the user password entries
+        let mut user = hash_password(user_password, &user_salts[..8], &[]).to_vec();
+        user.extend_from_slice(user_salts);
+        let user_key = hash_password(user_password, &user_salts[8..], &[]);
+
+        // Algorithm 9: the owner password entries
+        let mut owner = hash_password(owner_password, &owner_salts[..8], &user).to_vec();
+        owner.extend_from_slice(owner_salts);
+        let owner_key = hash_password(owner_password, &owner_salts[8..], &user);
+
+        // Algorithm 10: the permissions entry
+        let mut perms = [0xFF; 16];
+        perms[..4].copy_from_slice(&flags.to_le_bytes());
+        perms[8..12].copy_from_slice(b"Tadb");
+        perms[12..].copy_from_slice(perms_random);
+
+        SecurityEntries {
+            owner,
+            user,
+            owner_key: aes256_encrypt_blocks(&owner_key, key),
+            user_key: aes256_encrypt_blocks(&user_key, key),
+            perms: aes256_encrypt_blocks(key, &perms),
+        }
+    }
+}
+
+/// Computes the hash of the given password with the given salt and user password entry, see
+/// algorithm 2.B of ISO 32000-2.
+#[cfg(feature = "encryption")]
+fn hash_password(password: &[u8], salt: &[u8], user_entry: &[u8]) -> [u8; 32] {
+    use aes::cipher::{block_padding::NoPadding, BlockEncryptMut as _, KeyIvInit as _};
+    use sha2::Digest as _;
+
+    let mut hash = sha2::Sha256::new()
+        .chain_update(password)
+        .chain_update(salt)
+        .chain_update(user_entry)
+        .finalize()
+        .to_vec();
+    let mut round = 0;
+    loop {
+        let mut data = Vec::with_capacity(64 * (password.len() + hash.len() + user_entry.len()));
+        for _ in 0..64 {
+            data.extend_from_slice(password);
+            data.extend_from_slice(&hash);
+            data.extend_from_slice(user_entry);
+        }
+        let encrypted = cbc::Encryptor::<aes::Aes128>::new(hash[..16].into(), hash[16..32].into())
+            .encrypt_padded_vec_mut::<NoPadding>(&data);
+        // The first 16 bytes as a big-endian integer modulo 3 are equal to the sum of the bytes
+        // modulo 3 because 256 modulo 3 is 1.
+        let sum: u32 = encrypted[..16].iter().copied().map(u32::from).sum();
+        hash = match sum % 3 {
+            0 => sha2::Sha256::digest(&encrypted).to_vec(),
+            1 => sha2::Sha384::digest(&encrypted).to_vec(),
+            _ => sha2::Sha512::digest(&encrypted).to_vec(),
+        };
+        round += 1;
+        let last = encrypted.last().copied().map(u32::from).unwrap_or_default();
+        if round >= 64 && last <= round - 32 {
+            break;
+        }
+    }
+    let mut result = [0; 32];
+    result.copy_from_slice(&hash[..32]);
+    result
+}
+
+/// Encrypts the given data, which must be a multiple of the block size, with AES-256 in CBC mode
+/// with a zero initialization vector and without padding.
+#[cfg(feature = "encryption")]
+fn aes256_encrypt_blocks(key: &[u8; 32], data: &[u8]) -> Vec<u8> {
+    use aes::cipher::{block_padding::NoPadding, BlockEncryptMut as _, KeyIvInit as _};
+
+    cbc::Encryptor::<aes::Aes256>::new(key.into(), &[0; 16].into())
+        .encrypt_padded_vec_mut::<NoPadding>(data)
+}
+
+/// Encrypts the given string or stream data with AES-256 in CBC mode and a random initialization
+/// vector, which is prepended to the encrypted data.
+#[cfg(feature = "encryption")]
+fn aes256_encrypt(key: &[u8; 32], data: &[u8]) -> Result<Vec<u8>, Error> {
+    use aes::cipher::{block_padding::Pkcs7, BlockEncryptMut as _, KeyIvInit as _};
+
+    let iv: [u8; 16] = random_bytes()?;
+    let mut encrypted = iv.to_vec();
+    encrypted.extend(
+        cbc::Encryptor::<aes::Aes256>::new(key.into(), &iv.into())
+            .encrypt_padded_vec_mut::<Pkcs7>(data),
+    );
+    Ok(encrypted)
+}
+
+/// Returns random bytes from the random number generator of the operating system.
+#[cfg(feature = "encryption")]
+fn random_bytes<const N: usize>() -> Result<[u8; N], Error> {
+    let mut bytes = [0; N];
+    getrandom::getrandom(&mut bytes).map_err(|err| {
+        Error::new(
+            format!("Failed to generate random bytes: {}", err),
+            ErrorKind::Internal,
+        )
+    })?;
+    Ok(bytes)
+}
+
+/// Replaces the kids of the page tree of the given document with the pages with the given IDs in
+/// the given order.
+fn set_page_order(
+    doc: &mut printpdf::lopdf::Document,
+    page_ids: &[printpdf::lopdf::ObjectId],
+) -> Result<(), printpdf::lopdf::Error> {
+    use printpdf::lopdf::Object;
+
+    let catalog_id = doc.trailer.get(b"Root")?.as_reference()?;
+    let pages_id = doc
+        .get_dictionary(catalog_id)?
+        .get(b"Pages")?
+        .as_reference()?;
+    for page_id in page_ids {
+        doc.get_dictionary_mut(*page_id)?
+            .set("Parent", Object::Reference(pages_id));
+    }
+    let pages = doc.get_dictionary_mut(pages_id)?;
+    let kids: Vec<_> = page_ids.iter().copied().map(Object::Reference).collect();
+    pages.set("Kids", kids);
+    pages.set("Count", page_ids.len() as i64);
+    Ok(())
+}
+
+/// Adds link annotations with the given rectangles to the page with the given ID that jump to the
+/// top of the pages with the given indices.
+fn add_internal_links(
+    doc: &mut printpdf::lopdf::Document,
+    page_id: printpdf::lopdf::ObjectId,
+    links: Vec<(printpdf::Rect, usize)>,
+    page_ids: &[printpdf::lopdf::ObjectId],
+    page_heights: &[Mm],
+) -> Result<(), printpdf::lopdf::Error> {
+    use printpdf::lopdf::{Dictionary, Object};
+
+    let mut annotation_ids = Vec::with_capacity(links.len());
+    for (rect, target_page) in links {
+        let mut action = Dictionary::new();
+        action.set("S", Object::Name(b"GoTo".to_vec()));
+        action.set(
+            "D",
+            page_destination(page_ids[target_page], page_heights[target_page]),
+        );
+        let mut annotation = Dictionary::new();
+        annotation.set("Type", Object::Name(b"Annot".to_vec()));
+        annotation.set("Subtype", Object::Name(b"Link".to_vec()));
+        annotation.set(
+            "Rect",
+            vec![
+                rect.ll.x.into(),
+                rect.ll.y.into(),
+                rect.ur.x.into(),
+                rect.ur.y.into(),
+            ],
+        );
+        annotation.set("Border", vec![0.into(), 0.into(), 0.into()]);
+        annotation.set("A", action);
+        annotation_ids.push(doc.add_object(annotation));
+    }
+
+    let page = doc.get_dictionary_mut(page_id)?;
+    let mut annotations = match page.get(b"Annots") {
+        Ok(Object::Array(annotations)) => annotations.clone(),
+        _ => Vec::new(),
+    };
+    annotations.extend(annotation_ids.into_iter().map(Object::Reference));
+    page.set("Annots", annotations);
+    Ok(())
+}
+
+/// Adds the given soft masks to the images with the given XObject indices on the page with the
+/// given ID.
+fn add_soft_masks(
+    doc: &mut printpdf::lopdf::Document,
+    page_id: printpdf::lopdf::ObjectId,
+    masks: Vec<(usize, Vec<u8>)>,
+) -> Result<(), printpdf::lopdf::Error> {
+    use printpdf::lopdf::{Dictionary, Object, Stream};
+
+    for (index, alpha) in masks {
+        let image_id = page_xobject(doc, page_id, index)?;
+        let image = doc.get_object(image_id)?.as_stream()?;
+        let mut dict = Dictionary::new();
+        dict.set("Type", Object::Name(b"XObject".to_vec()));
+        dict.set("Subtype", Object::Name(b"Image".to_vec()));
+        dict.set("Width", image.dict.get(b"Width")?.clone());
+        dict.set("Height", image.dict.get(b"Height")?.clone());
+        dict.set("ColorSpace", Object::Name(b"DeviceGray".to_vec()));
+        dict.set("BitsPerComponent", 8);
+        let mut mask = Stream::new(dict, alpha);
+        mask.compress()?;
+        let mask_id = doc.add_object(mask);
+        doc.get_object_mut(image_id)?
+            .as_stream_mut()?
+            .dict
+            .set("SMask", Object::Reference(mask_id));
     }
+    Ok(())
 }
 
-/// Encodes the given string using the Windows-1252 encoding for use with built-in PDF fonts,
-/// returning an error if it contains unsupported characters.
-fn encode_win1252(s: &str) -> Result<Vec<u16>, Error> {
-    let bytes: Vec<_> = lopdf::Document::encode_text(Some("WinAnsiEncoding"), s)
+/// The name of the marked-content point that is added at the start of background layers.
+const BACKGROUND_LAYER_MARKER: &[u8] = b"GenpdfiBackground";
+
+/// Moves the content of the background layers of the page with the given ID to the beginning of
+/// its content stream and removes their markers.
+///
+/// printpdf wraps the content of every layer in a marked-content sequence for its optional
+/// content group and concatenates the layers into a single content stream, so the layers are
+/// identified by the top-level marked-content sequences.  The new content stream is compressed if
+/// the original content was compressed.
+fn move_background_layers(
+    doc: &mut printpdf::lopdf::Document,
+    page_id: printpdf::lopdf::ObjectId,
+) -> Result<(), printpdf::lopdf::Error> {
+    use printpdf::lopdf::content::{Content, Operation};
+    use printpdf::lopdf::{Dictionary, Object, Stream};
+
+    let content_ids = doc.get_page_contents(page_id);
+    let is_compressed = content_ids.iter().any(|id| {
+        doc.get_object(*id)
+            .and_then(Object::as_stream)
+            .map(|stream| stream.dict.has(b"Filter"))
+            .unwrap_or_default()
+    });
+    let content = doc.get_page_content(page_id)?;
+    let mut layers: Vec<Vec<Operation>> = Vec::new();
+    let mut depth = 0usize;
+    for operation in Content::decode(&content)?.operations {
+        let is_begin = ["BDC", "BMC"].contains(&operation.operator.as_str());
+        if layers.is_empty() || (depth == 0 && is_begin) {
+            layers.push(Vec::new());
+        }
+        if is_begin {
+            depth += 1;
+        } else if operation.operator == "EMC" {
+            depth = depth.saturating_sub(1);
+        }
+        if let Some(layer) = layers.last_mut() {
+            layer.push(operation);
+        }
+    }
+
+    let is_marker = |operation: &Operation| {
+        operation.operator == "MP"
+            && matches!(operation.operands.first(), Some(Object::Name(name)) if name == BACKGROUND_LAYER_MARKER)
+    };
+    let (mut operations, foreground): (Vec<_>, Vec<_>) = layers
         .into_iter()
-        .map(u16::from)
+        .partition(|layer| layer.iter().any(is_marker));
+    operations.extend(foreground);
+    let operations: Vec<_> = operations
+        .into_iter()
+        .flatten()
+        .filter(|operation| !is_marker(operation))
         .collect();
+    let content = Content { operations }.encode()?;
 
-    // Windows-1252 is a single-byte encoding, so one byte is one character.
-    if bytes.len() != s.chars().count() {
-        Err(Error::new(
-            format!(
-                "Tried to print a string with characters that are not supported by the \
-                Windows-1252 encoding with a built-in font: {}",
-                s
-            ),
-            ErrorKind::UnsupportedEncoding,
-        ))
+    // Document::change_page_content always compresses the content, so we replace the stream
+    // manually to keep the compression of the generated document.
+    let mut stream = Stream::new(Dictionary::new(), content);
+    if is_compressed {
+        stream.compress()?;
+    }
+    match content_ids.as_slice() {
+        [id] => {
+            doc.objects.insert(*id, Object::Stream(stream));
+        }
+        _ => {
+            let id = doc.add_object(stream);
+            doc.get_object_mut(page_id)
+                .and_then(Object::as_dict_mut)?
+                .set("Contents", id);
+        }
+    }
+    Ok(())
+}
+
+/// Replaces the image placeholders with the given XObject indices and image IDs with references to
+/// the embedded images with the same IDs and removes the placeholders.
+fn share_images(
+    doc: &mut printpdf::lopdf::Document,
+    page_ids: &[printpdf::lopdf::ObjectId],
+    embedded_images: Vec<Vec<(usize, usize)>>,
+    image_references: Vec<Vec<(usize, usize)>>,
+) -> Result<(), printpdf::lopdf::Error> {
+    use printpdf::lopdf::Object;
+
+    let mut image_ids = HashMap::new();
+    for (page_id, images) in page_ids.iter().copied().zip(embedded_images) {
+        for (index, id) in images {
+            image_ids.insert(id, page_xobject(doc, page_id, index)?);
+        }
+    }
+    for (page_id, references) in page_ids.iter().copied().zip(image_references) {
+        for (index, id) in references {
+            let image_id = image_ids
+                .get(&id)
+                .copied()
+                .ok_or(printpdf::lopdf::Error::ObjectNotFound)?;
+            let placeholder_id = page_xobject(doc, page_id, index)?;
+            let xobjects = page_resources_mut(doc, page_id)?
+                .get_mut(b"XObject")?
+                .as_dict_mut()?;
+            xobjects.set(format!("X{}", index), Object::Reference(image_id));
+            doc.objects.remove(&placeholder_id);
+        }
+    }
+    Ok(())
+}
+
+/// Returns the ID of the XObject with the given index in the resources of the page with the given
+/// ID.
+fn page_xobject(
+    doc: &printpdf::lopdf::Document,
+    page_id: printpdf::lopdf::ObjectId,
+    index: usize,
+) -> Result<printpdf::lopdf::ObjectId, printpdf::lopdf::Error> {
+    use printpdf::lopdf::Object;
+
+    let resources = match doc.get_dictionary(page_id)?.get(b"Resources")? {
+        Object::Reference(id) => doc.get_dictionary(*id)?,
+        resources => resources.as_dict()?,
+    };
+    resources
+        .get(b"XObject")?
+        .as_dict()?
+        .get(format!("X{}", index).as_bytes())?
+        .as_reference()
+}
+
+/// Returns the number of XObjects in the resources of the page with the given ID.
+#[cfg(any(feature = "images", feature = "svg"))]
+fn page_xobject_count(
+    doc: &printpdf::lopdf::Document,
+    page_id: printpdf::lopdf::ObjectId,
+) -> Result<usize, printpdf::lopdf::Error> {
+    use printpdf::lopdf::Object;
+
+    let resources = match doc.get_dictionary(page_id)?.get(b"Resources") {
+        Ok(Object::Reference(id)) => doc.get_dictionary(*id)?,
+        Ok(resources) => resources.as_dict()?,
+        Err(_) => return Ok(0),
+    };
+    match resources.get(b"XObject") {
+        Ok(xobjects) => Ok(xobjects.as_dict()?.len()),
+        Err(_) => Ok(0),
+    }
+}
+
+/// Clamps the given alpha value to the range from 0 (transparent) to 1 (opaque), mapping NaN to 1.
+fn clamp_alpha(alpha: f32) -> f32 {
+    if alpha.is_nan() {
+        1.0
     } else {
-        Ok(bytes)
+        alpha.clamp(0.0, 1.0)
+    }
+}
+
+/// Returns the index of the given page in the given printpdf document, or `None` if the page
+/// does not belong to the document.
+///
+/// printpdf does not expose the page index, but the bookmarks of a document map the page indices
+/// to the bookmark names.  So we temporarily add a bookmark for the page and look it up in the
+/// document of the page.  If the page does not belong to the given document, the bookmark is left
+/// in the given document, so it must be discarded in this case.
+fn pdf_page_index(
+    doc: &printpdf::PdfDocumentReference,
+    page: &printpdf::PdfPageReference,
+) -> Option<usize> {
+    let page_doc = page.document.upgrade()?;
+    let bookmarks = mem::take(&mut page_doc.borrow_mut().bookmarks);
+    doc.add_bookmark(String::new(), page.page);
+    let marked = mem::replace(&mut page_doc.borrow_mut().bookmarks, bookmarks);
+    marked.into_keys().next()
+}
+
+/// Returns an explicit destination for the top of the page with the given ID and height.
+fn page_destination(page_id: printpdf::lopdf::ObjectId, height: Mm) -> printpdf::lopdf::Object {
+    use printpdf::lopdf::Object;
+
+    vec![
+        Object::Reference(page_id),
+        Object::Name(b"XYZ".to_vec()),
+        Object::Null,
+        Object::Real(printpdf::Pt::from(height).0),
+        Object::Null,
+    ]
+    .into()
+}
+
+/// Sets the page layout, the page mode and the open action of the given document.
+///
+/// The open action is given as the ID and the height of the target page and the zoom factor.
+fn set_viewer_preferences(
+    doc: &mut printpdf::lopdf::Document,
+    page_layout: Option<PageLayout>,
+    page_mode: Option<PageMode>,
+    open_action: Option<(printpdf::lopdf::ObjectId, Mm, f32)>,
+) -> Result<(), printpdf::lopdf::Error> {
+    use printpdf::lopdf::Object;
+
+    let catalog_id = doc.trailer.get(b"Root")?.as_reference()?;
+    let catalog = doc.get_dictionary_mut(catalog_id)?;
+    if let Some(page_layout) = page_layout {
+        catalog.set("PageLayout", Object::Name(page_layout.pdf_name().into()));
+    }
+    if let Some(page_mode) = page_mode {
+        catalog.set("PageMode", Object::Name(page_mode.pdf_name().into()));
+    }
+    if let Some((page_id, height, zoom)) = open_action {
+        let mut destination = page_destination(page_id, height);
+        if let Object::Array(array) = &mut destination {
+            array[4] = Object::Real(zoom);
+        }
+        catalog.set("OpenAction", destination);
+    }
+    Ok(())
+}
+
+/// Sets the encoding of all built-in text fonts of the given document, which printpdf always
+/// writes with the Windows-1252 encoding.
+///
+/// The standard encoding is the built-in encoding of the text fonts, so the `Encoding` entry is
+/// removed instead of naming it explicitly.  The Symbol and ZapfDingbats fonts use their own
+/// built-in encodings and are not changed.
+fn set_builtin_font_encoding(
+    doc: &mut printpdf::lopdf::Document,
+    encoding: fonts::BuiltinEncoding,
+) {
+    use printpdf::lopdf::Object;
+
+    for object in doc.objects.values_mut() {
+        if let Object::Dictionary(dict) = object {
+            let is_type1_font = dict.get(b"Type").and_then(Object::as_name).ok() == Some(b"Font")
+                && dict.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Type1");
+            let base_font = dict.get(b"BaseFont").and_then(Object::as_name).ok();
+            let is_symbolic = matches!(base_font, Some(b"Symbol") | Some(b"ZapfDingbats"));
+            if !is_type1_font || is_symbolic {
+                continue;
+            }
+            if encoding == fonts::BuiltinEncoding::Standard {
+                dict.remove(b"Encoding");
+            } else {
+                dict.set(
+                    "Encoding",
+                    Object::Name(encoding.pdf_name().as_bytes().to_vec()),
+                );
+            }
+        }
+    }
+}
+
+/// Adds the given entries to the document information dictionary.
+fn add_info_entries(
+    doc: &mut printpdf::lopdf::Document,
+    entries: printpdf::lopdf::Dictionary,
+) -> Result<(), printpdf::lopdf::Error> {
+    let info_id = doc.trailer.get(b"Info")?.as_reference()?;
+    let info = doc.get_dictionary_mut(info_id)?;
+    for (name, value) in entries.iter() {
+        info.set(name.clone(), value.clone());
+    }
+    Ok(())
+}
+
+/// Replaces the font program of the given embedded font with the given subset and maps the glyph
+/// IDs used in the document, which are the glyph IDs of the original font, to the glyph IDs of the
+/// subset.
+fn replace_font_program(
+    doc: &mut printpdf::lopdf::Document,
+    font: &printpdf::IndirectFontRef,
+    data: Vec<u8>,
+    glyph_map: &HashMap<u16, u16>,
+) -> Result<(), printpdf::lopdf::Error> {
+    use printpdf::lopdf::{Dictionary, Error, Object, Stream};
+
+    // printpdf uses the resource name of the font as its base font name.
+    let font_id = doc
+        .objects
+        .iter()
+        .find(|(_, object)| {
+            object
+                .as_dict()
+                .and_then(|dict| dict.get(b"BaseFont"))
+                .and_then(Object::as_name)
+                .map(|name| printpdf::IndirectFontRef::new(String::from_utf8_lossy(name)) == *font)
+                .unwrap_or(false)
+                && object
+                    .as_dict()
+                    .and_then(|dict| dict.get(b"Subtype"))
+                    .and_then(Object::as_name)
+                    .map(|subtype| subtype == b"Type0")
+                    .unwrap_or(false)
+        })
+        .map(|(id, _)| *id)
+        .ok_or(Error::ObjectNotFound)?;
+
+    let descriptor_id = doc
+        .get_dictionary(font_id)?
+        .get(b"DescendantFonts")?
+        .as_array()?
+        .first()
+        .ok_or(Error::ObjectNotFound)?
+        .as_dict()?
+        .get(b"FontDescriptor")?
+        .as_reference()?;
+    let file_id = doc
+        .get_dictionary(descriptor_id)?
+        .get(b"FontFile2")?
+        .as_reference()?;
+    let mut dict = Dictionary::new();
+    dict.set("Length1", data.len() as i64);
+    // The font stream must not be compressed.
+    let stream = Stream::new(dict, data).with_compression(false);
+    doc.objects.insert(file_id, Object::Stream(stream));
+
+    // The CIDs in the content streams are the glyph IDs of the original font.
+    let cid_count = glyph_map
+        .keys()
+        .max()
+        .map_or(0, |cid| usize::from(*cid) + 1);
+    let mut cid_to_gid = vec![0; 2 * cid_count];
+    for (cid, gid) in glyph_map {
+        let idx = 2 * usize::from(*cid);
+        cid_to_gid[idx..idx + 2].copy_from_slice(&gid.to_be_bytes());
+    }
+    let map_id = doc.add_object(Stream::new(Dictionary::new(), cid_to_gid));
+    doc.get_dictionary_mut(font_id)?
+        .get_mut(b"DescendantFonts")?
+        .as_array_mut()?
+        .first_mut()
+        .ok_or(Error::ObjectNotFound)?
+        .as_dict_mut()?
+        .set("CIDToGIDMap", Object::Reference(map_id));
+    Ok(())
+}
+
+/// Adds the given entries to the resource dictionary with the given key of a page.
+fn add_page_resources(
+    doc: &mut printpdf::lopdf::Document,
+    page_id: printpdf::lopdf::ObjectId,
+    key: &str,
+    entries: printpdf::lopdf::Dictionary,
+) -> Result<(), printpdf::lopdf::Error> {
+    use printpdf::lopdf::Dictionary;
+
+    let resources = page_resources_mut(doc, page_id)?;
+
+    // Existing sub-dictionaries are always inlined by printpdf.
+    if !resources.has(key.as_bytes()) {
+        resources.set(key, Dictionary::new());
+    }
+    let dict = resources.get_mut(key.as_bytes())?.as_dict_mut()?;
+    for (name, value) in entries.iter() {
+        dict.set(name.clone(), value.clone());
+    }
+    Ok(())
+}
+
+/// Returns the resource dictionary of the page with the given ID, creating it if necessary.
+fn page_resources_mut(
+    doc: &mut printpdf::lopdf::Document,
+    page_id: printpdf::lopdf::ObjectId,
+) -> Result<&mut printpdf::lopdf::Dictionary, printpdf::lopdf::Error> {
+    use printpdf::lopdf::{Dictionary, Object};
+
+    let resources_id = match doc.get_dictionary(page_id)?.get(b"Resources") {
+        Ok(Object::Reference(id)) => Some(*id),
+        _ => None,
+    };
+    if let Some(id) = resources_id {
+        doc.get_dictionary_mut(id)
+    } else {
+        let page = doc.get_dictionary_mut(page_id)?;
+        if !page.has(b"Resources") {
+            page.set("Resources", Dictionary::new());
+        }
+        page.get_mut(b"Resources")?.as_dict_mut()
+    }
+}
+
+/// The images embedded in a document with their IDs, keyed by their [`image_key`].
+///
+/// The images are stored to compare them on a cache hit, as different images can have the same
+/// hash.
+#[cfg(feature = "images")]
+type ImageCache = HashMap<u64, Vec<(image::DynamicImage, usize)>>;
+#[cfg(not(feature = "images"))]
+type ImageCache = HashMap<u64, usize>;
+
+/// Returns the ID of the given image with the given key in the given image cache, if it has
+/// already been embedded.
+#[cfg(feature = "images")]
+fn cached_image_id(cache: &ImageCache, key: u64, image: &image::DynamicImage) -> Option<usize> {
+    cache
+        .get(&key)?
+        .iter()
+        .find(|(cached, _)| cached == image)
+        .map(|(_, id)| *id)
+}
+
+/// Returns a hash of the dimensions, the color type and the pixel data of the given image that is
+/// used to detect identical images.
+#[cfg(feature = "images")]
+fn image_key(image: &image::DynamicImage) -> u64 {
+    use std::hash::{Hash as _, Hasher as _};
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    image.width().hash(&mut hasher);
+    image.height().hash(&mut hasher);
+    image.color().hash(&mut hasher);
+    image.as_bytes().hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Encodes the given string using the given single-byte encoding, returning `None` if it contains
+/// unsupported characters.
+pub(crate) fn try_encode_builtin(s: &str, encoding: fonts::BuiltinEncoding) -> Option<Vec<u16>> {
+    let bytes: Vec<_> = lopdf::Document::encode_text(Some(encoding.pdf_name()), s)
+        .into_iter()
+        .map(u16::from)
+        .collect();
+
+    // All built-in encodings are single-byte encodings, so one byte is one character.
+    if bytes.len() == s.chars().count() {
+        Some(bytes)
+    } else {
+        None
+    }
+}
+
+/// Encodes the given string using the given encoding for use with built-in PDF fonts, returning an
+/// error if it contains unsupported characters.
+fn encode_builtin(s: &str, encoding: fonts::BuiltinEncoding) -> Result<Vec<u16>, Error> {
+    try_encode_builtin(s, encoding).ok_or_else(|| {
+        Error::new(
+            format!(
+                "Tried to print a string with characters that are not supported by the \
+                {} with a built-in font: {}",
+                encoding.pdf_name(),
+                s
+            ),
+            ErrorKind::UnsupportedEncoding,
+        )
+    })
+}
+
+/// The information from a JPEG header that is required to embed the image without decoding it.
+#[cfg(feature = "images")]
+#[derive(Clone, Copy, Debug)]
+struct JpegHeader {
+    width: usize,
+    height: usize,
+    color_space: printpdf::ColorSpace,
+}
+
+/// Reads the dimensions and the color space from the frame header of the given JPEG data.
+#[cfg(feature = "images")]
+fn parse_jpeg_header(data: &[u8]) -> Result<JpegHeader, Error> {
+    let invalid = |msg: &str| {
+        Error::new(
+            format!("Failed to parse JPEG header: {}", msg),
+            ErrorKind::InvalidData,
+        )
+    };
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return Err(invalid("missing start of image marker"));
+    }
+
+    let mut pos = 2;
+    loop {
+        // Markers may be preceded by any number of fill bytes.
+        while data.get(pos..pos + 2) == Some(&[0xFF, 0xFF]) {
+            pos += 1;
+        }
+        let marker = match data.get(pos..pos + 2) {
+            Some(&[0xFF, marker]) => marker,
+            Some(_) => return Err(invalid("expected a marker")),
+            None => return Err(invalid("missing frame header")),
+        };
+        pos += 2;
+
+        match marker {
+            // Markers without a segment
+            0x01 | 0xD0..=0xD7 => continue,
+            // End of image or start of scan before the frame header
+            0xD9 | 0xDA => return Err(invalid("missing frame header")),
+            _ => {}
+        }
+
+        let length = data
+            .get(pos..pos + 2)
+            .map(|bytes| usize::from(u16::from_be_bytes([bytes[0], bytes[1]])))
+            .filter(|length| *length >= 2)
+            .ok_or_else(|| invalid("invalid segment length"))?;
+        match marker {
+            // Baseline, extended sequential and progressive Huffman coding
+            0xC0..=0xC2 => {
+                let frame = data
+                    .get(pos + 2..pos + length)
+                    .filter(|frame| frame.len() >= 6)
+                    .ok_or_else(|| invalid("truncated frame header"))?;
+                if frame[0] != 8 {
+                    return Err(Error::new(
+                        format!("Unsupported JPEG sample precision: {} bits", frame[0]),
+                        ErrorKind::InvalidData,
+                    ));
+                }
+                let height = usize::from(u16::from_be_bytes([frame[1], frame[2]]));
+                let width = usize::from(u16::from_be_bytes([frame[3], frame[4]]));
+                if width == 0 || height == 0 {
+                    return Err(invalid("invalid image dimensions"));
+                }
+                let color_space = match frame[5] {
+                    1 => printpdf::ColorSpace::Greyscale,
+                    3 => printpdf::ColorSpace::Rgb,
+                    n => {
+                        return Err(Error::new(
+                            format!("Unsupported number of JPEG color components: {}", n),
+                            ErrorKind::InvalidData,
+                        ))
+                    }
+                };
+                return Ok(JpegHeader {
+                    width,
+                    height,
+                    color_space,
+                });
+            }
+            // Lossless, hierarchical and arithmetic coding
+            0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF => {
+                return Err(Error::new(
+                    "Unsupported JPEG coding process: only baseline and progressive JPEG images \
+                    can be embedded",
+                    ErrorKind::InvalidData,
+                ));
+            }
+            _ => pos += length,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_cmp::approx_eq;
+    use lopdf::content::{Content, Operation};
+
+    use super::Renderer;
+    use crate::fonts::{FontCache, FontData, FontFallbackChain, FontFamily};
+    use crate::style::LineStyle;
+    use crate::style::{Color, Style};
+    use crate::wrap;
+    use crate::{Alignment, Hyphenator, Margins, Mm, NoHyphenation, PaperSize, Position, Size};
+
+    const FONT: &[u8] = include_bytes!("../tests/fonts/DejaVuSans-Regular.ttf");
+    const LATIN_FONT: &[u8] = include_bytes!("../tests/fonts/DejaVuSans-Latin.ttf");
+
+    macro_rules! assert_approx_eq {
+        ($typ:ty, $lhs:expr, $rhs:expr) => {
+            let left = $lhs;
+            let right = $rhs;
+            assert!(
+                approx_eq!($typ, left, right, epsilon = 100.0 * f32::EPSILON, ulps = 10),
+                "assertion failed: `(left approx_eq right)`
+  left: `{:?}`,
+ right: `{:?}`",
+                left,
+                right
+            );
+        };
+    }
+
+    fn font_family() -> FontFamily<FontData> {
+        let data = FontData::new(FONT.to_vec(), None).expect("Could not load test font");
+        FontFamily {
+            regular: data.clone(),
+            bold: data.clone(),
+            italic: data.clone(),
+            bold_italic: data,
+        }
+    }
+
+    fn builtin_font_family() -> FontFamily<FontData> {
+        let data = FontData::new(FONT.to_vec(), Some(printpdf::BuiltinFont::Helvetica))
+            .expect("Could not load test font");
+        FontFamily {
+            regular: data.clone(),
+            bold: data.clone(),
+            italic: data.clone(),
+            bold_italic: data,
+        }
+    }
+
+    fn write_document(renderer: Renderer) -> lopdf::Document {
+        let mut buf = Vec::new();
+        renderer.write(&mut buf).expect("Could not write document");
+        lopdf::Document::load_mem(&buf).expect("Could not parse document")
+    }
+
+    fn first_page(doc: &lopdf::Document) -> &lopdf::Dictionary {
+        let page_id = *doc.get_pages().values().next().unwrap();
+        doc.get_dictionary(page_id).unwrap()
+    }
+
+    fn page_box(doc: &lopdf::Document, key: &[u8]) -> Vec<f32> {
+        let page = first_page(doc);
+        let array = page.get(key).unwrap().as_array().unwrap();
+        array.iter().map(|o| number(o) as f32).collect()
+    }
+
+    fn render(renderer: Renderer) -> Vec<Operation> {
+        let doc = write_document(renderer);
+        let page_id = *doc.get_pages().values().next().unwrap();
+        let content = doc.get_page_content(page_id).unwrap();
+        Content::decode(&content).unwrap().operations
+    }
+
+    fn number(object: &lopdf::Object) -> f64 {
+        match object {
+            lopdf::Object::Integer(i) => *i as f64,
+            lopdf::Object::Real(r) => *r,
+            _ => panic!("Expected a number, got {:?}", object),
+        }
+    }
+
+    fn find_operation<'a>(operations: &'a [Operation], operator: &str) -> &'a Operation {
+        operations
+            .iter()
+            .find(|op| op.operator == operator)
+            .unwrap_or_else(|| panic!("Missing {} operation", operator))
+    }
+
+    fn print_hello(default_style: Style, style: Style) -> Vec<Operation> {
+        let mut font_cache = FontCache::new(font_family());
+        // Fonts with identical data share the same PDF font, so use a different font here
+        let latin = FontData::new(LATIN_FONT.to_vec(), None).unwrap();
+        let family = font_cache.add_font_family(FontFamily {
+            regular: latin.clone(),
+            bold: latin.clone(),
+            italic: latin.clone(),
+            bold_italic: latin,
+        });
+        let mut renderer = Renderer::new(PaperSize::A4, "test").unwrap();
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+
+        let default_style = default_style.with_font_family(family);
+        renderer.set_default_style(default_style);
+        assert_eq!(default_style, renderer.default_style());
+
+        let area = renderer.first_page().first_layer().area();
+        assert!(area
+            .print_str(&font_cache, Position::default(), style, "Hello")
+            .unwrap());
+        drop(area);
+        render(renderer)
+    }
+
+    #[test]
+    fn test_default_style() {
+        let default_style = Style::new()
+            .with_font_size(20)
+            .with_color(Color::Rgb(255, 0, 0));
+        let operations = print_hello(default_style, Style::new());
+
+        let tf = find_operation(&operations, "Tf");
+        assert_eq!(20.0, number(&tf.operands[1]));
+        let rg = find_operation(&operations, "rg");
+        let rgb: Vec<_> = rg.operands.iter().map(number).collect();
+        assert_eq!(vec![1.0, 0.0, 0.0], rgb);
+
+        // The default style uses the second font family, so the font differs from the one that
+        // is selected if the default family of the font cache is used explicitly.
+        let font_cache = FontCache::new(font_family());
+        let default_family = font_cache.default_font_family();
+        let explicit = print_hello(Style::new(), Style::from(default_family));
+        assert_ne!(
+            tf.operands[0].as_name().unwrap(),
+            find_operation(&explicit, "Tf").operands[0]
+                .as_name()
+                .unwrap()
+        );
+
+        // Values set in the printed style take precedence over the default style.
+        let operations = print_hello(default_style, Style::new().with_font_size(8));
+        let tf = find_operation(&operations, "Tf");
+        assert_eq!(8.0, number(&tf.operands[1]));
+    }
+
+    #[test]
+    fn test_from_document() {
+        let size = Size::new(100, 100);
+        let new_document = || {
+            let (doc, page, layer) = printpdf::PdfDocument::new(
+                "test",
+                size.width.into(),
+                size.height.into(),
+                "Layer 1",
+            );
+            let (other_page, other_layer) =
+                doc.add_page(size.width.into(), size.height.into(), "Layer 1");
+            (doc, [(page, layer), (other_page, other_layer)])
+        };
+
+        let (doc, _) = new_document();
+        assert!(Renderer::from_document(doc, Vec::new()).is_err());
+
+        let (doc, indices) = new_document();
+        let page = doc.get_page(indices[0].0);
+        let layer = doc.get_page(indices[1].0).get_layer(indices[1].1);
+        assert!(Renderer::from_document(doc, vec![(page, layer, size)]).is_err());
+
+        // The pages and layers must belong to the document
+        let (other_doc, other_indices) = new_document();
+        let other_page = || other_doc.get_page(other_indices[0].0);
+        let other_layer = || other_page().get_layer(other_indices[0].1);
+        let (doc, _) = new_document();
+        assert!(Renderer::from_document(doc, vec![(other_page(), other_layer(), size)]).is_err());
+        let (doc, indices) = new_document();
+        let page = doc.get_page(indices[0].0);
+        assert!(Renderer::from_document(doc, vec![(page, other_layer(), size)]).is_err());
+        let (doc, indices) = new_document();
+        let page = doc.get_page(indices[0].0);
+        let layer = page.get_layer(indices[0].1);
+        let pages = vec![(page, layer, size), (other_page(), other_layer(), size)];
+        assert!(Renderer::from_document(doc, pages).is_err());
+
+        // The pages must be in order and without duplicates
+        for order in &[[1, 0], [0, 0]] {
+            let (doc, indices) = new_document();
+            let pages = order
+                .iter()
+                .map(|idx| {
+                    let page = doc.get_page(indices[*idx].0);
+                    let layer = page.get_layer(indices[*idx].1);
+                    (page, layer, size)
+                })
+                .collect();
+            assert!(Renderer::from_document(doc, pages).is_err());
+        }
+
+        // All pages of the document must be passed to the renderer
+        let (doc, indices) = new_document();
+        let page = doc.get_page(indices[0].0);
+        let layer = page.get_layer(indices[0].1);
+        let mut renderer = Renderer::from_document(doc, vec![(page, layer, size)]).unwrap();
+        renderer.add_bookmark("Page 1", 0).unwrap();
+        assert!(renderer.write(Vec::new()).is_err());
+
+        // Existing bookmarks are kept
+        let (doc, indices) = new_document();
+        doc.add_bookmark("Start", indices[0].0);
+        let pages = indices
+            .iter()
+            .map(|(page, layer)| {
+                let page = doc.get_page(*page);
+                let layer = page.get_layer(*layer);
+                (page, layer, size)
+            })
+            .collect();
+        let renderer = Renderer::from_document(doc, pages).unwrap();
+        assert_eq!(2, renderer.page_count());
+
+        let mut font_cache = FontCache::new(font_family());
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+        let area = renderer.get_page(1).unwrap().first_layer().area();
+        assert!(area
+            .print_str(&font_cache, Position::default(), Style::new(), "Hello")
+            .unwrap());
+        drop(area);
+
+        let doc = write_document(renderer);
+        let page_ids: Vec<_> = doc.get_pages().values().copied().collect();
+        assert_eq!(2, page_ids.len());
+        let content = doc.get_page_content(page_ids[1]).unwrap();
+        let operations = Content::decode(&content).unwrap().operations;
+        find_operation(&operations, "Tf");
+        let catalog_id = doc.trailer.get(b"Root").unwrap().as_reference().unwrap();
+        let outlines = doc.get_dictionary(catalog_id).unwrap().get(b"Outlines");
+        let outlines = doc.dereference(outlines.unwrap()).unwrap().1;
+        assert_eq!(
+            1,
+            outlines
+                .as_dict()
+                .unwrap()
+                .get(b"Count")
+                .unwrap()
+                .as_i64()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_crop_to_content() {
+        let mut renderer = Renderer::new(Size::new(400, 300), "test").unwrap();
+        let area = renderer.first_page().first_layer().area();
+        area.draw_line(
+            vec![Position::new(180, 140), Position::new(220, 160)],
+            LineStyle::new().with_thickness(2),
+        );
+        drop(area);
+
+        let page = renderer.first_page_mut();
+        let (origin, size) = page.content_bounds().unwrap();
+        assert_approx_eq!(Position, Position::new(179, 139), origin);
+        assert_approx_eq!(Size, Size::new(42, 22), size);
+        page.crop_to_content(Mm(5.0));
+
+        let doc = write_document(renderer);
+        let crop_box = page_box(&doc, b"CropBox");
+        let expected: Vec<_> = [174.0, 300.0 - 166.0, 226.0, 300.0 - 134.0]
+            .iter()
+            .map(|mm| printpdf::Pt::from(Mm(*mm)).0)
+            .collect();
+        assert_eq!(expected.len(), crop_box.len());
+        for (expected, actual) in expected.into_iter().zip(crop_box) {
+            assert!(
+                approx_eq!(f32, expected, actual, epsilon = 0.001),
+                "{} != {}",
+                expected,
+                actual
+            );
+        }
+    }
+
+    #[test]
+    fn test_crop_to_content_empty() {
+        let mut renderer = Renderer::new(Size::new(400, 300), "test").unwrap();
+        assert!(renderer.first_page().content_bounds().is_none());
+        renderer.first_page_mut().crop_to_content(Mm(5.0));
+        let doc = write_document(renderer);
+        assert_eq!(page_box(&doc, b"MediaBox"), page_box(&doc, b"CropBox"));
+    }
+
+    #[test]
+    fn test_content_bounds_text() {
+        let mut font_cache = FontCache::new(font_family());
+        let renderer = Renderer::new(Size::new(400, 300), "test").unwrap();
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+        let area = renderer.first_page().first_layer().area();
+        let style = Style::new();
+        area.print_str(&font_cache, Position::new(100, 50), style, "Hello")
+            .unwrap();
+        drop(area);
+
+        let (origin, size) = renderer.first_page().content_bounds().unwrap();
+        let expected_size = Size::new(
+            style.str_width(&font_cache, "Hello"),
+            style.line_height(&font_cache),
+        );
+        // The first glyph is moved to the left by its left side bearing.
+        let lsb = style.char_left_side_bearing(&font_cache, 'H');
+        assert_approx_eq!(Position, Position::new(Mm(100.0) - lsb, 50), origin);
+        assert_approx_eq!(Size, expected_size, size);
+    }
+
+    #[test]
+    fn test_fill_opacity() {
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        let layer = renderer.first_page().first_layer();
+        let rects = [
+            (Color::Rgb(255, 0, 0), 0.5, (10.0, 10.0, 60.0, 60.0)),
+            (Color::Rgb(0, 0, 255), 0.5, (40.0, 40.0, 90.0, 90.0)),
+            (Color::Rgb(0, 255, 0), 1.0, (0.0, 0.0, 10.0, 10.0)),
+            // Invalid values are clamped, and NaN is treated as opaque
+            (Color::Rgb(0, 255, 0), -0.2, (0.0, 0.0, 10.0, 10.0)),
+            (Color::Rgb(0, 255, 0), f32::NAN, (0.0, 0.0, 10.0, 10.0)),
+            (Color::Rgb(0, 255, 0), 1.5, (0.0, 0.0, 10.0, 10.0)),
+        ];
+        for (color, alpha, (llx, lly, urx, ury)) in rects.iter() {
+            layer.set_fill_color(Some(*color), *alpha);
+            let rect = printpdf::Rect::new(
+                printpdf::Mm(*llx),
+                printpdf::Mm(*lly),
+                printpdf::Mm(*urx),
+                printpdf::Mm(*ury),
+            );
+            layer.layer().add_rect(rect);
+        }
+        drop(layer);
+
+        let doc = write_document(renderer);
+        let page_id = *doc.get_pages().values().next().unwrap();
+        let content = doc.get_page_content(page_id).unwrap();
+        let operations = Content::decode(&content).unwrap().operations;
+
+        // The alpha is only changed if necessary.
+        let gs: Vec<_> = operations
+            .iter()
+            .filter(|op| op.operator == "gs")
+            .map(|op| op.operands[0].as_name_str().unwrap())
+            .collect();
+        assert_eq!(
+            vec![
+                "GsFillAlpha500",
+                "GsFillAlpha1000",
+                "GsFillAlpha0",
+                "GsFillAlpha1000"
+            ],
+            gs
+        );
+
+        let resources = first_page(&doc).get(b"Resources").unwrap();
+        let resources = doc.dereference(resources).unwrap().1;
+        let states = resources
+            .as_dict()
+            .unwrap()
+            .get(b"ExtGState")
+            .and_then(lopdf::Object::as_dict)
+            .unwrap();
+        let alpha = |name: &str| {
+            let state = states.get(name.as_bytes()).and_then(lopdf::Object::as_dict);
+            number(state.unwrap().get(b"ca").unwrap())
+        };
+        assert_eq!(0.5, alpha("GsFillAlpha500"));
+        assert_eq!(1.0, alpha("GsFillAlpha1000"));
+        assert_eq!(0.0, alpha("GsFillAlpha0"));
+    }
+
+    #[test]
+    fn test_layer_opacity() {
+        let mut renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        renderer.get_page_mut(0).unwrap().add_layer("Watermark");
+        let page = renderer.first_page();
+        page.first_layer().area().fill_rect(
+            Position::default(),
+            Size::new(10, 10),
+            Color::Rgb(255, 0, 0),
+        );
+        let layer = page.last_layer();
+        layer.set_opacity(0.2);
+        layer.set_opacity(0.2);
+        layer.area().fill_rect(
+            Position::default(),
+            Size::new(10, 10),
+            Color::Rgb(0, 0, 255),
+        );
+        // The opacity is clamped and rounded to the precision of the graphics state names
+        for alpha in [0.5001, 0.5004, 1.5, f32::NAN].iter() {
+            layer.set_opacity(*alpha);
+        }
+        drop(layer);
+
+        let doc = write_document(renderer);
+        let page_id = *doc.get_pages().values().next().unwrap();
+        let operations = Content::decode(&doc.get_page_content(page_id).unwrap())
+            .unwrap()
+            .operations;
+        let layers: Vec<_> = operations
+            .split(|op| op.operator == "BDC")
+            .skip(1)
+            .map(|ops| {
+                ops.iter()
+                    .filter(|op| op.operator == "gs")
+                    .map(|op| op.operands[0].as_name_str().unwrap())
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        assert_eq!(2, layers.len());
+        assert!(layers[0].is_empty());
+        assert_eq!(
+            vec![
+                "GsStrokeAlpha200",
+                "GsFillAlpha200",
+                "GsStrokeAlpha500",
+                "GsFillAlpha500",
+                "GsStrokeAlpha500",
+                "GsFillAlpha500",
+                "GsStrokeAlpha1000",
+                "GsFillAlpha1000"
+            ],
+            layers[1]
+        );
+
+        let resources = first_page(&doc).get(b"Resources").unwrap();
+        let resources = doc.dereference(resources).unwrap().1;
+        let states = resources.as_dict().unwrap().get(b"ExtGState").unwrap();
+        let state = states.as_dict().unwrap().get(b"GsStrokeAlpha500").unwrap();
+        let alpha = state.as_dict().unwrap().get(b"CA").unwrap();
+        assert_eq!(0.5, number(alpha));
+    }
+
+    #[test]
+    fn test_layer_by_name() {
+        let mut renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        let page = renderer.get_page_mut(0).unwrap();
+        page.add_layer("Background");
+        page.add_layer("Annotations");
+        let page = renderer.first_page();
+        assert_eq!(
+            page.get_layer(1).unwrap().layer().layer,
+            page.layer_by_name("Background").unwrap().layer().layer
+        );
+        assert_eq!(
+            page.get_layer(2).unwrap().layer().layer,
+            page.layer_by_name("Annotations").unwrap().layer().layer
+        );
+        assert_eq!(
+            page.first_layer().layer().layer,
+            page.layer_by_name("Layer 1").unwrap().layer().layer
+        );
+        let next = page.last_layer().next();
+        assert_eq!(
+            next.layer().layer,
+            page.layer_by_name("Layer 4").unwrap().layer().layer
+        );
+        assert!(page.layer_by_name("Missing").is_none());
+    }
+
+    #[test]
+    fn test_fill_opacity_text() {
+        let mut font_cache = FontCache::new(font_family());
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+        let area = renderer.first_page().first_layer().area();
+        let style = Style::new().with_fill_opacity(0.25);
+        assert_eq!(0.25, style.fill_opacity());
+        area.print_str(&font_cache, Position::default(), style, "Hello")
+            .unwrap();
+        drop(area);
+
+        let operations = render(renderer);
+        let gs = find_operation(&operations, "gs");
+        assert_eq!("GsFillAlpha250", gs.operands[0].as_name_str().unwrap());
+    }
+
+    #[test]
+    fn test_superscript_ordinals() {
+        let mut font_cache = FontCache::new(font_family());
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+        let style = Style::new().auto_superscript_ordinals(true);
+        let area = renderer.first_page().first_layer().area();
+        area.print_str(&font_cache, Position::default(), style, "1st")
+            .unwrap();
+        drop(area);
+
+        let operations: Vec<_> = render(renderer)
+            .into_iter()
+            .filter(|op| ["Tf", "Ts", "TJ"].contains(&op.operator.as_str()))
+            .collect();
+        let operators: Vec<_> = operations.iter().map(|op| op.operator.as_str()).collect();
+        assert_eq!(vec!["Tf", "TJ", "Tf", "Ts", "TJ", "Ts"], operators);
+        assert_eq!(12.0, number(&operations[0].operands[1]));
+        assert!((number(&operations[2].operands[1]) - 7.2).abs() < 0.001);
+        assert!(number(&operations[3].operands[0]) > 0.0);
+        assert_eq!(0.0, number(&operations[5].operands[0]));
+
+        let regular = Style::new();
+        let expected = regular.str_width(&font_cache, "1")
+            + regular
+                .with_font_size(12.0 * 0.6)
+                .str_width(&font_cache, "st");
+        assert_approx_eq!(Mm, expected, style.str_width(&font_cache, "1st"));
+        assert!(style.str_width(&font_cache, "1st") < regular.str_width(&font_cache, "1st"));
+        assert_eq!(
+            regular.str_width(&font_cache, "1 st"),
+            style.str_width(&font_cache, "1 st")
+        );
+    }
+
+    #[test]
+    fn test_underline() {
+        let mut font_cache = FontCache::new(font_family());
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+        let style = Style::new().underline();
+        let area = renderer.first_page().first_layer().area();
+        let mut section = area
+            .text_section(&font_cache, Position::default(), style.metrics(&font_cache))
+            .unwrap();
+        section.print_str("AV", style).unwrap();
+        section.print_str("AV", style).unwrap();
+        drop(section);
+        drop(area);
+
+        let operations = render(renderer);
+        let operators: Vec<_> = operations.iter().map(|op| op.operator.as_str()).collect();
+        let et = operators.iter().position(|op| *op == "ET").unwrap();
+        let m = operators.iter().position(|op| *op == "m").unwrap();
+        assert!(et < m);
+        assert_eq!(1, operators.iter().filter(|op| **op == "m").count());
+        assert_eq!(1, operators.iter().filter(|op| **op == "l").count());
+
+        // DejaVu Sans has an underline thickness of 90 units per 2048 units per em
+        let thickness = number(&find_operation(&operations, "w").operands[0]);
+        assert!((thickness - 90.0 / 2048.0 * 12.0).abs() < 0.001);
+
+        let start = number(&find_operation(&operations, "m").operands[0]);
+        let end = number(&find_operation(&operations, "l").operands[0]);
+        let width = printpdf::Pt::from(style.str_width(&font_cache, "AV") * 2.0).0;
+        let lsb = printpdf::Pt::from(style.char_left_side_bearing(&font_cache, 'A')).0;
+        assert!((start + f64::from(lsb)).abs() < 0.001);
+        assert!((end - f64::from(width - lsb)).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_strikethrough() {
+        let mut font_cache = FontCache::new(font_family());
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+        let style = Style::new().strikethrough();
+        let metrics = style.metrics(&font_cache);
+        let area = renderer.first_page().first_layer().area();
+        let mut section = area
+            .text_section(&font_cache, Position::default(), metrics)
+            .unwrap();
+        section.print_str("AV", style).unwrap();
+        drop(section);
+        drop(area);
+
+        // DejaVu Sans has a strikeout position of 530 units and thickness of 102 units per 2048
+        // units per em
+        let operations = render(renderer);
+        let thickness = number(&find_operation(&operations, "w").operands[0]);
+        assert!((thickness - 102.0 / 2048.0 * 12.0).abs() < 0.001);
+        let y = number(&find_operation(&operations, "m").operands[1]);
+        let baseline = printpdf::Pt::from(Mm(100.0) - metrics.ascent).0;
+        let expected = f64::from(baseline) + (530.0 - 51.0) / 2048.0 * 12.0;
+        assert!((y - expected).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_dash_pattern() {
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        let area = renderer.first_page().first_layer().area();
+        let line = vec![Position::new(10, 10), Position::new(90, 10)];
+        area.draw_line(line.clone(), LineStyle::dashed());
+        area.draw_line(line.clone(), LineStyle::dashed());
+        area.draw_line(line, LineStyle::new());
+        drop(area);
+
+        let operations = render(renderer);
+        let dashes: Vec<_> = operations
+            .iter()
+            .filter(|op| op.operator == "d")
+            .map(|op| match &op.operands[0] {
+                lopdf::Object::Array(array) => array.iter().map(number).collect::<Vec<_>>(),
+                _ => panic!("Dash array expected"),
+            })
+            .collect();
+        assert_eq!(2, dashes.len());
+        let expected = [printpdf::Pt::from(Mm(2.0)).0, printpdf::Pt::from(Mm(1.0)).0];
+        assert_eq!(2, dashes[0].len());
+        for (dash, expected) in dashes[0].iter().zip(&expected) {
+            assert!((dash - f64::from(*expected)).abs() < 0.001);
+        }
+        assert!(dashes[1].is_empty());
+
+        // Negative lengths and patterns with only zero lengths are drawn solid
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        let area = renderer.first_page().first_layer().area();
+        let line = vec![Position::new(10, 10), Position::new(90, 10)];
+        for pattern in [vec![2.0, -1.0], vec![0.0, 0.0]].iter() {
+            let line_style = LineStyle::new().with_dash_pattern(pattern.clone());
+            area.draw_line(line.clone(), line_style);
+        }
+        drop(area);
+        let operations = render(renderer);
+        assert!(operations.iter().all(|op| op.operator != "d"));
+    }
+
+    #[test]
+    fn test_text_stroke() {
+        let mut font_cache = FontCache::new(font_family());
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+        let style = Style::new();
+        let area = renderer.first_page().first_layer().area();
+        let mut section = area
+            .text_section(&font_cache, Position::default(), style.metrics(&font_cache))
+            .unwrap();
+        section
+            .print_str("A", style.with_stroke_only(Color::Rgb(255, 0, 0), 0.2))
+            .unwrap();
+        section.print_str("V", style).unwrap();
+        drop(section);
+        let mut section = area
+            .text_section(&font_cache, Position::default(), style.metrics(&font_cache))
+            .unwrap();
+        section
+            .print_str("AV", style.with_stroke(Color::Rgb(255, 0, 0), 0.2))
+            .unwrap();
+        drop(section);
+        drop(area);
+
+        let operations = render(renderer);
+        let operators: Vec<_> = operations
+            .iter()
+            .filter(|op| ["BT", "ET", "Tr", "TJ", "Tj"].contains(&op.operator.as_str()))
+            .map(|op| match op.operator.as_str() {
+                "Tr" => format!("Tr {}", number(&op.operands[0])),
+                "Tj" => "TJ".to_owned(),
+                operator => operator.to_owned(),
+            })
+            .collect();
+        assert_eq!(
+            vec!["BT", "Tr 1", "TJ", "Tr 0", "TJ", "ET", "BT", "Tr 2", "TJ", "Tr 0", "ET"],
+            operators
+        );
+
+        let width = number(&find_operation(&operations, "w").operands[0]);
+        assert!((width - f64::from(printpdf::Pt::from(Mm(0.2)).0)).abs() < 0.001);
+        assert_eq!(
+            12.0,
+            style.with_stroke_only(Color::Rgb(0, 0, 0), 1).font_size()
+        );
+        assert_eq!(
+            style.str_width(&font_cache, "AV"),
+            style
+                .with_stroke(Color::Rgb(0, 0, 0), 1)
+                .str_width(&font_cache, "AV")
+        );
+    }
+
+    #[test]
+    fn test_print_str_with_fallback() {
+        let latin = FontData::new(LATIN_FONT.to_vec(), None).unwrap();
+        let fallback = FontData::new(FONT.to_vec(), None).unwrap();
+        let chain = FontFallbackChain::new(latin.clone()).with_fallback(fallback.clone());
+        let mut font_cache = FontCache::new(FontFamily {
+            regular: latin.clone(),
+            bold: latin.clone(),
+            italic: latin.clone(),
+            bold_italic: latin,
+        });
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+        let style = Style::new();
+        let area = renderer.first_page().first_layer().area();
+        let mut section = area
+            .text_section(&font_cache, Position::default(), style.metrics(&font_cache))
+            .unwrap();
+        assert!(section
+            .print_str_with_fallback("Hello Мир", &chain, style)
+            .is_err());
+        drop(section);
+        drop(area);
+
+        font_cache.add_font(fallback.clone());
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+        let area = renderer.first_page().first_layer().area();
+        let mut section = area
+            .text_section(&font_cache, Position::default(), style.metrics(&font_cache))
+            .unwrap();
+        section
+            .print_str_with_fallback("Hello Мир", &chain, style)
+            .unwrap();
+        drop(section);
+        drop(area);
+
+        let operations = render(renderer);
+        let fonts: Vec<_> = operations
+            .iter()
+            .filter(|op| op.operator == "Tf")
+            .map(|op| op.operands[0].as_name().unwrap().to_vec())
+            .collect();
+        assert_eq!(2, fonts.len());
+        assert_ne!(fonts[0], fonts[1]);
+        assert_eq!(
+            2,
+            operations.iter().filter(|op| op.operator == "TJ").count()
+        );
+    }
+
+    #[test]
+    fn test_synthetic_bold() {
+        let mut font_cache = FontCache::new(font_family());
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+        let style = Style::new().allow_synthetic_bold(true);
+        let area = renderer.first_page().first_layer().area();
+        let mut section = area
+            .text_section(&font_cache, Position::default(), style.metrics(&font_cache))
+            .unwrap();
+        section.print_str("A", style).unwrap();
+        section.print_str("V", Style::new().bold()).unwrap();
+        section.print_str("A", style.bold()).unwrap();
+        drop(section);
+        drop(area);
+
+        let operations = render(renderer);
+        let modes: Vec<_> = operations
+            .iter()
+            .filter(|op| op.operator == "Tr")
+            .map(|op| number(&op.operands[0]))
+            .collect();
+        assert_eq!(vec![2.0, 0.0], modes);
+        let width = number(&find_operation(&operations, "w").operands[0]);
+        assert!((width - 12.0 * 0.03).abs() < 0.001);
+        assert_eq!(
+            style.str_width(&font_cache, "A"),
+            style.bold().str_width(&font_cache, "A")
+        );
+
+        let regular = FontData::new(FONT.to_vec(), None).unwrap();
+        let bold = FontData::new(LATIN_FONT.to_vec(), None).unwrap();
+        let font_cache = FontCache::new(FontFamily {
+            regular: regular.clone(),
+            bold: bold.clone(),
+            italic: regular,
+            bold_italic: bold,
+        });
+        assert!(!style.bold().is_synthetic_bold(&font_cache));
+        assert!(!style.bold().italic().is_synthetic_bold(&font_cache));
+    }
+
+    #[test]
+    fn test_synthetic_italic() {
+        let mut font_cache = FontCache::new(font_family());
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+        let style = Style::new().allow_synthetic_italic(true);
+        let area = renderer.first_page().first_layer().area();
+        let mut section = area
+            .text_section(&font_cache, Position::default(), style.metrics(&font_cache))
+            .unwrap();
+        section.print_str("A", style).unwrap();
+        section.print_str("V", Style::new().italic()).unwrap();
+        section.print_str("A", style.italic()).unwrap();
+        section.print_str("V", style).unwrap();
+        drop(section);
+        drop(area);
+
+        let operations = render(renderer);
+        let shears: Vec<_> = operations
+            .iter()
+            .filter(|op| op.operator == "Tm")
+            .map(|op| number(&op.operands[2]))
+            .collect();
+        assert_eq!(2, shears.len());
+        assert!((shears[0] - 0.2126).abs() < 0.001);
+        assert_eq!(0.0, shears[1]);
+
+        let regular = FontData::new(FONT.to_vec(), None).unwrap();
+        let italic = FontData::new(LATIN_FONT.to_vec(), None).unwrap();
+        let font_cache = FontCache::new(FontFamily {
+            regular: regular.clone(),
+            bold: regular,
+            italic: italic.clone(),
+            bold_italic: italic,
+        });
+        assert!(!style.italic().is_synthetic_italic(&font_cache));
+        assert!(!style.italic().bold().is_synthetic_italic(&font_cache));
+    }
+
+    #[test]
+    fn test_rotated_text() {
+        let mut font_cache = FontCache::new(font_family());
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+        let style = Style::new();
+        let area = renderer.first_page().first_layer().area();
+        let mut section = area
+            .text_section(&font_cache, Position::default(), style.metrics(&font_cache))
+            .unwrap();
+        section.rotate(90.0);
+        section.print_str("Axis", style).unwrap();
+        section.rotate(0.0);
+        section.print_str("X", style).unwrap();
+        drop(section);
+        drop(area);
+
+        let operations = render(renderer);
+        assert!(!operations.iter().any(|op| op.operator == "Td"));
+        let matrices: Vec<Vec<_>> = operations
+            .iter()
+            .filter(|op| op.operator == "Tm")
+            .map(|op| op.operands.iter().map(number).collect())
+            .collect();
+        assert_eq!(2, matrices.len());
+        let pt = |mm: Mm| f64::from(printpdf::Pt::from(mm).0);
+        let lsb = style.char_left_side_bearing(&font_cache, 'A');
+        let baseline = Mm(100.0) - style.metrics(&font_cache).ascent;
+        let width = style.str_width(&font_cache, "Axis");
+        // The left side bearing is compensated along the rotated baseline
+        let expected = [
+            vec![0.0, 1.0, -1.0, 0.0, 0.0, pt(baseline - lsb)],
+            vec![1.0, 0.0, 0.0, 1.0, 0.0, pt(baseline - lsb + width)],
+        ];
+        for (matrix, expected) in matrices.iter().zip(expected.iter()) {
+            for (value, expected) in matrix.iter().zip(expected) {
+                assert!((value - expected).abs() < 0.01, "{:?}", matrices);
+            }
+        }
+    }
+
+    #[test]
+    fn test_print_str_aligned() {
+        let mut font_cache = FontCache::new(font_family());
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+        let style = Style::new();
+        let width = style.str_width(&font_cache, "Title");
+        let lsb = style.char_left_side_bearing(&font_cache, 'T');
+
+        let cursor_x = |s: &str, alignment: Alignment| {
+            let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+            let mut area = renderer.first_page().first_layer().area();
+            area.add_offset(Position::new(10, 0));
+            assert!(area
+                .print_str_aligned(&font_cache, Position::new(20, 0), style, s, alignment)
+                .unwrap());
+            drop(area);
+            let operations = render(renderer);
+            number(&find_operation(&operations, "Td").operands[0])
+        };
+        let pt = |mm: Mm| f64::from(printpdf::Pt::from(mm).0);
+        let expected = [
+            (Alignment::Left, Mm(30.0)),
+            (Alignment::Center, Mm(30.0) + (Mm(70.0) - width) / 2.0),
+            (Alignment::Right, Mm(100.0) - width),
+        ];
+        for (alignment, x) in expected.iter() {
+            assert!((cursor_x("Title", *alignment) - pt(*x - lsb)).abs() < 0.01);
+        }
+
+        // Strings that are wider than the available space start at the given position
+        let long = "Title ".repeat(20);
+        assert!(style.str_width(&font_cache, &long) > Mm(70.0));
+        for alignment in [Alignment::Left, Alignment::Center, Alignment::Right].iter() {
+            assert!((cursor_x(&long, *alignment) - pt(Mm(30.0) - lsb)).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_print_wrapped() {
+        let mut font_cache = FontCache::new(font_family());
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+        let style = Style::new();
+        let line_height = style.metrics(&font_cache).line_height;
+        let text = "The quick brown fox jumps over the lazy dog.\nPack my box with five dozen \
+                    liquor jugs.";
+
+        let mut area = renderer.first_page().first_layer().area();
+        area.set_size(Size::new(40, 100));
+        let (height, remainder) = area
+            .print_wrapped(&font_cache, Position::default(), style, text)
+            .unwrap();
+        drop(area);
+        let operations = render(renderer);
+        let lines: Vec<_> = operations.iter().filter(|op| op.operator == "TJ").collect();
+        let expected = wrap::break_lines(&font_cache, style, text, Mm(40.0), &NoHyphenation);
+        assert_eq!(
+            vec![
+                "The quick brown",
+                "fox jumps over the",
+                "lazy dog.",
+                "Pack my box with",
+                "five dozen liquor",
+                "jugs."
+            ],
+            expected
+                .iter()
+                .map(|line| &text[line.range.clone()])
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(expected.len(), lines.len());
+        assert!(approx_eq!(
+            f32,
+            (line_height * 6.0).0,
+            height.0,
+            epsilon = 0.001
+        ));
+        assert_eq!("", remainder);
+
+        // Long words are broken and the remainder is returned if the area is too small.
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        let mut area = renderer.first_page().first_layer().area();
+        area.set_size(Size::new(15, line_height * 2.5));
+        let (height, remainder) = area
+            .print_wrapped(
+                &font_cache,
+                Position::default(),
+                style,
+                "Incomprehensibilities",
+            )
+            .unwrap();
+        assert!(approx_eq!(
+            f32,
+            (line_height * 2.0).0,
+            height.0,
+            epsilon = 0.001
+        ));
+        assert!(!remainder.is_empty());
+        assert!("Incomprehensibilities".ends_with(remainder));
+        assert!(remainder.len() < "Incomprehensibilities".len() - 2);
+    }
+
+    #[test]
+    fn test_current_position() {
+        let mut font_cache = FontCache::new(font_family());
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+        let style = Style::new();
+        let metrics = style.metrics(&font_cache);
+        let mut area = renderer.first_page().first_layer().area();
+        area.add_margins(10);
+        let mut section = area
+            .text_section(&font_cache, Position::new(5, 10), metrics)
+            .unwrap();
+        assert_eq!(Position::new(5, 10), section.current_position());
+
+        section.print_str("Hello", style).unwrap();
+        let width = style.str_width(&font_cache, "Hello");
+        let position = section.current_position();
+        assert!(approx_eq!(Mm, Mm(5.0) + width, position.x));
+        assert_eq!(Mm(10.0), position.y);
+
+        section.print_str(" World", style).unwrap();
+        let width = style.str_width(&font_cache, "Hello World");
+        assert!(approx_eq!(
+            Mm,
+            Mm(5.0) + width,
+            section.current_position().x
+        ));
+
+        assert!(section.add_newline());
+        let position = section.current_position();
+        assert_eq!(Mm(5.0), position.x);
+        assert!(approx_eq!(Mm, Mm(10.0) + metrics.line_height, position.y));
+    }
+
+    #[test]
+    fn test_remaining_height() {
+        let mut font_cache = FontCache::new(font_family());
+        let renderer = Renderer::new(Size::new(100, 30), "test").unwrap();
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+        let style = Style::new();
+        let metrics = style.metrics(&font_cache);
+        let area = renderer.first_page().first_layer().area();
+        let mut section = area
+            .text_section(&font_cache, Position::default(), metrics)
+            .unwrap();
+        assert_eq!(Mm(30.0), section.remaining_height());
+        section.print_str("Hello", style).unwrap();
+
+        let mut lines = 0;
+        while section.can_fit_line() {
+            let remaining = section.remaining_height();
+            assert!(section.add_newline());
+            assert!(approx_eq!(
+                Mm,
+                remaining - metrics.line_height,
+                section.remaining_height()
+            ));
+            lines += 1;
+        }
+        assert_eq!((30.0 / metrics.line_height.0) as usize, lines);
+        assert!(section.remaining_height() < metrics.line_height);
+        assert!(!section.add_newline());
+    }
+
+    #[test]
+    fn test_tab_stops() {
+        let mut font_cache = FontCache::new(font_family());
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+        let style = Style::new();
+        let pt = |mm: Mm| f64::from(printpdf::Pt::from(mm).0);
+
+        // Returns the horizontal position of the cursor after the last Td operation.
+        let cursor_x = |s: &str, stops: Option<Vec<Mm>>| {
+            let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+            let area = renderer.first_page().first_layer().area();
+            let mut section = area
+                .text_section(&font_cache, Position::default(), style.metrics(&font_cache))
+                .unwrap();
+            if let Some(stops) = stops {
+                section.set_tab_stops(stops);
+            }
+            section.print_str(s, style).unwrap();
+            drop(section);
+            drop(area);
+            render(renderer)
+                .iter()
+                .filter(|op| op.operator == "Td")
+                .map(|op| number(&op.operands[0]))
+                .sum::<f64>()
+        };
+
+        let stops = vec![Mm(40.0), Mm(25.0)];
+        let lsb = |c| style.char_left_side_bearing(&font_cache, c);
+        for s in &["a\tb", "abcdefgh\tb", "\tb"] {
+            let first = s.chars().next().filter(|c| *c != '\t').unwrap_or('b');
+            let expected = pt(Mm(25.0) - lsb(first));
+            assert!((cursor_x(s, Some(stops.clone())) - expected).abs() < 0.01);
+        }
+        let expected = pt(Mm(40.0) - lsb('a'));
+        assert!((cursor_x("a\t\tb", Some(stops)) - expected).abs() < 0.01);
+        let expected = pt(super::DEFAULT_TAB_INTERVAL * 2.0 - lsb('a'));
+        assert!((cursor_x("a\t\tb", None) - expected).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_print_wrapped_hyphenated() {
+        struct Stub;
+
+        impl Hyphenator for Stub {
+            fn hyphenation_points(&self, word: &str) -> Vec<usize> {
+                if word == "Donaudampfschifffahrt" {
+                    vec![5, 10, 16]
+                } else {
+                    Vec::new()
+                }
+            }
+        }
+
+        let mut font_cache = FontCache::new(font_family());
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+        let style = Style::new();
+        let text = "Die Donaudampfschifffahrt";
+        let lines: Vec<_> = wrap::break_lines(&font_cache, style, text, Mm(45.0), &Stub)
+            .into_iter()
+            .map(|line| (&text[line.range], line.is_hyphenated))
+            .collect();
+        assert_eq!(
+            vec![("Die Donaudampf", true), ("schifffahrt", false)],
+            lines
+        );
+        let lines = wrap::break_lines(&font_cache, style, text, Mm(45.0), &NoHyphenation);
+        assert!(lines.iter().all(|line| !line.is_hyphenated));
+
+        let mut area = renderer.first_page().first_layer().area();
+        area.set_size(Size::new(45, 100));
+        let (_, remainder) = area
+            .print_wrapped_with_hyphenator(&font_cache, Position::default(), style, text, &Stub)
+            .unwrap();
+        assert_eq!("", remainder);
+        drop(area);
+
+        let operations = render(renderer);
+        let hyphen = style.font(&font_cache).glyph_ids(&font_cache, "-".chars())[0]
+            .to_be_bytes()
+            .to_vec();
+        let last_glyphs: Vec<_> = operations
+            .iter()
+            .filter(|op| op.operator == "TJ")
+            .map(|op| {
+                let glyphs = op.operands[0].as_array().unwrap();
+                let bytes = glyphs.iter().rev().find_map(|o| o.as_str().ok()).unwrap();
+                bytes[bytes.len() - 2..].to_vec()
+            })
+            .collect();
+        assert_eq!(2, last_glyphs.len());
+        assert_eq!(hyphen, last_glyphs[0]);
+        assert_ne!(hyphen, last_glyphs[1]);
+    }
+
+    #[test]
+    fn test_font_subsetting() {
+        let render_text = |subsetting: bool| {
+            let mut font_cache = FontCache::new(font_family());
+            let mut renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+            font_cache.load_pdf_fonts(&renderer).unwrap();
+            let area = renderer.first_page().first_layer().area();
+            let style = Style::new();
+            let mut section = area
+                .text_section(&font_cache, Position::default(), style.metrics(&font_cache))
+                .unwrap();
+            section.print_str("Hello", style).unwrap();
+            drop(section);
+            drop(area);
+            if subsetting {
+                font_cache.subset_pdf_fonts(&mut renderer).unwrap();
+            }
+            let mut buf = Vec::new();
+            renderer.write(&mut buf).unwrap();
+            buf
+        };
+        let full = render_text(false);
+        let subset = render_text(true);
+        assert!(subset.len() < full.len() / 2);
+
+        let doc = lopdf::Document::load_mem(&subset).unwrap();
+        let descriptor = doc
+            .objects
+            .values()
+            .filter_map(|object| object.as_dict().ok())
+            .find(|dict| dict.has(b"FontFile2"))
+            .unwrap();
+        let file_id = descriptor
+            .get(b"FontFile2")
+            .unwrap()
+            .as_reference()
+            .unwrap();
+        let font_file = doc.get_object(file_id).unwrap().as_stream().unwrap();
+        assert!(font_file.content.len() < FONT.len() / 2);
+
+        let cid_font = doc
+            .objects
+            .values()
+            .filter_map(|object| object.as_dict().ok())
+            .find(|dict| dict.has(b"DescendantFonts"))
+            .unwrap()
+            .get(b"DescendantFonts")
+            .unwrap()
+            .as_array()
+            .unwrap()[0]
+            .as_dict()
+            .unwrap();
+        let map_id = cid_font
+            .get(b"CIDToGIDMap")
+            .unwrap()
+            .as_reference()
+            .unwrap();
+        let map = &doc.get_object(map_id).unwrap().as_stream().unwrap().content;
+        let font = rusttype::Font::from_bytes(FONT).unwrap();
+        let cid = usize::from(font.glyph('H').id().0 as u16);
+        let gid = u16::from_be_bytes([map[2 * cid], map[2 * cid + 1]]);
+        assert!(gid > 0 && gid <= 4);
+    }
+
+    #[test]
+    fn test_area_bounds() {
+        let renderer = Renderer::new(Size::new(100, 90), "test").unwrap();
+        let mut area = renderer.first_page().first_layer().area();
+        assert_eq!(Position::default(), area.origin());
+        area.add_margins(Margins::trbl(10, 5, 15, 20));
+        assert_eq!(Position::new(20, 10), area.origin());
+        area.add_offset(Position::new(5, 5));
+        assert_eq!((Position::new(25, 15), Size::new(70, 60)), area.bounds());
+        assert_eq!(area.size(), area.bounds().1);
+    }
+
+    #[test]
+    fn test_area_geometry() {
+        let renderer = Renderer::new(Size::new(100, 90), "test").unwrap();
+        let page = renderer.first_page().first_layer().area();
+        let area = |x, y, width, height| {
+            let mut area = page.clone();
+            area.add_offset(Position::new(x, y));
+            area.set_size(Size::new(width, height));
+            area
+        };
+
+        let a = area(10, 10, 30, 20);
+        assert!(a.contains(Position::new(10, 10)));
+        assert!(a.contains(Position::new(25, 20)));
+        assert!(a.contains(Position::new(40, 30)));
+        assert!(!a.contains(Position::new(41, 20)));
+        assert!(!a.contains(Position::new(25, 5)));
+
+        // Overlapping
+        let b = area(30, 20, 30, 30);
+        let intersection = a.intersection(&b).unwrap();
+        assert_eq!(
+            (Position::new(30, 20), Size::new(10, 10)),
+            intersection.bounds()
+        );
+        assert_eq!(intersection.bounds(), b.intersection(&a).unwrap().bounds());
+        let union = a.bounding_union(&b);
+        assert_eq!((Position::new(10, 10), Size::new(50, 40)), union.bounds());
+
+        // Disjoint
+        let c = area(50, 60, 10, 10);
+        assert!(a.intersection(&c).is_none());
+        // Touching edges do not overlap
+        assert!(a.intersection(&area(40, 10, 10, 10)).is_none());
+        let union = a.bounding_union(&c);
+        assert_eq!((Position::new(10, 10), Size::new(50, 60)), union.bounds());
+
+        // Nested
+        let d = area(15, 15, 5, 5);
+        assert_eq!(d.bounds(), a.intersection(&d).unwrap().bounds());
+        assert_eq!(a.bounds(), a.bounding_union(&d).bounds());
+    }
+
+    #[test]
+    fn test_split_vertically() {
+        let renderer = Renderer::new(Size::new(100, 90), "test").unwrap();
+        let area = renderer.first_page().first_layer().area();
+        assert!(area.split_vertically(&[]).is_empty());
+        let areas = area.split_vertically(&[0]);
+        assert_eq!(1, areas.len());
+        assert_eq!(area.size(), areas[0].size());
+
+        let areas = area.split_vertically(&[1, 2, 4]);
+        assert_eq!(3, areas.len());
+        let mut offset = Mm(0.0);
+        for (area, weight) in areas.iter().zip(&[1.0, 2.0, 4.0]) {
+            assert_eq!(Mm(100.0), area.size().width);
+            assert!(approx_eq!(Mm, Mm(90.0 * weight / 7.0), area.size().height));
+            assert!(approx_eq!(Mm, offset, area.origin.y));
+            offset += area.size().height;
+        }
+        assert!(approx_eq!(Mm, Mm(90.0), offset));
+    }
+
+    #[test]
+    fn test_split_horizontally_abs() {
+        let renderer = Renderer::new(Size::new(100, 90), "test").unwrap();
+        let area = renderer.first_page().first_layer().area();
+        let widths = |areas: Vec<super::Area<'_>>| -> Vec<_> {
+            areas.iter().map(|area| area.size().width).collect()
+        };
+
+        let areas = area.split_horizontally_abs(&[Mm(40.0), Mm(0.0)]);
+        assert_eq!(Mm(40.0), areas[1].origin.x);
+        assert_eq!(vec![Mm(40.0), Mm(60.0)], widths(areas));
+        assert_eq!(
+            vec![Mm(25.0), Mm(30.0), Mm(20.0), Mm(25.0)],
+            widths(area.split_horizontally_abs(&[Mm(0.0), Mm(30.0), Mm(20.0), Mm(0.0)]))
+        );
+        assert_eq!(
+            vec![Mm(80.0), Mm(20.0), Mm(0.0), Mm(0.0)],
+            widths(area.split_horizontally_abs(&[Mm(80.0), Mm(30.0), Mm(0.0), Mm(10.0)]))
+        );
+        assert!(area.split_horizontally_abs(&[]).is_empty());
+    }
+
+    #[cfg(feature = "svg")]
+    #[test]
+    fn test_add_svg() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="10">
+            <rect x="0" y="0" width="20" height="10" fill="red"/>
+        </svg>"#;
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        let area = renderer.first_page().first_layer().area();
+        area.add_svg(svg, Position::new(10, 20), Size::new(40, 20))
+            .unwrap();
+        assert!(area
+            .add_svg("not an svg", Position::default(), Size::new(10, 10))
+            .is_err());
+        drop(area);
+        let (origin, size) = renderer.first_page().content_bounds().unwrap();
+        assert_eq!(Position::new(10, 20), origin);
+        assert_eq!(Size::new(40, 20), size);
+
+        let operations = render(renderer);
+        find_operation(&operations, "Do");
+        let matrix: Vec<_> = operations
+            .iter()
+            .filter(|op| op.operator == "cm")
+            .flat_map(|op| op.operands.iter().map(number))
+            .collect();
+        let pt = |mm: f32| f64::from(printpdf::Pt::from(Mm(mm)).0);
+        for expected in [pt(40.0), pt(20.0), pt(10.0), pt(60.0)].iter() {
+            assert!(matrix.iter().any(|value| (value - expected).abs() < 0.01));
+        }
+    }
+
+    #[test]
+    fn test_metadata() {
+        let renderer = Renderer::new(Size::new(100, 100), "test")
+            .unwrap()
+            .with_author("Jane Doe")
+            .with_subject("Quarterly report")
+            .with_keywords(vec!["finance", "report"])
+            .with_producer("Archiver 2.0");
+        let doc = write_document(renderer);
+        let info_id = doc.trailer.get(b"Info").unwrap().as_reference().unwrap();
+        let info = doc.get_dictionary(info_id).unwrap();
+        let value = |key: &[u8]| {
+            String::from_utf8(info.get(key).unwrap().as_str().unwrap().to_vec()).unwrap()
+        };
+        assert_eq!("Jane Doe", value(b"Author"));
+        assert_eq!("Quarterly report", value(b"Subject"));
+        assert!(value(b"Keywords").contains("finance"));
+        assert!(value(b"Keywords").contains("report"));
+        assert_eq!("Archiver 2.0", value(b"Producer"));
+    }
+
+    #[test]
+    fn test_bookmarks() {
+        let mut renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        renderer.add_page(Size::new(100, 200));
+        let intro = renderer.add_bookmark("Introduction", 0).unwrap();
+        let results = renderer.add_bookmark("Results", 1).unwrap();
+        renderer
+            .add_child_bookmark(results, "Überblick", 1)
+            .unwrap();
+        assert_eq!(0, intro);
+        assert!(renderer.add_bookmark("Missing", 2).is_err());
+        assert!(renderer.add_child_bookmark(5, "Missing", 0).is_err());
+
+        let doc = write_document(renderer);
+        let catalog_id = doc.trailer.get(b"Root").unwrap().as_reference().unwrap();
+        let catalog = doc.get_dictionary(catalog_id).unwrap();
+        assert_eq!(
+            b"UseOutlines",
+            catalog.get(b"PageMode").unwrap().as_name().unwrap()
+        );
+        let outlines_id = catalog.get(b"Outlines").unwrap().as_reference().unwrap();
+        let outlines = doc.get_dictionary(outlines_id).unwrap();
+        assert_eq!(3, outlines.get(b"Count").unwrap().as_i64().unwrap());
+
+        let entry = |dict: &lopdf::Dictionary, key: &[u8]| {
+            let id = dict.get(key).unwrap().as_reference().unwrap();
+            doc.get_dictionary(id).unwrap()
+        };
+        let title =
+            |dict: &lopdf::Dictionary| dict.get(b"Title").unwrap().as_str().unwrap().to_vec();
+        let first = entry(outlines, b"First");
+        let last = entry(outlines, b"Last");
+        assert_eq!(b"Introduction".to_vec(), title(first));
+        assert_eq!(b"Results".to_vec(), title(last));
+        assert_eq!(title(last), title(entry(first, b"Next")));
+        assert_eq!(1, last.get(b"Count").unwrap().as_i64().unwrap());
+        let child = entry(last, b"First");
+        assert_eq!(title(last), title(entry(child, b"Parent")));
+        assert_eq!(&[0xfe, 0xff, 0x00, 0xdc], &title(child)[..4]);
+
+        let page_ids: Vec<_> = doc.get_pages().values().copied().collect();
+        let dest = child.get(b"Dest").unwrap().as_array().unwrap();
+        assert_eq!(page_ids[1], dest[0].as_reference().unwrap());
+        assert!((number(&dest[3]) - f64::from(printpdf::Pt::from(Mm(200.0)).0)).abs() < 0.01);
+    }
+
+    fn page_widths(doc: &lopdf::Document) -> Vec<i64> {
+        doc.get_pages()
+            .values()
+            .map(|page_id| {
+                let media_box = doc.get_dictionary(*page_id).unwrap().get(b"MediaBox");
+                let width = number(&media_box.unwrap().as_array().unwrap()[2]);
+                Mm::from(printpdf::Pt(width as f32)).0.round() as i64
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_for_each_page_area() {
+        let mut renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        renderer.add_page(Size::new(110, 100));
+        renderer.add_page(Size::new(120, 100));
+        let mut areas = Vec::new();
+        renderer.for_each_page_area(Margins::trbl(10, 5, 15, 20), |idx, area| {
+            areas.push((idx, area.origin(), area.size()));
+        });
+        assert_eq!(3, renderer.page_count());
+        assert_eq!(
+            vec![
+                (0, Position::new(20, 10), Size::new(75, 75)),
+                (1, Position::new(20, 10), Size::new(85, 75)),
+                (2, Position::new(20, 10), Size::new(95, 75)),
+            ],
+            areas
+        );
+    }
+
+    #[test]
+    fn test_stamp_page_numbers() {
+        let mut font_cache = FontCache::new(builtin_font_family());
+        let mut renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        renderer.add_page(Size::new(100, 100));
+        renderer.add_page(Size::new(100, 100));
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+        let style = Style::new();
+        renderer
+            .stamp_page_numbers(
+                Position::new(10, 10),
+                style,
+                "{page} of {total}",
+                &font_cache,
+            )
+            .unwrap();
+        assert!(renderer
+            .stamp_page_numbers(Position::new(10, 99), style, "{page}", &font_cache)
+            .is_err());
+
+        let doc = write_document(renderer);
+        let texts = page_texts(&doc);
+        assert_eq!(vec!["1 of 3", "2 of 3", "3 of 3"], texts);
+        for text in &texts {
+            assert_approx_eq!(
+                Mm,
+                style.str_width(&font_cache, "1 of 3"),
+                style.str_width(&font_cache, text)
+            );
+        }
+
+        // The page numbers are only printed if they fit on every page
+        let mut renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        renderer.add_page(Size::new(100, 100));
+        renderer.add_page(Size::new(100, 50));
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+        let err = renderer
+            .stamp_page_numbers(Position::new(10, 48), style, "{page}", &font_cache)
+            .unwrap_err();
+        assert_eq!("The page number does not fit on page 3", err.to_string());
+        assert_eq!(vec!["", "", ""], page_texts(&write_document(renderer)));
+    }
+
+    fn page_texts(doc: &lopdf::Document) -> Vec<String> {
+        doc.get_pages()
+            .values()
+            .map(|&page_id| {
+                let content = doc.get_page_content(page_id).unwrap();
+                let mut bytes = Vec::new();
+                for op in Content::decode(&content).unwrap().operations {
+                    let strings = match op.operator.as_str() {
+                        "Tj" => op.operands,
+                        "TJ" => op.operands[0].as_array().unwrap().clone(),
+                        _ => continue,
+                    };
+                    for string in strings {
+                        if let lopdf::Object::String(s, _) = string {
+                            bytes.extend(s);
+                        }
+                    }
+                }
+                String::from_utf8(bytes).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_add_watermark() {
+        let mut font_cache = FontCache::new(builtin_font_family());
+        let mut renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        renderer.add_page(Size::new(100, 100));
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+        let style = Style::new();
+        for idx in 0..2 {
+            let area = renderer.get_page(idx).unwrap().first_layer().area();
+            area.print_str(&font_cache, Position::default(), style, "Body")
+                .unwrap();
+        }
+        renderer
+            .add_watermark("DRAFT", style, &font_cache, 0.3, 45.0)
+            .unwrap();
+        let metrics = style.metrics(&font_cache);
+        let center = (
+            style.char_left_side_bearing(&font_cache, 'D')
+                + style.str_width(&font_cache, "DRAFT") / 2.0,
+            metrics.ascent - metrics.line_height / 2.0,
+        );
+        let pt = |mm: Mm| f64::from(printpdf::Pt::from(mm).0);
+        for idx in 0..2 {
+            let page = renderer.get_page(idx).unwrap();
+            assert_eq!(2, page.layer_count());
+            assert!(page.layer_by_name("Watermark").is_some());
+        }
+
+        let doc = write_document(renderer);
+        for page_id in doc.get_pages().values().copied() {
+            let content = doc.get_page_content(page_id).unwrap();
+            let operations = Content::decode(&content).unwrap().operations;
+            assert!(!operations.iter().any(|op| op.operator == "MP"));
+            let matrix: Vec<_> = find_operation(&operations, "Tm")
+                .operands
+                .iter()
+                .map(number)
+                .collect();
+            let (sin, cos) = 45f64.to_radians().sin_cos();
+            assert!((matrix[0] - cos).abs() < 0.001);
+            assert!((matrix[1] - sin).abs() < 0.001);
+            // The center of the text in text space is mapped to the center of the page
+            let (u, v) = (pt(center.0), pt(center.1));
+            let x = matrix[0] * u + matrix[2] * v + matrix[4];
+            let y = matrix[1] * u + matrix[3] * v + matrix[5];
+            assert!((x - pt(Mm(50.0))).abs() < 0.01);
+            assert!((y - pt(Mm(50.0))).abs() < 0.01);
+            let texts: Vec<_> = operations
+                .iter()
+                .filter(|op| ["Tj", "TJ"].contains(&op.operator.as_str()))
+                .map(|op| {
+                    let strings = match op.operator.as_str() {
+                        "Tj" => op.operands.clone(),
+                        _ => op.operands[0].as_array().unwrap().clone(),
+                    };
+                    let mut bytes = Vec::new();
+                    for string in strings {
+                        if let lopdf::Object::String(s, _) = string {
+                            bytes.extend(s);
+                        }
+                    }
+                    String::from_utf8(bytes).unwrap()
+                })
+                .collect();
+            assert_eq!(vec!["DRAFT", "Body"], texts);
+        }
+
+        // Watermarks that do not fit on a page are not added to any page
+        let mut renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        renderer.add_page(Size::new(100, 20));
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+        let err = renderer
+            .add_watermark(&"DRAFT".repeat(3), style, &font_cache, 0.3, 90.0)
+            .unwrap_err();
+        assert_eq!("The watermark does not fit on page 2", err.to_string());
+        for idx in 0..2 {
+            assert_eq!(1, renderer.get_page(idx).unwrap().layer_count());
+        }
+    }
+
+    #[test]
+    fn test_move_background_layers() {
+        use printpdf::lopdf::content::{Content, Operation};
+        use printpdf::lopdf::{Dictionary, Object, Stream};
+
+        let layer = |name: &str, is_background: bool| {
+            let mut operations = vec![Operation::new(
+                "BDC",
+                vec![Object::Name(b"OC".to_vec()), Object::Name(name.into())],
+            )];
+            if is_background {
+                operations.push(Operation::new(
+                    "MP",
+                    vec![Object::Name(super::BACKGROUND_LAYER_MARKER.to_vec())],
+                ));
+            }
+            for _ in 0..20 {
+                operations.push(Operation::new(
+                    "re",
+                    vec![0.into(), 0.into(), 10.into(), 10.into()],
+                ));
+            }
+            operations.push(Operation::new("EMC", vec![]));
+            operations
+        };
+        let operators = |operations: &[Operation]| -> Vec<String> {
+            operations.iter().map(|op| op.operator.clone()).collect()
+        };
+        let operations: Vec<_> = layer("L0", false)
+            .into_iter()
+            .chain(layer("L1", true))
+            .collect();
+        let content = Content { operations }.encode().unwrap();
+        let mut expected = layer("L1", false);
+        expected.extend(layer("L0", false));
+
+        for is_compressed in [false, true].iter().copied() {
+            let mut doc = printpdf::lopdf::Document::with_version("1.5");
+            let mut stream = Stream::new(Dictionary::new(), content.clone());
+            if is_compressed {
+                stream.compress().unwrap();
+            }
+            assert_eq!(is_compressed, stream.dict.has(b"Filter"));
+            let stream_id = doc.add_object(stream);
+            let mut page = Dictionary::new();
+            page.set("Type", "Page");
+            page.set("Contents", stream_id);
+            let page_id = doc.add_object(page);
+
+            super::move_background_layers(&mut doc, page_id).unwrap();
+            assert_eq!(vec![stream_id], doc.get_page_contents(page_id));
+            let stream = doc.get_object(stream_id).unwrap().as_stream().unwrap();
+            assert_eq!(is_compressed, stream.dict.has(b"Filter"));
+            let content = doc.get_page_content(page_id).unwrap();
+            let operations = Content::decode(&content).unwrap().operations;
+            assert_eq!(operators(&expected), operators(&operations));
+            assert_eq!(b"L1", operations[0].operands[1].as_name().unwrap());
+        }
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn test_encryption() {
+        use super::{hash_password, Permissions};
+        use aes::cipher::{
+            block_padding::{NoPadding, Pkcs7},
+            BlockDecryptMut as _, KeyIvInit as _,
+        };
+
+        fn decrypt_blocks(key: &[u8], data: &[u8]) -> Vec<u8> {
+            cbc::Decryptor::<aes::Aes256>::new(key.into(), &[0; 16].into())
+                .decrypt_padded_vec_mut::<NoPadding>(data)
+                .unwrap()
+        }
+
+        let permissions = Permissions {
+            print: true,
+            copy: false,
+            modify: false,
+        };
+        let renderer = Renderer::new(Size::new(100, 100), "test")
+            .unwrap()
+            .with_encryption("user", "owner", permissions);
+        let area = renderer.first_page().first_layer().area();
+        area.fill_rect(
+            Position::new(10, 10),
+            Size::new(20, 20),
+            Color::Rgb(255, 0, 0),
+        );
+        drop(area);
+        let bytes = renderer.write_to_vec().unwrap();
+
+        let mut doc = printpdf::lopdf::Document::load_mem(&bytes).unwrap();
+        let encrypt = doc.get_encrypted().unwrap();
+        let get = |key: &[u8]| encrypt.get(key).unwrap();
+        assert_eq!(b"Standard", get(b"Filter").as_name().unwrap());
+        assert_eq!(5, get(b"V").as_i64().unwrap());
+        assert_eq!(6, get(b"R").as_i64().unwrap());
+        assert_eq!(256, get(b"Length").as_i64().unwrap());
+        let filter = get(b"CF").as_dict().unwrap().get(b"StdCF").unwrap();
+        let filter = filter.as_dict().unwrap();
+        assert_eq!(b"AESV3", filter.get(b"CFM").unwrap().as_name().unwrap());
+        let flags = get(b"P").as_i64().unwrap() as u32;
+        assert_eq!(0x4, flags & 0x4);
+        assert_eq!(0, flags & (0x8 | 0x10));
+
+        // Algorithm 11 and 12: validate the passwords and retrieve the file key
+        let user_entry = get(b"U").as_str().unwrap().to_vec();
+        let owner_entry = get(b"O").as_str().unwrap();
+        assert_eq!(48, user_entry.len());
+        assert_eq!(
+            user_entry[..32],
+            hash_password(b"user", &user_entry[32..40], &[])
+        );
+        assert_ne!(
+            user_entry[..32],
+            hash_password(b"wrong", &user_entry[32..40], &[])
+        );
+        assert_eq!(
+            owner_entry[..32],
+            hash_password(b"owner", &owner_entry[32..40], &user_entry)
+        );
+        let key = decrypt_blocks(
+            &hash_password(b"user", &user_entry[40..], &[]),
+            get(b"UE").as_str().unwrap(),
+        );
+        let owner_key = decrypt_blocks(
+            &hash_password(b"owner", &owner_entry[40..], &user_entry),
+            get(b"OE").as_str().unwrap(),
+        );
+        assert_eq!(key, owner_key);
+
+        // Algorithm 13: validate the permissions
+        let perms = decrypt_blocks(&key, get(b"Perms").as_str().unwrap());
+        assert_eq!(b"adb", &perms[9..12]);
+        assert_eq!(flags.to_le_bytes(), perms[..4]);
+
+        let catalog_id = doc.trailer.get(b"Root").unwrap().as_reference().unwrap();
+        let catalog = doc.get_dictionary(catalog_id).unwrap();
+        assert!(catalog.get(b"Extensions").is_ok());
+
+        let page_id = *doc.get_pages().values().next().unwrap();
+        let content = doc.get_page_content(page_id).unwrap_or_default();
+        assert!(!content.windows(3).any(|w| w == b" re"));
+
+        let content_id = doc
+            .get_dictionary(page_id)
+            .unwrap()
+            .get(b"Contents")
+            .unwrap()
+            .as_reference()
+            .unwrap();
+        let stream = doc
+            .get_object_mut(content_id)
+            .unwrap()
+            .as_stream_mut()
+            .unwrap();
+        let (iv, data) = stream.content.split_at(16);
+        let content = cbc::Decryptor::<aes::Aes256>::new(key[..].into(), iv.into())
+            .decrypt_padded_vec_mut::<Pkcs7>(data)
+            .unwrap();
+        stream.set_content(content);
+        let content = doc.get_page_content(page_id).unwrap();
+        let operations = Content::decode(&content).unwrap().operations;
+        find_operation(&operations, "re");
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn test_encryption_known_answers() {
+        use super::{Permissions, SecurityEntries};
+        use std::convert::TryInto as _;
+
+        fn hex(s: &str) -> Vec<u8> {
+            (0..s.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+                .collect()
+        }
+
+        // The expected entries have been computed with an independent implementation of the
+        // algorithms 2.B and 8 to 10 of ISO 32000-2.
+        let key: Vec<_> = (0..32).collect();
+        let user_salts: Vec<_> = (0x20..0x30).collect();
+        let owner_salts: Vec<_> = (0x30..0x40).collect();
+        let permissions = Permissions {
+            print: true,
+            copy: false,
+            modify: false,
+        };
+        let long_password = "\u{fc}".repeat(100);
+        let cases = [
+            (
+                "user",
+                "owner",
+                permissions.flags(),
+                "641957c838a6af724badd497b43e3b232414ff58c797fd80cb5b3aa706837b6a\
+                303132333435363738393a3b3c3d3e3f",
+                "0883bdd9f6387104b4382dc453dea14d56ec345fc7e06b5dc5e22d4cdb744d7f\
+                202122232425262728292a2b2c2d2e2f",
+                "e324f0d67ebebc2337de7cce144767b118f16fd0e9f5f64a7a6b5cf657a41a41",
+                "0aced4b8d236ce53b71feba657b9267d9a27e4ccc510f93c30e3a198b59a9b25",
+                "007de01d75bfaef52bda66160978c210",
+            ),
+            // The owner password is truncated to 127 bytes
+            (
+                "",
+                long_password.as_str(),
+                Permissions::default().flags(),
+                "1a047ffdfd922dc0d08b609278427d4a91b94c9cd64b307935a4e33c1244d573\
+                303132333435363738393a3b3c3d3e3f",
+                "56af12f56d50526f2e99953dd63ca826ec81f539b71da705740e096d967fbef6\
+                202122232425262728292a2b2c2d2e2f",
+                "364f3db9e2a49c7afee03cfb48f3503947a1e6ac1db07739012e2dbbc6ddee92",
+                "9c8f59dc44b500985d3d882fc2f63c913476e210dd15edbed43845779e8aab8b",
+                "d4d0080c1ed86563a9fe9f05f865f017",
+            ),
+        ];
+        for (user_password, owner_password, flags, owner, user, owner_key, user_key, perms) in
+            cases.iter()
+        {
+            let entries = SecurityEntries::new(
+                user_password,
+                owner_password,
+                *flags,
+                key[..].try_into().unwrap(),
+                user_salts[..].try_into().unwrap(),
+                owner_salts[..].try_into().unwrap(),
+                &[0xA0, 0xA1, 0xA2, 0xA3],
+            );
+            assert_eq!(hex(owner), entries.owner);
+            assert_eq!(hex(user), entries.user);
+            assert_eq!(hex(owner_key), entries.owner_key);
+            assert_eq!(hex(user_key), entries.user_key);
+            assert_eq!(hex(perms), entries.perms);
+        }
+    }
+
+    #[test]
+    fn test_viewer_preferences() {
+        use super::{PageLayout, PageMode};
+
+        let renderer = Renderer::new(Size::new(100, 100), "test")
+            .unwrap()
+            .with_open_action(1, 0.0);
+        assert!(renderer.write_to_vec().is_err());
+
+        let mut renderer = Renderer::new(Size::new(100, 100), "test")
+            .unwrap()
+            .with_page_layout(PageLayout::TwoPageLeft)
+            .with_page_mode(PageMode::FullScreen)
+            .with_open_action(1, 1.5);
+        renderer.add_page(Size::new(100, 100));
+        let doc = write_document(renderer);
+        let catalog_id = doc.trailer.get(b"Root").unwrap().as_reference().unwrap();
+        let catalog = doc.get_dictionary(catalog_id).unwrap();
+        let name = |key: &[u8]| catalog.get(key).unwrap().as_name_str().unwrap();
+        assert_eq!("TwoPageLeft", name(b"PageLayout"));
+        assert_eq!("FullScreen", name(b"PageMode"));
+        let action = catalog.get(b"OpenAction").unwrap().as_array().unwrap();
+        let page_ids: Vec<_> = doc.get_pages().values().copied().collect();
+        assert_eq!(page_ids[1], action[0].as_reference().unwrap());
+        assert_eq!(b"XYZ", action[1].as_name().unwrap());
+        assert_eq!(1.5, number(&action[4]));
+    }
+
+    #[test]
+    fn test_insert_page() {
+        let mut renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        renderer.add_page(Size::new(110, 100));
+        renderer.add_bookmark("Body", 1).unwrap();
+        let rect = printpdf::Rect::new(
+            printpdf::Mm(0.0),
+            printpdf::Mm(0.0),
+            printpdf::Mm(10.0),
+            printpdf::Mm(10.0),
+        );
+        renderer
+            .first_page()
+            .first_layer()
+            .add_internal_link(rect, 1);
+        assert!(renderer.insert_page(3, Size::new(120, 100)).is_err());
+        renderer.insert_page(0, Size::new(120, 100)).unwrap();
+        assert_eq!(3, renderer.page_count());
+        assert_eq!(Size::new(120, 100), renderer.first_page().size);
+
+        let doc = write_document(renderer);
+        assert_eq!(vec![120, 100, 110], page_widths(&doc));
+        let page_ids: Vec<_> = doc.get_pages().values().copied().collect();
+        let pages = doc.get_dictionary(page_ids[0]).unwrap().get(b"Parent");
+        let pages = doc.get_dictionary(pages.unwrap().as_reference().unwrap());
+        assert_eq!(3, pages.unwrap().get(b"Count").unwrap().as_i64().unwrap());
+
+        let catalog_id = doc.trailer.get(b"Root").unwrap().as_reference().unwrap();
+        let catalog = doc.get_dictionary(catalog_id).unwrap();
+        let outlines_id = catalog.get(b"Outlines").unwrap().as_reference().unwrap();
+        let outlines = doc.get_dictionary(outlines_id).unwrap();
+        let bookmark_id = outlines.get(b"First").unwrap().as_reference().unwrap();
+        let bookmark = doc.get_dictionary(bookmark_id).unwrap();
+        let dest = bookmark.get(b"Dest").unwrap().as_array().unwrap();
+        assert_eq!(page_ids[2], dest[0].as_reference().unwrap());
+
+        let annots = doc.get_dictionary(page_ids[1]).unwrap().get(b"Annots");
+        let annot_id = annots.unwrap().as_array().unwrap()[0]
+            .as_reference()
+            .unwrap();
+        let action = doc.get_dictionary(annot_id).unwrap().get(b"A").unwrap();
+        let dest = action
+            .as_dict()
+            .unwrap()
+            .get(b"D")
+            .unwrap()
+            .as_array()
+            .unwrap();
+        assert_eq!(page_ids[2], dest[0].as_reference().unwrap());
+    }
+
+    #[test]
+    fn test_move_and_remove_pages() {
+        let mut renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        renderer.add_page(Size::new(110, 100));
+        renderer.add_page(Size::new(120, 100));
+        renderer.add_page(Size::new(130, 100));
+        renderer.add_bookmark("Removed", 3).unwrap();
+        assert!(renderer.move_page(0, 4).is_err());
+        renderer.move_page(0, 2).unwrap();
+        renderer.move_page(3, 0).unwrap();
+        renderer.remove_page(1).unwrap();
+        assert!(renderer.remove_page(3).is_err());
+        assert_eq!(3, renderer.page_count());
+
+        let doc = write_document(renderer);
+        assert_eq!(vec![130, 120, 100], page_widths(&doc));
+        // The removed page is not part of the document anymore
+        let page_count = doc
+            .objects
+            .values()
+            .filter_map(|object| object.as_dict().ok())
+            .filter(|dict| dict.get(b"Type").and_then(|t| t.as_name()).ok() == Some(b"Page"))
+            .count();
+        assert_eq!(3, page_count);
+
+        let mut renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        assert!(renderer.remove_page(0).is_err());
+    }
+
+    #[cfg(feature = "images")]
+    #[test]
+    fn test_add_jpeg_raw() {
+        let jpeg = include_bytes!("../examples/images/test_image.jpg");
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        let area = renderer.first_page().first_layer().area();
+        area.add_jpeg_raw(jpeg, Position::new(10, 10), Size::new(40, 30))
+            .unwrap();
+        drop(area);
+
+        let doc = write_document(renderer);
+        let images: Vec<_> = doc
+            .objects
+            .values()
+            .filter_map(|object| object.as_stream().ok())
+            .filter(|stream| {
+                stream.dict.get(b"Subtype").and_then(|o| o.as_name()).ok() == Some(b"Image")
+            })
+            .collect();
+        assert_eq!(1, images.len());
+        let image = images[0];
+        let filter = image.dict.get(b"Filter").unwrap().as_array().unwrap();
+        assert_eq!(b"DCTDecode", filter[0].as_name().unwrap());
+        assert_eq!(jpeg.len(), image.content.len());
+        assert_eq!(&jpeg[..], &image.content[..]);
+    }
+
+    #[cfg(feature = "images")]
+    #[test]
+    fn test_parse_jpeg_header() {
+        let jpeg = include_bytes!("../examples/images/test_image.jpg");
+        let header = super::parse_jpeg_header(jpeg).unwrap();
+        let image = image::load_from_memory(jpeg).unwrap();
+        assert_eq!(image.width() as usize, header.width);
+        assert_eq!(image.height() as usize, header.height);
+
+        assert!(super::parse_jpeg_header(b"").is_err());
+        assert!(super::parse_jpeg_header(b"\x89PNG").is_err());
+        assert!(super::parse_jpeg_header(&jpeg[..100]).is_err());
+    }
+
+    #[cfg(feature = "images")]
+    #[test]
+    fn test_image_deduplication() {
+        use crate::{Rotation, Scale};
+
+        let logo = image::DynamicImage::ImageRgb8(image::RgbImage::from_pixel(
+            4,
+            3,
+            image::Rgb([255, 0, 0]),
+        ));
+        let other = image::DynamicImage::ImageRgb8(image::RgbImage::from_pixel(
+            4,
+            3,
+            image::Rgb([0, 0, 255]),
+        ));
+        let mut renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        renderer.add_page(Size::new(100, 100));
+        renderer.add_page(Size::new(100, 100));
+        let add_image = |renderer: &Renderer, page: usize, image: &image::DynamicImage| {
+            let area = renderer.get_page(page).unwrap().first_layer().area();
+            let scale = Scale::new(1.0, 1.0);
+            area.add_image(
+                image,
+                Position::new(10, 10),
+                scale,
+                Rotation::default(),
+                None,
+            );
+        };
+        let assert_shared = |doc: &lopdf::Document| {
+            let image_ids: Vec<_> = doc
+                .objects
+                .iter()
+                .filter(|(_, object)| {
+                    object
+                        .as_stream()
+                        .and_then(|stream| stream.dict.get(b"Subtype"))
+                        .and_then(lopdf::Object::as_name)
+                        .ok()
+                        == Some(b"Image")
+                })
+                .map(|(id, _)| *id)
+                .collect();
+            assert_eq!(1, image_ids.len());
+            for page_id in doc.get_pages().values() {
+                let page = doc.get_dictionary(*page_id).unwrap();
+                let resources = doc.dereference(page.get(b"Resources").unwrap()).unwrap().1;
+                let xobjects = resources.as_dict().unwrap().get(b"XObject").unwrap();
+                let xobject = xobjects.as_dict().unwrap().get(b"X0").unwrap();
+                assert_eq!(image_ids[0], xobject.as_reference().unwrap());
+            }
+        };
+        for page in 0..3 {
+            add_image(&renderer, page, &logo);
+        }
+        assert_shared(&write_document(renderer));
+
+        // The image data is kept if the page that embeds it is removed
+        let mut renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        renderer.add_page(Size::new(100, 100));
+        add_image(&renderer, 0, &logo);
+        add_image(&renderer, 1, &logo);
+        renderer.remove_page(0).unwrap();
+        let doc = write_document(renderer);
+        assert_eq!(1, doc.get_pages().len());
+        assert_shared(&doc);
+
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        add_image(&renderer, 0, &logo);
+        add_image(&renderer, 0, &other);
+        add_image(&renderer, 0, &logo);
+        let doc = write_document(renderer);
+        let image_count = doc
+            .objects
+            .values()
+            .filter_map(|object| object.as_stream().ok())
+            .filter(|stream| stream.dict.get(b"Width").is_ok())
+            .count();
+        assert_eq!(2, image_count);
+
+        // Images with colliding hashes are not shared
+        let mut cache = super::ImageCache::new();
+        cache.insert(0, vec![(logo.clone(), 0)]);
+        assert_eq!(Some(0), super::cached_image_id(&cache, 0, &logo));
+        assert_eq!(None, super::cached_image_id(&cache, 0, &other));
+        assert_eq!(None, super::cached_image_id(&cache, 1, &logo));
+    }
+
+    #[cfg(feature = "images")]
+    #[test]
+    fn test_from_document_xobjects() {
+        use crate::Scale;
+
+        let size = Size::new(100, 100);
+        let (doc, page, layer) =
+            printpdf::PdfDocument::new("test", size.width.into(), size.height.into(), "Layer 1");
+        let page = doc.get_page(page);
+        let layer = page.get_layer(layer);
+        printpdf::Image::from(printpdf::ImageXObject {
+            width: printpdf::Px(1),
+            height: printpdf::Px(1),
+            color_space: printpdf::ColorSpace::Greyscale,
+            bits_per_component: printpdf::ColorBits::Bit8,
+            interpolate: false,
+            image_data: vec![0],
+            image_filter: None,
+            smask: None,
+            clipping_bbox: None,
+        })
+        .add_to_layer(layer.clone(), printpdf::ImageTransform::default());
+        let renderer = Renderer::from_document(doc, vec![(page, layer, size)]).unwrap();
+
+        // The index of the soft mask image does not match the index of the XObject
+        let area = renderer.first_page().first_layer().area();
+        let pixels = [255, 0, 0, 255, 0, 0, 0, 0];
+        area.add_rgba(
+            &pixels,
+            2,
+            1,
+            Position::default(),
+            Scale::new(1.0, 1.0),
+            None,
+        )
+        .unwrap();
+        drop(area);
+        assert!(renderer.write(Vec::new()).is_err());
+    }
+
+    #[cfg(feature = "images")]
+    #[test]
+    fn test_add_rgba() {
+        use crate::Scale;
+
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        let area = renderer.first_page().first_layer().area();
+        let red = [255, 0, 0, 255];
+        let transparent = [0, 0, 0, 0];
+        let pixels = [red, transparent, transparent, red].concat();
+        let scale = Scale::new(1.0, 1.0);
+        area.add_rgba(&pixels, 2, 2, Position::new(10, 10), scale, None)
+            .unwrap();
+        area.add_rgba(&pixels[..12], 3, 1, Position::new(20, 20), scale, None)
+            .unwrap();
+        assert!(area
+            .add_rgba(&pixels, 3, 2, Position::new(0, 0), scale, None)
+            .is_err());
+        drop(area);
+
+        let doc = write_document(renderer);
+        let resources = first_page(&doc).get(b"Resources").unwrap();
+        let resources = doc.dereference(resources).unwrap().1.as_dict().unwrap();
+        let xobjects = resources.get(b"XObject").unwrap().as_dict().unwrap();
+        let mut images: Vec<_> = xobjects
+            .iter()
+            .map(|(_, object)| doc.get_object(object.as_reference().unwrap()).unwrap())
+            .map(|object| object.as_stream().unwrap())
+            .collect();
+        images.sort_by_key(|image| image.dict.get(b"Width").unwrap().as_i64().unwrap());
+        assert_eq!(2, images.len());
+        assert_eq!(
+            vec![255, 0, 0, 0, 0, 0, 0, 0, 0, 255, 0, 0],
+            images[0].content
+        );
+
+        for (image, alpha) in images.iter().zip(&[vec![255, 0, 0, 255], vec![255, 0, 0]]) {
+            let mask_id = image.dict.get(b"SMask").unwrap().as_reference().unwrap();
+            let mask = doc.get_object(mask_id).unwrap().as_stream().unwrap();
+            let width =
+                |stream: &lopdf::Stream| stream.dict.get(b"Width").unwrap().as_i64().unwrap();
+            assert_eq!(width(image), width(mask));
+            let height =
+                |stream: &lopdf::Stream| stream.dict.get(b"Height").unwrap().as_i64().unwrap();
+            assert_eq!(height(image), height(mask));
+            assert_eq!(alpha, &mask.content);
+        }
+    }
+
+    #[test]
+    fn test_add_internal_link() {
+        let mut font_cache = FontCache::new(font_family());
+        let mut renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        renderer.add_page(Size::new(100, 100));
+        renderer.add_page(Size::new(100, 150));
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+        let area = renderer.first_page().first_layer().area();
+        assert!(area
+            .add_link(
+                &font_cache,
+                Position::new(0, 0),
+                Style::new(),
+                "Web",
+                "https://example.com"
+            )
+            .unwrap());
+        assert!(area
+            .add_internal_link(
+                &font_cache,
+                Position::new(0, 10),
+                Style::new(),
+                "Results",
+                2
+            )
+            .unwrap());
+        drop(area);
+
+        let doc = write_document(renderer);
+        let page_ids: Vec<_> = doc.get_pages().values().copied().collect();
+        let annotations = first_page(&doc).get(b"Annots").unwrap().as_array().unwrap();
+        assert_eq!(2, annotations.len());
+        let actions: Vec<_> = annotations
+            .iter()
+            .map(|annotation| {
+                let id = annotation.as_reference().unwrap();
+                let annotation = doc.get_dictionary(id).unwrap();
+                annotation.get(b"A").unwrap().as_dict().unwrap()
+            })
+            .collect();
+        let action = actions
+            .iter()
+            .find(|action| action.get(b"S").unwrap().as_name().unwrap() == b"GoTo")
+            .expect("Missing GoTo action");
+        let dest = action.get(b"D").unwrap().as_array().unwrap();
+        assert_eq!(page_ids[2], dest[0].as_reference().unwrap());
+        assert_eq!(b"XYZ", dest[1].as_name().unwrap());
+        assert!((number(&dest[3]) - f64::from(printpdf::Pt::from(Mm(150.0)).0)).abs() < 0.01);
+
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+        let area = renderer.first_page().first_layer().area();
+        area.add_internal_link(&font_cache, Position::new(0, 0), Style::new(), "Missing", 1)
+            .unwrap();
+        drop(area);
+        assert!(renderer.write_to_vec().is_err());
+    }
+
+    #[test]
+    fn test_encode_builtin() {
+        use super::encode_builtin;
+        use crate::fonts::BuiltinEncoding;
+
+        let ascii = [0x43, 0x61, 0x66];
+        let win_ansi = encode_builtin("Caf\u{e9}", BuiltinEncoding::WinAnsi).unwrap();
+        let mac_roman = encode_builtin("Caf\u{e9}", BuiltinEncoding::MacRoman).unwrap();
+        assert_eq!(&ascii[..], &win_ansi[..3]);
+        assert_eq!(&ascii[..], &mac_roman[..3]);
+        assert_eq!(0xe9, win_ansi[3]);
+        assert_eq!(0x8e, mac_roman[3]);
+        assert!(encode_builtin("\u{a6}", BuiltinEncoding::WinAnsi).is_ok());
+        assert!(encode_builtin("\u{a6}", BuiltinEncoding::MacRoman).is_err());
+        assert!(encode_builtin("\u{65e5}", BuiltinEncoding::Standard).is_err());
+
+        let mut font_cache = FontCache::new(builtin_font_family());
+        let mut renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        renderer.set_builtin_encoding(BuiltinEncoding::MacRoman);
+        assert_eq!(BuiltinEncoding::MacRoman, renderer.builtin_encoding());
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+        let area = renderer.first_page().first_layer().area();
+        assert!(area
+            .print_str(&font_cache, Position::default(), Style::new(), "Caf\u{e9}")
+            .unwrap());
+        drop(area);
+
+        let doc = write_document(renderer);
+        let fonts: Vec<_> = doc
+            .objects
+            .values()
+            .filter_map(|object| object.as_dict().ok())
+            .filter(|dict| {
+                dict.get(b"Subtype").and_then(lopdf::Object::as_name).ok() == Some(b"Type1")
+            })
+            .collect();
+        assert!(!fonts.is_empty());
+        for font in fonts {
+            assert_eq!(
+                b"MacRomanEncoding",
+                font.get(b"Encoding").unwrap().as_name().unwrap()
+            );
+        }
+        let page_id = *doc.get_pages().values().next().unwrap();
+        let content = doc.get_page_content(page_id).unwrap();
+        let operations = Content::decode(&content).unwrap().operations;
+        let text = &find_operation(&operations, "Tj").operands[0];
+        assert_eq!(&[0x43, 0x61, 0x66, 0x8e], text.as_str().unwrap());
+    }
+
+    #[test]
+    fn test_standard_builtin_encoding() {
+        use crate::fonts::{BuiltinEncoding, FontData};
+
+        let mut font_cache = FontCache::new(builtin_font_family());
+        let symbol = FontData::new(FONT.to_vec(), Some(printpdf::BuiltinFont::Symbol)).unwrap();
+        font_cache.add_font(symbol);
+        let mut renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        renderer.set_builtin_encoding(BuiltinEncoding::Standard);
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+        let area = renderer.first_page().first_layer().area();
+        assert!(area
+            .print_str(&font_cache, Position::default(), Style::new(), "Cafe")
+            .unwrap());
+        drop(area);
+
+        let doc = write_document(renderer);
+        let fonts: Vec<_> = doc
+            .objects
+            .values()
+            .filter_map(|object| object.as_dict().ok())
+            .filter(|dict| {
+                dict.get(b"Subtype").and_then(lopdf::Object::as_name).ok() == Some(b"Type1")
+            })
+            .collect();
+        for font in &fonts {
+            let base_font = font.get(b"BaseFont").unwrap().as_name().unwrap();
+            if base_font == b"Symbol" {
+                assert_eq!(
+                    b"WinAnsiEncoding",
+                    font.get(b"Encoding").unwrap().as_name().unwrap()
+                );
+            } else {
+                assert!(font.get(b"Encoding").is_err());
+            }
+        }
+        assert!(fonts
+            .iter()
+            .any(|font| font.get(b"BaseFont").unwrap().as_name().unwrap() == b"Symbol"));
+    }
+
+    #[test]
+    fn test_builtin_link_alignment() {
+        let mut font_cache = FontCache::new(builtin_font_family());
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+        let style = Style::new();
+        let text = "Built-in link";
+        let lsb = style.char_left_side_bearing(&font_cache, 'B');
+        let width = style.str_width(&font_cache, text);
+        let mut area = renderer.first_page().first_layer().area();
+        area.add_offset(Position::new(10, 0));
+        assert!(area
+            .add_link(
+                &font_cache,
+                Position::new(5, 0),
+                style,
+                text,
+                "https://example.com"
+            )
+            .unwrap());
+        drop(area);
+
+        let doc = write_document(renderer);
+        let page_id = *doc.get_pages().values().next().unwrap();
+        let content = doc.get_page_content(page_id).unwrap();
+        let operations = Content::decode(&content).unwrap().operations;
+        find_operation(&operations, "Tj");
+        let text_x = number(&find_operation(&operations, "Td").operands[0]);
+
+        let annotations = first_page(&doc).get(b"Annots").unwrap().as_array().unwrap();
+        let id = annotations[0].as_reference().unwrap();
+        let rect = doc
+            .get_dictionary(id)
+            .unwrap()
+            .get(b"Rect")
+            .unwrap()
+            .as_array()
+            .unwrap();
+        let pt = |mm: Mm| f64::from(printpdf::Pt::from(mm).0);
+        assert!((number(&rect[0]) - text_x).abs() < 0.01);
+        assert!((number(&rect[0]) - pt(Mm(15.0) - lsb)).abs() < 0.01);
+        assert!((number(&rect[2]) - number(&rect[0]) - pt(width)).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_link_after_newline() {
+        let mut font_cache = FontCache::new(font_family());
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+        let style = Style::new();
+        let lsb = style.char_left_side_bearing(&font_cache, 'L');
+        let mut area = renderer.first_page().first_layer().area();
+        area.add_offset(Position::new(10, 0));
+        let mut section = area
+            .text_section(&font_cache, Position::default(), style.metrics(&font_cache))
+            .unwrap();
+        section
+            .print_str("A rather long first line", style)
+            .unwrap();
+        assert!(section.add_newline());
+        section
+            .add_link("Link", "https://example.com", style)
+            .unwrap();
+        drop(section);
+        drop(area);
+
+        let doc = write_document(renderer);
+        let annotations = first_page(&doc).get(b"Annots").unwrap().as_array().unwrap();
+        let id = annotations[0].as_reference().unwrap();
+        let rect = doc
+            .get_dictionary(id)
+            .unwrap()
+            .get(b"Rect")
+            .unwrap()
+            .as_array()
+            .unwrap();
+        let pt = |mm: Mm| f64::from(printpdf::Pt::from(mm).0);
+        assert!((number(&rect[0]) - pt(Mm(10.0) - lsb)).abs() < 0.01);
+        // The link is on the second line
+        let line_height = style.metrics(&font_cache).line_height;
+        assert!(number(&rect[3]) <= pt(Mm(100.0) - line_height));
+        assert!(number(&rect[3]) > pt(Mm(100.0) - line_height * 2.0));
+    }
+
+    #[test]
+    fn test_builtin_left_side_bearing() {
+        let mut font_cache = FontCache::new(builtin_font_family());
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+        let style = Style::new();
+        // The left side bearings from the bounding boxes in the Helvetica AFM file
+        let afm_bearing = |llx: f32| Mm::from(printpdf::Pt(llx * 12.0 / 1000.0));
+        for (c, llx) in [('A', 14.0), ('o', 35.0), ('j', -16.0)].iter() {
+            assert_approx_eq!(
+                Mm,
+                afm_bearing(*llx),
+                style.char_left_side_bearing(&font_cache, *c)
+            );
+        }
+
+        let area = renderer.first_page().first_layer().area();
+        assert!(area
+            .print_str(&font_cache, Position::new(10, 0), style, "Apple")
+            .unwrap());
+        drop(area);
+        let x = number(&find_operation(&render(renderer), "Td").operands[0]);
+        let expected = printpdf::Pt::from(Mm(10.0) - afm_bearing(14.0)).0;
+        assert!((x - f64::from(expected)).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_oversized_margins() {
+        let renderer = Renderer::new(Size::new(100, 50), "test").unwrap();
+        let mut area = renderer.first_page().first_layer().area();
+        area.add_margins(Margins::trbl(10, 60, 30, 50));
+        assert_eq!(Size::new(0, 10), area.size());
+
+        let mut area = renderer.first_page().first_layer().area();
+        area.add_offset(Position::new(120, 20));
+        assert_eq!(Size::new(0, 30), area.size());
+        area.add_offset(Position::new(0, 40));
+        assert_eq!(Size::new(0, 0), area.size());
+    }
+
+    #[test]
+    fn test_write_to_vec() {
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        let bytes = renderer.write_to_vec().unwrap();
+        assert!(bytes.starts_with(b"%PDF-"));
+        assert!(lopdf::Document::load_mem(&bytes).is_ok());
+    }
+
+    #[cfg(feature = "qrcode")]
+    #[test]
+    fn test_draw_qrcode() {
+        let code = qrcode::QrCode::new("HELLO").unwrap();
+        assert_eq!(qrcode::Version::Normal(1), code.version());
+        assert_eq!(21, code.width());
+        // The number of runs of dark modules in the rows of the code
+        let dark_runs = code
+            .to_colors()
+            .chunks(code.width())
+            .map(|row| {
+                let starts = row
+                    .windows(2)
+                    .filter(|w| w[0] != w[1] && w[1] == qrcode::Color::Dark);
+                starts.count() + usize::from(row[0] == qrcode::Color::Dark)
+            })
+            .sum::<usize>();
+
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        let area = renderer.first_page().first_layer().area();
+        let size = area
+            .draw_qrcode("HELLO", Position::new(10, 10), Mm(1.0), Color::Rgb(0, 0, 0))
+            .unwrap();
+        assert_eq!(Size::new(29, 29), size);
+        assert!(area
+            .draw_qrcode(
+                &"x".repeat(8000),
+                Position::default(),
+                Mm(1.0),
+                Color::Rgb(0, 0, 0)
+            )
+            .is_err());
+        drop(area);
+        let (origin, size) = renderer.first_page().content_bounds().unwrap();
+        assert_eq!(Position::new(14, 14), origin);
+        assert_eq!(Size::new(21, 21), size);
+
+        let operations = render(renderer);
+        let count = |operator| {
+            operations
+                .iter()
+                .filter(|op| op.operator == operator)
+                .count()
+        };
+        assert_eq!(dark_runs, count("re"));
+        assert_eq!(1, count("f"));
+        // The first row of the code starts with a finder pattern of seven dark modules
+        let re = find_operation(&operations, "re");
+        let width = f64::from(printpdf::Pt::from(Mm(7.0)).0);
+        assert!((number(&re.operands[2]) - width).abs() < 0.001);
+    }
+
+    #[cfg(feature = "barcode")]
+    #[test]
+    fn test_draw_barcode() {
+        use crate::barcode::Barcode;
+
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        let area = renderer.first_page().first_layer().area();
+        area.draw_barcode(
+            "HELLO",
+            Barcode::Code128,
+            Position::new(10, 20),
+            Size::new(90, 15),
+        )
+        .unwrap();
+        assert!(area
+            .draw_barcode(
+                "\u{e9}",
+                Barcode::Code128,
+                Position::default(),
+                Size::new(90, 15)
+            )
+            .is_err());
+        drop(area);
+        let (origin, size) = renderer.first_page().content_bounds().unwrap();
+        assert_eq!(Position::new(10, 20), origin);
+        assert_eq!(Size::new(90, 15), size);
+
+        // start, five characters and checksum with three bars each, stop with four bars
+        let operations = render(renderer);
+        assert_eq!(
+            7 * 3 + 4,
+            operations.iter().filter(|op| op.operator == "re").count()
+        );
+    }
+
+    #[test]
+    fn test_fill_rect() {
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        let mut area = renderer.first_page().first_layer().area();
+        area.add_offset(Position::new(10, 20));
+        area.fill_rect(
+            Position::new(5, 10),
+            Size::new(30, 15),
+            Color::Rgb(255, 0, 0),
+        );
+        drop(area);
+
+        let operations = render(renderer);
+        let color: Vec<_> = find_operation(&operations, "rg")
+            .operands
+            .iter()
+            .map(number)
+            .collect();
+        assert_eq!(vec![1.0, 0.0, 0.0], color);
+        let idx = operations
+            .iter()
+            .position(|op| op.operator == "re")
+            .unwrap();
+        let rect: Vec<_> = operations[idx].operands.iter().map(number).collect();
+        let expected = [15.0, 55.0, 30.0, 15.0]
+            .iter()
+            .map(|mm| f64::from(printpdf::Pt::from(Mm(*mm)).0));
+        for (value, expected) in rect.into_iter().zip(expected) {
+            assert!((value - expected).abs() < 0.01);
+        }
+        assert_eq!("f", operations[idx + 1].operator);
+        assert!(!operations.iter().any(|op| op.operator == "S"));
+    }
+
+    #[test]
+    fn test_draw_rect() {
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        let mut area = renderer.first_page().first_layer().area();
+        area.add_offset(Position::new(10, 20));
+        area.draw_rect(Position::new(5, 10), Size::new(30, 15), LineStyle::dashed());
+        drop(area);
+
+        let operations = render(renderer);
+        assert!(!find_operation(&operations, "d").operands[0]
+            .as_array()
+            .unwrap()
+            .is_empty());
+        let path: Vec<_> = operations
+            .iter()
+            .skip_while(|op| op.operator != "m")
+            .take(6)
+            .collect();
+        let operators: Vec<_> = path.iter().map(|op| op.operator.as_str()).collect();
+        assert_eq!(vec!["m", "l", "l", "l", "h", "S"], operators);
+        let expected = [(15.0, 70.0), (45.0, 70.0), (45.0, 55.0), (15.0, 55.0)];
+        for (op, (x, y)) in path.iter().zip(&expected) {
+            let point: Vec<_> = op.operands.iter().map(number).collect();
+            assert!((point[0] - f64::from(printpdf::Pt::from(Mm(*x)).0)).abs() < 0.01);
+            assert!((point[1] - f64::from(printpdf::Pt::from(Mm(*y)).0)).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_draw_ellipse() {
+        let operators = |style: Option<LineStyle>, fill: Option<Color>| {
+            let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+            let area = renderer.first_page().first_layer().area();
+            area.draw_circle(Position::new(50, 50), 10, style, fill);
+            drop(area);
+            render(renderer)
+        };
+
+        let operations = operators(Some(LineStyle::new()), Some(Color::Rgb(0, 0, 255)));
+        let start: Vec<_> = find_operation(&operations, "m")
+            .operands
+            .iter()
+            .map(number)
+            .collect();
+        let expected = [50.0, 60.0]
+            .iter()
+            .map(|mm| f64::from(printpdf::Pt::from(Mm(*mm)).0));
+        for (value, expected) in start.into_iter().zip(expected) {
+            assert!((value - expected).abs() < 0.01);
+        }
+        let path: Vec<_> = operations
+            .iter()
+            .map(|op| op.operator.as_str())
+            .skip_while(|op| *op != "m")
+            .take(7)
+            .collect();
+        assert_eq!(vec!["m", "c", "c", "c", "c", "h", "B"], path);
+        let color: Vec<_> = find_operation(&operations, "rg")
+            .operands
+            .iter()
+            .map(number)
+            .collect();
+        assert_eq!(vec![0.0, 0.0, 1.0], color);
+
+        let operations = operators(None, Some(Color::Rgb(0, 0, 255)));
+        assert!(operations.iter().any(|op| op.operator == "f"));
+        assert!(!operations.iter().any(|op| op.operator == "B"));
+
+        let operations = operators(Some(LineStyle::new()), None);
+        assert!(operations.iter().any(|op| op.operator == "S"));
+        assert!(!operations.iter().any(|op| op.operator == "rg"));
+
+        let operations = operators(None, None);
+        assert!(!operations.iter().any(|op| op.operator == "m"));
+    }
+
+    #[test]
+    fn test_draw_bezier() {
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        let area = renderer.first_page().first_layer().area();
+        let (start, control1, control2, end) = (
+            Position::new(10, 50),
+            Position::new(30, 10),
+            Position::new(70, 90),
+            Position::new(90, 50),
+        );
+        let flags: Vec<_> = area
+            .bezier_points(start, control1, control2, end)
+            .into_iter()
+            .map(|(_, flag)| flag)
+            .collect();
+        assert_eq!(vec![true, true, false, false], flags);
+        area.draw_bezier(start, control1, control2, end, LineStyle::new());
+        drop(area);
+
+        let operations = render(renderer);
+        let curve: Vec<_> = find_operation(&operations, "c")
+            .operands
+            .iter()
+            .map(number)
+            .collect();
+        let expected = [30.0, 90.0, 70.0, 10.0, 90.0, 50.0]
+            .iter()
+            .map(|mm| f64::from(printpdf::Pt::from(Mm(*mm)).0));
+        assert_eq!(6, curve.len());
+        for (value, expected) in curve.into_iter().zip(expected) {
+            assert!((value - expected).abs() < 0.01);
+        }
+        assert!(!operations.iter().any(|op| op.operator == "l"));
+        assert!(operations.iter().any(|op| op.operator == "S"));
+    }
+
+    #[test]
+    fn test_with_clip() {
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        let mut area = renderer.first_page().first_layer().area();
+        area.add_offset(Position::new(10, 20));
+        area.set_size(Size::new(30, 40));
+        area.with_clip(|area| {
+            area.fill_rect(
+                Position::default(),
+                Size::new(50, 50),
+                Color::Rgb(255, 0, 0),
+            );
+        });
+        area.fill_rect(Position::default(), Size::new(5, 5), Color::Rgb(255, 0, 0));
+        drop(area);
+
+        let operations = render(renderer);
+        let operators: Vec<_> = operations.iter().map(|op| op.operator.as_str()).collect();
+        // The outer q/Q pair is added by printpdf for the layer
+        assert_eq!(
+            vec!["q", "q", "re", "W", "n", "rg", "re", "f", "Q", "rg", "re", "f", "Q"],
+            operators
+                .into_iter()
+                .filter(|op| ["q", "Q", "re", "W", "n", "rg", "f"].contains(op))
+                .collect::<Vec<_>>()
+        );
+        let clip: Vec<_> = find_operation(&operations, "re")
+            .operands
+            .iter()
+            .map(number)
+            .collect();
+        let expected = [10.0, 40.0, 30.0, 40.0]
+            .iter()
+            .map(|mm| f64::from(printpdf::Pt::from(Mm(*mm)).0));
+        for (value, expected) in clip.into_iter().zip(expected) {
+            assert!((value - expected).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_custom_property() {
+        let mut renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        renderer
+            .set_custom_property("DocumentClass", "Internal")
+            .unwrap();
+        renderer
+            .set_custom_property("DocumentClass", "Confidential")
+            .unwrap();
+        renderer.set_custom_property("Retention", "7y").unwrap();
+        renderer.set_custom_property("Owner", "Zoë").unwrap();
+        assert!(renderer.set_custom_property("", "value").is_err());
+        assert!(renderer.set_custom_property("Title", "value").is_err());
+
+        let doc = write_document(renderer);
+        let info_id = doc.trailer.get(b"Info").unwrap().as_reference().unwrap();
+        let info = doc.get_dictionary(info_id).unwrap();
+        let value = |key: &[u8]| info.get(key).unwrap().as_str().unwrap().to_vec();
+        assert_eq!(b"Confidential".to_vec(), value(b"DocumentClass"));
+        assert_eq!(b"7y".to_vec(), value(b"Retention"));
+        assert_eq!(
+            vec![0xfe, 0xff, 0x00, b'Z', 0x00, b'o', 0x00, 0xeb],
+            value(b"Owner")
+        );
+        assert_eq!(b"test".to_vec(), value(b"Title"));
+    }
+
+    #[test]
+    fn test_highlight() {
+        let mut font_cache = FontCache::new(font_family());
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+        let style = Style::new();
+        let highlighted = style.with_highlight(Color::Rgb(255, 255, 0));
+        let area = renderer.first_page().first_layer().area();
+        let mut section = area
+            .text_section(&font_cache, Position::default(), style.metrics(&font_cache))
+            .unwrap();
+        section.print_str("Hello ", style).unwrap();
+        section.print_str("World", highlighted).unwrap();
+        assert!(section.add_newline());
+        section.print_str("Hello", style).unwrap();
+        drop(section);
+        drop(area);
+
+        let operations = render(renderer);
+        let operators: Vec<_> = operations.iter().map(|op| op.operator.as_str()).collect();
+        let re = operators.iter().position(|op| *op == "re").unwrap();
+        let text = operators.iter().rposition(|op| *op == "TJ").unwrap();
+        assert_eq!(
+            vec!["Tf", "TJ", "ET", "rg", "re", "f", "BT", "Td", "rg", "TJ", "Td", "TJ", "ET"],
+            operators[operators.iter().position(|op| *op == "Tf").unwrap()..]
+                .iter()
+                .filter(|op| !["q", "Q", "gs", "EMC"].contains(op))
+                .copied()
+                .collect::<Vec<_>>()
+        );
+        assert!(re < text);
+
+        let pt = |mm: Mm| f64::from(printpdf::Pt::from(mm).0);
+        let font = style.font(&font_cache);
+        let lsb = style.char_left_side_bearing(&font_cache, 'H');
+        let x = style.str_width(&font_cache, "Hello ") - lsb;
+        let height = font.ascent(12.0) - font.descent(12.0);
+        let bottom = Mm(100.0) - font.ascent(12.0) + font.descent(12.0);
+        let rect: Vec<_> = operations[re].operands.iter().map(number).collect();
+        let expected = [
+            pt(x),
+            pt(bottom),
+            pt(style.str_width(&font_cache, "World")),
+            pt(height),
+        ];
+        for (actual, expected) in rect.into_iter().zip(expected.iter()) {
+            assert!(
+                (actual - expected).abs() < 0.001,
+                "{} != {}",
+                actual,
+                expected
+            );
+        }
+
+        // The new line starts at the text origin instead of the start of the highlighted run.
+        let td: Vec<_> = operations
+            .iter()
+            .filter(|op| op.operator == "Td")
+            .map(|op| (number(&op.operands[0]), number(&op.operands[1])))
+            .collect();
+        let line_height = pt(style.line_height(&font_cache));
+        assert_eq!(3, td.len());
+        assert!((td[2].0 + pt(x + lsb)).abs() < 0.001);
+        assert!((td[2].1 + line_height).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_print_ruby() {
+        let mut font_cache = FontCache::new(font_family());
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+        let style = Style::new();
+        let ruby_style = style.ruby_style(&font_cache);
+        let area = renderer.first_page().first_layer().area();
+        let mut section = area
+            .text_section(&font_cache, Position::default(), style.metrics(&font_cache))
+            .unwrap();
+        section.print_ruby("A", "abcdef", style).unwrap();
+        let ruby_width = ruby_style.str_width(&font_cache, "abcdef");
+        assert!(ruby_width > style.str_width(&font_cache, "A"));
+        assert_approx_eq!(Mm, ruby_width, section.current_x_offset);
+        section.print_ruby("Hello", "a", style).unwrap();
+        assert_approx_eq!(
+            Mm,
+            ruby_width + style.str_width(&font_cache, "Hello"),
+            section.current_x_offset
+        );
+        drop(section);
+        drop(area);
+
+        let operations: Vec<_> = render(renderer)
+            .into_iter()
+            .filter(|op| ["Tf", "Ts", "TJ", "Td"].contains(&op.operator.as_str()))
+            .collect();
+        let operators: Vec<_> = operations.iter().map(|op| op.operator.as_str()).collect();
+        assert_eq!(
+            vec![
+                "Td", "Td", "Tf", "TJ", "Td", "Tf", "Ts", "TJ", "Ts", "Tf", "TJ", "Td", "Tf", "Ts",
+                "TJ", "Ts", "Td"
+            ],
+            operators
+        );
+        assert_eq!(12.0, number(&operations[2].operands[1]));
+        assert_eq!(6.0, number(&operations[5].operands[1]));
+
+        // The ruby text is raised above the ascent of the base text.
+        let font = style.font(&font_cache);
+        let rise = printpdf::Pt::from(font.ascent(12.0) - font.descent(6.0)).0;
+        assert!((number(&operations[6].operands[0]) - f64::from(rise)).abs() < 0.001);
+        assert_eq!(0.0, number(&operations[8].operands[0]));
+
+        // The base text is centered below the wider ruby text.
+        let base_offset = (ruby_width - style.str_width(&font_cache, "A")) / 2.0;
+        let td = number(&operations[1].operands[0]);
+        assert!((td - f64::from(printpdf::Pt::from(base_offset).0)).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_word_spacing() {
+        let builtin = FontData::new(FONT.to_vec(), Some(printpdf::BuiltinFont::Helvetica)).unwrap();
+        let mut font_cache = FontCache::new(font_family());
+        let builtin = font_cache.add_font_family(FontFamily {
+            regular: builtin.clone(),
+            bold: builtin.clone(),
+            italic: builtin.clone(),
+            bold_italic: builtin,
+        });
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+
+        let style = Style::new().with_word_spacing(Mm(2.0));
+        let builtin_style = style.with_font_family(builtin);
+        for style in [style, builtin_style].iter() {
+            let without_spacing = style.with_word_spacing(0);
+            assert_approx_eq!(
+                Mm,
+                without_spacing.str_width(&font_cache, "Hello World") + Mm(2.0),
+                style.str_width(&font_cache, "Hello World")
+            );
+            assert_eq!(
+                without_spacing.str_width(&font_cache, "Hello"),
+                style.str_width(&font_cache, "Hello")
+            );
+        }
+
+        let area = renderer.first_page().first_layer().area();
+        let mut section = area
+            .text_section(&font_cache, Position::default(), style.metrics(&font_cache))
+            .unwrap();
+        section.print_str("Hello World", style).unwrap();
+        section.print_str("Hello ", builtin_style).unwrap();
+        drop(section);
+        drop(area);
+
+        let spacing = -f64::from(printpdf::Pt::from(Mm(2.0)).0) / 12.0 * 1000.0;
+        let arrays: Vec<_> = render(renderer)
+            .into_iter()
+            .filter(|op| op.operator == "TJ")
+            .map(|op| op.operands[0].as_array().unwrap().clone())
+            .collect();
+        assert_eq!(2, arrays.len());
+
+        // The word spacing is inserted before the glyph that follows the space.
+        let font = style.font(&font_cache);
+        let w = font.glyph_ids(&font_cache, "W".chars())[0].to_be_bytes();
+        let embedded = &arrays[0];
+        let w_idx = embedded
+            .iter()
+            .position(|o| o.as_str().map(|s| s == w).unwrap_or_default())
+            .unwrap();
+        assert!((number(&embedded[w_idx - 1]) - spacing).abs() < 0.01);
+
+        // The built-in font uses single-byte codes and a trailing adjustment for the last space.
+        let builtin = &arrays[1];
+        assert_eq!(
+            b"Hello ".to_vec(),
+            builtin[..6]
+                .iter()
+                .flat_map(|o| o.as_str().unwrap().to_vec())
+                .collect::<Vec<_>>()
+        );
+        assert!((number(&builtin[6]) - spacing).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_zero_font_size() {
+        let mut font_cache = FontCache::new(font_family());
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+        let area = renderer.first_page().first_layer().area();
+        let style = Style::new().with_font_size(0);
+        let mut section = area
+            .text_section(&font_cache, Position::default(), style.metrics(&font_cache))
+            .unwrap();
+        section.print_str("a b ", style).unwrap();
+        section
+            .print_str("a b ", style.with_word_spacing(Mm(2.0)))
+            .unwrap();
+        drop(section);
+        drop(area);
+
+        let doc = write_document(renderer);
+        let page_id = *doc.get_pages().values().next().unwrap();
+        let content = doc.get_page_content(page_id).unwrap();
+        assert!(!content.windows(3).any(|w| w == b"NaN" || w == b"inf"));
+        for op in Content::decode(&content).unwrap().operations {
+            if op.operator == "TJ" {
+                for object in op.operands[0].as_array().unwrap() {
+                    if !matches!(object, lopdf::Object::String(..)) {
+                        assert!(number(object).is_finite());
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_character_spacing() {
+        let mut font_cache = FontCache::new(font_family());
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+        let style = Style::new();
+        let area = renderer.first_page().first_layer().area();
+        let mut section = area
+            .text_section(&font_cache, Position::default(), style.metrics(&font_cache))
+            .unwrap();
+        section.set_character_spacing(Mm(1.0));
+        section.set_character_spacing(Mm(1.0));
+        section.print_str("Hello", style).unwrap();
+        assert_approx_eq!(
+            Mm,
+            style.str_width(&font_cache, "Hello") + Mm(5.0),
+            section.current_position().x
+        );
+        drop(section);
+        drop(area);
+
+        let spacings: Vec<_> = render(renderer)
+            .into_iter()
+            .filter(|op| op.operator == "Tc")
+            .map(|op| number(&op.operands[0]))
+            .collect();
+        assert_eq!(2, spacings.len());
+        assert!((spacings[0] - f64::from(printpdf::Pt::from(Mm(1.0)).0)).abs() < 0.01);
+        assert_eq!(0.0, spacings[1]);
+    }
+
+    #[test]
+    fn test_superscript_subscript() {
+        let mut font_cache = FontCache::new(font_family());
+        let renderer = Renderer::new(Size::new(100, 100), "test").unwrap();
+        font_cache.load_pdf_fonts(&renderer).unwrap();
+        let style = Style::new();
+        let area = renderer.first_page().first_layer().area();
+        let mut section = area
+            .text_section(&font_cache, Position::default(), style.metrics(&font_cache))
+            .unwrap();
+        section.print_str("x", style).unwrap();
+        section.print_str("2", style.superscript()).unwrap();
+        section.print_str("H", style).unwrap();
+        section.print_str("2", style.subscript()).unwrap();
+        drop(section);
+        drop(area);
+
+        let operations: Vec<_> = render(renderer)
+            .into_iter()
+            .filter(|op| ["Tf", "Ts"].contains(&op.operator.as_str()))
+            .collect();
+        let operators: Vec<_> = operations.iter().map(|op| op.operator.as_str()).collect();
+        assert_eq!(
+            vec!["Tf", "Tf", "Ts", "Ts", "Tf", "Tf", "Ts", "Ts"],
+            operators
+        );
+        assert!((number(&operations[1].operands[1]) - 7.2).abs() < 0.001);
+        assert!(number(&operations[2].operands[0]) > 0.0);
+        assert_eq!(0.0, number(&operations[3].operands[0]));
+        assert!((number(&operations[5].operands[1]) - 7.2).abs() < 0.001);
+        assert!(number(&operations[6].operands[0]) < 0.0);
+
+        let width = style.str_width(&font_cache, "2");
+        assert!(style.superscript().str_width(&font_cache, "2") < width);
+        assert_approx_eq!(
+            Mm,
+            style.with_font_size(12.0 * 0.6).str_width(&font_cache, "2"),
+            style.superscript().str_width(&font_cache, "2")
+        );
     }
 }
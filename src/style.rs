@@ -45,6 +45,7 @@ use crate::Mm;
 /// let grey = genpdfi::style::Color::Greyscale(127);
 /// ```
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Color {
     /// An RGB color with red, green and blue values between 0 and 255.
     Rgb(u8, u8, u8),
@@ -54,6 +55,416 @@ pub enum Color {
     Greyscale(u8),
 }
 
+impl Color {
+    /// Creates an RGB color from a [CSS named color][], for example `"tomato"` or
+    /// `"rebeccapurple"`.
+    ///
+    /// Matching is case-insensitive and ignores leading and trailing whitespace.  If the name is
+    /// unknown, `None` is returned.  The CSS keyword `transparent` cannot be represented by this
+    /// type and also yields `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use genpdfi::style::Color;
+    /// assert_eq!(Some(Color::Rgb(255, 99, 71)), Color::from_name("tomato"));
+    /// assert_eq!(Some(Color::Rgb(70, 130, 180)), Color::from_name(" SteelBlue "));
+    /// assert_eq!(None, Color::from_name("transparent"));
+    /// ```
+    ///
+    /// [CSS named color]: https://www.w3.org/TR/css-color-4/#named-colors
+    pub fn from_name(name: &str) -> Option<Color> {
+        let name = name.trim().to_ascii_lowercase();
+        NAMED_COLORS
+            .binary_search_by(|(n, _)| n.cmp(&name.as_str()))
+            .ok()
+            .map(|idx| {
+                let (r, g, b) = NAMED_COLORS[idx].1;
+                Color::Rgb(r, g, b)
+            })
+    }
+
+    /// Creates an RGB color from the given hue (in degrees), saturation and lightness.
+    ///
+    /// The hue wraps around modulo 360 degrees.  Saturation and lightness are clamped to the range
+    /// from 0 to 1.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use genpdfi::style::Color;
+    /// assert_eq!(Color::Rgb(255, 0, 0), Color::from_hsl(0.0, 1.0, 0.5));
+    /// assert_eq!(Color::Rgb(102, 51, 153), Color::from_hsl(270.0, 0.5, 0.4));
+    /// ```
+    pub fn from_hsl(h: f32, s: f32, l: f32) -> Color {
+        let s = clamp_unit(s);
+        let l = clamp_unit(l);
+        let chroma = (1.0 - (2.0 * l - 1.0).abs()) * s;
+        Color::from_hue_chroma(h, chroma, l - chroma / 2.0)
+    }
+
+    /// Creates an RGB color from the given hue (in degrees), saturation and value.
+    ///
+    /// The hue wraps around modulo 360 degrees.  Saturation and value are clamped to the range
+    /// from 0 to 1.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use genpdfi::style::Color;
+    /// assert_eq!(Color::Rgb(0, 0, 255), Color::from_hsv(240.0, 1.0, 1.0));
+    /// assert_eq!(Color::Rgb(128, 128, 128), Color::from_hsv(0.0, 0.0, 0.5));
+    /// ```
+    pub fn from_hsv(h: f32, s: f32, v: f32) -> Color {
+        let s = clamp_unit(s);
+        let v = clamp_unit(v);
+        let chroma = v * s;
+        Color::from_hue_chroma(h, chroma, v - chroma)
+    }
+
+    /// Mixes this color with white and returns the result as an RGB color.
+    ///
+    /// The amount is clamped to the range from 0 (unchanged) to 1 (white).  CMYK and greyscale
+    /// colors are converted to RGB first.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use genpdfi::style::Color;
+    /// assert_eq!(Color::Rgb(255, 128, 128), Color::Rgb(255, 0, 0).lighten(0.5));
+    /// ```
+    pub fn lighten(self, amount: f32) -> Color {
+        self.mix(Color::Rgb(255, 255, 255), amount)
+    }
+
+    /// Mixes this color with black and returns the result as an RGB color.
+    ///
+    /// The amount is clamped to the range from 0 (unchanged) to 1 (black).  CMYK and greyscale
+    /// colors are converted to RGB first.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use genpdfi::style::Color;
+    /// assert_eq!(Color::Rgb(0, 0, 128), Color::Rgb(0, 0, 255).darken(0.5));
+    /// ```
+    pub fn darken(self, amount: f32) -> Color {
+        self.mix(Color::Rgb(0, 0, 0), amount)
+    }
+
+    /// Linearly interpolates between this color and the given color in the RGB color space.
+    ///
+    /// `t` is clamped to the range from 0 (this color) to 1 (the other color).  CMYK and
+    /// greyscale colors are converted to RGB first.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use genpdfi::style::Color;
+    /// let grey = Color::Rgb(0, 0, 0).mix(Color::Rgb(255, 255, 255), 0.5);
+    /// assert_eq!(Color::Rgb(128, 128, 128), grey);
+    /// ```
+    pub fn mix(self, other: Color, t: f32) -> Color {
+        let t = clamp_unit(t);
+        let (r1, g1, b1) = self.to_rgb();
+        let (r2, g2, b2) = other.to_rgb();
+        let mix = |a: f32, b: f32| (clamp_unit(a + (b - a) * t) * 255.0).round() as u8;
+        Color::Rgb(mix(r1, r2), mix(g1, g2), mix(b1, b2))
+    }
+
+    /// Returns the relative luminance of this color as defined by WCAG 2.
+    ///
+    /// The sRGB components are linearized with `c / 12.92` if `c <= 0.03928` and
+    /// `((c + 0.055) / 1.055) ^ 2.4` otherwise, and then weighted as
+    /// `0.2126 * r + 0.7152 * g + 0.0722 * b`.  The result ranges from 0 (black) to 1 (white).
+    /// CMYK and greyscale colors are converted to RGB first.
+    pub fn relative_luminance(&self) -> f32 {
+        let linearize = |c: f32| {
+            if c <= 0.03928 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        let (r, g, b) = self.to_rgb();
+        0.2126 * linearize(r) + 0.7152 * linearize(g) + 0.0722 * linearize(b)
+    }
+
+    /// Returns the WCAG 2 contrast ratio between this color and the given color.
+    ///
+    /// The ratio is `(l1 + 0.05) / (l2 + 0.05)`, where `l1` is the relative luminance of the
+    /// lighter and `l2` the relative luminance of the darker color, see
+    /// [`relative_luminance`](#method.relative_luminance).  It ranges from 1 (identical
+    /// luminance) to 21 (black and white), and the order of the colors does not matter.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use genpdfi::style::Color;
+    /// let ratio = Color::Rgb(0, 0, 0).contrast_ratio(&Color::Rgb(255, 255, 255));
+    /// assert!((ratio - 21.0).abs() < 0.01);
+    /// ```
+    pub fn contrast_ratio(&self, other: &Color) -> f32 {
+        let l1 = self.relative_luminance();
+        let l2 = other.relative_luminance();
+        (l1.max(l2) + 0.05) / (l1.min(l2) + 0.05)
+    }
+
+    /// Returns the red, green and blue components of this color in the range from 0 to 1.
+    fn to_rgb(self) -> (f32, f32, f32) {
+        let unit = |val: u8| f32::from(val) / 255.0;
+        match self {
+            Color::Rgb(r, g, b) => (unit(r), unit(g), unit(b)),
+            Color::Cmyk(c, m, y, k) => {
+                let k = 1.0 - unit(k);
+                (
+                    (1.0 - unit(c)) * k,
+                    (1.0 - unit(m)) * k,
+                    (1.0 - unit(y)) * k,
+                )
+            }
+            Color::Greyscale(val) => (unit(val), unit(val), unit(val)),
+        }
+    }
+
+    /// Creates an RGB color from the given hue, chroma and the lightness offset that is added to
+    /// all components, as used by the HSL and HSV conversions.
+    fn from_hue_chroma(h: f32, chroma: f32, offset: f32) -> Color {
+        let h = if h.is_finite() { h } else { 0.0 };
+        let h = h.rem_euclid(360.0) / 60.0;
+        let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+        let (r, g, b) = match h as u8 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let to_u8 = |val: f32| (clamp_unit(val + offset) * 255.0).round() as u8;
+        Color::Rgb(to_u8(r), to_u8(g), to_u8(b))
+    }
+}
+
+/// The smallest font size in points, see [`Style::set_font_size`][].
+///
+/// [`Style::set_font_size`]: struct.Style.html#method.set_font_size
+const MIN_FONT_SIZE: f32 = 0.01;
+/// The font size of superscripted and subscripted text relative to the regular font size.
+const SCRIPT_SIZE: f32 = 0.6;
+/// The baseline shift of superscripted text relative to the regular font size.
+const SUPERSCRIPT_RISE: f32 = 0.33;
+/// The baseline shift of subscripted text relative to the regular font size.
+const SUBSCRIPT_RISE: f32 = -0.15;
+/// The font size of ruby annotations relative to the font size of the base text.
+const RUBY_SIZE: f32 = 0.5;
+
+/// Returns the byte ranges of the English ordinal suffixes (*st*, *nd*, *rd* and *th*) in the
+/// given string.
+///
+/// A suffix is only detected if it directly follows a number, if it is not followed by another
+/// alphanumeric character and if it matches the number, for example *1st* or *11th*, but not
+/// *1th* or *1stly*.
+fn find_ordinal_suffixes(s: &str) -> Vec<(usize, usize)> {
+    let bytes = s.as_bytes();
+    let mut suffixes = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if !bytes[i].is_ascii_digit() || (i > 0 && bytes[i - 1].is_ascii_alphanumeric()) {
+            i += 1;
+            continue;
+        }
+
+        let digits_start = i;
+        while i < bytes.len() && bytes[i].is_ascii_digit() {
+            i += 1;
+        }
+        let digits = &bytes[digits_start..i];
+        let expected: &[u8] = match digits {
+            [.., b'1', b'1'..=b'3'] => b"th",
+            [.., b'1'] => b"st",
+            [.., b'2'] => b"nd",
+            [.., b'3'] => b"rd",
+            _ => b"th",
+        };
+
+        let suffix_end = i + 2;
+        if suffix_end <= bytes.len()
+            && bytes[i..suffix_end].eq_ignore_ascii_case(expected)
+            && !s[suffix_end..]
+                .chars()
+                .next()
+                .map(char::is_alphanumeric)
+                .unwrap_or_default()
+        {
+            suffixes.push((i, suffix_end));
+            i = suffix_end;
+        }
+    }
+    suffixes
+}
+
+/// Clamps the given value to the range from 0 to 1, mapping NaN to 0.
+fn clamp_unit(val: f32) -> f32 {
+    if val.is_nan() {
+        0.0
+    } else {
+        val.clamp(0.0, 1.0)
+    }
+}
+
+/// The CSS Color Module Level 4 named colors, sorted by name.
+const NAMED_COLORS: &[(&str, (u8, u8, u8))] = &[
+    ("aliceblue", (240, 248, 255)),
+    ("antiquewhite", (250, 235, 215)),
+    ("aqua", (0, 255, 255)),
+    ("aquamarine", (127, 255, 212)),
+    ("azure", (240, 255, 255)),
+    ("beige", (245, 245, 220)),
+    ("bisque", (255, 228, 196)),
+    ("black", (0, 0, 0)),
+    ("blanchedalmond", (255, 235, 205)),
+    ("blue", (0, 0, 255)),
+    ("blueviolet", (138, 43, 226)),
+    ("brown", (165, 42, 42)),
+    ("burlywood", (222, 184, 135)),
+    ("cadetblue", (95, 158, 160)),
+    ("chartreuse", (127, 255, 0)),
+    ("chocolate", (210, 105, 30)),
+    ("coral", (255, 127, 80)),
+    ("cornflowerblue", (100, 149, 237)),
+    ("cornsilk", (255, 248, 220)),
+    ("crimson", (220, 20, 60)),
+    ("cyan", (0, 255, 255)),
+    ("darkblue", (0, 0, 139)),
+    ("darkcyan", (0, 139, 139)),
+    ("darkgoldenrod", (184, 134, 11)),
+    ("darkgray", (169, 169, 169)),
+    ("darkgreen", (0, 100, 0)),
+    ("darkgrey", (169, 169, 169)),
+    ("darkkhaki", (189, 183, 107)),
+    ("darkmagenta", (139, 0, 139)),
+    ("darkolivegreen", (85, 107, 47)),
+    ("darkorange", (255, 140, 0)),
+    ("darkorchid", (153, 50, 204)),
+    ("darkred", (139, 0, 0)),
+    ("darksalmon", (233, 150, 122)),
+    ("darkseagreen", (143, 188, 143)),
+    ("darkslateblue", (72, 61, 139)),
+    ("darkslategray", (47, 79, 79)),
+    ("darkslategrey", (47, 79, 79)),
+    ("darkturquoise", (0, 206, 209)),
+    ("darkviolet", (148, 0, 211)),
+    ("deeppink", (255, 20, 147)),
+    ("deepskyblue", (0, 191, 255)),
+    ("dimgray", (105, 105, 105)),
+    ("dimgrey", (105, 105, 105)),
+    ("dodgerblue", (30, 144, 255)),
+    ("firebrick", (178, 34, 34)),
+    ("floralwhite", (255, 250, 240)),
+    ("forestgreen", (34, 139, 34)),
+    ("fuchsia", (255, 0, 255)),
+    ("gainsboro", (220, 220, 220)),
+    ("ghostwhite", (248, 248, 255)),
+    ("gold", (255, 215, 0)),
+    ("goldenrod", (218, 165, 32)),
+    ("gray", (128, 128, 128)),
+    ("green", (0, 128, 0)),
+    ("greenyellow", (173, 255, 47)),
+    ("grey", (128, 128, 128)),
+    ("honeydew", (240, 255, 240)),
+    ("hotpink", (255, 105, 180)),
+    ("indianred", (205, 92, 92)),
+    ("indigo", (75, 0, 130)),
+    ("ivory", (255, 255, 240)),
+    ("khaki", (240, 230, 140)),
+    ("lavender", (230, 230, 250)),
+    ("lavenderblush", (255, 240, 245)),
+    ("lawngreen", (124, 252, 0)),
+    ("lemonchiffon", (255, 250, 205)),
+    ("lightblue", (173, 216, 230)),
+    ("lightcoral", (240, 128, 128)),
+    ("lightcyan", (224, 255, 255)),
+    ("lightgoldenrodyellow", (250, 250, 210)),
+    ("lightgray", (211, 211, 211)),
+    ("lightgreen", (144, 238, 144)),
+    ("lightgrey", (211, 211, 211)),
+    ("lightpink", (255, 182, 193)),
+    ("lightsalmon", (255, 160, 122)),
+    ("lightseagreen", (32, 178, 170)),
+    ("lightskyblue", (135, 206, 250)),
+    ("lightslategray", (119, 136, 153)),
+    ("lightslategrey", (119, 136, 153)),
+    ("lightsteelblue", (176, 196, 222)),
+    ("lightyellow", (255, 255, 224)),
+    ("lime", (0, 255, 0)),
+    ("limegreen", (50, 205, 50)),
+    ("linen", (250, 240, 230)),
+    ("magenta", (255, 0, 255)),
+    ("maroon", (128, 0, 0)),
+    ("mediumaquamarine", (102, 205, 170)),
+    ("mediumblue", (0, 0, 205)),
+    ("mediumorchid", (186, 85, 211)),
+    ("mediumpurple", (147, 112, 219)),
+    ("mediumseagreen", (60, 179, 113)),
+    ("mediumslateblue", (123, 104, 238)),
+    ("mediumspringgreen", (0, 250, 154)),
+    ("mediumturquoise", (72, 209, 204)),
+    ("mediumvioletred", (199, 21, 133)),
+    ("midnightblue", (25, 25, 112)),
+    ("mintcream", (245, 255, 250)),
+    ("mistyrose", (255, 228, 225)),
+    ("moccasin", (255, 228, 181)),
+    ("navajowhite", (255, 222, 173)),
+    ("navy", (0, 0, 128)),
+    ("oldlace", (253, 245, 230)),
+    ("olive", (128, 128, 0)),
+    ("olivedrab", (107, 142, 35)),
+    ("orange", (255, 165, 0)),
+    ("orangered", (255, 69, 0)),
+    ("orchid", (218, 112, 214)),
+    ("palegoldenrod", (238, 232, 170)),
+    ("palegreen", (152, 251, 152)),
+    ("paleturquoise", (175, 238, 238)),
+    ("palevioletred", (219, 112, 147)),
+    ("papayawhip", (255, 239, 213)),
+    ("peachpuff", (255, 218, 185)),
+    ("peru", (205, 133, 63)),
+    ("pink", (255, 192, 203)),
+    ("plum", (221, 160, 221)),
+    ("powderblue", (176, 224, 230)),
+    ("purple", (128, 0, 128)),
+    ("rebeccapurple", (102, 51, 153)),
+    ("red", (255, 0, 0)),
+    ("rosybrown", (188, 143, 143)),
+    ("royalblue", (65, 105, 225)),
+    ("saddlebrown", (139, 69, 19)),
+    ("salmon", (250, 128, 114)),
+    ("sandybrown", (244, 164, 96)),
+    ("seagreen", (46, 139, 87)),
+    ("seashell", (255, 245, 238)),
+    ("sienna", (160, 82, 45)),
+    ("silver", (192, 192, 192)),
+    ("skyblue", (135, 206, 235)),
+    ("slateblue", (106, 90, 205)),
+    ("slategray", (112, 128, 144)),
+    ("slategrey", (112, 128, 144)),
+    ("snow", (255, 250, 250)),
+    ("springgreen", (0, 255, 127)),
+    ("steelblue", (70, 130, 180)),
+    ("tan", (210, 180, 140)),
+    ("teal", (0, 128, 128)),
+    ("thistle", (216, 191, 216)),
+    ("tomato", (255, 99, 71)),
+    ("turquoise", (64, 224, 208)),
+    ("violet", (238, 130, 238)),
+    ("wheat", (245, 222, 179)),
+    ("white", (255, 255, 255)),
+    ("whitesmoke", (245, 245, 245)),
+    ("yellow", (255, 255, 0)),
+    ("yellowgreen", (154, 205, 50)),
+];
+
 impl From<Color> for printpdf::Color {
     fn from(color: Color) -> printpdf::Color {
         match color {
@@ -77,8 +488,9 @@ impl From<Color> for printpdf::Color {
     }
 }
 
-/// A text effect (bold, italic, underline, or strikethrough).
+/// A text effect (bold, italic, underline, strikethrough, superscript or subscript).
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Effect {
     /// Bold text.
     Bold,
@@ -88,6 +500,37 @@ pub enum Effect {
     Underline,
     /// Strikethrough text.
     Strikethrough,
+    /// Superscripted text.
+    Superscript,
+    /// Subscripted text.
+    Subscript,
+}
+
+/// The vertical position of superscripted or subscripted text.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum Script {
+    Superscript,
+    Subscript,
+}
+
+/// The outline of the glyphs of a text.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct TextStroke {
+    color: Color,
+    width: Mm,
+    fill: bool,
+}
+
+impl Script {
+    /// Returns the baseline shift relative to the regular font size.
+    fn rise(&self) -> f32 {
+        match self {
+            Script::Superscript => SUPERSCRIPT_RISE,
+            Script::Subscript => SUBSCRIPT_RISE,
+        }
+    }
 }
 
 /// A style annotation for a string.
@@ -97,25 +540,55 @@ pub enum Effect {
 /// - a font size in points (defaults to 12)
 /// - a line spacing factor, with 1 meaning single line spacing (defaults to 1)
 /// - an outline color, see [`Color`][] (defaults to black)
+/// - a fill opacity between 0 and 1 (defaults to 1, fully opaque)
+/// - a highlight color that is drawn behind the text, see [`Color`][] (defaults to none)
+/// - a stroke color and width for outlined glyphs, see [`Color`][] (defaults to none)
+/// - an additional word spacing that is added to every space character (defaults to 0)
 /// - a combination of text effects, see [`Effect`][] (defaults to none)
 ///
 /// All properties are optional.  If they are not set, they can be inferred from parent styles or
 /// from the defaults.
 ///
+/// The font family, the font size and the color can also be explicitly cleared, see for example
+/// [`clear_color`][].  If a style with a cleared property is merged into another style, the
+/// property is unset in the result, so the default value is used unless the property is set
+/// again by a style that is merged later.
+///
+/// If the `serde` feature is enabled, styles can be serialized and deserialized.  The font family
+/// is only valid for the font cache that it has been created with, so it is never serialized and
+/// has to be set again after deserializing a style.  Properties that are missing from the
+/// serialized data are not set.
+///
 /// [`Color`]: enum.Color.html
 /// [`Effect`]: enum.Effect.html
 /// [`FontFamily`]: ../fonts/struct.FontFamily.html
 /// [`FontCache`]: ../fonts/struct.FontCache.html
+/// [`clear_color`]: #method.clear_color
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct Style {
+    #[cfg_attr(feature = "serde", serde(skip))]
     font_family: Option<fonts::FontFamily<fonts::Font>>,
-    font_size: Option<u8>,
+    font_size: Option<f32>,
     line_spacing: Option<f32>,
     color: Option<Color>,
+    fill_opacity: Option<f32>,
+    highlight: Option<Color>,
+    stroke: Option<TextStroke>,
+    word_spacing: Option<Mm>,
+    superscript_ordinals: Option<bool>,
+    synthetic_bold: Option<bool>,
+    synthetic_italic: Option<bool>,
+    baseline_shift: Option<f32>,
+    script: Option<Script>,
     is_bold: bool,
     is_italic: bool,
     is_underline: bool,
     is_strikethrough: bool,
+    clears_font_family: bool,
+    clears_font_size: bool,
+    clears_color: bool,
 }
 
 impl Style {
@@ -127,14 +600,47 @@ impl Style {
     /// Merges the given style into this style.
     pub fn merge(&mut self, style: impl Into<Style>) {
         let style = style.into();
-        if let Some(font_family) = style.font_family {
-            self.font_family = Some(font_family);
-        };
-        if let Some(font_size) = style.font_size {
-            self.font_size = Some(font_size);
+        if style.font_family.is_some() || style.clears_font_family {
+            self.font_family = style.font_family;
+            self.clears_font_family = style.clears_font_family;
+        }
+        if style.font_size.is_some() || style.clears_font_size {
+            self.font_size = style.font_size;
+            self.clears_font_size = style.clears_font_size;
+        }
+        if let Some(line_spacing) = style.line_spacing {
+            self.line_spacing = Some(line_spacing);
+        }
+        if style.color.is_some() || style.clears_color {
+            self.color = style.color;
+            self.clears_color = style.clears_color;
+        }
+        if let Some(fill_opacity) = style.fill_opacity {
+            self.fill_opacity = Some(fill_opacity);
+        }
+        if let Some(highlight) = style.highlight {
+            self.highlight = Some(highlight);
+        }
+        if let Some(stroke) = style.stroke {
+            self.stroke = Some(stroke);
+        }
+        if let Some(word_spacing) = style.word_spacing {
+            self.word_spacing = Some(word_spacing);
+        }
+        if let Some(superscript_ordinals) = style.superscript_ordinals {
+            self.superscript_ordinals = Some(superscript_ordinals);
+        }
+        if let Some(synthetic_bold) = style.synthetic_bold {
+            self.synthetic_bold = Some(synthetic_bold);
+        }
+        if let Some(synthetic_italic) = style.synthetic_italic {
+            self.synthetic_italic = Some(synthetic_italic);
+        }
+        if let Some(baseline_shift) = style.baseline_shift {
+            self.baseline_shift = Some(baseline_shift);
         }
-        if let Some(color) = style.color {
-            self.color = Some(color);
+        if let Some(script) = style.script {
+            self.script = Some(script);
         }
         if style.is_bold {
             self.is_bold = true;
@@ -166,6 +672,97 @@ impl Style {
         self.color
     }
 
+    /// Returns the fill opacity for this style, or 1 (fully opaque) if no fill opacity is set.
+    pub fn fill_opacity(&self) -> f32 {
+        self.fill_opacity.unwrap_or(1.0)
+    }
+
+    /// Returns the highlight color for this style, if set.
+    pub fn highlight(&self) -> Option<Color> {
+        self.highlight
+    }
+
+    /// Returns the stroke color and width for this style, if set.
+    pub fn stroke(&self) -> Option<(Color, Mm)> {
+        self.stroke.map(|stroke| (stroke.color, stroke.width))
+    }
+
+    /// Returns whether the glyphs are only stroked and not filled.
+    pub fn is_stroke_only(&self) -> bool {
+        self.stroke.map(|stroke| !stroke.fill).unwrap_or(false)
+    }
+
+    /// Returns the additional word spacing for this style, or 0 if no word spacing is set.
+    pub fn word_spacing(&self) -> Mm {
+        self.word_spacing.unwrap_or_default()
+    }
+
+    /// Returns whether ordinal suffixes are automatically superscripted, see
+    /// [`auto_superscript_ordinals`][].
+    ///
+    /// [`auto_superscript_ordinals`]: #method.auto_superscript_ordinals
+    pub fn has_auto_superscript_ordinals(&self) -> bool {
+        self.superscript_ordinals.unwrap_or(false)
+    }
+
+    /// Returns whether a synthetic bold font may be used, see
+    /// [`allow_synthetic_bold`][].
+    ///
+    /// [`allow_synthetic_bold`]: #method.allow_synthetic_bold
+    pub fn allows_synthetic_bold(&self) -> bool {
+        self.synthetic_bold.unwrap_or(false)
+    }
+
+    /// Returns whether text with this style is printed with a synthetic bold font, i. e. whether
+    /// it is bold and synthetic bold is allowed, but the font family does not have a bold font.
+    pub(crate) fn is_synthetic_bold(&self, font_cache: &fonts::FontCache) -> bool {
+        if !self.is_bold || !self.allows_synthetic_bold() {
+            return false;
+        }
+        let family = self.font_family(font_cache);
+        let font = if self.is_italic {
+            family.italic
+        } else {
+            family.regular
+        };
+        font_cache.is_same_font(self.font(font_cache), font)
+    }
+
+    /// Returns whether a synthetic italic font may be used, see
+    /// [`allow_synthetic_italic`][].
+    ///
+    /// [`allow_synthetic_italic`]: #method.allow_synthetic_italic
+    pub fn allows_synthetic_italic(&self) -> bool {
+        self.synthetic_italic.unwrap_or(false)
+    }
+
+    /// Returns whether text with this style is printed with a synthetic italic font, i. e.
+    /// whether it is italic and synthetic italic is allowed, but the font family does not have an
+    /// italic font.
+    pub(crate) fn is_synthetic_italic(&self, font_cache: &fonts::FontCache) -> bool {
+        if !self.is_italic || !self.allows_synthetic_italic() {
+            return false;
+        }
+        let family = self.font_family(font_cache);
+        let font = if self.is_bold {
+            family.bold
+        } else {
+            family.regular
+        };
+        font_cache.is_same_font(self.font(font_cache), font)
+    }
+
+    /// Returns the shift of the baseline in points, with positive values raising the text.
+    ///
+    /// This includes the shift of the superscript and subscript effects.
+    pub(crate) fn baseline_shift(&self) -> f32 {
+        let rise = self
+            .script
+            .map(|script| script.rise() * self.nominal_font_size())
+            .unwrap_or_default();
+        self.baseline_shift.unwrap_or(0.0) + rise
+    }
+
     /// Returns whether the bold text effect is set.
     pub fn is_bold(&self) -> bool {
         self.is_bold
@@ -186,9 +783,32 @@ impl Style {
         self.is_strikethrough
     }
 
+    /// Returns whether the superscript text effect is set.
+    pub fn is_superscript(&self) -> bool {
+        self.script == Some(Script::Superscript)
+    }
+
+    /// Returns whether the subscript text effect is set.
+    pub fn is_subscript(&self) -> bool {
+        self.script == Some(Script::Subscript)
+    }
+
     /// Returns the font size for this style in points, or 12 if no font size is set.
-    pub fn font_size(&self) -> u8 {
-        self.font_size.unwrap_or(12)
+    ///
+    /// If the superscript or subscript effect is set, the font size is reduced accordingly.
+    pub fn font_size(&self) -> f32 {
+        let font_size = self.nominal_font_size();
+        if self.script.is_some() {
+            font_size * SCRIPT_SIZE
+        } else {
+            font_size
+        }
+    }
+
+    /// Returns the font size for this style without the reduction of the superscript and
+    /// subscript effects.
+    fn nominal_font_size(&self) -> f32 {
+        self.font_size.unwrap_or(12.0)
     }
 
     /// Returns the line spacing factor for this style, or 1 if no line spacing factor is set.
@@ -240,9 +860,36 @@ impl Style {
         self
     }
 
+    /// Sets the superscript effect for this style, replacing the subscript effect.
+    ///
+    /// Superscripted text is printed with a reduced font size and a raised baseline.
+    pub fn set_superscript(&mut self) {
+        self.script = Some(Script::Superscript);
+    }
+
+    /// Sets the superscript effect for this style and returns it.
+    pub fn superscript(mut self) -> Style {
+        self.set_superscript();
+        self
+    }
+
+    /// Sets the subscript effect for this style, replacing the superscript effect.
+    ///
+    /// Subscripted text is printed with a reduced font size and a lowered baseline.
+    pub fn set_subscript(&mut self) {
+        self.script = Some(Script::Subscript);
+    }
+
+    /// Sets the subscript effect for this style and returns it.
+    pub fn subscript(mut self) -> Style {
+        self.set_subscript();
+        self
+    }
+
     /// Sets the font family for this style.
     pub fn set_font_family(&mut self, font_family: fonts::FontFamily<fonts::Font>) {
         self.font_family = Some(font_family);
+        self.clears_font_family = false;
     }
 
     /// Sets the font family for this style and returns it.
@@ -251,6 +898,22 @@ impl Style {
         self
     }
 
+    /// Clears the font family for this style.
+    ///
+    /// If this style is merged into another style, the font family of the other style is unset,
+    /// so the default font family of the font cache is used.
+    pub fn clear_font_family(&mut self) {
+        self.font_family = None;
+        self.clears_font_family = true;
+    }
+
+    /// Clears the font family for this style and returns it, see
+    /// [`clear_font_family`](#method.clear_font_family).
+    pub fn without_font_family(mut self) -> Style {
+        self.clear_font_family();
+        self
+    }
+
     /// Sets the line spacing factor for this style.
     pub fn set_line_spacing(&mut self, line_spacing: f32) {
         self.line_spacing = Some(line_spacing);
@@ -263,19 +926,48 @@ impl Style {
     }
 
     /// Sets the font size in points for this style.
-    pub fn set_font_size(&mut self, font_size: u8) {
+    ///
+    /// The font size can be given as an integer or as a floating-point number, for example
+    /// `10.5`.  Zero, negative font sizes and NaN are clamped to the smallest font size of 0.01
+    /// points, and font sizes that are not finite as an `f32` are clamped to the largest finite
+    /// `f32` value.
+    pub fn set_font_size(&mut self, font_size: impl Into<f64>) {
+        let font_size = font_size.into();
+        let font_size = if font_size.is_nan() {
+            MIN_FONT_SIZE
+        } else {
+            font_size.clamp(f64::from(MIN_FONT_SIZE), f64::from(f32::MAX)) as f32
+        };
         self.font_size = Some(font_size);
+        self.clears_font_size = false;
     }
 
     /// Sets the font size in points for this style and returns it.
-    pub fn with_font_size(mut self, font_size: u8) -> Style {
+    pub fn with_font_size(mut self, font_size: impl Into<f64>) -> Style {
         self.set_font_size(font_size);
         self
     }
 
+    /// Clears the font size for this style.
+    ///
+    /// If this style is merged into another style, the font size of the other style is unset, so
+    /// the default font size of 12 points is used.
+    pub fn clear_font_size(&mut self) {
+        self.font_size = None;
+        self.clears_font_size = true;
+    }
+
+    /// Clears the font size for this style and returns it, see
+    /// [`clear_font_size`](#method.clear_font_size).
+    pub fn without_font_size(mut self) -> Style {
+        self.clear_font_size();
+        self
+    }
+
     /// Sets the outline color for this style.
     pub fn set_color(&mut self, color: Color) {
         self.color = Some(color);
+        self.clears_color = false;
     }
 
     /// Sets the outline color for this style and returns it.
@@ -284,6 +976,211 @@ impl Style {
         self
     }
 
+    /// Clears the outline color for this style.
+    ///
+    /// If this style is merged into another style, the color of the other style is unset, so the
+    /// text is printed in the default color (black).
+    pub fn clear_color(&mut self) {
+        self.color = None;
+        self.clears_color = true;
+    }
+
+    /// Clears the outline color for this style and returns it, see
+    /// [`clear_color`](#method.clear_color).
+    pub fn without_color(mut self) -> Style {
+        self.clear_color();
+        self
+    }
+
+    /// Sets the highlight color for this style.
+    ///
+    /// Text printed with this style is drawn on top of a rectangle with the highlight color that
+    /// spans the ascent and descent of the font, like a marker pen.
+    pub fn set_highlight(&mut self, color: Color) {
+        self.highlight = Some(color);
+    }
+
+    /// Sets the highlight color for this style and returns it.
+    pub fn with_highlight(mut self, color: Color) -> Self {
+        self.set_highlight(color);
+        self
+    }
+
+    /// Sets the stroke color and width for this style.
+    ///
+    /// Text printed with this style is filled with the text color and then outlined with the
+    /// given color and width.  The width does not change the text width.
+    pub fn set_stroke(&mut self, color: Color, width: impl Into<Mm>) {
+        self.stroke = Some(TextStroke {
+            color,
+            width: width.into(),
+            fill: true,
+        });
+    }
+
+    /// Sets the stroke color and width for this style and returns it.
+    pub fn with_stroke(mut self, color: Color, width: impl Into<Mm>) -> Self {
+        self.set_stroke(color, width);
+        self
+    }
+
+    /// Sets the stroke color and width for this style and disables filling the glyphs.
+    ///
+    /// Text printed with this style is only drawn as an outline with the given color and width.
+    pub fn set_stroke_only(&mut self, color: Color, width: impl Into<Mm>) {
+        self.stroke = Some(TextStroke {
+            color,
+            width: width.into(),
+            fill: false,
+        });
+    }
+
+    /// Sets the stroke color and width for this style, disables filling the glyphs and returns
+    /// the style.
+    pub fn with_stroke_only(mut self, color: Color, width: impl Into<Mm>) -> Self {
+        self.set_stroke_only(color, width);
+        self
+    }
+
+    /// Sets the additional word spacing for this style.
+    ///
+    /// The word spacing is added to the advance of every space character (U+0020), so it only
+    /// widens the gaps between words, for example for loose justification.
+    pub fn set_word_spacing(&mut self, word_spacing: impl Into<Mm>) {
+        self.word_spacing = Some(word_spacing.into());
+    }
+
+    /// Sets the additional word spacing for this style and returns it.
+    pub fn with_word_spacing(mut self, word_spacing: impl Into<Mm>) -> Self {
+        self.set_word_spacing(word_spacing);
+        self
+    }
+
+    /// Enables or disables automatically superscripted ordinal suffixes for this style.
+    pub fn set_auto_superscript_ordinals(&mut self, enabled: bool) {
+        self.superscript_ordinals = Some(enabled);
+    }
+
+    /// Enables or disables automatically superscripted ordinal suffixes for this style and
+    /// returns it.
+    ///
+    /// If enabled, English ordinal suffixes following a number, like the *st* in *1st* or the *th*
+    /// in *12th*, are printed with a reduced font size and a raised baseline.  The width
+    /// calculations of this style take the superscripted suffixes into account.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use genpdfi::style::Style;
+    /// let style = Style::new().auto_superscript_ordinals(true);
+    /// assert!(style.has_auto_superscript_ordinals());
+    /// ```
+    pub fn auto_superscript_ordinals(mut self, enabled: bool) -> Style {
+        self.set_auto_superscript_ordinals(enabled);
+        self
+    }
+
+    /// Allows or disallows a synthetic bold font for this style.
+    pub fn set_allow_synthetic_bold(&mut self, enabled: bool) {
+        self.synthetic_bold = Some(enabled);
+    }
+
+    /// Allows or disallows a synthetic bold font for this style and returns it.
+    ///
+    /// If allowed and the font family uses the same font for the bold and the regular (or the
+    /// bold italic and the italic) style, bold text is emboldened by additionally stroking the
+    /// glyph outlines with the text color.  The width calculations are not affected.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use genpdfi::style::Style;
+    /// let style = Style::new().bold().allow_synthetic_bold(true);
+    /// assert!(style.allows_synthetic_bold());
+    /// ```
+    pub fn allow_synthetic_bold(mut self, enabled: bool) -> Style {
+        self.set_allow_synthetic_bold(enabled);
+        self
+    }
+
+    /// Allows or disallows a synthetic italic font for this style.
+    pub fn set_allow_synthetic_italic(&mut self, enabled: bool) {
+        self.synthetic_italic = Some(enabled);
+    }
+
+    /// Allows or disallows a synthetic italic font for this style and returns it.
+    ///
+    /// If allowed and the font family uses the same font for the italic and the regular (or the
+    /// bold italic and the bold) style, italic text is slanted by shearing the text matrix by
+    /// about 12 degrees.  The width calculations are not affected.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use genpdfi::style::Style;
+    /// let style = Style::new().italic().allow_synthetic_italic(true);
+    /// assert!(style.allows_synthetic_italic());
+    /// ```
+    pub fn allow_synthetic_italic(mut self, enabled: bool) -> Style {
+        self.set_allow_synthetic_italic(enabled);
+        self
+    }
+
+    /// Splits the given string into runs that should be printed with different styles.
+    ///
+    /// Currently, this only handles superscripted ordinal suffixes if they are enabled for this
+    /// style.  Otherwise, the full string is returned with this style.
+    pub(crate) fn split_runs<'s>(&self, s: &'s str) -> Vec<(&'s str, Style)> {
+        if !self.has_auto_superscript_ordinals() {
+            return vec![(s, *self)];
+        }
+
+        let superscript = self.superscript();
+
+        let mut runs = Vec::new();
+        let mut start = 0;
+        for (suffix_start, suffix_end) in find_ordinal_suffixes(s) {
+            if start < suffix_start {
+                runs.push((&s[start..suffix_start], *self));
+            }
+            runs.push((&s[suffix_start..suffix_end], superscript));
+            start = suffix_end;
+        }
+        if start < s.len() || runs.is_empty() {
+            runs.push((&s[start..], *self));
+        }
+        runs
+    }
+
+    /// Returns the style for ruby annotations printed above text with this style.
+    ///
+    /// The ruby style uses a reduced font size and its baseline is raised so that the descent of
+    /// the ruby text is placed on the ascent of the base text.
+    pub(crate) fn ruby_style(&self, font_cache: &fonts::FontCache) -> Style {
+        let font = self.font(font_cache);
+        let font_size = self.font_size() * RUBY_SIZE;
+        let rise = font.ascent(self.font_size()) - font.descent(font_size);
+
+        let mut ruby = *self;
+        ruby.font_size = Some(font_size);
+        ruby.script = None;
+        ruby.baseline_shift = Some(self.baseline_shift() + printpdf::Pt::from(rise).0);
+        ruby
+    }
+
+    /// Sets the fill opacity for this style.
+    ///
+    /// The opacity is clamped to the range from 0 (fully transparent) to 1 (fully opaque).
+    pub fn set_fill_opacity(&mut self, fill_opacity: f32) {
+        self.fill_opacity = Some(clamp_unit(fill_opacity));
+    }
+
+    /// Sets the fill opacity for this style and returns it.
+    pub fn with_fill_opacity(mut self, fill_opacity: f32) -> Self {
+        self.set_fill_opacity(fill_opacity);
+        self
+    }
+
     /// Calculates the width of the given character with this style using the data in the given
     /// font cache.
     ///
@@ -313,8 +1210,22 @@ impl Style {
     ///
     /// [`FontCache`]: ../fonts/struct.FontCache.html
     pub fn str_width(&self, font_cache: &fonts::FontCache, s: &str) -> Mm {
+        if self.has_auto_superscript_ordinals() {
+            return self
+                .split_runs(s)
+                .into_iter()
+                .map(|(s, style)| style.run_width(font_cache, s))
+                .sum();
+        }
+        self.run_width(font_cache, s)
+    }
+
+    /// Calculates the width of the given string with the font of this style, including the word
+    /// spacing.
+    pub(crate) fn run_width(&self, font_cache: &fonts::FontCache, s: &str) -> Mm {
         let font = self.font(font_cache);
-        font.str_width(font_cache, s, self.font_size())
+        let spaces = s.chars().filter(|c| *c == ' ').count();
+        font.str_width(font_cache, s, self.font_size()) + self.word_spacing() * spaces as f32
     }
 
     /// Returns the font family for this style or the default font family using the given font
@@ -365,8 +1276,7 @@ impl Style {
     ///
     /// [`FontCache`]: ../fonts/struct.FontCache.html
     pub fn text_width(&self, font_cache: &fonts::FontCache, s: &str) -> Mm {
-        let font = self.font(font_cache);
-        font.str_width(font_cache, s, self.font_size())
+        self.str_width(font_cache, s)
     }
 }
 
@@ -384,6 +1294,8 @@ impl From<Effect> for Style {
             Effect::Italic => style.italic(),
             Effect::Underline => style.underline(),
             Effect::Strikethrough => style.strikethrough(),
+            Effect::Superscript => style.superscript(),
+            Effect::Subscript => style.subscript(),
         }
     }
 }
@@ -516,7 +1428,7 @@ impl<'s> StyledStr<'s> {
     ///
     /// [`FontCache`]: ../fonts/struct.FontCache.html
     pub fn width(&self, font_cache: &fonts::FontCache) -> Mm {
-        self.style.str_width(font_cache, &self.s)
+        self.style.str_width(font_cache, self.s)
     }
 }
 
@@ -627,15 +1539,18 @@ impl<'s> From<StyledString> for StyledCow<'s> {
 /// The style consists of:
 /// - the line thickness in millimeters (defaults to 0.1)
 /// - the color of the line, see [`Color`][] (defaults to black)
+/// - the dash pattern of the line in millimeters (defaults to a solid line)
 ///
 /// Note that a line thickness of 0.0 does not make the line disappear, but rather makes it appear
 /// 1px wide across all devices and resolutions.
 ///
 /// [`Color`]: enum.Color.html
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LineStyle {
     thickness: Mm,
     color: Color,
+    dash_pattern: Option<Vec<f32>>,
 }
 
 impl Default for LineStyle {
@@ -643,6 +1558,7 @@ impl Default for LineStyle {
         LineStyle {
             thickness: Mm::from(0.1),
             color: Color::Rgb(0, 0, 0),
+            dash_pattern: None,
         }
     }
 }
@@ -662,6 +1578,16 @@ impl LineStyle {
         LineStyle::default()
     }
 
+    /// Creates a new dashed line style with dashes of 2 mm separated by gaps of 1 mm.
+    pub fn dashed() -> LineStyle {
+        LineStyle::new().with_dash_pattern(vec![2.0, 1.0])
+    }
+
+    /// Creates a new dotted line style with dots of 0.3 mm separated by gaps of 0.7 mm.
+    pub fn dotted() -> LineStyle {
+        LineStyle::new().with_dash_pattern(vec![0.3, 0.7])
+    }
+
     /// Sets the line thickness.
     ///
     /// Setting this to 0.0 will not hide the line, rather it’s a special value that tells PDF
@@ -699,4 +1625,363 @@ impl LineStyle {
     pub fn color(&self) -> Color {
         self.color
     }
+
+    /// Sets the dash pattern of the line.
+    ///
+    /// The pattern contains the alternating lengths of the dashes and gaps in millimeters,
+    /// starting with a dash.  If the pattern is empty, the line is drawn solid.  Patterns that
+    /// are invalid in PDF documents, i. e. patterns with negative or non-finite lengths or with
+    /// only zero lengths, are ignored and the line is drawn solid too.
+    pub fn set_dash_pattern(&mut self, dash_pattern: Vec<f32>) {
+        self.dash_pattern = if is_valid_dash_pattern(&dash_pattern) {
+            Some(dash_pattern)
+        } else {
+            None
+        };
+    }
+
+    /// Sets the dash pattern of the line and returns the line style.
+    ///
+    /// See [`set_dash_pattern`](#method.set_dash_pattern) for more information.
+    pub fn with_dash_pattern(mut self, dash_pattern: Vec<f32>) -> Self {
+        self.set_dash_pattern(dash_pattern);
+        self
+    }
+
+    /// Returns the dash pattern of the line in millimeters, or `None` if the line is solid.
+    pub fn dash_pattern(&self) -> Option<&[f32]> {
+        // Deserialized line styles have not been validated by set_dash_pattern.
+        self.dash_pattern
+            .as_deref()
+            .filter(|dash_pattern| is_valid_dash_pattern(dash_pattern))
+    }
+}
+
+/// Returns whether the given dash pattern is non-empty, has only finite and non-negative lengths
+/// and at least one non-zero length, as required for the dash arrays of PDF documents.
+fn is_valid_dash_pattern(dash_pattern: &[f32]) -> bool {
+    dash_pattern
+        .iter()
+        .all(|length| length.is_finite() && *length >= 0.0)
+        && dash_pattern.iter().any(|length| *length > 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Color, LineStyle, Style};
+
+    #[test]
+    fn test_color_from_name() {
+        let cases = [
+            ("black", (0, 0, 0)),
+            ("white", (255, 255, 255)),
+            ("red", (255, 0, 0)),
+            ("lime", (0, 255, 0)),
+            ("green", (0, 128, 0)),
+            ("navy", (0, 0, 128)),
+            ("tomato", (255, 99, 71)),
+            ("steelblue", (70, 130, 180)),
+            ("rebeccapurple", (102, 51, 153)),
+            ("aliceblue", (240, 248, 255)),
+            ("yellowgreen", (154, 205, 50)),
+            ("lightgoldenrodyellow", (250, 250, 210)),
+        ];
+        for (name, (r, g, b)) in cases.iter() {
+            assert_eq!(
+                Some(Color::Rgb(*r, *g, *b)),
+                Color::from_name(name),
+                "{}",
+                name
+            );
+        }
+
+        assert_eq!(Color::from_name("gray"), Color::from_name("grey"));
+        assert_eq!(
+            Some(Color::Rgb(255, 99, 71)),
+            Color::from_name("  ToMaTo\t")
+        );
+        assert_eq!(None, Color::from_name("transparent"));
+        assert_eq!(None, Color::from_name("notacolor"));
+        assert_eq!(None, Color::from_name(""));
+    }
+
+    #[test]
+    fn test_color_from_hsl() {
+        assert_eq!(Color::Rgb(255, 0, 0), Color::from_hsl(0.0, 1.0, 0.5));
+        assert_eq!(Color::Rgb(0, 255, 0), Color::from_hsl(120.0, 1.0, 0.5));
+        assert_eq!(Color::Rgb(0, 0, 255), Color::from_hsl(240.0, 1.0, 0.5));
+        assert_eq!(Color::Rgb(0, 0, 0), Color::from_hsl(123.0, 0.7, 0.0));
+        assert_eq!(Color::Rgb(255, 255, 255), Color::from_hsl(42.0, 0.3, 1.0));
+        assert_eq!(Color::Rgb(128, 128, 128), Color::from_hsl(0.0, 0.0, 0.5));
+        // CSS named colors
+        assert_eq!(
+            Color::from_name("rebeccapurple"),
+            Some(Color::from_hsl(270.0, 0.5, 0.4))
+        );
+        assert_eq!(
+            Color::from_name("tomato"),
+            Some(Color::from_hsl(9.13, 1.0, 0.639))
+        );
+        assert_eq!(
+            Color::from_name("steelblue"),
+            Some(Color::from_hsl(207.27, 0.44, 0.49))
+        );
+        // hue wraps around
+        assert_eq!(
+            Color::from_hsl(30.0, 0.8, 0.4),
+            Color::from_hsl(390.0, 0.8, 0.4)
+        );
+        assert_eq!(
+            Color::from_hsl(300.0, 0.8, 0.4),
+            Color::from_hsl(-60.0, 0.8, 0.4)
+        );
+        assert_eq!(Color::Rgb(255, 0, 0), Color::from_hsl(360.0, 1.0, 0.5));
+        // saturation and lightness are clamped
+        assert_eq!(
+            Color::from_hsl(200.0, 1.0, 0.5),
+            Color::from_hsl(200.0, 1.5, 0.5)
+        );
+        assert_eq!(
+            Color::from_hsl(200.0, 0.5, 1.0),
+            Color::from_hsl(200.0, 0.5, 7.0)
+        );
+        assert_eq!(
+            Color::from_hsl(200.0, 0.0, 0.3),
+            Color::from_hsl(200.0, -1.0, 0.3)
+        );
+    }
+
+    #[test]
+    fn test_color_from_hsv() {
+        assert_eq!(Color::Rgb(255, 0, 0), Color::from_hsv(0.0, 1.0, 1.0));
+        assert_eq!(Color::Rgb(255, 255, 0), Color::from_hsv(60.0, 1.0, 1.0));
+        assert_eq!(Color::Rgb(0, 255, 255), Color::from_hsv(180.0, 1.0, 1.0));
+        assert_eq!(Color::Rgb(255, 0, 255), Color::from_hsv(300.0, 1.0, 1.0));
+        assert_eq!(Color::Rgb(0, 0, 0), Color::from_hsv(77.0, 0.4, 0.0));
+        assert_eq!(Color::Rgb(255, 255, 255), Color::from_hsv(77.0, 0.0, 1.0));
+        assert_eq!(Color::Rgb(0, 128, 0), Color::from_hsv(120.0, 1.0, 0.5));
+        assert_eq!(
+            Color::Rgb(191, 64, 64),
+            Color::from_hsv(0.0, 2.0 / 3.0, 0.75)
+        );
+        // hue wraps around
+        assert_eq!(
+            Color::from_hsv(45.0, 0.5, 0.5),
+            Color::from_hsv(765.0, 0.5, 0.5)
+        );
+        assert_eq!(
+            Color::from_hsv(270.0, 0.5, 0.5),
+            Color::from_hsv(-90.0, 0.5, 0.5)
+        );
+        // saturation and value are clamped
+        assert_eq!(
+            Color::from_hsv(10.0, 1.0, 1.0),
+            Color::from_hsv(10.0, 3.0, 1.5)
+        );
+        assert_eq!(
+            Color::from_hsv(10.0, 0.0, 0.0),
+            Color::from_hsv(10.0, -3.0, -1.5)
+        );
+    }
+
+    #[test]
+    fn test_color_mix() {
+        let black = Color::Rgb(0, 0, 0);
+        let white = Color::Rgb(255, 255, 255);
+        assert_eq!(Color::Rgb(128, 128, 128), black.mix(white, 0.5));
+        assert_eq!(
+            Color::Rgb(128, 128, 128),
+            Color::Greyscale(0).mix(white, 0.5)
+        );
+        assert_eq!(
+            Color::Rgb(128, 128, 128),
+            Color::Cmyk(0, 0, 0, 255).lighten(0.5)
+        );
+        assert_eq!(black, black.mix(white, -1.0));
+        assert_eq!(white, black.mix(white, 2.0));
+        assert_eq!(
+            Color::Rgb(255, 0, 0),
+            Color::Cmyk(0, 255, 255, 0).mix(white, 0.0)
+        );
+        assert_eq!(
+            Color::Rgb(51, 102, 128),
+            Color::Rgb(102, 204, 255).darken(0.5)
+        );
+        assert_eq!(white, Color::Rgb(10, 20, 30).lighten(1.0));
+    }
+
+    #[test]
+    fn test_contrast_ratio() {
+        let black = Color::Rgb(0, 0, 0);
+        let white = Color::Rgb(255, 255, 255);
+        assert_eq!(0.0, black.relative_luminance());
+        assert!((white.relative_luminance() - 1.0).abs() < 1e-6);
+        assert!((black.contrast_ratio(&white) - 21.0).abs() < 0.01);
+        assert!((white.contrast_ratio(&black) - 21.0).abs() < 0.01);
+        assert!(
+            (Color::Cmyk(0, 0, 0, 255).contrast_ratio(&Color::Greyscale(255)) - 21.0).abs() < 0.01
+        );
+
+        let tomato = Color::from_name("tomato").unwrap();
+        assert_eq!(1.0, tomato.contrast_ratio(&tomato));
+        assert_eq!(
+            1.0,
+            Color::Greyscale(128).contrast_ratio(&Color::Rgb(128, 128, 128))
+        );
+
+        // #767676 is the darkest grey that passes the WCAG AA threshold of 4.5 on white
+        let grey = Color::Rgb(0x76, 0x76, 0x76);
+        assert!((grey.contrast_ratio(&white) - 4.54).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_superscript_subscript() {
+        let style = Style::new().with_font_size(20).superscript();
+        assert!(style.is_superscript());
+        assert_eq!(12.0, style.font_size());
+        assert_eq!(20.0 * super::SUPERSCRIPT_RISE, style.baseline_shift());
+
+        let style = style.and(Style::new().subscript());
+        assert!(style.is_subscript());
+        assert!(!style.is_superscript());
+        assert!(style.baseline_shift() < 0.0);
+        assert_eq!(12.0, Style::new().font_size());
+        assert_eq!(0.0, Style::new().baseline_shift());
+    }
+
+    #[test]
+    fn test_font_size_clamp() {
+        assert_eq!(10.5, Style::new().with_font_size(10.5).font_size());
+        assert_eq!(0.01, Style::new().with_font_size(0).font_size());
+        assert_eq!(0.01, Style::new().with_font_size(-3).font_size());
+        assert_eq!(0.01, Style::new().with_font_size(f64::NAN).font_size());
+        assert_eq!(
+            f32::MAX,
+            Style::new().with_font_size(f64::INFINITY).font_size()
+        );
+    }
+
+    #[test]
+    fn test_merge_line_spacing() {
+        let mut style = Style::new().with_font_size(14).bold();
+        style.merge(Style::new().with_line_spacing(1.5));
+        assert_eq!(1.5, style.line_spacing());
+        assert_eq!(14.0, style.font_size());
+
+        style.merge(Style::new().italic());
+        assert_eq!(1.5, style.line_spacing());
+        assert_eq!(1.0, Style::new().line_spacing());
+    }
+
+    #[test]
+    fn test_clear() {
+        let red = Color::Rgb(255, 0, 0);
+        let paragraph = Style::new().with_color(red).with_font_size(20).bold();
+        let span = Style::new().without_color().without_font_size();
+
+        let merged = paragraph.and(span);
+        assert_eq!(None, merged.color());
+        assert_eq!(Style::new().font_size(), merged.font_size());
+        assert!(merged.is_bold());
+
+        // Clearing is kept when merging cleared styles into other styles
+        let nested = Style::new()
+            .with_color(Color::Rgb(0, 0, 255))
+            .and(paragraph.and(span));
+        assert_eq!(None, nested.color());
+        assert_eq!(
+            nested,
+            Style::new().with_color(red).and(paragraph).and(span)
+        );
+
+        // Properties set after clearing take precedence
+        let blue = Color::Rgb(0, 0, 255);
+        let merged = paragraph.and(span).and(Style::new().with_color(blue));
+        assert_eq!(Some(blue), merged.color());
+        assert_eq!(Some(blue), span.with_color(blue).color());
+        assert_eq!(Some(red), paragraph.and(Style::new()).color());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        use super::LineStyle;
+        use crate::fonts::{FontCache, FontData, FontFamily};
+        use crate::{Margins, Size};
+
+        let style = Style::new().bold().with_color(Color::Rgb(1, 2, 3));
+        let json = serde_json::to_string(&style).unwrap();
+        assert_eq!(style, serde_json::from_str(&json).unwrap());
+
+        // The font family is only valid for its font cache and is never serialized
+        let data = include_bytes!("../tests/fonts/DejaVuSans-Regular.ttf").to_vec();
+        let font_cache =
+            FontCache::new(FontFamily::from_single(FontData::new(data, None).unwrap()));
+        let with_family = style.with_font_family(font_cache.default_font_family());
+        let json = serde_json::to_string(&with_family).unwrap();
+        assert!(!json.contains("\"font_family\""));
+        assert_eq!(style, serde_json::from_str(&json).unwrap());
+
+        let partial: Style =
+            serde_json::from_str(r#"{"font_size": 14.0, "is_italic": true}"#).unwrap();
+        assert_eq!(Style::new().with_font_size(14).italic(), partial);
+
+        let line_style = LineStyle::dashed().with_color(Color::Cmyk(0, 10, 20, 30));
+        let json = serde_json::to_string(&line_style).unwrap();
+        assert_eq!(line_style, serde_json::from_str(&json).unwrap());
+
+        let margins = Margins::trbl(1, 2, 3, 4);
+        let json = serde_json::to_string(&margins).unwrap();
+        assert_eq!(margins, serde_json::from_str(&json).unwrap());
+        let size = Size::new(210, 297);
+        assert_eq!(
+            r#"{"width":210.0,"height":297.0}"#,
+            serde_json::to_string(&size).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_dash_pattern() {
+        assert_eq!(Some(&[2.0, 1.0][..]), LineStyle::dashed().dash_pattern());
+        assert_eq!(
+            Some(&[0.0, 1.0][..]),
+            LineStyle::new()
+                .with_dash_pattern(vec![0.0, 1.0])
+                .dash_pattern()
+        );
+        for pattern in [
+            vec![],
+            vec![0.0, 0.0],
+            vec![2.0, -1.0],
+            vec![f32::NAN, 1.0],
+            vec![f32::INFINITY],
+        ]
+        .iter()
+        {
+            let line_style = LineStyle::dashed().with_dash_pattern(pattern.clone());
+            assert_eq!(None, line_style.dash_pattern());
+        }
+    }
+
+    #[test]
+    fn test_find_ordinal_suffixes() {
+        use super::find_ordinal_suffixes;
+
+        assert_eq!(vec![(1, 3)], find_ordinal_suffixes("1st"));
+        assert_eq!(vec![(5, 7)], find_ordinal_suffixes("the 2nd"));
+        assert_eq!(
+            vec![(3, 5), (12, 14)],
+            find_ordinal_suffixes("103rd and 11TH")
+        );
+        assert_eq!(vec![(2, 4), (7, 9)], find_ordinal_suffixes("12th, 4th."));
+        assert_eq!(vec![(2, 4)], find_ordinal_suffixes("21st"));
+        assert!(find_ordinal_suffixes("1th 11st 2rd").is_empty());
+        assert!(find_ordinal_suffixes("1stly a1st 1").is_empty());
+        assert!(find_ordinal_suffixes("first").is_empty());
+    }
+
+    #[test]
+    fn test_named_colors_sorted() {
+        assert!(super::NAMED_COLORS.windows(2).all(|w| w[0].0 < w[1].0));
+    }
 }
@@ -5,7 +5,7 @@
 
 use crate::error::{Error, ErrorKind};
 use crate::fonts::GlyphIdMap;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use subsetter::{subset, GlyphRemapper};
 use ttf_parser::Face;
 
@@ -28,6 +28,11 @@ pub struct SubsetResult {
 
 /// Creates a subset of a font containing only the specified characters.
 ///
+/// The subset always contains the `.notdef` glyph with the glyph ID 0, which is used for missing
+/// characters, and the space glyph, even if the text does not contain a space.  Characters that
+/// are not supported by the font are skipped, see [`subset_font_lenient`][] for a variant that
+/// reports them.
+///
 /// # Arguments
 /// * `font_data` - The original font file data (TTF/OTF)
 /// * `text` - The text containing all characters to include in the subset
@@ -47,31 +52,95 @@ pub struct SubsetResult {
 /// // subset now contains a smaller font with only the used glyphs
 /// assert!(subset.len() < font_data.len());
 /// ```
+///
+/// [`subset_font_lenient`]: fn.subset_font_lenient.html
 pub fn subset_font(font_data: &[u8], text: &str) -> Result<Vec<u8>, Error> {
-    let face = Face::parse(font_data, 0).map_err(|e| {
-        Error::new(
-            format!("Failed to parse font: {:?}", e),
-            ErrorKind::InvalidFont,
-        )
-    })?;
-
-    let mut remapper = GlyphRemapper::new();
-    remapper.remap(0);
+    subset_font_chars(font_data, &collect_used_chars(text))
+}
 
-    for ch in text.chars() {
-        if let Some(glyph_id) = face.glyph_index(ch) {
-            remapper.remap(glyph_id.0);
-        }
-    }
+/// Creates a subset of a font containing only the specified characters and returns the characters
+/// that are not supported by the font.
+///
+/// This works like [`subset_font`][], but additionally returns the unique characters of the text
+/// that have been dropped from the subset because the font has no glyph for them, in ascending
+/// order.  These characters are rendered with the `.notdef` glyph.
+///
+/// # Example
+/// ```rust,no_run
+/// use genpdfi::subsetting::subset_font_lenient;
+///
+/// let font_data = std::fs::read("font.ttf").unwrap();
+/// let (subset, dropped) = subset_font_lenient(&font_data, "Hello 世界").unwrap();
+/// if !dropped.is_empty() {
+///     eprintln!("The font does not support these characters: {:?}", dropped);
+/// }
+/// ```
+///
+/// [`subset_font`]: fn.subset_font.html
+pub fn subset_font_lenient(font_data: &[u8], text: &str) -> Result<(Vec<u8>, Vec<char>), Error> {
+    subset_glyphs(font_data, text.chars()).map(|subset| (subset.data, subset.missing_chars))
+}
 
-    let result = subset(font_data, 0, &remapper).map_err(|e| {
-        Error::new(
-            format!("Font subsetting failed: {:?}", e),
-            ErrorKind::InvalidFont,
-        )
-    })?;
+/// Creates a subset of a font containing exactly the glyphs for the given characters, the
+/// `.notdef` glyph and the space glyph.
+///
+/// This is useful if the characters have already been collected from many strings, for example
+/// with [`collect_used_chars`][].  Characters that are not supported by the font are ignored.
+///
+/// # Arguments
+/// * `font_data` - The original font file data (TTF/OTF)
+/// * `chars` - The characters to include in the subset
+///
+/// # Returns
+/// * `Ok(Vec<u8>)` - The subset font data
+/// * `Err(Error)` - If subsetting fails
+///
+/// # Example
+/// ```rust,no_run
+/// use genpdfi::subsetting::{collect_used_chars, subset_font_chars};
+///
+/// let font_data = std::fs::read("font.ttf").unwrap();
+/// let mut chars = collect_used_chars("Hello");
+/// chars.extend(collect_used_chars("World"));
+/// let subset = subset_font_chars(&font_data, &chars).unwrap();
+/// ```
+///
+/// [`collect_used_chars`]: fn.collect_used_chars.html
+pub fn subset_font_chars(font_data: &[u8], chars: &HashSet<char>) -> Result<Vec<u8>, Error> {
+    subset_glyphs(font_data, chars.iter().copied()).map(|subset| subset.data)
+}
 
-    Ok(result)
+/// Creates a subset of a font and returns the subset data together with the mapping from the
+/// glyph IDs of the original font to the glyph IDs in the subset.
+///
+/// The mapping can be used to rewrite glyph IDs that have been determined with the original font.
+/// The `.notdef` glyph is always included and keeps the glyph ID 0.  If the font has a glyph for
+/// the space character, it is always included too and gets the glyph ID 1.  The glyphs for the
+/// other characters are numbered consecutively after that, ordered by the characters they are
+/// used for.  The components of composite glyphs are added to the subset after these glyphs, but
+/// they are not part of the mapping as they are not used directly.
+///
+/// # Arguments
+/// * `font_data` - The original font file data (TTF/OTF)
+/// * `text` - The text containing all characters to include in the subset
+///
+/// # Returns
+/// * `Ok((Vec<u8>, HashMap<u16, u16>))` - The subset font data and the glyph ID mapping
+/// * `Err(Error)` - If subsetting fails
+///
+/// # Example
+/// ```rust,no_run
+/// use genpdfi::subsetting::subset_font_with_map;
+///
+/// let font_data = std::fs::read("font.ttf").unwrap();
+/// let (subset, glyph_map) = subset_font_with_map(&font_data, "Hello").unwrap();
+/// assert_eq!(Some(&0), glyph_map.get(&0));
+/// ```
+pub fn subset_font_with_map(
+    font_data: &[u8],
+    text: &str,
+) -> Result<(Vec<u8>, HashMap<u16, u16>), Error> {
+    subset_glyphs(font_data, text.chars()).map(|subset| (subset.data, subset.glyph_map))
 }
 
 /// Creates a subset font and returns both the data and glyph ID mapping.
@@ -114,8 +183,9 @@ pub fn subset_font_with_mapping(font_data: &[u8], text: &str) -> Result<SubsetRe
 
     let mut glyph_id_map = GlyphIdMap::new();
 
-    // Collect unique characters to avoid duplicate mapping
-    let unique_chars: HashSet<char> = text.chars().collect();
+    // Collect unique characters to avoid duplicate mapping, always including the space
+    let mut unique_chars: HashSet<char> = text.chars().collect();
+    unique_chars.insert(' ');
 
     for ch in unique_chars {
         if let Some(glyph_id) = face.glyph_index(ch) {
@@ -125,14 +195,80 @@ pub fn subset_font_with_mapping(font_data: &[u8], text: &str) -> Result<SubsetRe
         }
     }
 
-    let data = subset(font_data, 0, &remapper).map_err(|e| {
+    let data = subset(font_data, 0, &remapper).map_err(subset_error)?;
+
+    Ok(SubsetResult { data, glyph_id_map })
+}
+
+/// A font subset created by [`subset_glyphs`](fn.subset_glyphs.html).
+pub(crate) struct Subset {
+    /// The subset font data.
+    pub data: Vec<u8>,
+    /// The mapping from the glyph IDs in the original font to the glyph IDs in the subset.
+    pub glyph_map: HashMap<u16, u16>,
+    /// The requested characters that are not supported by the font, in ascending order.
+    pub missing_chars: Vec<char>,
+}
+
+/// Creates a subset of a font containing the glyphs for the given characters.
+///
+/// The characters are sorted before they are added to the subset, so the result does not depend
+/// on the order of the characters.  The `.notdef` glyph is always included and keeps the ID 0,
+/// and the space glyph is always included if the font supports it.
+pub(crate) fn subset_glyphs(
+    font_data: &[u8],
+    chars: impl IntoIterator<Item = char>,
+) -> Result<Subset, Error> {
+    let face = Face::parse(font_data, 0).map_err(|e| {
         Error::new(
-            format!("Font subsetting failed: {:?}", e),
+            format!("Failed to parse font: {:?}", e),
             ErrorKind::InvalidFont,
         )
     })?;
 
-    Ok(SubsetResult { data, glyph_id_map })
+    let mut chars: Vec<char> = chars.into_iter().collect();
+    chars.sort_unstable();
+    chars.dedup();
+
+    let mut remapper = GlyphRemapper::new();
+    let mut glyph_map = HashMap::new();
+    glyph_map.insert(0, remapper.remap(0));
+    // Some viewers do not render spaces correctly if the space glyph is missing.
+    if let Some(glyph_id) = face.glyph_index(' ') {
+        glyph_map.insert(glyph_id.0, remapper.remap(glyph_id.0));
+    }
+    let mut missing_chars = Vec::new();
+    for ch in chars {
+        if let Some(glyph_id) = face.glyph_index(ch) {
+            glyph_map.insert(glyph_id.0, remapper.remap(glyph_id.0));
+        } else {
+            missing_chars.push(ch);
+        }
+    }
+
+    let data = subset(font_data, 0, &remapper).map_err(subset_error)?;
+
+    Ok(Subset {
+        data,
+        glyph_map,
+        missing_chars,
+    })
+}
+
+/// Converts an error returned by the `subsetter` crate.
+///
+/// Only fonts with TrueType (`glyf`) or CFF (`CFF `) outlines can be subset, so the errors for
+/// other font flavors, for example variable fonts with `CFF2` outlines, get a clear message.
+fn subset_error(err: subsetter::Error) -> Error {
+    let msg = match err {
+        subsetter::Error::UnknownKind | subsetter::Error::Unimplemented => {
+            "Font subsetting failed: Unsupported font flavor, only fonts with TrueType or CFF \
+            outlines can be subset"
+                .to_owned()
+        }
+        err => format!("Font subsetting failed: {:?}", err),
+    };
+    Error::new(msg, ErrorKind::InvalidFont)
 }
 
 /// Collects all unique characters from a string.
@@ -156,6 +292,130 @@ pub fn collect_used_chars(text: &str) -> HashSet<char> {
 mod tests {
     use super::*;
 
+    const FONT: &[u8] = include_bytes!("../tests/fonts/DejaVuSans-Regular.ttf");
+
+    #[test]
+    fn test_subset_font_chars() {
+        let text = "Hello World!";
+        let chars = collect_used_chars(text);
+        let subset = subset_font_chars(FONT, &chars).unwrap();
+        assert_eq!(subset_font(FONT, text).unwrap(), subset);
+        assert_eq!(subset_font(FONT, "!dlroW olleH").unwrap(), subset);
+        assert!(subset.len() < FONT.len());
+    }
+
+    #[test]
+    fn test_subset_font_with_map() {
+        let (subset, glyph_map) = subset_font_with_map(FONT, "BA").unwrap();
+        assert_eq!(subset_font(FONT, "AB").unwrap(), subset);
+        assert_eq!(4, glyph_map.len());
+        assert_eq!(Some(&0), glyph_map.get(&0));
+
+        let face = Face::parse(FONT, 0).unwrap();
+        let a = face.glyph_index('A').unwrap().0;
+        let b = face.glyph_index('B').unwrap().0;
+        assert_eq!(Some(&2), glyph_map.get(&a));
+        assert_eq!(Some(&3), glyph_map.get(&b));
+
+        let subset_face = Face::parse(&subset, 0).unwrap();
+        assert_eq!(4, subset_face.number_of_glyphs());
+        assert_eq!(
+            face.glyph_hor_advance(ttf_parser::GlyphId(a)),
+            subset_face.glyph_hor_advance(ttf_parser::GlyphId(2))
+        );
+    }
+
+    #[test]
+    fn test_subset_without_space() {
+        let face = Face::parse(FONT, 0).unwrap();
+        let space = face.glyph_index(' ').unwrap().0;
+        let (_, glyph_map) = subset_font_with_map(FONT, "Hello").unwrap();
+        assert_eq!(Some(&0), glyph_map.get(&0));
+        assert_eq!(Some(&1), glyph_map.get(&space));
+
+        let result = subset_font_with_mapping(FONT, "Hello").unwrap();
+        assert!(result.glyph_id_map.get(' ').is_some());
+
+        let (subset, dropped) = subset_font_lenient(FONT, "Hello\u{4e16}\u{754c}\u{4e16}").unwrap();
+        assert_eq!(subset_font(FONT, "Hello").unwrap(), subset);
+        assert_eq!(vec!['\u{4e16}', '\u{754c}'], dropped);
+    }
+
+    #[test]
+    fn test_composite_glyphs() {
+        struct Counter(usize);
+
+        impl ttf_parser::OutlineBuilder for Counter {
+            fn move_to(&mut self, _: f32, _: f32) {
+                self.0 += 1;
+            }
+            fn line_to(&mut self, _: f32, _: f32) {
+                self.0 += 1;
+            }
+            fn quad_to(&mut self, _: f32, _: f32, _: f32, _: f32) {
+                self.0 += 1;
+            }
+            fn curve_to(&mut self, _: f32, _: f32, _: f32, _: f32, _: f32, _: f32) {
+                self.0 += 1;
+            }
+            fn close(&mut self) {
+                self.0 += 1;
+            }
+        }
+
+        fn segments(face: &Face<'_>, glyph_id: u16) -> usize {
+            let mut counter = Counter(0);
+            face.outline_glyph(ttf_parser::GlyphId(glyph_id), &mut counter);
+            counter.0
+        }
+
+        // A with diaeresis is a composite of the glyphs for A and the diaeresis.
+        let face = Face::parse(FONT, 0).unwrap();
+        let glyph_id = face.glyph_index('\u{c4}').unwrap().0;
+        assert!(segments(&face, glyph_id) > 0);
+
+        let (subset, glyph_map) = subset_font_with_map(FONT, "\u{c4}").unwrap();
+        let subset_face = Face::parse(&subset, 0).unwrap();
+        // The components are included in the subset, but not in the mapping.
+        assert!(usize::from(subset_face.number_of_glyphs()) > glyph_map.len());
+        assert_eq!(
+            segments(&face, glyph_id),
+            segments(&subset_face, glyph_map[&glyph_id])
+        );
+    }
+
+    #[test]
+    fn test_cff_subset() {
+        let font = include_bytes!("../tests/fonts/Minimal-CFF.otf");
+        let face = Face::parse(font, 0).unwrap();
+        assert!(face.tables().cff.is_some());
+        let (subset, glyph_map) = subset_font_with_map(font, "CA").unwrap();
+
+        let subset_face = Face::parse(&subset, 0).unwrap();
+        assert!(subset_face.tables().cff.is_some());
+        assert_eq!(4, subset_face.number_of_glyphs());
+        for c in &['A', 'C', ' '] {
+            let glyph_id = face.glyph_index(*c).unwrap();
+            let subset_glyph_id = ttf_parser::GlyphId(glyph_map[&glyph_id.0]);
+            assert_eq!(
+                face.glyph_bounding_box(glyph_id),
+                subset_face.glyph_bounding_box(subset_glyph_id)
+            );
+            assert_eq!(
+                face.glyph_hor_advance(glyph_id),
+                subset_face.glyph_hor_advance(subset_glyph_id)
+            );
+        }
+
+        // Rename the CFF table to CFF2, which is not supported.
+        let mut font = font.to_vec();
+        let pos = font.windows(4).position(|tag| tag == b"CFF ").unwrap();
+        font[pos + 3] = b'2';
+        let err = subset_font(&font, "A").unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::InvalidFont));
+        assert!(err.to_string().contains("Unsupported font flavor"));
+    }
+
     #[test]
     fn test_collect_used_chars() {
         let text = "Hello World!";
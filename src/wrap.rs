@@ -1,7 +1,10 @@
 //! Utilities for text wrapping.
 
+use std::iter;
 use std::mem;
+use std::ops;
 
+use crate::fonts;
 use crate::style;
 use crate::Context;
 use crate::Mm;
@@ -45,7 +48,7 @@ impl<'c, 's, I: Iterator<Item = style::StyledStr<'s>>> Iterator for Wrapper<'c,
 
     fn next(&mut self) -> Option<(Vec<style::StyledCow<'s>>, usize)> {
         // Append words to self.buf until the maximum line length is reached
-        while let Some(s) = self.iter.next() {
+        for s in self.iter.by_ref() {
             let mut width = s.width(&self.context.font_cache);
 
             if self.x + width > self.width {
@@ -188,3 +191,105 @@ impl<I: Iterator<Item = style::StyledString>> Iterator for Words<I> {
         }
     }
 }
+
+/// A line produced by [`break_lines`][].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Line {
+    /// The byte range of the line in the input string.
+    pub range: ops::Range<usize>,
+    /// Whether the last word of the line was hyphenated, i. e. whether a hyphen has to be
+    /// appended to the line.
+    pub is_hyphenated: bool,
+}
+
+impl Line {
+    fn new(range: ops::Range<usize>, is_hyphenated: bool) -> Line {
+        Line {
+            range,
+            is_hyphenated,
+        }
+    }
+}
+
+/// Greedily breaks the given string into lines that are not wider than the given width when
+/// printed with the given style.
+///
+/// Lines are broken at whitespace and at newline characters.  The whitespace at a line break is
+/// not part of the lines.  If a word does not fit into a line, it is hyphenated at the last
+/// hyphenation point returned by the hyphenator that fits into the line.  Words that are wider
+/// than a line and that cannot be hyphenated are broken after the last character that fits into
+/// the line, but each line contains at least one character.
+pub fn break_lines(
+    font_cache: &fonts::FontCache,
+    style: style::Style,
+    s: &str,
+    width: Mm,
+    hyphenator: &dyn crate::Hyphenator,
+) -> Vec<Line> {
+    let fits = |range: ops::Range<usize>| style.str_width(font_cache, &s[range]) <= width;
+    let hyphen_width = style.str_width(font_cache, "-");
+    let fits_hyphenated =
+        |range: ops::Range<usize>| style.str_width(font_cache, &s[range]) + hyphen_width <= width;
+    let mut lines = Vec::new();
+    let mut offset = 0;
+    for paragraph in s.split('\n') {
+        let mut line: Option<ops::Range<usize>> = None;
+        for word in words(paragraph, offset) {
+            let points = hyphenator.hyphenation_points(&s[word.clone()]);
+            let mut start = word.start;
+            while start < word.end {
+                let line_start = line.as_ref().map_or(start, |line| line.start);
+                if fits(line_start..word.end) {
+                    line = Some(line_start..word.end);
+                    break;
+                }
+
+                let hyphenation = points
+                    .iter()
+                    .map(|point| word.start + point)
+                    .filter(|end| start < *end && *end < word.end && s.is_char_boundary(*end))
+                    .filter(|end| fits_hyphenated(line_start..*end))
+                    .max();
+                if let Some(end) = hyphenation {
+                    lines.push(Line::new(line_start..end, true));
+                    line = None;
+                    start = end;
+                } else if let Some(range) = line.take() {
+                    // Try again on a new line
+                    lines.push(Line::new(range, false));
+                } else {
+                    // The word is too wide for a line and has to be broken
+                    let first = start + s[start..].chars().next().map_or(0, char::len_utf8);
+                    let end = s[start..word.end]
+                        .char_indices()
+                        .map(|(i, c)| start + i + c.len_utf8())
+                        .take_while(|i| fits(start..*i))
+                        .last()
+                        .unwrap_or(first);
+                    lines.push(Line::new(start..end, false));
+                    start = end;
+                }
+            }
+        }
+        lines.push(Line::new(line.unwrap_or(offset..offset), false));
+        offset += paragraph.len() + 1;
+    }
+    lines
+}
+
+/// Returns the byte ranges of the whitespace-separated words of the given string, shifted by the
+/// given offset.
+fn words(s: &str, offset: usize) -> Vec<ops::Range<usize>> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, c) in s.char_indices().chain(iter::once((s.len(), ' '))) {
+        if c.is_whitespace() {
+            if let Some(start) = start.take() {
+                words.push(offset + start..offset + i);
+            }
+        } else if start.is_none() {
+            start = Some(i);
+        }
+    }
+    words
+}